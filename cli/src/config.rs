@@ -0,0 +1,169 @@
+//! `config` subcommands.
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::{system_program, InstructionData};
+use anyhow::Result;
+use clap::Subcommand;
+use limit_order::{accounts, instruction, GlobalConfig};
+use serde_json::{json, Value};
+
+use crate::{pda, Context};
+
+#[derive(Subcommand)]
+pub enum ConfigCommand {
+    /// Print the global config.
+    Show,
+    /// Set the platform fee, in basis points.
+    SetFee { platform_fee: u16 },
+    /// Round platform fees up instead of down.
+    SetFeeRounding {
+        #[arg(action = clap::ArgAction::Set)]
+        round_fees_up: bool,
+    },
+    /// Send future fees to another wallet.
+    SetTreasury { treasury: Pubkey },
+    /// Stop new orders and executions; cancels keep working.
+    Pause,
+    /// Resume new orders and executions.
+    Unpause,
+    /// Route swapped executions through a swap adapter program; the default
+    /// pubkey disables them.
+    SetSwapConfig {
+        swap_adapter: Pubkey,
+        /// Take the platform fee from the swap output instead of the escrow.
+        #[arg(long)]
+        fee_on_output: bool,
+    },
+    /// Bound the seconds to expiry orders can be opened with; a zero maximum
+    /// leaves it unbounded.
+    SetOrderLifetime {
+        min_order_lifetime: i64,
+        max_order_lifetime: i64,
+    },
+    /// Set the smallest orders that can be opened: lamports for SOL orders,
+    /// and notional value for orders whose mint has a price.
+    SetOrderMinimums {
+        min_sol_order_size: u64,
+        min_notional: u64,
+        /// Seconds a published price stays usable.
+        #[arg(long, default_value_t = GlobalConfig::DEFAULT_MAX_PRICE_AGE)]
+        max_price_age: i64,
+    },
+    /// Publish the admin-set notional value of one base unit of a mint, the
+    /// native mint for SOL, scaled by 10^9. Not an oracle price.
+    SetPrice { mint: Pubkey, price: u64 },
+}
+
+pub fn run(ctx: &Context, command: ConfigCommand) -> Result<Value> {
+    match command {
+        ConfigCommand::Show => {
+            let config = ctx.global_config()?;
+            Ok(config_json(&pda::global_config(&ctx.program_id), &config))
+        }
+        ConfigCommand::SetFee { platform_fee } => {
+            update(ctx, instruction::SetPlatformFee { platform_fee })
+        }
+        ConfigCommand::SetFeeRounding { round_fees_up } => {
+            update(ctx, instruction::SetFeeRounding { round_fees_up })
+        }
+        ConfigCommand::SetTreasury { treasury } => {
+            update(ctx, instruction::SetTreasury { treasury })
+        }
+        ConfigCommand::Pause => update(ctx, instruction::SetPaused { paused: true }),
+        ConfigCommand::Unpause => update(ctx, instruction::SetPaused { paused: false }),
+        ConfigCommand::SetSwapConfig {
+            swap_adapter,
+            fee_on_output,
+        } => update(
+            ctx,
+            instruction::SetSwapConfig {
+                swap_adapter,
+                fee_on_output,
+            },
+        ),
+        ConfigCommand::SetOrderLifetime {
+            min_order_lifetime,
+            max_order_lifetime,
+        } => update(
+            ctx,
+            instruction::SetOrderLifetime {
+                min_order_lifetime,
+                max_order_lifetime,
+            },
+        ),
+        ConfigCommand::SetOrderMinimums {
+            min_sol_order_size,
+            min_notional,
+            max_price_age,
+        } => update(
+            ctx,
+            instruction::SetOrderMinimums {
+                min_sol_order_size,
+                min_notional,
+                max_price_age,
+            },
+        ),
+        ConfigCommand::SetPrice { mint, price } => set_price(ctx, mint, price),
+    }
+}
+
+/// Sends an `UpdateConfig` instruction signed by the keypair.
+fn update(ctx: &Context, args: impl InstructionData) -> Result<Value> {
+    let config = ctx.global_config()?;
+    let instruction = ctx.instruction(
+        accounts::UpdateConfig {
+            global_config: pda::global_config(&ctx.program_id),
+            roles: ctx.roles(&config),
+            authority: ctx.signer_key()?,
+        },
+        args,
+    );
+    Ok(json!({ "transaction": ctx.submit(&[instruction])? }))
+}
+
+fn set_price(ctx: &Context, mint: Pubkey, price: u64) -> Result<Value> {
+    let config = ctx.global_config()?;
+    let price_feed = pda::price_feed(&ctx.program_id, &mint);
+    let instruction = ctx.instruction(
+        accounts::SetPrice {
+            price_feed,
+            global_config: pda::global_config(&ctx.program_id),
+            roles: ctx.roles(&config),
+            authority: ctx.signer_key()?,
+            system_program: system_program::ID,
+        },
+        instruction::SetPrice { mint, price },
+    );
+    Ok(json!({
+        "price_feed": price_feed.to_string(),
+        "transaction": ctx.submit(&[instruction])?,
+    }))
+}
+
+fn config_json(address: &Pubkey, config: &GlobalConfig) -> Value {
+    json!({
+        "address": address.to_string(),
+        "owner": config.owner.to_string(),
+        "treasury": config.treasury.to_string(),
+        "platform_fee": config.platform_fee,
+        "paused": config.paused,
+        "chain_id": config.chain_id,
+        "roles_enabled": config.roles_enabled,
+        "attestation_required": config.attestation_required,
+        "guardian_set_index": config.guardian_set_index,
+        "mint_allowlist_mode": config.mint_allowlist_mode,
+        "cancel_grace_period": config.cancel_grace_period,
+        "bond_multiple": config.bond_multiple,
+        "unbond_cooldown": config.unbond_cooldown,
+        "slash_window": config.slash_window,
+        "swap_adapter": config.swap_adapter.to_string(),
+        "swap_fee_on_output": config.swap_fee_on_output,
+        "min_order_lifetime": config.min_order_lifetime,
+        "max_order_lifetime": config.max_order_lifetime,
+        "min_sol_order_size": config.min_sol_order_size,
+        "min_notional": config.min_notional,
+        "max_price_age": config.max_price_age,
+        "round_fees_up": config.round_fees_up,
+        "version": config.version,
+    })
+}
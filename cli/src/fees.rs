@@ -0,0 +1,123 @@
+//! `fees` subcommands.
+//!
+//! Executions pay fees straight into the treasury wallet (SOL) or its
+//! associated token accounts (SPL and WSOL), so withdrawing is a transfer
+//! signed by the treasury keypair.
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::program_pack::Pack;
+use anchor_spl::associated_token::{get_associated_token_address, spl_associated_token_account};
+use anchor_spl::token::spl_token;
+use anyhow::{anyhow, bail, Result};
+use clap::Subcommand;
+use serde_json::{json, Value};
+use solana_system_interface::instruction as system_instruction;
+
+use crate::Context;
+
+#[derive(Subcommand)]
+pub enum FeesCommand {
+    /// Move collected fees out of the treasury; the keypair must be the
+    /// treasury wallet.
+    Withdraw {
+        /// Wallet receiving the fees; token fees go to its associated token
+        /// account, which is created if missing.
+        #[arg(long)]
+        to: Pubkey,
+        /// Withdraw token fees of this mint instead of SOL.
+        #[arg(long)]
+        mint: Option<Pubkey>,
+        /// Amount in base units; defaults to everything withdrawable.
+        #[arg(long)]
+        amount: Option<u64>,
+    },
+}
+
+pub fn run(ctx: &Context, command: FeesCommand) -> Result<Value> {
+    match command {
+        FeesCommand::Withdraw { to, mint, amount } => {
+            let treasury = ctx.global_config()?.treasury;
+            if ctx.signer_key()? != treasury {
+                bail!("the keypair is not the treasury {treasury}");
+            }
+            match mint {
+                None => withdraw_sol(ctx, treasury, to, amount),
+                Some(mint) => withdraw_token(ctx, treasury, to, mint, amount),
+            }
+        }
+    }
+}
+
+fn withdraw_sol(ctx: &Context, treasury: Pubkey, to: Pubkey, amount: Option<u64>) -> Result<Value> {
+    let balance = ctx
+        .rpc
+        .get_account(&treasury)?
+        .map_or(0, |account| account.lamports);
+    let amount = match amount {
+        Some(amount) => amount,
+        // Everything except the fee of the withdrawal itself.
+        None => {
+            let probe =
+                ctx.transaction(&[system_instruction::transfer(&treasury, &to, balance)])?;
+            balance.saturating_sub(ctx.rpc.get_fee_for_message(&probe)?)
+        }
+    };
+    if amount == 0 {
+        bail!("the treasury holds no withdrawable SOL");
+    }
+    let transfer = system_instruction::transfer(&treasury, &to, amount);
+    Ok(json!({
+        "amount": amount,
+        "to": to.to_string(),
+        "transaction": ctx.submit(&[transfer])?,
+    }))
+}
+
+fn withdraw_token(
+    ctx: &Context,
+    treasury: Pubkey,
+    to: Pubkey,
+    mint: Pubkey,
+    amount: Option<u64>,
+) -> Result<Value> {
+    let source = get_associated_token_address(&treasury, &mint);
+    let source_account = ctx
+        .rpc
+        .get_account(&source)?
+        .ok_or_else(|| anyhow!("the treasury has no token account for {mint}"))?;
+    let balance = spl_token::state::Account::unpack(&source_account.data)?.amount;
+    let mint_account = ctx
+        .rpc
+        .get_account(&mint)?
+        .ok_or_else(|| anyhow!("mint {mint} does not exist"))?;
+    let decimals = spl_token::state::Mint::unpack(&mint_account.data)?.decimals;
+
+    let amount = amount.unwrap_or(balance);
+    if amount == 0 {
+        bail!("the treasury holds no {mint} fees");
+    }
+    let destination = get_associated_token_address(&to, &mint);
+    let instructions = [
+        spl_associated_token_account::instruction::create_associated_token_account_idempotent(
+            &treasury,
+            &to,
+            &mint,
+            &spl_token::ID,
+        ),
+        spl_token::instruction::transfer_checked(
+            &spl_token::ID,
+            &source,
+            &mint,
+            &destination,
+            &treasury,
+            &[],
+            amount,
+            decimals,
+        )?,
+    ];
+    Ok(json!({
+        "amount": amount,
+        "to": destination.to_string(),
+        "transaction": ctx.submit(&instructions)?,
+    }))
+}
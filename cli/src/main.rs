@@ -0,0 +1,363 @@
+//! Admin and user CLI for the limit order program.
+//!
+//! Defaults to a local validator and the Solana CLI keypair:
+//!
+//! ```text
+//! limit-order-cli init --platform-fee 30 --treasury <TREASURY> --chain-id 10002
+//! limit-order-cli order open-sol --amount 1000000000 --to-chain 2 \
+//!     --to-token 0x<TOKEN> --recipient 0x<RECIPIENT>
+//! limit-order-cli --dry-run order execute <ORDER> --target <WALLET>
+//! limit-order-cli -o json order list --sender <SENDER>
+//! ```
+
+mod config;
+mod fees;
+mod order;
+mod pda;
+mod rpc;
+
+use std::cell::OnceCell;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::{AccountDeserialize, InstructionData, ToAccountMetas};
+use anyhow::{anyhow, bail, Context as _, Result};
+use clap::{Parser, Subcommand, ValueEnum};
+use limit_order::GlobalConfig;
+use serde_json::{json, Value};
+use solana_keypair::{read_keypair_file, Keypair};
+use solana_signer::Signer;
+use solana_transaction::Transaction;
+
+use crate::rpc::RpcClient;
+
+#[derive(Parser)]
+#[command(name = "limit-order-cli", version, about)]
+struct Cli {
+    /// RPC URL, or one of `localhost`, `devnet` and `mainnet-beta`.
+    #[arg(short, long, global = true, default_value = "localhost")]
+    url: String,
+
+    /// Keypair file that signs and pays for transactions.
+    #[arg(short, long, global = true, default_value = "~/.config/solana/id.json")]
+    keypair: String,
+
+    /// Deployment of the limit order program to talk to.
+    #[arg(long, global = true, default_value_t = limit_order::ID)]
+    program_id: Pubkey,
+
+    /// Print results as text or as JSON.
+    #[arg(short, long, global = true, value_enum, default_value_t = Output::Text)]
+    output: Output,
+
+    /// Simulate transactions and print their logs instead of sending them.
+    #[arg(long, global = true)]
+    dry_run: bool,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Output {
+    Text,
+    Json,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Create the global config with the signer as owner.
+    Init {
+        /// Platform fee in basis points.
+        #[arg(long)]
+        platform_fee: u16,
+        #[arg(long)]
+        treasury: Pubkey,
+        /// Chain id of this deployment.
+        #[arg(long)]
+        chain_id: u64,
+    },
+    /// Show and update the global config.
+    #[command(subcommand)]
+    Config(config::ConfigCommand),
+    /// Open, inspect, cancel and execute orders.
+    #[command(subcommand)]
+    Order(order::OrderCommand),
+    /// Withdraw collected platform fees.
+    #[command(subcommand)]
+    Fees(fees::FeesCommand),
+}
+
+/// Connection, signer and program shared by every command.
+pub struct Context {
+    pub rpc: RpcClient,
+    pub program_id: Pubkey,
+    keypair_path: PathBuf,
+    keypair: OnceCell<Keypair>,
+    dry_run: bool,
+}
+
+impl Context {
+    /// The keypair is only read once a command needs to sign, so read-only
+    /// commands work without one.
+    pub fn signer(&self) -> Result<&Keypair> {
+        if let Some(keypair) = self.keypair.get() {
+            return Ok(keypair);
+        }
+        let keypair = read_keypair_file(&self.keypair_path).map_err(|err| {
+            anyhow!(
+                "failed to read keypair {}: {err}",
+                self.keypair_path.display()
+            )
+        })?;
+        Ok(self.keypair.get_or_init(|| keypair))
+    }
+
+    pub fn signer_key(&self) -> Result<Pubkey> {
+        Ok(self.signer()?.pubkey())
+    }
+
+    pub fn try_fetch<T: AccountDeserialize>(&self, address: &Pubkey) -> Result<Option<T>> {
+        let Some(account) = self.rpc.get_account(address)? else {
+            return Ok(None);
+        };
+        let value = T::try_deserialize(&mut account.data.as_slice())
+            .with_context(|| format!("failed to decode account {address}"))?;
+        Ok(Some(value))
+    }
+
+    pub fn fetch<T: AccountDeserialize>(&self, address: &Pubkey) -> Result<T> {
+        self.try_fetch(address)?
+            .ok_or_else(|| anyhow!("account {address} does not exist"))
+    }
+
+    pub fn exists(&self, address: &Pubkey) -> Result<bool> {
+        Ok(self.rpc.get_account(address)?.is_some())
+    }
+
+    pub fn global_config(&self) -> Result<GlobalConfig> {
+        self.try_fetch(&pda::global_config(&self.program_id))?
+            .ok_or_else(|| anyhow!("the program has not been initialized"))
+    }
+
+    /// The roles account to pass to role-gated instructions, once enabled.
+    pub fn roles(&self, config: &GlobalConfig) -> Option<Pubkey> {
+        config.roles_enabled.then(|| pda::roles(&self.program_id))
+    }
+
+    pub fn instruction(
+        &self,
+        accounts: impl ToAccountMetas,
+        args: impl InstructionData,
+    ) -> Instruction {
+        Instruction {
+            program_id: self.program_id,
+            accounts: accounts.to_account_metas(None),
+            data: args.data(),
+        }
+    }
+
+    pub fn transaction(&self, instructions: &[Instruction]) -> Result<Transaction> {
+        let signer = self.signer()?;
+        let blockhash = self.rpc.get_latest_blockhash()?;
+        Ok(Transaction::new_signed_with_payer(
+            instructions,
+            Some(&signer.pubkey()),
+            &[signer],
+            blockhash,
+        ))
+    }
+
+    /// Sends `instructions` in one transaction signed by the keypair, or only
+    /// simulates them with `--dry-run`.
+    pub fn submit(&self, instructions: &[Instruction]) -> Result<Value> {
+        let transaction = self.transaction(instructions)?;
+        if !self.dry_run {
+            let signature = self.rpc.send_and_confirm_transaction(&transaction)?;
+            return Ok(json!({ "signature": signature }));
+        }
+        let simulation = self.rpc.simulate_transaction(&transaction)?;
+        Ok(json!({
+            "simulated": true,
+            "error": simulation.err,
+            "units_consumed": simulation.units_consumed,
+            "logs": simulation.logs,
+        }))
+    }
+}
+
+fn rpc_url(url: &str) -> String {
+    match url {
+        "localhost" | "l" => "http://127.0.0.1:8899",
+        "devnet" | "d" => "https://api.devnet.solana.com",
+        "mainnet-beta" | "m" => "https://api.mainnet-beta.solana.com",
+        url => url,
+    }
+    .to_string()
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), std::env::var_os("HOME")) {
+        (Some(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+pub fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs() as i64)
+}
+
+fn decode_hex(hex: &str) -> Result<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        bail!("hex string {hex:?} has an odd length");
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|index| {
+            u8::from_str_radix(&hex[index..index + 2], 16)
+                .map_err(|_| anyhow!("invalid hex string {hex:?}"))
+        })
+        .collect()
+}
+
+pub fn hex(bytes: &[u8]) -> String {
+    let digits: String = bytes.iter().map(|byte| format!("{byte:02x}")).collect();
+    format!("0x{digits}")
+}
+
+/// Parses a foreign-chain address or token: `0x`-prefixed hex of up to 32
+/// bytes, left-padded like an EVM address, or a base58 Solana address.
+pub fn parse_bytes32(value: &str) -> Result<[u8; 32]> {
+    let Some(hex) = value.strip_prefix("0x") else {
+        return Pubkey::from_str(value)
+            .map(Pubkey::to_bytes)
+            .map_err(|_| anyhow!("{value:?} is neither 0x-hex nor a base58 address"));
+    };
+    let bytes = decode_hex(hex)?;
+    if bytes.len() > 32 {
+        bail!("{value:?} is longer than 32 bytes");
+    }
+    let mut padded = [0; 32];
+    padded[32 - bytes.len()..].copy_from_slice(&bytes);
+    Ok(padded)
+}
+
+/// Parses a big-endian 256-bit amount, given in decimal or `0x`-hex.
+pub fn parse_u256(value: &str) -> Result<[u8; 32]> {
+    if value.starts_with("0x") {
+        return parse_bytes32(value);
+    }
+    let amount: u128 = value
+        .parse()
+        .map_err(|_| anyhow!("{value:?} is not a decimal amount"))?;
+    let mut bytes = [0; 32];
+    bytes[16..].copy_from_slice(&amount.to_be_bytes());
+    Ok(bytes)
+}
+
+fn print(value: &Value, output: Output) {
+    match output {
+        Output::Json => println!(
+            "{}",
+            serde_json::to_string_pretty(value).unwrap_or_default()
+        ),
+        Output::Text => print_text(value, 0),
+    }
+}
+
+fn print_text(value: &Value, indent: usize) {
+    let pad = " ".repeat(indent);
+    match value {
+        Value::Object(fields) => {
+            for (key, value) in fields {
+                match value {
+                    Value::Object(_) | Value::Array(_) => {
+                        println!("{pad}{key}:");
+                        print_text(value, indent + 2);
+                    }
+                    value => println!("{pad}{key}: {}", scalar(value)),
+                }
+            }
+        }
+        Value::Array(items) => {
+            for (index, item) in items.iter().enumerate() {
+                if item.is_object() {
+                    if index > 0 {
+                        println!();
+                    }
+                    print_text(item, indent);
+                } else {
+                    println!("{pad}{}", scalar(item));
+                }
+            }
+        }
+        value => println!("{pad}{}", scalar(value)),
+    }
+}
+
+fn scalar(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        value => value.to_string(),
+    }
+}
+
+fn init(ctx: &Context, platform_fee: u16, treasury: Pubkey, chain_id: u64) -> Result<Value> {
+    let global_config = pda::global_config(&ctx.program_id);
+    let instruction = ctx.instruction(
+        limit_order::accounts::Initialize {
+            global_config,
+            signer: ctx.signer_key()?,
+            system_program: anchor_lang::system_program::ID,
+        },
+        limit_order::instruction::Initialize {
+            platform_fee,
+            treasury,
+            chain_id,
+        },
+    );
+    Ok(json!({
+        "global_config": global_config.to_string(),
+        "transaction": ctx.submit(&[instruction])?,
+    }))
+}
+
+fn run(cli: Cli) -> Result<Value> {
+    let ctx = Context {
+        rpc: RpcClient::new(rpc_url(&cli.url)),
+        program_id: cli.program_id,
+        keypair_path: expand_home(&cli.keypair),
+        keypair: OnceCell::new(),
+        dry_run: cli.dry_run,
+    };
+    match cli.command {
+        Command::Init {
+            platform_fee,
+            treasury,
+            chain_id,
+        } => init(&ctx, platform_fee, treasury, chain_id),
+        Command::Config(command) => config::run(&ctx, command),
+        Command::Order(command) => order::run(&ctx, command),
+        Command::Fees(command) => fees::run(&ctx, command),
+    }
+}
+
+fn main() {
+    let cli = Cli::parse();
+    let output = cli.output;
+    match run(cli) {
+        Ok(value) => print(&value, output),
+        Err(err) => {
+            match output {
+                Output::Json => eprintln!("{}", json!({ "error": format!("{err:#}") })),
+                Output::Text => eprintln!("error: {err:#}"),
+            }
+            std::process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,558 @@
+//! `order` subcommands.
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::{system_program, Discriminator};
+use anchor_spl::associated_token::{get_associated_token_address, spl_associated_token_account};
+use anchor_spl::token::spl_token;
+use anyhow::{bail, Result};
+use clap::{Args, Subcommand};
+use limit_order::{accounts, instruction, GlobalConfig, LimitOrder, OpenOrderParams};
+use serde_json::{json, Value};
+
+use crate::{hex, now, parse_bytes32, parse_u256, pda, Context};
+
+/// Offset of `LimitOrder::sender` in the account data.
+const SENDER_OFFSET: usize = 8 + 32 + 8 + 8 + 8 + 32 + 32;
+
+#[derive(Subcommand)]
+pub enum OrderCommand {
+    /// Open an order escrowing native SOL.
+    OpenSol(OrderArgs),
+    /// Open an order escrowing an SPL token from the signer's associated
+    /// token account.
+    OpenSpl {
+        #[arg(long)]
+        mint: Pubkey,
+        #[command(flatten)]
+        order: OrderArgs,
+    },
+    /// Print an order.
+    Show { order: Pubkey },
+    /// List open orders, optionally only those of one sender.
+    List {
+        #[arg(long)]
+        sender: Option<Pubkey>,
+    },
+    /// Cancel an order and refund its refund address.
+    Cancel { order: Pubkey },
+    /// Execute an order, paying `target` and the treasury fee.
+    Execute {
+        order: Pubkey,
+        /// Wallet receiving the proceeds; SPL proceeds go to its associated
+        /// token account, which is created if missing.
+        #[arg(long)]
+        target: Pubkey,
+        #[arg(long, default_value_t = 0)]
+        native_token_volume: u64,
+    },
+    /// Take a same-chain order, paying its recipient from the signer's
+    /// associated token account of the output mint.
+    Take {
+        order: Pubkey,
+        /// Escrow to take, in base units; defaults to all that is left.
+        #[arg(long)]
+        amount: Option<u64>,
+    },
+}
+
+#[derive(Args)]
+pub struct OrderArgs {
+    /// Amount to escrow, in base units.
+    #[arg(long)]
+    amount: u64,
+    /// Destination chain id.
+    #[arg(long)]
+    to_chain: u64,
+    /// Token on the destination chain, as 0x-hex or a base58 address.
+    #[arg(long, value_parser = parse_bytes32)]
+    to_token: [u8; 32],
+    /// Recipient on the destination chain, as 0x-hex or a base58 address.
+    #[arg(long, value_parser = parse_bytes32)]
+    recipient: [u8; 32],
+    /// Minimum amount to deliver, as a big-endian uint256 in decimal or 0x-hex.
+    #[arg(long, value_parser = parse_u256, default_value = "0")]
+    amount_out: [u8; 32],
+    /// Unix timestamp the order expires at.
+    #[arg(long, conflicts_with = "ttl")]
+    expiry: Option<i64>,
+    /// Seconds from now the order expires in.
+    #[arg(long, default_value_t = 3600)]
+    ttl: i64,
+    /// Wallet that cancels and reclaims refund to; defaults to the signer.
+    #[arg(long)]
+    refund_address: Option<Pubkey>,
+    /// Keep the order executable past its expiry until it is cancelled.
+    #[arg(long)]
+    gtc: bool,
+}
+
+/// Where an order's funds are escrowed.
+#[derive(Clone, Copy)]
+enum Escrow {
+    Sol,
+    Spl,
+    Wsol,
+}
+
+impl Escrow {
+    fn of(order: &LimitOrder) -> Self {
+        match (order.uses_token_escrow(), order.wrapped_sol) {
+            (false, _) => Escrow::Sol,
+            (true, false) => Escrow::Spl,
+            (true, true) => Escrow::Wsol,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Escrow::Sol => "sol",
+            Escrow::Spl => "spl",
+            Escrow::Wsol => "wsol",
+        }
+    }
+}
+
+pub fn run(ctx: &Context, command: OrderCommand) -> Result<Value> {
+    match command {
+        OrderCommand::OpenSol(args) => open(ctx, spl_token::native_mint::ID, args),
+        OrderCommand::OpenSpl { mint, order } => open(ctx, mint, order),
+        OrderCommand::Show { order } => {
+            let limit_order: LimitOrder = ctx.fetch(&order)?;
+            Ok(order_json(&order, &limit_order))
+        }
+        OrderCommand::List { sender } => list(ctx, sender),
+        OrderCommand::Cancel { order } => cancel(ctx, order),
+        OrderCommand::Execute {
+            order,
+            target,
+            native_token_volume,
+        } => execute(ctx, order, target, native_token_volume),
+        OrderCommand::Take { order, amount } => take(ctx, order, amount),
+    }
+}
+
+/// The order book of the order's market, if one has been created.
+fn listed_order_book(
+    ctx: &Context,
+    from_token: &Pubkey,
+    to_chain_id: u64,
+) -> Result<Option<Pubkey>> {
+    let order_book = pda::order_book(&ctx.program_id, from_token, to_chain_id);
+    Ok(ctx.exists(&order_book)?.then_some(order_book))
+}
+
+fn order_book_of(ctx: &Context, order: &LimitOrder) -> Option<Pubkey> {
+    order
+        .in_order_book
+        .then(|| pda::order_book(&ctx.program_id, &order.from_token, order.to_chain_id))
+}
+
+fn open(ctx: &Context, from_token: Pubkey, args: OrderArgs) -> Result<Value> {
+    let config = ctx.global_config()?;
+    let user = ctx.signer_key()?;
+    let params = OpenOrderParams {
+        from_token,
+        from_chain_id: config.chain_id,
+        amount_in: args.amount,
+        to_chain_id: args.to_chain,
+        to_token: args.to_token,
+        recipient: args.recipient,
+        expiry: args.expiry.unwrap_or_else(|| now() + args.ttl),
+        amount_out: args.amount_out,
+        hashlock: [0; 32],
+        timelock: 0,
+        htlc_solver: Pubkey::default(),
+        exclusive_executor: Pubkey::default(),
+        exclusivity_deadline: 0,
+        refund_address: args.refund_address.unwrap_or_default(),
+        good_till_cancelled: args.gtc,
+    };
+    let order = pda::order(&ctx.program_id, &user, params.expiry);
+    let global_config = pda::global_config(&ctx.program_id);
+    let chain_config = pda::chain_config(&ctx.program_id, params.to_chain_id);
+    let token_route = pda::token_route(
+        &ctx.program_id,
+        &from_token,
+        params.to_chain_id,
+        &params.to_token,
+    );
+    let order_book = listed_order_book(ctx, &from_token, params.to_chain_id)?;
+
+    let (instruction, escrow) = if from_token == spl_token::native_mint::ID {
+        let accounts = accounts::OpenOrderSol {
+            order,
+            user,
+            payer: user,
+            global_config,
+            chain_config,
+            token_route,
+            price_feed: pda::price_feed(&ctx.program_id, &from_token),
+            system_program: system_program::ID,
+            instructions: None,
+            order_book,
+            session_key: None,
+        };
+        (
+            ctx.instruction(accounts, instruction::OpenOrderSol { params }),
+            order,
+        )
+    } else {
+        let order_token_account = get_associated_token_address(&order, &from_token);
+        let accounts = accounts::OpenOrderSpl {
+            order,
+            user,
+            payer: user,
+            user_token_account: get_associated_token_address(&user, &from_token),
+            order_token_account,
+            token_mint: from_token,
+            global_config,
+            chain_config,
+            token_route,
+            mint_policy: pda::mint_policy(&ctx.program_id, &from_token),
+            price_feed: pda::price_feed(&ctx.program_id, &from_token),
+            token_program: spl_token::ID,
+            system_program: system_program::ID,
+            associated_token_program: spl_associated_token_account::ID,
+            instructions: None,
+            order_book,
+            session_key: None,
+        };
+        (
+            ctx.instruction(accounts, instruction::OpenOrderSpl { params }),
+            order_token_account,
+        )
+    };
+    Ok(json!({
+        "order": order.to_string(),
+        "escrow": escrow.to_string(),
+        "transaction": ctx.submit(&[instruction])?,
+    }))
+}
+
+fn list(ctx: &Context, sender: Option<Pubkey>) -> Result<Value> {
+    let mut filters: Vec<(usize, &[u8])> = vec![(0, LimitOrder::DISCRIMINATOR)];
+    if let Some(sender) = &sender {
+        filters.push((SENDER_OFFSET, sender.as_ref()));
+    }
+    let mut orders = Vec::new();
+    // Orders opened before the last layout change don't decode until migrated.
+    let mut unmigrated = 0;
+    for (address, account) in ctx.rpc.get_program_accounts(&ctx.program_id, &filters)? {
+        match anchor_lang::AccountDeserialize::try_deserialize(&mut account.data.as_slice()) {
+            Ok(order) => orders.push((address, order)),
+            Err(_) => unmigrated += 1,
+        }
+    }
+    orders.sort_by_key(|(_, order): &(Pubkey, LimitOrder)| order.live_until());
+    Ok(json!({
+        "orders": orders
+            .iter()
+            .map(|(address, order)| order_json(address, order))
+            .collect::<Vec<_>>(),
+        "unmigrated": unmigrated,
+    }))
+}
+
+fn cancel(ctx: &Context, order: Pubkey) -> Result<Value> {
+    let limit_order: LimitOrder = ctx.fetch(&order)?;
+    if limit_order.is_htlc() {
+        bail!("HTLC orders are claimed or refunded, not cancelled");
+    }
+    let user = ctx.signer_key()?;
+    let global_config = pda::global_config(&ctx.program_id);
+    // Admins may cancel any order, which needs the roles account.
+    let roles = ctx.roles(&ctx.global_config()?);
+    let order_book = order_book_of(ctx, &limit_order);
+    let order_token_account = get_associated_token_address(&order, &limit_order.from_token);
+    let refund_receiver = limit_order.refund_to();
+
+    let instruction = match Escrow::of(&limit_order) {
+        Escrow::Sol => ctx.instruction(
+            accounts::CancelOrderSol {
+                order,
+                user,
+                refund_receiver,
+                global_config,
+                system_program: system_program::ID,
+                order_book,
+                session_key: None,
+                roles,
+            },
+            instruction::CancelOrderSol {},
+        ),
+        Escrow::Spl => ctx.instruction(
+            accounts::CancelOrderSpl {
+                order,
+                user,
+                user_token_account: get_associated_token_address(
+                    &refund_receiver,
+                    &limit_order.from_token,
+                ),
+                order_token_account,
+                refund_receiver,
+                global_config,
+                token_program: spl_token::ID,
+                order_book,
+                session_key: None,
+                roles,
+            },
+            instruction::CancelOrderSpl {},
+        ),
+        Escrow::Wsol => ctx.instruction(
+            accounts::CancelOrderWsol {
+                order,
+                user,
+                order_token_account,
+                refund_receiver,
+                global_config,
+                token_program: spl_token::ID,
+                order_book,
+                session_key: None,
+                roles,
+            },
+            instruction::CancelOrderWsol {},
+        ),
+    };
+    Ok(json!({
+        "order": order.to_string(),
+        "transaction": ctx.submit(&[instruction])?,
+    }))
+}
+
+/// Creates `wallet`'s associated token account for `mint` if it is missing.
+fn ensure_token_account(
+    ctx: &Context,
+    instructions: &mut Vec<Instruction>,
+    wallet: &Pubkey,
+    mint: &Pubkey,
+) -> Result<Pubkey> {
+    let address = get_associated_token_address(wallet, mint);
+    if !ctx.exists(&address)? {
+        instructions.push(
+            spl_associated_token_account::instruction::create_associated_token_account_idempotent(
+                &ctx.signer_key()?,
+                wallet,
+                mint,
+                &spl_token::ID,
+            ),
+        );
+    }
+    Ok(address)
+}
+
+fn execute(
+    ctx: &Context,
+    order: Pubkey,
+    target: Pubkey,
+    native_token_volume: u64,
+) -> Result<Value> {
+    let config: GlobalConfig = ctx.global_config()?;
+    if config.attestation_required {
+        bail!("executions need guardian attestations, which this CLI does not collect");
+    }
+    let limit_order: LimitOrder = ctx.fetch(&order)?;
+    if limit_order.is_htlc() {
+        bail!("HTLC orders are claimed with their preimage, not executed");
+    }
+    if limit_order.is_local() {
+        bail!("same-chain orders are filled with `order take`");
+    }
+    let executor = ctx.signer_key()?;
+    let global_config = pda::global_config(&ctx.program_id);
+    let chain_config = pda::chain_config(&ctx.program_id, limit_order.to_chain_id);
+    let order_book = order_book_of(ctx, &limit_order);
+    let roles = ctx.roles(&config);
+    let order_token_account = get_associated_token_address(&order, &limit_order.from_token);
+    let refund_receiver = limit_order.refund_to();
+
+    let mut instructions = Vec::new();
+    let execute = match Escrow::of(&limit_order) {
+        Escrow::Sol => ctx.instruction(
+            accounts::ExecuteOrderSol {
+                order,
+                target_sol: target,
+                treasury: config.treasury,
+                executor,
+                global_config,
+                chain_config,
+                refund_receiver,
+                guardian_set: None,
+                instructions: None,
+                executor_bond: None,
+                order_book,
+                roles,
+            },
+            instruction::ExecuteOrderSol {
+                native_token_volume,
+            },
+        ),
+        Escrow::Spl => {
+            let mint = limit_order.from_token;
+            let target_token_account =
+                ensure_token_account(ctx, &mut instructions, &target, &mint)?;
+            let treasury_token_account =
+                ensure_token_account(ctx, &mut instructions, &config.treasury, &mint)?;
+            ctx.instruction(
+                accounts::ExecuteOrderSpl {
+                    order,
+                    order_token_account,
+                    target_token_account,
+                    treasury_token_account,
+                    global_config,
+                    chain_config,
+                    refund_receiver,
+                    executor,
+                    token_program: spl_token::ID,
+                    guardian_set: None,
+                    instructions: None,
+                    executor_bond: None,
+                    order_book,
+                    roles,
+                },
+                instruction::ExecuteOrderSpl {
+                    native_token_volume,
+                },
+            )
+        }
+        Escrow::Wsol => {
+            let treasury_token_account = ensure_token_account(
+                ctx,
+                &mut instructions,
+                &config.treasury,
+                &spl_token::native_mint::ID,
+            )?;
+            ctx.instruction(
+                accounts::ExecuteOrderWsol {
+                    order,
+                    order_token_account,
+                    target_sol: target,
+                    treasury_token_account,
+                    global_config,
+                    chain_config,
+                    refund_receiver,
+                    executor,
+                    token_program: spl_token::ID,
+                    guardian_set: None,
+                    instructions: None,
+                    executor_bond: None,
+                    order_book,
+                    roles,
+                },
+                instruction::ExecuteOrderWsol {
+                    native_token_volume,
+                },
+            )
+        }
+    };
+    instructions.push(execute);
+    Ok(json!({
+        "order": order.to_string(),
+        "transaction": ctx.submit(&instructions)?,
+    }))
+}
+
+fn take(ctx: &Context, order: Pubkey, amount: Option<u64>) -> Result<Value> {
+    let config: GlobalConfig = ctx.global_config()?;
+    let limit_order: LimitOrder = ctx.fetch(&order)?;
+    if !limit_order.is_local() {
+        bail!("only same-chain orders can be taken");
+    }
+    let taker = ctx.signer_key()?;
+    let from_token = limit_order.from_token;
+    let output_mint = Pubkey::new_from_array(limit_order.to_token);
+    let recipient = Pubkey::new_from_array(limit_order.recipient);
+    let token_escrow = limit_order.uses_token_escrow();
+
+    let mut instructions = Vec::new();
+    let (taker_token_account, treasury_token_account) = if token_escrow {
+        (
+            Some(ensure_token_account(
+                ctx,
+                &mut instructions,
+                &taker,
+                &from_token,
+            )?),
+            Some(ensure_token_account(
+                ctx,
+                &mut instructions,
+                &config.treasury,
+                &from_token,
+            )?),
+        )
+    } else {
+        (None, None)
+    };
+    let take = ctx.instruction(
+        accounts::FillLocalOrder {
+            order,
+            order_token_account: token_escrow
+                .then(|| get_associated_token_address(&order, &from_token)),
+            taker_token_account,
+            output_mint,
+            taker_output_account: get_associated_token_address(&taker, &output_mint),
+            recipient,
+            recipient_token_account: get_associated_token_address(&recipient, &output_mint),
+            treasury: config.treasury,
+            treasury_token_account,
+            global_config: pda::global_config(&ctx.program_id),
+            chain_config: pda::chain_config(&ctx.program_id, limit_order.to_chain_id),
+            refund_receiver: limit_order.refund_to(),
+            taker,
+            token_program: spl_token::ID,
+            associated_token_program: spl_associated_token_account::ID,
+            system_program: system_program::ID,
+            order_book: order_book_of(ctx, &limit_order),
+            roles: None,
+        },
+        instruction::TakeOrder {
+            amount_in: amount.unwrap_or(limit_order.amount_in),
+        },
+    );
+    instructions.push(take);
+    Ok(json!({
+        "order": order.to_string(),
+        "transaction": ctx.submit(&instructions)?,
+    }))
+}
+
+fn order_json(address: &Pubkey, order: &LimitOrder) -> Value {
+    let escrow = Escrow::of(order);
+    let mut value = json!({
+        "address": address.to_string(),
+        "escrow": escrow.name(),
+        "status": if order.is_live(now()) { "open" } else { "expired" },
+        "sender": order.sender.to_string(),
+        "refund_address": order.refund_to().to_string(),
+        "from_token": order.from_token.to_string(),
+        "amount_in": order.amount_in,
+        "from_chain_id": order.from_chain_id,
+        "to_chain_id": order.to_chain_id,
+        "to_token": hex(&order.to_token),
+        "recipient": hex(&order.recipient),
+        "amount_out": hex(&order.amount_out),
+        "expiry": order.expiry,
+        "good_till_cancelled": order.good_till_cancelled,
+        "cancel_requested_at": order.cancel_requested_at,
+        "in_order_book": order.in_order_book,
+    });
+    if !matches!(escrow, Escrow::Sol) {
+        value["escrow_account"] = get_associated_token_address(address, &order.from_token)
+            .to_string()
+            .into();
+    }
+    if order.is_local() {
+        value["to_token"] = Pubkey::new_from_array(order.to_token).to_string().into();
+        value["recipient"] = Pubkey::new_from_array(order.recipient).to_string().into();
+    }
+    if order.is_htlc() {
+        value["hashlock"] = hex(&order.hashlock).into();
+        value["timelock"] = order.timelock.into();
+        value["htlc_solver"] = order.htlc_solver.to_string().into();
+    }
+    if order.exclusive_executor != Pubkey::default() {
+        value["exclusive_executor"] = order.exclusive_executor.to_string().into();
+        value["exclusivity_deadline"] = order.exclusivity_deadline.into();
+    }
+    value
+}
@@ -0,0 +1,61 @@
+//! Addresses derived from the program's seeds.
+
+use anchor_lang::prelude::Pubkey;
+
+pub fn global_config(program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"global-config"], program_id).0
+}
+
+pub fn roles(program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"roles"], program_id).0
+}
+
+pub fn chain_config(program_id: &Pubkey, chain_id: u64) -> Pubkey {
+    Pubkey::find_program_address(&[b"chain-config", &chain_id.to_le_bytes()], program_id).0
+}
+
+pub fn token_route(
+    program_id: &Pubkey,
+    from_token: &Pubkey,
+    to_chain_id: u64,
+    to_token: &[u8; 32],
+) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            b"token-route",
+            from_token.as_ref(),
+            &to_chain_id.to_le_bytes(),
+            to_token,
+        ],
+        program_id,
+    )
+    .0
+}
+
+pub fn mint_policy(program_id: &Pubkey, mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"mint-policy", mint.as_ref()], program_id).0
+}
+
+pub fn price_feed(program_id: &Pubkey, mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"price-feed", mint.as_ref()], program_id).0
+}
+
+pub fn order(program_id: &Pubkey, sender: &Pubkey, expiry: i64) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"limit_order", sender.as_ref(), &expiry.to_le_bytes()],
+        program_id,
+    )
+    .0
+}
+
+pub fn order_book(program_id: &Pubkey, from_token: &Pubkey, to_chain_id: u64) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            b"order-book",
+            from_token.as_ref(),
+            &to_chain_id.to_le_bytes(),
+        ],
+        program_id,
+    )
+    .0
+}
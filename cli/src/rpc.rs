@@ -0,0 +1,228 @@
+//! Minimal JSON-RPC client for the handful of methods the CLI needs.
+
+use std::str::FromStr;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::hash::Hash;
+use anyhow::{anyhow, bail, Context, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde_json::{json, Value};
+use solana_transaction::Transaction;
+
+const COMMITMENT: &str = "confirmed";
+const CONFIRM_TIMEOUT: Duration = Duration::from_secs(60);
+
+pub struct Account {
+    pub lamports: u64,
+    pub owner: Pubkey,
+    pub data: Vec<u8>,
+}
+
+pub struct Simulation {
+    pub err: Value,
+    pub logs: Vec<String>,
+    pub units_consumed: Option<u64>,
+}
+
+pub struct RpcClient {
+    url: String,
+    agent: ureq::Agent,
+}
+
+impl RpcClient {
+    pub fn new(url: String) -> Self {
+        Self {
+            url,
+            agent: ureq::Agent::new(),
+        }
+    }
+
+    fn call(&self, method: &str, params: Value) -> Result<Value> {
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method,
+            "params": params,
+        });
+        let response = self
+            .agent
+            .post(&self.url)
+            .set("Content-Type", "application/json")
+            .send_string(&request.to_string())
+            .with_context(|| format!("{method} request to {} failed", self.url))?;
+        let mut response: Value = serde_json::from_reader(response.into_reader())
+            .with_context(|| format!("{method} returned invalid JSON"))?;
+        if let Some(error) = response.get("error") {
+            bail!(rpc_error(method, error));
+        }
+        Ok(response["result"].take())
+    }
+
+    pub fn get_account(&self, address: &Pubkey) -> Result<Option<Account>> {
+        let result = self.call(
+            "getAccountInfo",
+            json!([address.to_string(), {"encoding": "base64", "commitment": COMMITMENT}]),
+        )?;
+        match &result["value"] {
+            Value::Null => Ok(None),
+            value => parse_account(value).map(Some),
+        }
+    }
+
+    /// Accounts of `program` whose data matches every `(offset, bytes)` filter.
+    pub fn get_program_accounts(
+        &self,
+        program: &Pubkey,
+        filters: &[(usize, &[u8])],
+    ) -> Result<Vec<(Pubkey, Account)>> {
+        let filters: Vec<Value> = filters
+            .iter()
+            .map(|(offset, bytes)| {
+                json!({"memcmp": {"offset": offset, "bytes": BASE64.encode(bytes), "encoding": "base64"}})
+            })
+            .collect();
+        let result = self.call(
+            "getProgramAccounts",
+            json!([program.to_string(), {
+                "encoding": "base64",
+                "commitment": COMMITMENT,
+                "filters": filters,
+            }]),
+        )?;
+        let entries = result
+            .as_array()
+            .ok_or_else(|| anyhow!("getProgramAccounts returned no list"))?;
+        entries
+            .iter()
+            .map(|entry| {
+                let address = parse_pubkey(&entry["pubkey"])?;
+                Ok((address, parse_account(&entry["account"])?))
+            })
+            .collect()
+    }
+
+    pub fn get_latest_blockhash(&self) -> Result<Hash> {
+        let result = self.call("getLatestBlockhash", json!([{"commitment": COMMITMENT}]))?;
+        let blockhash = result["value"]["blockhash"]
+            .as_str()
+            .ok_or_else(|| anyhow!("getLatestBlockhash returned no blockhash"))?;
+        Hash::from_str(blockhash).map_err(|err| anyhow!("invalid blockhash {blockhash}: {err}"))
+    }
+
+    pub fn get_fee_for_message(&self, transaction: &Transaction) -> Result<u64> {
+        let message = bincode::serialize(&transaction.message)?;
+        let result = self.call(
+            "getFeeForMessage",
+            json!([BASE64.encode(message), {"commitment": COMMITMENT}]),
+        )?;
+        result["value"]
+            .as_u64()
+            .ok_or_else(|| anyhow!("getFeeForMessage returned no fee"))
+    }
+
+    pub fn simulate_transaction(&self, transaction: &Transaction) -> Result<Simulation> {
+        let result = self.call(
+            "simulateTransaction",
+            json!([encode_transaction(transaction)?, {
+                "encoding": "base64",
+                "commitment": COMMITMENT,
+                "sigVerify": false,
+                "replaceRecentBlockhash": true,
+            }]),
+        )?;
+        let value = &result["value"];
+        Ok(Simulation {
+            err: value["err"].clone(),
+            logs: string_list(&value["logs"]),
+            units_consumed: value["unitsConsumed"].as_u64(),
+        })
+    }
+
+    /// Sends `transaction` and waits until it is confirmed, returning its
+    /// signature.
+    pub fn send_and_confirm_transaction(&self, transaction: &Transaction) -> Result<String> {
+        let result = self.call(
+            "sendTransaction",
+            json!([encode_transaction(transaction)?, {
+                "encoding": "base64",
+                "preflightCommitment": COMMITMENT,
+            }]),
+        )?;
+        let signature = result
+            .as_str()
+            .ok_or_else(|| anyhow!("sendTransaction returned no signature"))?
+            .to_string();
+
+        let started = Instant::now();
+        while started.elapsed() < CONFIRM_TIMEOUT {
+            let result = self.call(
+                "getSignatureStatuses",
+                json!([[signature], {"searchTransactionHistory": false}]),
+            )?;
+            let status = &result["value"][0];
+            if !status.is_null() {
+                if !status["err"].is_null() {
+                    bail!("transaction {signature} failed: {}", status["err"]);
+                }
+                if matches!(
+                    status["confirmationStatus"].as_str(),
+                    Some("confirmed" | "finalized")
+                ) {
+                    return Ok(signature);
+                }
+            }
+            sleep(Duration::from_millis(500));
+        }
+        bail!("transaction {signature} was not confirmed within {CONFIRM_TIMEOUT:?}")
+    }
+}
+
+fn encode_transaction(transaction: &Transaction) -> Result<String> {
+    Ok(BASE64.encode(bincode::serialize(transaction)?))
+}
+
+fn parse_pubkey(value: &Value) -> Result<Pubkey> {
+    let text = value
+        .as_str()
+        .ok_or_else(|| anyhow!("expected a base58 address, got {value}"))?;
+    Pubkey::from_str(text).map_err(|err| anyhow!("invalid address {text}: {err}"))
+}
+
+fn parse_account(value: &Value) -> Result<Account> {
+    let data = value["data"][0]
+        .as_str()
+        .ok_or_else(|| anyhow!("account data is not base64 encoded"))?;
+    Ok(Account {
+        lamports: value["lamports"]
+            .as_u64()
+            .ok_or_else(|| anyhow!("account has no lamports"))?,
+        owner: parse_pubkey(&value["owner"])?,
+        data: BASE64.decode(data)?,
+    })
+}
+
+fn string_list(value: &Value) -> Vec<String> {
+    value
+        .as_array()
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|item| item.as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Folds the program logs of a failed preflight into the error message.
+fn rpc_error(method: &str, error: &Value) -> String {
+    let message = error["message"].as_str().unwrap_or("unknown error");
+    let mut text = format!("{method}: {message}");
+    for line in string_list(&error["data"]["logs"]) {
+        text.push_str("\n  ");
+        text.push_str(&line);
+    }
+    text
+}
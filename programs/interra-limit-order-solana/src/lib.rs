@@ -1,9 +1,15 @@
 // Cross-chain Limit Order Anchor Contract (Solana version)
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::ed25519_program;
+use anchor_lang::solana_program::hash::hash;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::program::invoke_signed;
+use anchor_lang::solana_program::sysvar::instructions as sysvar_instructions;
 use anchor_lang::system_program;
 use anchor_spl::associated_token::get_associated_token_address;
-use anchor_spl::token::{self, CloseAccount, Mint, Token, TokenAccount, Transfer as TokenTransfer};
-use std::str::FromStr;
+use anchor_spl::token::{
+    self, spl_token, CloseAccount, Mint, SyncNative, Token, TokenAccount, Transfer as TokenTransfer,
+};
 
 // Declare the program ID
 declare_id!("DV7Ni48rt8frfLkpfLHkTuN4i8Zijj7ojM5XaZwetHW6");
@@ -12,63 +18,765 @@ declare_id!("DV7Ni48rt8frfLkpfLHkTuN4i8Zijj7ojM5XaZwetHW6");
 pub mod limit_order {
     use super::*;
 
-    pub fn initialize(ctx: Context<Initialize>, platform_fee: u16, treasury: Pubkey) -> Result<()> {
-        require!(platform_fee < 10000, CustomError::InvalidPlatformFee);
+    pub fn initialize(
+        ctx: Context<Initialize>,
+        platform_fee: u16,
+        treasury: Pubkey,
+        chain_id: u64,
+    ) -> Result<()> {
+        require!(
+            platform_fee <= GlobalConfig::MAX_PLATFORM_FEE,
+            CustomError::InvalidPlatformFee
+        );
+        require!(chain_id != 0, CustomError::InvalidParameter);
 
         let config = &mut ctx.accounts.global_config;
         config.owner = ctx.accounts.signer.key();
         config.platform_fee = platform_fee;
         config.treasury = treasury;
         config.paused = false;
-        config.reserved = [0; 128]; // Initialize reserved space to zero
+        config.guardian_set_index = 0;
+        config.attestation_required = false;
+        config.chain_id = chain_id;
+        config.mint_allowlist_mode = false;
+        config.cancel_grace_period = 0;
+        config.bond_multiple = 0;
+        config.unbond_cooldown = 0;
+        config.slash_window = 0;
+        config.version = GlobalConfig::VERSION;
+        config.roles_enabled = false;
+        config.swap_adapter = Pubkey::default();
+        config.swap_fee_on_output = false;
+        config.min_order_lifetime = 0;
+        config.max_order_lifetime = 0;
+        config.min_sol_order_size = 0;
+        config.min_notional = 0;
+        config.max_price_age = GlobalConfig::DEFAULT_MAX_PRICE_AGE;
+        config.round_fees_up = false;
+        config.reserved = [0; 12]; // Initialize reserved space to zero
         emit!(Initialized {
             owner: config.owner,
             platform_fee: config.platform_fee,
             treasury: config.treasury,
             paused: config.paused,
+            chain_id: config.chain_id,
         });
         Ok(())
     }
 
-    pub fn update_config(
-        ctx: Context<UpdateConfig>,
-        new_owner: Pubkey,
-        new_platform_fee: u16,
-        new_treasury: Pubkey,
-        new_paused: bool,
-    ) -> Result<()> {
-        require!(new_platform_fee < 10000, CustomError::InvalidPlatformFee);
+    pub fn set_platform_fee(ctx: Context<UpdateConfig>, platform_fee: u16) -> Result<()> {
+        require_role(
+            &ctx.accounts.global_config,
+            ctx.accounts.roles.as_ref(),
+            &ctx.accounts.authority.key(),
+            Role::FeeManager,
+        )?;
+        require!(
+            platform_fee <= GlobalConfig::MAX_PLATFORM_FEE,
+            CustomError::InvalidPlatformFee
+        );
 
         let config = &mut ctx.accounts.global_config;
+        let old_platform_fee = config.platform_fee;
+        config.platform_fee = platform_fee;
 
-        config.owner = new_owner;
-        config.platform_fee = new_platform_fee;
-        config.treasury = new_treasury;
-        config.paused = new_paused;
+        emit!(PlatformFeeUpdated {
+            old_platform_fee,
+            new_platform_fee: platform_fee,
+        });
+        Ok(())
+    }
 
-        emit!(ConfigUpdated {
-            owner: config.owner,
-            platform_fee: config.platform_fee,
-            treasury: config.treasury,
-            paused: config.paused,
+    /// Rounds platform fees up instead of down, so small orders can't be
+    /// executed for free.
+    pub fn set_fee_rounding(ctx: Context<UpdateConfig>, round_fees_up: bool) -> Result<()> {
+        require_role(
+            &ctx.accounts.global_config,
+            ctx.accounts.roles.as_ref(),
+            &ctx.accounts.authority.key(),
+            Role::FeeManager,
+        )?;
+        ctx.accounts.global_config.round_fees_up = round_fees_up;
+
+        emit!(FeeRoundingUpdated { round_fees_up });
+        Ok(())
+    }
+
+    /// Pausing stops new orders and executions; cancels and refunds still work.
+    pub fn set_paused(ctx: Context<UpdateConfig>, paused: bool) -> Result<()> {
+        require_role(
+            &ctx.accounts.global_config,
+            ctx.accounts.roles.as_ref(),
+            &ctx.accounts.authority.key(),
+            Role::Pauser,
+        )?;
+
+        let config = &mut ctx.accounts.global_config;
+        let old_paused = config.paused;
+        config.paused = paused;
+
+        emit!(PausedUpdated {
+            old_paused,
+            new_paused: paused,
+        });
+        Ok(())
+    }
+
+    pub fn set_treasury(ctx: Context<UpdateConfig>, treasury: Pubkey) -> Result<()> {
+        run_admin_action(ctx.accounts, AdminAction::SetTreasury { treasury })
+    }
+
+    /// Hands the config, and every admin right with it, over to `new_owner`.
+    /// Once `init_roles` has run, rights move through role grants instead and
+    /// this is rejected.
+    pub fn transfer_ownership(ctx: Context<UpdateConfig>, new_owner: Pubkey) -> Result<()> {
+        run_admin_action(
+            ctx.accounts,
+            AdminAction::TransferOwnership { owner: new_owner },
+        )
+    }
+
+    /// Enables role-based access control. The owner starts out holding every
+    /// role and can grant and revoke them from there.
+    pub fn init_roles(ctx: Context<InitRoles>) -> Result<()> {
+        let owner = ctx.accounts.owner.key();
+        let roles = &mut ctx.accounts.roles;
+        roles.members = vec![RoleMember {
+            key: owner,
+            roles: Role::ALL,
+        }];
+        roles.admin_threshold = 0;
+        roles.proposal_count = 0;
+        roles.bump = ctx.bumps.roles;
+
+        ctx.accounts.global_config.roles_enabled = true;
+
+        emit!(RolesInitialized { admin: owner });
+        Ok(())
+    }
+
+    pub fn grant_role(ctx: Context<UpdateConfig>, key: Pubkey, role: Role) -> Result<()> {
+        run_admin_action(ctx.accounts, AdminAction::GrantRole { key, role })
+    }
+
+    pub fn revoke_role(ctx: Context<UpdateConfig>, key: Pubkey, role: Role) -> Result<()> {
+        run_admin_action(ctx.accounts, AdminAction::RevokeRole { key, role })
+    }
+
+    /// Sets how many Admins must approve an Admin action. Above one, Admin
+    /// actions only go through `propose_admin_action`.
+    pub fn set_admin_threshold(ctx: Context<UpdateConfig>, threshold: u8) -> Result<()> {
+        run_admin_action(ctx.accounts, AdminAction::SetAdminThreshold { threshold })
+    }
+
+    pub fn propose_admin_action(
+        ctx: Context<ProposeAdminAction>,
+        action: AdminAction,
+    ) -> Result<()> {
+        let proposer = ctx.accounts.proposer.key();
+        require_role(
+            &ctx.accounts.global_config,
+            Some(&ctx.accounts.roles),
+            &proposer,
+            Role::Admin,
+        )?;
+
+        let roles = &mut ctx.accounts.roles;
+        let proposal = &mut ctx.accounts.proposal;
+        proposal.id = roles.proposal_count;
+        proposal.proposer = proposer;
+        proposal.action = action;
+        proposal.approvals = vec![proposer];
+        proposal.executed = false;
+        proposal.bump = ctx.bumps.proposal;
+        roles.proposal_count = roles
+            .proposal_count
+            .checked_add(1)
+            .ok_or(CustomError::Overflow)?;
+
+        emit!(AdminActionProposed {
+            proposal: proposal.key(),
+            id: proposal.id,
+            proposer,
         });
+        Ok(())
+    }
+
+    pub fn approve_admin_action(ctx: Context<ApproveAdminAction>) -> Result<()> {
+        let approver = ctx.accounts.approver.key();
+        require_role(
+            &ctx.accounts.global_config,
+            Some(&ctx.accounts.roles),
+            &approver,
+            Role::Admin,
+        )?;
+
+        let proposal = &mut ctx.accounts.proposal;
+        require!(!proposal.executed, CustomError::ProposalExecuted);
+        require!(
+            !proposal.approvals.contains(&approver),
+            CustomError::AlreadyApproved
+        );
+        proposal.approvals.push(approver);
 
+        emit!(AdminActionApproved {
+            proposal: proposal.key(),
+            approver,
+            approvals: proposal.approvals.len() as u8,
+        });
         Ok(())
     }
-    pub fn open_order_sol(ctx: Context<OpenOrderSol>, params: OpenOrderParams) -> Result<()> {
-        if params.from_token != native_token()
-            || params.from_chain_id != 10002
-            || params.amount_in == 0
-            || params.to_chain_id == 0
-            || params.to_token == [0u8; 32]
-            || params.recipient == [0u8; 32]
-        {
-            return Err(error!(CustomError::InvalidParameter));
+
+    /// Applies a proposal once enough of its approvers still hold Admin.
+    pub fn execute_admin_action(ctx: Context<ExecuteAdminAction>) -> Result<()> {
+        require_role(
+            &ctx.accounts.global_config,
+            Some(&ctx.accounts.roles),
+            &ctx.accounts.executor.key(),
+            Role::Admin,
+        )?;
+
+        execute_proposal(&ctx.accounts.roles, &mut ctx.accounts.proposal)?;
+        apply_admin_action(
+            &mut ctx.accounts.global_config,
+            Some(&mut ctx.accounts.roles),
+            ctx.accounts.proposal.action.clone(),
+        )
+    }
+
+    pub fn init_guardian_set(
+        ctx: Context<InitGuardianSet>,
+        keys: Vec<Pubkey>,
+        threshold: u8,
+    ) -> Result<()> {
+        authorize_admin_action(
+            &ctx.accounts.global_config,
+            ctx.accounts.roles.as_ref(),
+            &ctx.accounts.authority.key(),
+            ctx.accounts.proposal.as_mut(),
+            &AdminAction::InitGuardianSet {
+                keys: keys.clone(),
+                threshold,
+            },
+        )?;
+        validate_guardian_keys(&keys, threshold)?;
+
+        let guardian_set = &mut ctx.accounts.guardian_set;
+        guardian_set.index = 0;
+        guardian_set.keys = keys;
+        guardian_set.threshold = threshold;
+        guardian_set.expiration_time = 0;
+        guardian_set.bump = ctx.bumps.guardian_set;
+
+        let config = &mut ctx.accounts.global_config;
+        config.guardian_set_index = 0;
+        config.attestation_required = true;
+
+        emit!(GuardianSetUpdated {
+            index: guardian_set.index,
+            keys: guardian_set.keys.clone(),
+            threshold,
+            previous_set_expiration_time: 0,
+        });
+        Ok(())
+    }
+
+    pub fn rotate_guardian_set(
+        ctx: Context<RotateGuardianSet>,
+        keys: Vec<Pubkey>,
+        threshold: u8,
+        previous_set_ttl: i64,
+    ) -> Result<()> {
+        authorize_admin_action(
+            &ctx.accounts.global_config,
+            ctx.accounts.roles.as_ref(),
+            &ctx.accounts.authority.key(),
+            ctx.accounts.proposal.as_mut(),
+            &AdminAction::RotateGuardianSet {
+                keys: keys.clone(),
+                threshold,
+                previous_set_ttl,
+            },
+        )?;
+        validate_guardian_keys(&keys, threshold)?;
+        require!(previous_set_ttl >= 0, CustomError::InvalidParameter);
+
+        let clock = Clock::get()?;
+        let current_set = &mut ctx.accounts.current_guardian_set;
+        current_set.expiration_time = clock
+            .unix_timestamp
+            .checked_add(previous_set_ttl)
+            .ok_or(CustomError::Overflow)?;
+
+        let new_index = current_set
+            .index
+            .checked_add(1)
+            .ok_or(CustomError::Overflow)?;
+        let new_set = &mut ctx.accounts.new_guardian_set;
+        new_set.index = new_index;
+        new_set.keys = keys;
+        new_set.threshold = threshold;
+        new_set.expiration_time = 0;
+        new_set.bump = ctx.bumps.new_guardian_set;
+
+        ctx.accounts.global_config.guardian_set_index = new_index;
+
+        emit!(GuardianSetUpdated {
+            index: new_index,
+            keys: new_set.keys.clone(),
+            threshold,
+            previous_set_expiration_time: current_set.expiration_time,
+        });
+        Ok(())
+    }
+
+    pub fn set_attestation_required(
+        ctx: Context<UpdateConfig>,
+        attestation_required: bool,
+    ) -> Result<()> {
+        run_admin_action(
+            ctx.accounts,
+            AdminAction::SetAttestationRequired {
+                attestation_required,
+            },
+        )
+    }
+
+    pub fn set_chain_id(ctx: Context<UpdateConfig>, chain_id: u64) -> Result<()> {
+        run_admin_action(ctx.accounts, AdminAction::SetChainId { chain_id })
+    }
+
+    pub fn set_chain_config(
+        ctx: Context<SetChainConfig>,
+        chain_id: u64,
+        params: ChainConfigParams,
+    ) -> Result<()> {
+        authorize_admin_action(
+            &ctx.accounts.global_config,
+            ctx.accounts.roles.as_ref(),
+            &ctx.accounts.authority.key(),
+            ctx.accounts.proposal.as_mut(),
+            &AdminAction::SetChainConfig {
+                chain_id,
+                params: params.clone(),
+            },
+        )?;
+        // A per-chain override is a platform fee change of its own.
+        if params.platform_fee_override != ctx.accounts.chain_config.platform_fee_override {
+            require_role(
+                &ctx.accounts.global_config,
+                ctx.accounts.roles.as_ref(),
+                &ctx.accounts.authority.key(),
+                Role::FeeManager,
+            )?;
         }
+        require!(chain_id != 0, CustomError::InvalidParameter);
+        require!(
+            params.max_amount_in == 0 || params.max_amount_in >= params.min_amount_in,
+            CustomError::InvalidParameter
+        );
+        if let Some(fee) = params.platform_fee_override {
+            require!(
+                fee <= GlobalConfig::MAX_PLATFORM_FEE,
+                CustomError::InvalidPlatformFee
+            );
+        }
+
+        let chain_config = &mut ctx.accounts.chain_config;
+        chain_config.chain_id = chain_id;
+        chain_config.enabled = params.enabled;
+        chain_config.address_format = params.address_format;
+        chain_config.min_amount_in = params.min_amount_in;
+        chain_config.max_amount_in = params.max_amount_in;
+        chain_config.platform_fee_override = params.platform_fee_override;
+        chain_config.bump = ctx.bumps.chain_config;
+
+        emit!(ChainConfigUpdated {
+            chain_id,
+            enabled: params.enabled,
+            address_format: params.address_format,
+            min_amount_in: params.min_amount_in,
+            max_amount_in: params.max_amount_in,
+            platform_fee_override: params.platform_fee_override,
+        });
+        Ok(())
+    }
+
+    pub fn set_token_route(
+        ctx: Context<SetTokenRoute>,
+        from_token: Pubkey,
+        to_chain_id: u64,
+        to_token: [u8; 32],
+        params: TokenRouteParams,
+    ) -> Result<()> {
+        authorize_admin_action(
+            &ctx.accounts.global_config,
+            ctx.accounts.roles.as_ref(),
+            &ctx.accounts.authority.key(),
+            ctx.accounts.proposal.as_mut(),
+            &AdminAction::SetTokenRoute {
+                from_token,
+                to_chain_id,
+                to_token,
+                params: params.clone(),
+            },
+        )?;
+        require!(
+            from_token != Pubkey::default() && to_chain_id != 0 && to_token != [0u8; 32],
+            CustomError::InvalidParameter
+        );
+        require!(
+            params.max_amount_in == 0 || params.max_amount_in >= params.min_amount_in,
+            CustomError::InvalidParameter
+        );
+
+        let route = &mut ctx.accounts.token_route;
+        route.from_token = from_token;
+        route.to_chain_id = to_chain_id;
+        route.to_token = to_token;
+        route.enabled = params.enabled;
+        route.from_decimals = params.from_decimals;
+        route.to_decimals = params.to_decimals;
+        route.min_amount_in = params.min_amount_in;
+        route.max_amount_in = params.max_amount_in;
+        route.bump = ctx.bumps.token_route;
+
+        emit!(TokenRouteUpdated {
+            from_token,
+            to_chain_id,
+            to_token,
+            enabled: params.enabled,
+            from_decimals: params.from_decimals,
+            to_decimals: params.to_decimals,
+            min_amount_in: params.min_amount_in,
+            max_amount_in: params.max_amount_in,
+        });
+        Ok(())
+    }
+
+    pub fn set_mint_policy(
+        ctx: Context<SetMintPolicy>,
+        mint: Pubkey,
+        params: MintPolicyParams,
+    ) -> Result<()> {
+        authorize_admin_action(
+            &ctx.accounts.global_config,
+            ctx.accounts.roles.as_ref(),
+            &ctx.accounts.authority.key(),
+            ctx.accounts.proposal.as_mut(),
+            &AdminAction::SetMintPolicy {
+                mint,
+                params: params.clone(),
+            },
+        )?;
+
+        let policy = &mut ctx.accounts.mint_policy;
+        policy.mint = mint;
+        policy.status = params.status;
+        policy.min_order_size = params.min_order_size;
+        policy.require_no_freeze_authority = params.require_no_freeze_authority;
+        policy.bump = ctx.bumps.mint_policy;
+
+        emit!(MintPolicyUpdated {
+            mint,
+            status: params.status,
+            min_order_size: params.min_order_size,
+            require_no_freeze_authority: params.require_no_freeze_authority,
+        });
+        Ok(())
+    }
+
+    /// Publishes the notional value of `mint`, the native mint for SOL, that
+    /// opens check against `GlobalConfig::min_notional`. This is an admin-set
+    /// price, not an oracle: nothing ties it to Pyth, Switchboard or a
+    /// market, so it is only as fresh and accurate as the PriceUpdater
+    /// keeps it.
+    pub fn set_price(ctx: Context<SetPrice>, mint: Pubkey, price: u64) -> Result<()> {
+        require_role(
+            &ctx.accounts.global_config,
+            ctx.accounts.roles.as_ref(),
+            &ctx.accounts.authority.key(),
+            Role::PriceUpdater,
+        )?;
+        require!(price > 0, CustomError::InvalidParameter);
+
         let clock = Clock::get()?;
-        if params.expiry <= clock.unix_timestamp {
-            return Err(error!(CustomError::InvalidParameter));
+        let price_feed = &mut ctx.accounts.price_feed;
+        price_feed.mint = mint;
+        price_feed.price = price;
+        price_feed.updated_at = clock.unix_timestamp;
+        price_feed.bump = ctx.bumps.price_feed;
+
+        emit!(PriceUpdated {
+            mint,
+            price,
+            updated_at: clock.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    pub fn set_mint_allowlist_mode(
+        ctx: Context<UpdateConfig>,
+        mint_allowlist_mode: bool,
+    ) -> Result<()> {
+        run_admin_action(
+            ctx.accounts,
+            AdminAction::SetMintAllowlistMode {
+                mint_allowlist_mode,
+            },
+        )
+    }
+
+    pub fn set_cancel_grace_period(
+        ctx: Context<UpdateConfig>,
+        cancel_grace_period: i64,
+    ) -> Result<()> {
+        run_admin_action(
+            ctx.accounts,
+            AdminAction::SetCancelGracePeriod {
+                cancel_grace_period,
+            },
+        )
+    }
+
+    /// Configures third-party execution. A zero `bond_multiple` leaves
+    /// execution to holders of the Executor role; otherwise bonded executors
+    /// can have fills of up to `bond_multiple` times their bond open to
+    /// slashing at once.
+    pub fn set_bond_config(
+        ctx: Context<UpdateConfig>,
+        bond_multiple: u16,
+        unbond_cooldown: i64,
+        slash_window: i64,
+    ) -> Result<()> {
+        run_admin_action(
+            ctx.accounts,
+            AdminAction::SetBondConfig {
+                bond_multiple,
+                unbond_cooldown,
+                slash_window,
+            },
+        )
+    }
+
+    /// Sets the swap adapter program `execute_order_swap` routes through, or
+    /// disables swapped execution with the default pubkey. `fee_on_output`
+    /// takes the platform fee from the swap output instead of the escrow.
+    pub fn set_swap_config(
+        ctx: Context<UpdateConfig>,
+        swap_adapter: Pubkey,
+        fee_on_output: bool,
+    ) -> Result<()> {
+        run_admin_action(
+            ctx.accounts,
+            AdminAction::SetSwapConfig {
+                swap_adapter,
+                fee_on_output,
+            },
+        )
+    }
+
+    /// Sets the smallest orders that can be opened: `min_sol_order_size`
+    /// lamports for SOL, and `min_notional` in value for any order whose
+    /// `from_token` has a `PriceFeed`. Orders are rejected while that feed is
+    /// older than `max_price_age` seconds, which must be positive. This fails
+    /// closed: an updater that falls behind blocks every open in its mint
+    /// until it publishes again or `min_notional` goes back to zero, so a
+    /// short `max_price_age` trades availability for fresher prices.
+    pub fn set_order_minimums(
+        ctx: Context<UpdateConfig>,
+        min_sol_order_size: u64,
+        min_notional: u64,
+        max_price_age: i64,
+    ) -> Result<()> {
+        run_admin_action(
+            ctx.accounts,
+            AdminAction::SetOrderMinimums {
+                min_sol_order_size,
+                min_notional,
+                max_price_age,
+            },
+        )
+    }
+
+    /// Bounds the time to expiry regular orders can be opened with. A zero
+    /// `max_order_lifetime` leaves it unbounded; good-till-cancelled orders
+    /// are exempt.
+    pub fn set_order_lifetime(
+        ctx: Context<UpdateConfig>,
+        min_order_lifetime: i64,
+        max_order_lifetime: i64,
+    ) -> Result<()> {
+        run_admin_action(
+            ctx.accounts,
+            AdminAction::SetOrderLifetime {
+                min_order_lifetime,
+                max_order_lifetime,
+            },
+        )
+    }
+
+    /// Upgrades the config account to the current `GlobalConfig` layout and
+    /// stamps the current version. Every layout so far carved its fields out
+    /// of `reserved`, so the account keeps its size and new fields read as
+    /// zero; only those whose zero means something else get a default here.
+    /// `chain_id` fills in a config that predates the chain registry and
+    /// must match the stored one otherwise.
+    pub fn migrate_config(ctx: Context<MigrateConfig>, chain_id: u64) -> Result<()> {
+        let config_info = ctx.accounts.global_config.to_account_info();
+        check_account_type(&config_info, GlobalConfig::DISCRIMINATOR)?;
+
+        let mut data = config_info.try_borrow_mut_data()?;
+        let mut config = GlobalConfig::try_deserialize(&mut &data[..])?;
+        // A config that predates roles is still authorized by its owner.
+        authorize_admin_action(
+            &config,
+            ctx.accounts.roles.as_ref(),
+            &ctx.accounts.authority.key(),
+            ctx.accounts.proposal.as_mut(),
+            &AdminAction::MigrateConfig { chain_id },
+        )?;
+        let from_version = config.version;
+        require!(
+            from_version < GlobalConfig::VERSION,
+            CustomError::AlreadyMigrated
+        );
+        require!(
+            chain_id != 0 && (config.chain_id == 0 || config.chain_id == chain_id),
+            CustomError::InvalidParameter
+        );
+        config.chain_id = chain_id;
+        // Versions 2 to 4 added roles, the swap adapter and order lifetime
+        // bounds, all of which are off at zero.
+        if from_version < 5 {
+            config.max_price_age = GlobalConfig::DEFAULT_MAX_PRICE_AGE;
         }
+        config.version = GlobalConfig::VERSION;
+        config.try_serialize(&mut &mut data[..])?;
+
+        emit!(AccountMigrated {
+            account: config_info.key(),
+            from_version,
+            to_version: GlobalConfig::VERSION,
+        });
+        Ok(())
+    }
+
+    /// Upgrades an order opened under an older `LimitOrder` layout. Fields
+    /// added since are zero-filled, which every one of them treats as unset.
+    pub fn migrate_order(ctx: Context<MigrateOrder>) -> Result<()> {
+        authorize_admin_action(
+            &ctx.accounts.global_config,
+            ctx.accounts.roles.as_ref(),
+            &ctx.accounts.authority.key(),
+            ctx.accounts.proposal.as_mut(),
+            &AdminAction::MigrateOrder {
+                order: ctx.accounts.order.key(),
+            },
+        )?;
+
+        let order_info = ctx.accounts.order.to_account_info();
+        check_account_type(&order_info, LimitOrder::DISCRIMINATOR)?;
+        grow_account(
+            &order_info,
+            8 + LimitOrder::INIT_SPACE,
+            &ctx.accounts.authority,
+            &ctx.accounts.system_program,
+        )?;
+
+        let mut data = order_info.try_borrow_mut_data()?;
+        let mut order = LimitOrder::try_deserialize(&mut &data[..])?;
+        let from_version = order.version;
+        require!(
+            from_version < LimitOrder::VERSION,
+            CustomError::AlreadyMigrated
+        );
+        order.version = LimitOrder::VERSION;
+        order.try_serialize(&mut &mut data[..])?;
+
+        emit!(AccountMigrated {
+            account: order_info.key(),
+            from_version,
+            to_version: LimitOrder::VERSION,
+        });
+        Ok(())
+    }
+
+    pub fn init_order_book(
+        ctx: Context<InitOrderBook>,
+        from_token: Pubkey,
+        to_chain_id: u64,
+    ) -> Result<()> {
+        authorize_admin_action(
+            &ctx.accounts.global_config,
+            ctx.accounts.roles.as_ref(),
+            &ctx.accounts.authority.key(),
+            ctx.accounts.proposal.as_mut(),
+            &AdminAction::InitOrderBook {
+                from_token,
+                to_chain_id,
+            },
+        )?;
+
+        let mut book = ctx.accounts.order_book.load_init()?;
+        book.from_token = from_token;
+        book.to_chain_id = to_chain_id;
+        book.count = 0;
+        book.bump = ctx.bumps.order_book;
+
+        emit!(OrderBookInitialized {
+            order_book: ctx.accounts.order_book.key(),
+            from_token,
+            to_chain_id,
+        });
+        Ok(())
+    }
+
+    /// Drops expired orders from `order_book`, along with listed orders passed
+    /// in `remaining_accounts` that have since been closed, such as orders
+    /// listed before every closing path kept the book in sync.
+    pub fn prune_order_book<'info>(
+        ctx: Context<'_, '_, 'info, 'info, PruneOrderBook<'info>>,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        let closed: Vec<Pubkey> = ctx
+            .remaining_accounts
+            .iter()
+            .filter(|account| *account.owner != crate::ID)
+            .map(|account| account.key())
+            .collect();
+
+        let mut book = ctx.accounts.order_book.load_mut()?;
+        let removed =
+            book.retain(|slot| slot.expiry > clock.unix_timestamp && !closed.contains(&slot.order));
+
+        emit!(OrderBookPruned {
+            order_book: ctx.accounts.order_book.key(),
+            removed,
+        });
+        Ok(())
+    }
+
+    pub fn open_order_sol(ctx: Context<OpenOrderSol>, params: OpenOrderParams) -> Result<()> {
+        require_keys_eq!(
+            params.from_token,
+            native_token(),
+            CustomError::InvalidParameter
+        );
+        validate_open_order(
+            &params,
+            &ctx.accounts.global_config,
+            &ctx.accounts.chain_config,
+            &ctx.accounts.token_route,
+        )?;
+        check_order_size(
+            &ctx.accounts.global_config,
+            &ctx.accounts.price_feed,
+            &params,
+        )?;
+        let sender = order_sender(&ctx.accounts.user, &ctx.accounts.session_key);
+        verify_fill_quote(&params, &sender, ctx.accounts.instructions.as_ref())?;
+        spend_session_allowance(ctx.accounts.session_key.as_mut(), &params)?;
 
         require!(
             ctx.accounts.user.lamports() >= params.amount_in,
@@ -86,7 +794,8 @@ pub mod limit_order {
             params.amount_in, // lamports
         )?;
         let order = &mut ctx.accounts.order;
-        add_order(order, &ctx.accounts.user, &params, ctx.bumps.order);
+        add_order(order, sender, &params, ctx.bumps.order);
+        list_order(ctx.accounts.order_book.as_ref(), order)?;
 
         emit!(OrderOpened {
             order_pubkey: ctx.accounts.order.key(),
@@ -96,25 +805,38 @@ pub mod limit_order {
     }
 
     pub fn open_order_spl(ctx: Context<OpenOrderSpl>, params: OpenOrderParams) -> Result<()> {
-        if params.from_token == Pubkey::default()
-            || params.from_token == native_token()
-            || params.from_chain_id != 10002
-            || params.amount_in == 0
-            || params.to_chain_id == 0
-            || params.to_token == [0u8; 32]
-            || params.recipient == [0u8; 32]
-        {
-            return Err(error!(CustomError::InvalidParameter));
-        }
-        let clock = Clock::get()?;
-        if params.expiry <= clock.unix_timestamp {
+        if params.from_token == Pubkey::default() || params.from_token == native_token() {
             return Err(error!(CustomError::InvalidParameter));
         }
+        validate_open_order(
+            &params,
+            &ctx.accounts.global_config,
+            &ctx.accounts.chain_config,
+            &ctx.accounts.token_route,
+        )?;
+        check_order_size(
+            &ctx.accounts.global_config,
+            &ctx.accounts.price_feed,
+            &params,
+        )?;
+        let sender = order_sender(&ctx.accounts.user, &ctx.accounts.session_key);
+        verify_fill_quote(&params, &sender, ctx.accounts.instructions.as_ref())?;
+        spend_session_allowance(ctx.accounts.session_key.as_mut(), &params)?;
         require_keys_eq!(
             ctx.accounts.token_mint.key(),
             params.from_token,
             CustomError::InvalidParameter
         );
+        require!(
+            ctx.accounts.token_mint.decimals == ctx.accounts.token_route.from_decimals,
+            CustomError::TokenRouteNotSupported
+        );
+        check_mint_policy(
+            &ctx.accounts.global_config,
+            &ctx.accounts.mint_policy,
+            &ctx.accounts.token_mint,
+            params.amount_in,
+        )?;
         require_keys_eq!(
             ctx.accounts.user_token_account.mint,
             params.from_token,
@@ -133,7 +855,8 @@ pub mod limit_order {
         token::transfer(cpi_ctx, params.amount_in)?;
 
         let order = &mut ctx.accounts.order;
-        add_order(order, &ctx.accounts.user, &params, ctx.bumps.order);
+        add_order(order, sender, &params, ctx.bumps.order);
+        list_order(ctx.accounts.order_book.as_ref(), order)?;
 
         emit!(OrderOpened {
             order_pubkey: ctx.accounts.order.key(),
@@ -143,15 +866,27 @@ pub mod limit_order {
 
     pub fn cancel_order_sol(ctx: Context<CancelOrderSol>) -> Result<()> {
         let order = &ctx.accounts.order;
+        require!(!order.is_htlc(), CustomError::HtlcOrder);
+        require!(!order.wrapped_sol, CustomError::InvalidEscrow);
 
-        require!(
-            ctx.accounts.user.key() == order.sender
-                || ctx.accounts.user.key() == ctx.accounts.global_config.owner,
-            CustomError::OnlySenderOrOwner
-        );
+        let clock = Clock::get()?;
+        let canceller = session_canceller(
+            &ctx.accounts.user,
+            ctx.accounts.session_key.as_ref(),
+            order,
+            clock.unix_timestamp,
+        )?;
+        let reason = cancel_reason(
+            &ctx.accounts.global_config,
+            ctx.accounts.roles.as_ref(),
+            order,
+            &canceller,
+            clock.unix_timestamp,
+        )?;
+        delist_order(ctx.accounts.order_book.as_ref(), order)?;
 
         require!(
-            ctx.accounts.refund_receiver.key() == order.sender,
+            ctx.accounts.refund_receiver.key() == order.refund_to(),
             CustomError::InvalidRefundReceiver
         );
 
@@ -169,6 +904,8 @@ pub mod limit_order {
         emit!(OrderCancelled {
             order_pubkey: ctx.accounts.order.key(),
             by: ctx.accounts.user.key(),
+            reason,
+            reason_code: 0,
         });
 
         Ok(())
@@ -176,15 +913,26 @@ pub mod limit_order {
 
     pub fn cancel_order_spl(ctx: Context<CancelOrderSpl>) -> Result<()> {
         let order = &ctx.accounts.order;
+        require!(!order.is_htlc(), CustomError::HtlcOrder);
 
-        require!(
-            ctx.accounts.user.key() == order.sender
-                || ctx.accounts.user.key() == ctx.accounts.global_config.owner,
-            CustomError::OnlySenderOrOwner
-        );
+        let clock = Clock::get()?;
+        let canceller = session_canceller(
+            &ctx.accounts.user,
+            ctx.accounts.session_key.as_ref(),
+            order,
+            clock.unix_timestamp,
+        )?;
+        let reason = cancel_reason(
+            &ctx.accounts.global_config,
+            ctx.accounts.roles.as_ref(),
+            order,
+            &canceller,
+            clock.unix_timestamp,
+        )?;
+        delist_order(ctx.accounts.order_book.as_ref(), order)?;
 
         require!(
-            ctx.accounts.refund_receiver.key() == order.sender,
+            ctx.accounts.refund_receiver.key() == order.refund_to(),
             CustomError::InvalidRefundReceiver
         );
 
@@ -225,6 +973,8 @@ pub mod limit_order {
         emit!(OrderCancelled {
             order_pubkey: ctx.accounts.order.key(),
             by: ctx.accounts.user.key(),
+            reason,
+            reason_code: 0,
         });
 
         Ok(())
@@ -236,36 +986,47 @@ pub mod limit_order {
     ) -> Result<()> {
         let order = &ctx.accounts.order;
         let config = &ctx.accounts.global_config;
-
-        require_keys_eq!(
-            ctx.accounts.executor.key(),
-            ctx.accounts.global_config.owner,
-            CustomError::OnlyOwnerCanExecute
-        );
+        require!(!order.is_htlc(), CustomError::HtlcOrder);
+        require!(!order.is_local(), CustomError::LocalOrder);
 
         let clock = Clock::get()?;
         require!(
-            order.expiry > clock.unix_timestamp,
+            order.is_live(clock.unix_timestamp),
             CustomError::ExpiryEarlier
         );
 
+        authorize_executor(
+            config,
+            ctx.accounts.roles.as_ref(),
+            &ctx.accounts.executor.key(),
+            ctx.accounts.executor_bond.as_mut(),
+            &order.key(),
+            order,
+            clock.unix_timestamp,
+        )?;
+        delist_order(ctx.accounts.order_book.as_ref(), order)?;
+
+        if config.attestation_required {
+            verify_fill_attestation(
+                config,
+                ctx.accounts.guardian_set.as_ref(),
+                ctx.accounts.instructions.as_ref(),
+                &order.key(),
+                order,
+                clock.unix_timestamp,
+            )?;
+        }
+
         require_keys_eq!(
             ctx.accounts.refund_receiver.key(),
-            ctx.accounts.order.sender,
+            ctx.accounts.order.refund_to(),
             CustomError::InvalidRefundReceiver
         );
 
         let amount_in = order.amount_in;
-        let platform_fee = config.platform_fee;
-
-        let fee_amount = amount_in
-            .checked_mul(platform_fee as u64)
-            .ok_or(CustomError::Overflow)?
-            / 10000;
-        let send_amount = amount_in
-            .checked_sub(fee_amount)
-            .ok_or(CustomError::Overflow)?;
-        require!(send_amount > 0, CustomError::InsufficientFunds);
+        let platform_fee = effective_platform_fee(config, &ctx.accounts.chain_config);
+        let (fee_amount, send_amount) =
+            calculate_fee(amount_in, platform_fee, config.round_fees_up)?;
 
         // PDA 签名 seeds
         let seeds = &[
@@ -329,36 +1090,48 @@ pub mod limit_order {
     ) -> Result<()> {
         let order = &ctx.accounts.order;
         let config = &ctx.accounts.global_config;
+        require!(!order.is_htlc(), CustomError::HtlcOrder);
+        require!(!order.is_local(), CustomError::LocalOrder);
+        require!(!order.wrapped_sol, CustomError::InvalidEscrow);
 
         let clock = Clock::get()?;
         require!(
-            order.expiry > clock.unix_timestamp,
+            order.is_live(clock.unix_timestamp),
             CustomError::ExpiryEarlier
         );
 
-        require_keys_eq!(
-            ctx.accounts.executor.key(),
-            ctx.accounts.global_config.owner,
-            CustomError::OnlyOwnerCanExecute
-        );
+        authorize_executor(
+            config,
+            ctx.accounts.roles.as_ref(),
+            &ctx.accounts.executor.key(),
+            ctx.accounts.executor_bond.as_mut(),
+            &order.key(),
+            order,
+            clock.unix_timestamp,
+        )?;
+        delist_order(ctx.accounts.order_book.as_ref(), order)?;
+
+        if config.attestation_required {
+            verify_fill_attestation(
+                config,
+                ctx.accounts.guardian_set.as_ref(),
+                ctx.accounts.instructions.as_ref(),
+                &order.key(),
+                order,
+                clock.unix_timestamp,
+            )?;
+        }
 
         require_keys_eq!(
             ctx.accounts.refund_receiver.key(),
-            ctx.accounts.order.sender,
+            ctx.accounts.order.refund_to(),
             CustomError::InvalidRefundReceiver
         );
 
         let amount_in = order.amount_in;
-        let platform_fee = config.platform_fee;
-
-        let fee_amount = amount_in
-            .checked_mul(platform_fee as u64)
-            .ok_or(CustomError::Overflow)?
-            / 10000;
-        let send_amount = amount_in
-            .checked_sub(fee_amount)
-            .ok_or(CustomError::Overflow)?;
-        require!(send_amount > 0, CustomError::InsufficientFunds);
+        let platform_fee = effective_platform_fee(config, &ctx.accounts.chain_config);
+        let (fee_amount, send_amount) =
+            calculate_fee(amount_in, platform_fee, config.round_fees_up)?;
 
         **ctx
             .accounts
@@ -375,102 +1148,4171 @@ pub mod limit_order {
         });
         Ok(())
     }
-}
 
-fn native_token() -> Pubkey {
-    Pubkey::from_str("So11111111111111111111111111111111111111112").unwrap()
-}
+    /// Executes token-escrowed orders (SPL and wrapped SOL) passed through
+    /// `remaining_accounts`, `ExecuteOrdersBatch::ACCOUNTS_PER_ORDER` per order:
+    /// `[order, order_token_account, target_token_account,
+    /// treasury_token_account, refund_receiver, chain_config, order_book]`.
+    /// Each order names the book of its own market, so one batch can span
+    /// markets; see `batch_order_book` for unlisted orders.
+    ///
+    /// With `skip_failed`, orders that fail validation are skipped and
+    /// reported through `BatchOrderResult`; failures inside the token CPIs
+    /// still abort the whole transaction. Each order costs about 18,000
+    /// compute units in SPL Token CPIs (two transfers and a close) plus this
+    /// program's checks, and the SBF build is held to 30,000 per order, so
+    /// the four orders a legacy transaction fits stay under the default
+    /// 200,000 limit. Batches need the Executor role; bonded executors fill
+    /// orders one at a time.
+    pub fn execute_orders_batch<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ExecuteOrdersBatch<'info>>,
+        native_token_volumes: Vec<u64>,
+        skip_failed: bool,
+    ) -> Result<()> {
+        let config = &ctx.accounts.global_config;
+        require!(!config.paused, CustomError::Paused);
+        require!(
+            has_role(
+                config,
+                ctx.accounts.roles.as_ref(),
+                &ctx.accounts.executor.key(),
+                Role::Executor
+            ),
+            CustomError::OnlyOwnerCanExecute
+        );
 
-fn add_order(order: &mut Account<LimitOrder>, user: &Signer, params: &OpenOrderParams, bump: u8) {
-    order.from_token = params.from_token;
-    order.from_chain_id = params.from_chain_id;
-    order.amount_in = params.amount_in;
-    order.to_chain_id = params.to_chain_id;
-    order.to_token = params.to_token;
-    order.recipient = params.recipient;
-    order.sender = user.key();
+        let remaining = ctx.remaining_accounts;
+        require!(
+            !native_token_volumes.is_empty()
+                && remaining.len()
+                    == native_token_volumes.len() * ExecuteOrdersBatch::ACCOUNTS_PER_ORDER,
+            CustomError::InvalidBatchAccounts
+        );
+
+        let clock = Clock::get()?;
+        for (accounts, native_token_volume) in remaining
+            .chunks(ExecuteOrdersBatch::ACCOUNTS_PER_ORDER)
+            .zip(native_token_volumes)
+        {
+            let order_info = &accounts[0];
+            let prepared = prepare_batch_execution(
+                config,
+                &ctx.accounts.executor.key(),
+                ctx.accounts.guardian_set.as_ref(),
+                ctx.accounts.instructions.as_ref(),
+                accounts,
+                clock.unix_timestamp,
+            );
+            let (order, fee_amount, send_amount) = match prepared {
+                Ok(prepared) => prepared,
+                Err(err) if skip_failed => {
+                    emit!(BatchOrderResult {
+                        order_pubkey: order_info.key(),
+                        executed: false,
+                        error_code: error_code(&err),
+                    });
+                    continue;
+                }
+                Err(err) => return Err(err),
+            };
+            delist_order(batch_order_book(&accounts[6])?.as_ref(), &order)?;
+
+            let seeds = &[
+                b"limit_order",
+                order.sender.as_ref(),
+                &order.expiry.to_le_bytes(),
+                &[order.bump],
+            ];
+            let signer = &[&seeds[..]];
+            let order_token_account = &accounts[1];
+            let refund_receiver = &accounts[4];
+
+            for (to, amount) in [(&accounts[2], send_amount), (&accounts[3], fee_amount)] {
+                let cpi_accounts = TokenTransfer {
+                    from: order_token_account.clone(),
+                    to: to.clone(),
+                    authority: order_info.clone(),
+                };
+                let cpi_ctx = CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    cpi_accounts,
+                    signer,
+                );
+                token::transfer(cpi_ctx, amount)?;
+            }
+
+            let close_cpi_accounts = CloseAccount {
+                account: order_token_account.clone(),
+                destination: refund_receiver.clone(),
+                authority: order_info.clone(),
+            };
+            let close_cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                close_cpi_accounts,
+                signer,
+            );
+            token::close_account(close_cpi_ctx)?;
+
+            order.close(refund_receiver.clone())?;
+
+            emit!(OrderExecuted {
+                order_pubkey: order_info.key(),
+                by: ctx.accounts.executor.key(),
+                native_token_volume,
+            });
+            emit!(BatchOrderResult {
+                order_pubkey: order_info.key(),
+                executed: true,
+                error_code: 0,
+            });
+        }
+        Ok(())
+    }
+
+    /// Flags an order for cancellation. The sender can cancel it once
+    /// `GlobalConfig::cancel_grace_period` has elapsed, which gives solvers
+    /// already filling on the destination chain time to finish. Session keys
+    /// that may cancel can request it for their wallet.
+    pub fn request_cancel(ctx: Context<RequestCancel>) -> Result<()> {
+        let order = &mut ctx.accounts.order;
+        require!(!order.is_htlc(), CustomError::HtlcOrder);
+        require!(
+            order.cancel_requested_at == 0,
+            CustomError::CancelAlreadyRequested
+        );
+
+        let clock = Clock::get()?;
+        let requester = session_canceller(
+            &ctx.accounts.user,
+            ctx.accounts.session_key.as_ref(),
+            order,
+            clock.unix_timestamp,
+        )?;
+        require_keys_eq!(requester, order.sender, CustomError::OnlySenderOrOwner);
+        order.cancel_requested_at = clock.unix_timestamp;
+        let cancellable_at = clock
+            .unix_timestamp
+            .checked_add(ctx.accounts.global_config.cancel_grace_period)
+            .ok_or(CustomError::Overflow)?;
+
+        emit!(CancelRequested {
+            order_pubkey: order.key(),
+            cancellable_at,
+        });
+        Ok(())
+    }
+
+    /// Authorises `params.key` to open and cancel orders on behalf of the
+    /// signer until `params.expires_at`. Orders opened through a session
+    /// belong to the signer, so refunds only ever go back to its wallet.
+    pub fn create_session_key(
+        ctx: Context<CreateSessionKey>,
+        params: SessionKeyParams,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        require!(
+            params.key != Pubkey::default() && params.key != ctx.accounts.user.key(),
+            CustomError::InvalidParameter
+        );
+        require!(
+            params.expires_at > clock.unix_timestamp,
+            CustomError::InvalidParameter
+        );
+        require!(
+            params.allowed_mints.len() <= SessionKey::MAX_ALLOWED_MINTS,
+            CustomError::InvalidParameter
+        );
+        // The allowance is counted in base units, which only add up within
+        // a single mint.
+        require!(
+            params.open_allowance == 0 || params.allowed_mints.len() == 1,
+            CustomError::InvalidParameter
+        );
+
+        let session = &mut ctx.accounts.session_key;
+        session.user = ctx.accounts.user.key();
+        session.key = params.key;
+        session.expires_at = params.expires_at;
+        session.open_allowance = params.open_allowance;
+        session.can_cancel = params.can_cancel;
+        session.allowed_mints = params.allowed_mints;
+        session.bump = ctx.bumps.session_key;
+
+        emit!(SessionKeyCreated {
+            user: session.user,
+            key: session.key,
+            expires_at: session.expires_at,
+            open_allowance: session.open_allowance,
+            can_cancel: session.can_cancel,
+            allowed_mints: session.allowed_mints.clone(),
+        });
+        Ok(())
+    }
+
+    /// Revokes a session key and returns its rent to the user.
+    pub fn revoke_session_key(ctx: Context<RevokeSessionKey>) -> Result<()> {
+        emit!(SessionKeyRevoked {
+            user: ctx.accounts.user.key(),
+            key: ctx.accounts.session_key.key,
+        });
+        Ok(())
+    }
+
+    /// Cancels several of the signer's own orders. See `refund_orders` for
+    /// the per-order account layout in `remaining_accounts`.
+    pub fn cancel_orders_batch<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CancelOrdersBatch<'info>>,
+    ) -> Result<()> {
+        refund_orders(
+            ctx.remaining_accounts,
+            &ctx.accounts.token_program,
+            &ctx.accounts.global_config,
+            ctx.accounts.user.key(),
+            None,
+        )
+        .map(|_| ())
+    }
+
+    /// Admin bulk refund for incidents and migrations. Funds always go back
+    /// to each order's refund address; `reason_code` is attached to every
+    /// `OrderCancelled` event. Under a multisig threshold the proposal names
+    /// the exact orders to refund.
+    pub fn force_refund_batch<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ForceRefundBatch<'info>>,
+        reason_code: u16,
+    ) -> Result<()> {
+        require!(reason_code != 0, CustomError::InvalidParameter);
+        let orders = refund_orders(
+            ctx.remaining_accounts,
+            &ctx.accounts.token_program,
+            &ctx.accounts.global_config,
+            ctx.accounts.authority.key(),
+            Some(reason_code),
+        )?;
+        // The orders are only known once the batch has been walked; a failed
+        // check still reverts every refund.
+        authorize_admin_action(
+            &ctx.accounts.global_config,
+            ctx.accounts.roles.as_ref(),
+            &ctx.accounts.authority.key(),
+            ctx.accounts.proposal.as_mut(),
+            &AdminAction::ForceRefund {
+                reason_code,
+                orders,
+            },
+        )
+    }
+
+    /// Opens a SOL order whose lamports are wrapped into a WSOL escrow
+    /// account owned by the order, the same escrow model as SPL orders.
+    pub fn open_order_wsol(ctx: Context<OpenOrderWsol>, params: OpenOrderParams) -> Result<()> {
+        require_keys_eq!(
+            params.from_token,
+            native_token(),
+            CustomError::InvalidParameter
+        );
+        validate_open_order(
+            &params,
+            &ctx.accounts.global_config,
+            &ctx.accounts.chain_config,
+            &ctx.accounts.token_route,
+        )?;
+        check_order_size(
+            &ctx.accounts.global_config,
+            &ctx.accounts.price_feed,
+            &params,
+        )?;
+        let sender = order_sender(&ctx.accounts.user, &ctx.accounts.session_key);
+        verify_fill_quote(&params, &sender, ctx.accounts.instructions.as_ref())?;
+        spend_session_allowance(ctx.accounts.session_key.as_mut(), &params)?;
+
+        require!(
+            ctx.accounts.user.lamports() >= params.amount_in,
+            CustomError::InsufficientFunds
+        );
+
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.user.to_account_info(),
+                    to: ctx.accounts.order_token_account.to_account_info(),
+                },
+            ),
+            params.amount_in,
+        )?;
+        token::sync_native(CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            SyncNative {
+                account: ctx.accounts.order_token_account.to_account_info(),
+            },
+        ))?;
+
+        let order = &mut ctx.accounts.order;
+        add_order(order, sender, &params, ctx.bumps.order);
+        list_order(ctx.accounts.order_book.as_ref(), order)?;
+        order.wrapped_sol = true;
+
+        emit!(OrderOpened {
+            order_pubkey: ctx.accounts.order.key(),
+        });
+        Ok(())
+    }
+
+    /// Cancels a wrapped SOL order, unwrapping the escrow straight back to the
+    /// refund address.
+    pub fn cancel_order_wsol(ctx: Context<CancelOrderWsol>) -> Result<()> {
+        let order = &ctx.accounts.order;
+        require!(!order.is_htlc(), CustomError::HtlcOrder);
+        require!(order.wrapped_sol, CustomError::InvalidEscrow);
+
+        let clock = Clock::get()?;
+        let canceller = session_canceller(
+            &ctx.accounts.user,
+            ctx.accounts.session_key.as_ref(),
+            order,
+            clock.unix_timestamp,
+        )?;
+        let reason = cancel_reason(
+            &ctx.accounts.global_config,
+            ctx.accounts.roles.as_ref(),
+            order,
+            &canceller,
+            clock.unix_timestamp,
+        )?;
+        delist_order(ctx.accounts.order_book.as_ref(), order)?;
+
+        require!(
+            ctx.accounts.refund_receiver.key() == order.refund_to(),
+            CustomError::InvalidRefundReceiver
+        );
+
+        let seeds = &[
+            b"limit_order",
+            order.sender.as_ref(),
+            &order.expiry.to_le_bytes(),
+            &[order.bump],
+        ];
+        let signer = &[&seeds[..]];
+
+        // Closing a native token account releases the wrapped lamports with its rent.
+        let close_cpi_accounts = CloseAccount {
+            account: ctx.accounts.order_token_account.to_account_info(),
+            destination: ctx.accounts.refund_receiver.to_account_info(),
+            authority: ctx.accounts.order.to_account_info(),
+        };
+        let close_cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            close_cpi_accounts,
+            signer,
+        );
+        token::close_account(close_cpi_ctx)?;
+
+        emit!(OrderCancelled {
+            order_pubkey: ctx.accounts.order.key(),
+            by: ctx.accounts.user.key(),
+            reason,
+            reason_code: 0,
+        });
+
+        Ok(())
+    }
+
+    /// Executes a wrapped SOL order. The fee stays wrapped in the treasury's
+    /// WSOL account like any SPL fee; the rest is unwrapped to `target_sol`.
+    pub fn execute_order_wsol(
+        ctx: Context<ExecuteOrderWsol>,
+        native_token_volume: u64,
+    ) -> Result<()> {
+        let order = &ctx.accounts.order;
+        let config = &ctx.accounts.global_config;
+        require!(!order.is_htlc(), CustomError::HtlcOrder);
+        require!(!order.is_local(), CustomError::LocalOrder);
+        require!(order.wrapped_sol, CustomError::InvalidEscrow);
+
+        let clock = Clock::get()?;
+        require!(
+            order.is_live(clock.unix_timestamp),
+            CustomError::ExpiryEarlier
+        );
+
+        authorize_executor(
+            config,
+            ctx.accounts.roles.as_ref(),
+            &ctx.accounts.executor.key(),
+            ctx.accounts.executor_bond.as_mut(),
+            &order.key(),
+            order,
+            clock.unix_timestamp,
+        )?;
+        delist_order(ctx.accounts.order_book.as_ref(), order)?;
+
+        if config.attestation_required {
+            verify_fill_attestation(
+                config,
+                ctx.accounts.guardian_set.as_ref(),
+                ctx.accounts.instructions.as_ref(),
+                &order.key(),
+                order,
+                clock.unix_timestamp,
+            )?;
+        }
+
+        require_keys_eq!(
+            ctx.accounts.refund_receiver.key(),
+            ctx.accounts.order.refund_to(),
+            CustomError::InvalidRefundReceiver
+        );
+
+        let platform_fee = effective_platform_fee(config, &ctx.accounts.chain_config);
+        let (fee_amount, send_amount) =
+            calculate_fee(order.amount_in, platform_fee, config.round_fees_up)?;
+
+        let seeds = &[
+            b"limit_order",
+            order.sender.as_ref(),
+            &order.expiry.to_le_bytes(),
+            &[order.bump],
+        ];
+        let signer = &[&seeds[..]];
+
+        let cpi_accounts = TokenTransfer {
+            from: ctx.accounts.order_token_account.to_account_info(),
+            to: ctx.accounts.treasury_token_account.to_account_info(),
+            authority: ctx.accounts.order.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            cpi_accounts,
+            signer,
+        );
+        token::transfer(cpi_ctx, fee_amount)?;
+
+        // Unwrap into the order account, then pay out; the escrow rent goes
+        // back to the refund address when the order is closed.
+        let close_cpi_accounts = CloseAccount {
+            account: ctx.accounts.order_token_account.to_account_info(),
+            destination: ctx.accounts.order.to_account_info(),
+            authority: ctx.accounts.order.to_account_info(),
+        };
+        let close_cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            close_cpi_accounts,
+            signer,
+        );
+        token::close_account(close_cpi_ctx)?;
+
+        **ctx
+            .accounts
+            .order
+            .to_account_info()
+            .try_borrow_mut_lamports()? -= send_amount;
+        **ctx.accounts.target_sol.try_borrow_mut_lamports()? += send_amount;
+
+        emit!(OrderExecuted {
+            order_pubkey: ctx.accounts.order.key(),
+            by: ctx.accounts.executor.key(),
+            native_token_volume,
+        });
+        Ok(())
+    }
+
+    /// Executes a token-escrowed order by swapping the escrow into
+    /// `output_mint` through `global_config.swap_adapter` and depositing the
+    /// output in `settlement_vault`, for solvers that bridge another asset.
+    /// The adapter receives `[order, order_token_account, swap_output_account,
+    /// token_program]` followed by `remaining_accounts`.
+    pub fn execute_order_swap<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ExecuteOrderSwap<'info>>,
+        min_amount_out: u64,
+        native_token_volume: u64,
+    ) -> Result<()> {
+        let order = &ctx.accounts.order;
+        let config = &ctx.accounts.global_config;
+        require!(!order.is_htlc(), CustomError::HtlcOrder);
+        require!(!order.is_local(), CustomError::LocalOrder);
+        require!(order.uses_token_escrow(), CustomError::InvalidEscrow);
+        require!(
+            config.swap_adapter != Pubkey::default(),
+            CustomError::SwapNotConfigured
+        );
+        require!(min_amount_out > 0, CustomError::InvalidParameter);
+        require_keys_neq!(
+            ctx.accounts.output_mint.key(),
+            order.from_token,
+            CustomError::InvalidParameter
+        );
+
+        let clock = Clock::get()?;
+        require!(
+            order.is_live(clock.unix_timestamp),
+            CustomError::ExpiryEarlier
+        );
+
+        authorize_executor(
+            config,
+            ctx.accounts.roles.as_ref(),
+            &ctx.accounts.executor.key(),
+            ctx.accounts.executor_bond.as_mut(),
+            &order.key(),
+            order,
+            clock.unix_timestamp,
+        )?;
+        delist_order(ctx.accounts.order_book.as_ref(), order)?;
+
+        if config.attestation_required {
+            verify_fill_attestation(
+                config,
+                ctx.accounts.guardian_set.as_ref(),
+                ctx.accounts.instructions.as_ref(),
+                &order.key(),
+                order,
+                clock.unix_timestamp,
+            )?;
+        }
+
+        require_keys_eq!(
+            ctx.accounts.refund_receiver.key(),
+            ctx.accounts.order.refund_to(),
+            CustomError::InvalidRefundReceiver
+        );
+
+        let fee_on_output = config.swap_fee_on_output;
+        let platform_fee = effective_platform_fee(config, &ctx.accounts.chain_config);
+        let (input_fee, swap_amount) = if fee_on_output {
+            (0, order.amount_in)
+        } else {
+            calculate_fee(order.amount_in, platform_fee, config.round_fees_up)?
+        };
+
+        let seeds = &[
+            b"limit_order",
+            order.sender.as_ref(),
+            &order.expiry.to_le_bytes(),
+            &[order.bump],
+        ];
+        let signer = &[&seeds[..]];
+
+        if !fee_on_output {
+            let cpi_accounts = TokenTransfer {
+                from: ctx.accounts.order_token_account.to_account_info(),
+                to: ctx.accounts.treasury_token_account.to_account_info(),
+                authority: ctx.accounts.order.to_account_info(),
+            };
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                cpi_accounts,
+                signer,
+            );
+            token::transfer(cpi_ctx, input_fee)?;
+        }
+
+        let escrow_before = ctx.accounts.order_token_account.amount - input_fee;
+        let output_before = ctx.accounts.swap_output_account.amount;
+        let mut metas = vec![
+            AccountMeta::new_readonly(ctx.accounts.order.key(), true),
+            AccountMeta::new(ctx.accounts.order_token_account.key(), false),
+            AccountMeta::new(ctx.accounts.swap_output_account.key(), false),
+            AccountMeta::new_readonly(ctx.accounts.token_program.key(), false),
+        ];
+        let mut infos = vec![
+            ctx.accounts.order.to_account_info(),
+            ctx.accounts.order_token_account.to_account_info(),
+            ctx.accounts.swap_output_account.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+        ];
+        for account in ctx.remaining_accounts {
+            metas.push(if account.is_writable {
+                AccountMeta::new(*account.key, account.is_signer)
+            } else {
+                AccountMeta::new_readonly(*account.key, account.is_signer)
+            });
+            infos.push(account.clone());
+        }
+        infos.push(ctx.accounts.swap_program.to_account_info());
+        invoke_signed(
+            &Instruction {
+                program_id: ctx.accounts.swap_program.key(),
+                accounts: metas,
+                data: swap_adapter_data(swap_amount, min_amount_out),
+            },
+            &infos,
+            signer,
+        )?;
+
+        // Trust balances rather than the adapter: the whole swap amount must
+        // have left the escrow, and enough output must have arrived.
+        ctx.accounts.order_token_account.reload()?;
+        ctx.accounts.swap_output_account.reload()?;
+        require!(
+            escrow_before.checked_sub(ctx.accounts.order_token_account.amount) == Some(swap_amount),
+            CustomError::InvalidSwap
+        );
+        let amount_out = ctx
+            .accounts
+            .swap_output_account
+            .amount
+            .checked_sub(output_before)
+            .ok_or(CustomError::InvalidSwap)?;
+        require!(amount_out >= min_amount_out, CustomError::SwapOutputTooLow);
+
+        let mut output_fee = 0;
+        if fee_on_output {
+            (output_fee, _) = calculate_fee(amount_out, platform_fee, config.round_fees_up)?;
+            let cpi_accounts = TokenTransfer {
+                from: ctx.accounts.swap_output_account.to_account_info(),
+                to: ctx.accounts.treasury_token_account.to_account_info(),
+                authority: ctx.accounts.order.to_account_info(),
+            };
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                cpi_accounts,
+                signer,
+            );
+            token::transfer(cpi_ctx, output_fee)?;
+        }
+
+        // Everything left in the output account settles, so it can be closed.
+        let cpi_accounts = TokenTransfer {
+            from: ctx.accounts.swap_output_account.to_account_info(),
+            to: ctx.accounts.settlement_vault.to_account_info(),
+            authority: ctx.accounts.order.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            cpi_accounts,
+            signer,
+        );
+        token::transfer(
+            cpi_ctx,
+            ctx.accounts.swap_output_account.amount - output_fee,
+        )?;
+
+        let close_cpi_accounts = CloseAccount {
+            account: ctx.accounts.swap_output_account.to_account_info(),
+            destination: ctx.accounts.executor.to_account_info(),
+            authority: ctx.accounts.order.to_account_info(),
+        };
+        let close_cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            close_cpi_accounts,
+            signer,
+        );
+        token::close_account(close_cpi_ctx)?;
+
+        let close_cpi_accounts = CloseAccount {
+            account: ctx.accounts.order_token_account.to_account_info(),
+            destination: ctx.accounts.refund_receiver.to_account_info(),
+            authority: ctx.accounts.order.to_account_info(),
+        };
+        let close_cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            close_cpi_accounts,
+            signer,
+        );
+        token::close_account(close_cpi_ctx)?;
+
+        let order = &ctx.accounts.order;
+        emit!(OrderSwapped {
+            order_pubkey: order.key(),
+            output_mint: ctx.accounts.output_mint.key(),
+            amount_in: swap_amount,
+            amount_out,
+            fee_amount: input_fee + output_fee,
+            fee_mint: if fee_on_output {
+                ctx.accounts.output_mint.key()
+            } else {
+                order.from_token
+            },
+        });
+        emit!(OrderExecuted {
+            order_pubkey: order.key(),
+            by: ctx.accounts.executor.key(),
+            native_token_volume,
+        });
+        Ok(())
+    }
+
+    /// Fills a same-chain order in full for an Executor: the taker pays
+    /// `amount_out` of the order's `to_token` mint to the recipient's
+    /// associated token account and receives the escrow less the platform
+    /// fee, in one transaction.
+    pub fn fill_local_order(ctx: Context<FillLocalOrder>) -> Result<()> {
+        let clock = Clock::get()?;
+        check_local_fill(ctx.accounts, clock.unix_timestamp)?;
+        require!(
+            has_role(
+                &ctx.accounts.global_config,
+                ctx.accounts.roles.as_ref(),
+                &ctx.accounts.taker.key(),
+                Role::Executor
+            ),
+            CustomError::OnlyOwnerCanExecute
+        );
+        let amount_in = ctx.accounts.order.amount_in;
+        take_local_order(ctx.accounts, amount_in)
+    }
+
+    /// Takes `amount_in` of a same-chain order's escrow for the pro-rated
+    /// share of its `amount_out`, rounded up in the maker's favour. Anyone can
+    /// take; the order closes once its escrow has been taken in full.
+    pub fn take_order(ctx: Context<FillLocalOrder>, amount_in: u64) -> Result<()> {
+        let clock = Clock::get()?;
+        check_local_fill(ctx.accounts, clock.unix_timestamp)?;
+        require!(
+            amount_in > 0 && amount_in <= ctx.accounts.order.amount_in,
+            CustomError::InvalidParameter
+        );
+        take_local_order(ctx.accounts, amount_in)
+    }
+
+    pub fn claim_htlc_order_sol(ctx: Context<ClaimHtlcOrderSol>, preimage: [u8; 32]) -> Result<()> {
+        let order = &ctx.accounts.order;
+        let config = &ctx.accounts.global_config;
+        require!(!config.paused, CustomError::Paused);
+        require!(order.is_htlc(), CustomError::NotHtlcOrder);
+        require_keys_eq!(
+            ctx.accounts.solver.key(),
+            order.htlc_solver,
+            CustomError::NotHtlcSolver
+        );
+        require!(!order.wrapped_sol, CustomError::InvalidEscrow);
+
+        let clock = Clock::get()?;
+        require!(
+            clock.unix_timestamp < order.timelock,
+            CustomError::TimelockExpired
+        );
+        require!(
+            hash(&preimage).to_bytes() == order.hashlock,
+            CustomError::InvalidPreimage
+        );
+
+        require_keys_eq!(
+            ctx.accounts.refund_receiver.key(),
+            order.refund_to(),
+            CustomError::InvalidRefundReceiver
+        );
+        delist_order(ctx.accounts.order_book.as_ref(), order)?;
+
+        let amount_in = order.amount_in;
+        let platform_fee = effective_platform_fee(config, &ctx.accounts.chain_config);
+        let (fee_amount, send_amount) =
+            calculate_fee(amount_in, platform_fee, config.round_fees_up)?;
+
+        **ctx
+            .accounts
+            .order
+            .to_account_info()
+            .try_borrow_mut_lamports()? -= amount_in;
+        **ctx
+            .accounts
+            .solver
+            .to_account_info()
+            .try_borrow_mut_lamports()? += send_amount;
+        **ctx.accounts.treasury.try_borrow_mut_lamports()? += fee_amount;
+
+        emit!(HtlcClaimed {
+            order_pubkey: ctx.accounts.order.key(),
+            by: ctx.accounts.solver.key(),
+            preimage,
+        });
+        Ok(())
+    }
+
+    pub fn claim_htlc_order_spl(ctx: Context<ClaimHtlcOrderSpl>, preimage: [u8; 32]) -> Result<()> {
+        let order = &ctx.accounts.order;
+        let config = &ctx.accounts.global_config;
+        require!(!config.paused, CustomError::Paused);
+        require!(order.is_htlc(), CustomError::NotHtlcOrder);
+        require_keys_eq!(
+            ctx.accounts.solver.key(),
+            order.htlc_solver,
+            CustomError::NotHtlcSolver
+        );
+
+        let clock = Clock::get()?;
+        require!(
+            clock.unix_timestamp < order.timelock,
+            CustomError::TimelockExpired
+        );
+        require!(
+            hash(&preimage).to_bytes() == order.hashlock,
+            CustomError::InvalidPreimage
+        );
+
+        require_keys_eq!(
+            ctx.accounts.refund_receiver.key(),
+            order.refund_to(),
+            CustomError::InvalidRefundReceiver
+        );
+        delist_order(ctx.accounts.order_book.as_ref(), order)?;
+
+        let platform_fee = effective_platform_fee(config, &ctx.accounts.chain_config);
+        let (fee_amount, send_amount) =
+            calculate_fee(order.amount_in, platform_fee, config.round_fees_up)?;
+
+        let seeds = &[
+            b"limit_order",
+            order.sender.as_ref(),
+            &order.expiry.to_le_bytes(),
+            &[order.bump],
+        ];
+        let signer = &[&seeds[..]];
+
+        let cpi_accounts = TokenTransfer {
+            from: ctx.accounts.order_token_account.to_account_info(),
+            to: ctx.accounts.solver_token_account.to_account_info(),
+            authority: ctx.accounts.order.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            cpi_accounts,
+            signer,
+        );
+        token::transfer(cpi_ctx, send_amount)?;
+
+        let cpi_accounts = TokenTransfer {
+            from: ctx.accounts.order_token_account.to_account_info(),
+            to: ctx.accounts.treasury_token_account.to_account_info(),
+            authority: ctx.accounts.order.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            cpi_accounts,
+            signer,
+        );
+        token::transfer(cpi_ctx, fee_amount)?;
+
+        let close_cpi_accounts = CloseAccount {
+            account: ctx.accounts.order_token_account.to_account_info(),
+            destination: ctx.accounts.refund_receiver.to_account_info(),
+            authority: ctx.accounts.order.to_account_info(),
+        };
+        let close_cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            close_cpi_accounts,
+            signer,
+        );
+        token::close_account(close_cpi_ctx)?;
+
+        emit!(HtlcClaimed {
+            order_pubkey: ctx.accounts.order.key(),
+            by: ctx.accounts.solver.key(),
+            preimage,
+        });
+        Ok(())
+    }
+
+    pub fn refund_htlc_order_sol(ctx: Context<RefundHtlcOrderSol>) -> Result<()> {
+        let order = &ctx.accounts.order;
+        require!(order.is_htlc(), CustomError::NotHtlcOrder);
+        require!(!order.wrapped_sol, CustomError::InvalidEscrow);
+
+        let clock = Clock::get()?;
+        require!(
+            clock.unix_timestamp >= order.timelock,
+            CustomError::TimelockNotExpired
+        );
+
+        require_keys_eq!(
+            ctx.accounts.refund_receiver.key(),
+            order.refund_to(),
+            CustomError::InvalidRefundReceiver
+        );
+        delist_order(ctx.accounts.order_book.as_ref(), order)?;
+
+        **ctx
+            .accounts
+            .order
+            .to_account_info()
+            .try_borrow_mut_lamports()? -= order.amount_in;
+        **ctx
+            .accounts
+            .refund_receiver
+            .to_account_info()
+            .try_borrow_mut_lamports()? += order.amount_in;
+
+        emit!(HtlcRefunded {
+            order_pubkey: ctx.accounts.order.key(),
+            by: ctx.accounts.caller.key(),
+        });
+        Ok(())
+    }
+
+    pub fn refund_htlc_order_spl(ctx: Context<RefundHtlcOrderSpl>) -> Result<()> {
+        let order = &ctx.accounts.order;
+        require!(order.is_htlc(), CustomError::NotHtlcOrder);
+
+        let clock = Clock::get()?;
+        require!(
+            clock.unix_timestamp >= order.timelock,
+            CustomError::TimelockNotExpired
+        );
+
+        require_keys_eq!(
+            ctx.accounts.refund_receiver.key(),
+            order.refund_to(),
+            CustomError::InvalidRefundReceiver
+        );
+        delist_order(ctx.accounts.order_book.as_ref(), order)?;
+
+        let seeds = &[
+            b"limit_order",
+            order.sender.as_ref(),
+            &order.expiry.to_le_bytes(),
+            &[order.bump],
+        ];
+        let signer = &[&seeds[..]];
+
+        let cpi_accounts = TokenTransfer {
+            from: ctx.accounts.order_token_account.to_account_info(),
+            to: ctx.accounts.user_token_account.to_account_info(),
+            authority: ctx.accounts.order.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            cpi_accounts,
+            signer,
+        );
+        token::transfer(cpi_ctx, order.amount_in)?;
+
+        let close_cpi_accounts = CloseAccount {
+            account: ctx.accounts.order_token_account.to_account_info(),
+            destination: ctx.accounts.refund_receiver.to_account_info(),
+            authority: ctx.accounts.order.to_account_info(),
+        };
+        let close_cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            close_cpi_accounts,
+            signer,
+        );
+        token::close_account(close_cpi_ctx)?;
+
+        emit!(HtlcRefunded {
+            order_pubkey: ctx.accounts.order.key(),
+            by: ctx.accounts.caller.key(),
+        });
+        Ok(())
+    }
+
+    /// Bonds lamports for `executor`, backing fills of SOL orders, both
+    /// native and wrapped.
+    pub fn bond_executor_sol(ctx: Context<BondExecutorSol>, amount: u64) -> Result<()> {
+        require!(amount > 0, CustomError::InvalidParameter);
+
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.executor.to_account_info(),
+                    to: ctx.accounts.executor_bond.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+
+        let bond = &mut ctx.accounts.executor_bond;
+        bond.executor = ctx.accounts.executor.key();
+        bond.mint = native_token();
+        bond.bump = ctx.bumps.executor_bond;
+        bond.amount = bond
+            .amount
+            .checked_add(amount)
+            .ok_or(CustomError::Overflow)?;
+
+        emit!(ExecutorBonded {
+            executor: bond.executor,
+            mint: bond.mint,
+            amount,
+            total: bond.amount,
+        });
+        Ok(())
+    }
+
+    /// Bonds SPL tokens for `executor`, backing fills of orders in that mint.
+    pub fn bond_executor_spl(ctx: Context<BondExecutorSpl>, amount: u64) -> Result<()> {
+        require!(amount > 0, CustomError::InvalidParameter);
+        // SOL bonds are held as lamports so they cover native and wrapped orders.
+        require_keys_neq!(
+            ctx.accounts.token_mint.key(),
+            native_token(),
+            CustomError::InvalidBond
+        );
+
+        let cpi_accounts = TokenTransfer {
+            from: ctx.accounts.executor_token_account.to_account_info(),
+            to: ctx.accounts.bond_token_account.to_account_info(),
+            authority: ctx.accounts.executor.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
+        token::transfer(cpi_ctx, amount)?;
+
+        let bond = &mut ctx.accounts.executor_bond;
+        bond.executor = ctx.accounts.executor.key();
+        bond.mint = ctx.accounts.token_mint.key();
+        bond.bump = ctx.bumps.executor_bond;
+        bond.amount = bond
+            .amount
+            .checked_add(amount)
+            .ok_or(CustomError::Overflow)?;
+
+        emit!(ExecutorBonded {
+            executor: bond.executor,
+            mint: bond.mint,
+            amount,
+            total: bond.amount,
+        });
+        Ok(())
+    }
+
+    /// Moves `amount` out of the active bond. It stays slashable until
+    /// `GlobalConfig::unbond_cooldown` has passed since the latest request.
+    pub fn request_unbond(ctx: Context<RequestUnbond>, amount: u64) -> Result<()> {
+        let config = &ctx.accounts.global_config;
+        let bond = &mut ctx.accounts.executor_bond;
+        require!(
+            amount > 0 && amount <= bond.amount,
+            CustomError::InvalidParameter
+        );
+
+        let clock = Clock::get()?;
+        bond.release_settled(clock.unix_timestamp, config.slash_window);
+        let remaining = bond.amount - amount;
+        require!(
+            bond.exposure()? <= remaining.saturating_mul(config.bond_multiple as u64),
+            CustomError::BondLimitExceeded
+        );
+
+        bond.amount = remaining;
+        bond.unbonding_amount = bond
+            .unbonding_amount
+            .checked_add(amount)
+            .ok_or(CustomError::Overflow)?;
+        bond.unbond_requested_at = clock.unix_timestamp;
+
+        emit!(UnbondRequested {
+            executor: bond.executor,
+            mint: bond.mint,
+            amount,
+            withdrawable_at: clock
+                .unix_timestamp
+                .checked_add(config.unbond_cooldown)
+                .ok_or(CustomError::Overflow)?,
+        });
+        Ok(())
+    }
+
+    pub fn withdraw_bond_sol(ctx: Context<WithdrawBondSol>) -> Result<()> {
+        let clock = Clock::get()?;
+        let amount = take_unbonded(
+            &ctx.accounts.global_config,
+            &mut ctx.accounts.executor_bond,
+            clock.unix_timestamp,
+        )?;
+
+        **ctx
+            .accounts
+            .executor_bond
+            .to_account_info()
+            .try_borrow_mut_lamports()? -= amount;
+        **ctx.accounts.executor.try_borrow_mut_lamports()? += amount;
+
+        emit!(BondWithdrawn {
+            executor: ctx.accounts.executor.key(),
+            mint: native_token(),
+            amount,
+        });
+        Ok(())
+    }
+
+    pub fn withdraw_bond_spl(ctx: Context<WithdrawBondSpl>) -> Result<()> {
+        let clock = Clock::get()?;
+        let amount = take_unbonded(
+            &ctx.accounts.global_config,
+            &mut ctx.accounts.executor_bond,
+            clock.unix_timestamp,
+        )?;
+
+        let bond = &ctx.accounts.executor_bond;
+        let seeds = &[
+            b"executor-bond".as_ref(),
+            bond.executor.as_ref(),
+            bond.mint.as_ref(),
+            &[bond.bump],
+        ];
+        let signer = &[&seeds[..]];
+
+        let cpi_accounts = TokenTransfer {
+            from: ctx.accounts.bond_token_account.to_account_info(),
+            to: ctx.accounts.executor_token_account.to_account_info(),
+            authority: ctx.accounts.executor_bond.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            cpi_accounts,
+            signer,
+        );
+        token::transfer(cpi_ctx, amount)?;
+
+        emit!(BondWithdrawn {
+            executor: bond.executor,
+            mint: bond.mint,
+            amount,
+        });
+        Ok(())
+    }
+
+    /// Pays the refund address of `order` out of the lamport bond of the
+    /// executor that filled it, once guardians attest the fill was never delivered.
+    pub fn slash_executor_sol(ctx: Context<SlashExecutorSol>, order: Pubkey) -> Result<()> {
+        let clock = Clock::get()?;
+        require_slasher(
+            &ctx.accounts.global_config,
+            ctx.accounts.roles.as_ref(),
+            &ctx.accounts.guardian_set,
+            &ctx.accounts.authority.key(),
+        )?;
+        let (execution, amount) = slash_bond(
+            &ctx.accounts.global_config,
+            &mut ctx.accounts.executor_bond,
+            &ctx.accounts.guardian_set,
+            &ctx.accounts.instructions,
+            &order,
+            clock.unix_timestamp,
+        )?;
+        require_keys_eq!(
+            ctx.accounts.recipient.key(),
+            execution.refund_to,
+            CustomError::InvalidRefundReceiver
+        );
+
+        **ctx
+            .accounts
+            .executor_bond
+            .to_account_info()
+            .try_borrow_mut_lamports()? -= amount;
+        **ctx.accounts.recipient.try_borrow_mut_lamports()? += amount;
+
+        emit!(ExecutorSlashed {
+            executor: ctx.accounts.executor_bond.executor,
+            mint: native_token(),
+            order_pubkey: order,
+            recipient: execution.refund_to,
+            amount,
+        });
+        Ok(())
+    }
+
+    /// Token-bond counterpart of `slash_executor_sol`; pays into the refund
+    /// address's token account for the bonded mint.
+    pub fn slash_executor_spl(ctx: Context<SlashExecutorSpl>, order: Pubkey) -> Result<()> {
+        let clock = Clock::get()?;
+        require_slasher(
+            &ctx.accounts.global_config,
+            ctx.accounts.roles.as_ref(),
+            &ctx.accounts.guardian_set,
+            &ctx.accounts.authority.key(),
+        )?;
+        let (execution, amount) = slash_bond(
+            &ctx.accounts.global_config,
+            &mut ctx.accounts.executor_bond,
+            &ctx.accounts.guardian_set,
+            &ctx.accounts.instructions,
+            &order,
+            clock.unix_timestamp,
+        )?;
+        require_keys_eq!(
+            ctx.accounts.recipient_token_account.owner,
+            execution.refund_to,
+            CustomError::InvalidRefundReceiver
+        );
+
+        let bond = &ctx.accounts.executor_bond;
+        let seeds = &[
+            b"executor-bond".as_ref(),
+            bond.executor.as_ref(),
+            bond.mint.as_ref(),
+            &[bond.bump],
+        ];
+        let signer = &[&seeds[..]];
+
+        let cpi_accounts = TokenTransfer {
+            from: ctx.accounts.bond_token_account.to_account_info(),
+            to: ctx.accounts.recipient_token_account.to_account_info(),
+            authority: ctx.accounts.executor_bond.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            cpi_accounts,
+            signer,
+        );
+        token::transfer(cpi_ctx, amount)?;
+
+        emit!(ExecutorSlashed {
+            executor: bond.executor,
+            mint: bond.mint,
+            order_pubkey: order,
+            recipient: execution.refund_to,
+            amount,
+        });
+        Ok(())
+    }
+}
+
+fn native_token() -> Pubkey {
+    spl_token::native_mint::ID
+}
+
+/// Instruction data for the swap adapter interface,
+/// `swap(amount_in: u64, min_amount_out: u64)`.
+fn swap_adapter_data(amount_in: u64, min_amount_out: u64) -> Vec<u8> {
+    let mut data = hash(b"global:swap").to_bytes()[..8].to_vec();
+    data.extend_from_slice(&amount_in.to_le_bytes());
+    data.extend_from_slice(&min_amount_out.to_le_bytes());
+    data
+}
+
+fn add_order(order: &mut Account<LimitOrder>, sender: Pubkey, params: &OpenOrderParams, bump: u8) {
+    order.from_token = params.from_token;
+    order.from_chain_id = params.from_chain_id;
+    order.amount_in = params.amount_in;
+    order.to_chain_id = params.to_chain_id;
+    order.to_token = params.to_token;
+    order.recipient = params.recipient;
+    order.sender = sender;
     order.expiry = params.expiry;
     order.amount_out = params.amount_out;
     order.bump = bump;
+    order.hashlock = params.hashlock;
+    order.timelock = params.timelock;
+    order.htlc_solver = params.htlc_solver;
+    order.wrapped_sol = false;
+    order.cancel_requested_at = 0;
+    order.exclusive_executor = params.exclusive_executor;
+    order.exclusivity_deadline = params.exclusivity_deadline;
+    order.version = LimitOrder::VERSION;
+    order.in_order_book = false;
+    order.refund_address = if params.refund_address == Pubkey::default() {
+        sender
+    } else {
+        params.refund_address
+    };
+    order.good_till_cancelled = params.good_till_cancelled;
+}
+
+/// The wallet an order is opened for: the session key's user when `user`
+/// signs through one, otherwise `user` itself.
+fn order_sender(user: &Signer, session_key: &Option<Account<SessionKey>>) -> Pubkey {
+    session_key
+        .as_ref()
+        .map_or_else(|| user.key(), |session| session.user)
+}
+
+/// Charges an order opened through a session key against the session's
+/// expiry, mint list and remaining allowance. Such orders always refund to
+/// the session's user.
+fn spend_session_allowance(
+    session_key: Option<&mut Account<SessionKey>>,
+    params: &OpenOrderParams,
+) -> Result<()> {
+    let Some(session) = session_key else {
+        return Ok(());
+    };
+    let clock = Clock::get()?;
+    require!(
+        clock.unix_timestamp < session.expires_at,
+        CustomError::SessionKeyExpired
+    );
+    require!(
+        params.refund_address == Pubkey::default() || params.refund_address == session.user,
+        CustomError::SessionNotPermitted
+    );
+    require!(
+        session.allows_mint(&params.from_token),
+        CustomError::SessionNotPermitted
+    );
+    session.open_allowance = session
+        .open_allowance
+        .checked_sub(params.amount_in)
+        .ok_or(CustomError::SessionAllowanceExceeded)?;
+    Ok(())
+}
+
+/// The key a cancel acts as: the order's sender when `user` signs through a
+/// live session key of that sender that may cancel, otherwise `user` itself.
+fn session_canceller(
+    user: &Signer,
+    session_key: Option<&Account<SessionKey>>,
+    order: &LimitOrder,
+    now: i64,
+) -> Result<Pubkey> {
+    let Some(session) = session_key else {
+        return Ok(user.key());
+    };
+    require_keys_eq!(session.user, order.sender, CustomError::InvalidSessionKey);
+    require!(now < session.expires_at, CustomError::SessionKeyExpired);
+    require!(session.can_cancel, CustomError::SessionNotPermitted);
+    Ok(session.user)
+}
+
+/// Whether `key` holds `role`. Until `init_roles` runs the owner holds every
+/// role; afterwards membership in the `Roles` account decides.
+fn has_role(
+    config: &GlobalConfig,
+    roles: Option<&Account<Roles>>,
+    key: &Pubkey,
+    role: Role,
+) -> bool {
+    if !config.roles_enabled {
+        return *key == config.owner;
+    }
+    roles.is_some_and(|roles| roles.has(key, role))
+}
+
+fn require_role(
+    config: &GlobalConfig,
+    roles: Option<&Account<Roles>>,
+    key: &Pubkey,
+    role: Role,
+) -> Result<()> {
+    require!(has_role(config, roles, key, role), CustomError::MissingRole);
+    Ok(())
+}
+
+/// Admin actions can be taken by a single Admin only while the admin
+/// threshold is at most one; above that they go through a proposal.
+fn require_direct_admin(
+    config: &GlobalConfig,
+    roles: Option<&Account<Roles>>,
+    key: &Pubkey,
+) -> Result<()> {
+    require_role(config, roles, key, Role::Admin)?;
+    if let Some(roles) = roles.filter(|_| config.roles_enabled) {
+        require!(roles.admin_threshold <= 1, CustomError::MultisigRequired);
+    }
+    Ok(())
+}
+
+/// Whether `key` may act alone as an Admin: it holds Admin and no multisig
+/// threshold is set.
+fn is_direct_admin(config: &GlobalConfig, roles: Option<&Account<Roles>>, key: &Pubkey) -> bool {
+    require_direct_admin(config, roles, key).is_ok()
+}
+
+/// Authorizes an Admin action that runs through an instruction of its own:
+/// taken directly by a single Admin, or by an Admin passing the approved
+/// `proposal` for exactly `action`.
+fn authorize_admin_action(
+    config: &GlobalConfig,
+    roles: Option<&Account<Roles>>,
+    authority: &Pubkey,
+    proposal: Option<&mut Account<AdminProposal>>,
+    action: &AdminAction,
+) -> Result<()> {
+    let Some(proposal) = proposal else {
+        return require_direct_admin(config, roles, authority);
+    };
+    require_role(config, roles, authority, Role::Admin)?;
+    let roles = roles.ok_or(CustomError::RolesRequired)?;
+    require!(proposal.action == *action, CustomError::ProposalMismatch);
+    execute_proposal(roles, proposal)
+}
+
+/// Marks `proposal` executed once enough of its approvers still hold Admin.
+fn execute_proposal(roles: &Roles, proposal: &mut Account<AdminProposal>) -> Result<()> {
+    require!(!proposal.executed, CustomError::ProposalExecuted);
+    let approvals = proposal
+        .approvals
+        .iter()
+        .filter(|approver| roles.has(approver, Role::Admin))
+        .count();
+    require!(
+        approvals >= roles.admin_threshold.max(1) as usize,
+        CustomError::InsufficientApprovals
+    );
+    proposal.executed = true;
+
+    emit!(AdminActionExecuted {
+        proposal: proposal.key(),
+        id: proposal.id,
+    });
+    Ok(())
+}
+
+fn run_admin_action(accounts: &mut UpdateConfig, action: AdminAction) -> Result<()> {
+    require_direct_admin(
+        &accounts.global_config,
+        accounts.roles.as_ref(),
+        &accounts.authority.key(),
+    )?;
+    apply_admin_action(&mut accounts.global_config, accounts.roles.as_mut(), action)
+}
+
+/// Applies an Admin action once it has been authorized, either directly or
+/// through an approved proposal.
+fn apply_admin_action(
+    config: &mut GlobalConfig,
+    roles: Option<&mut Account<Roles>>,
+    action: AdminAction,
+) -> Result<()> {
+    match action {
+        AdminAction::SetTreasury { treasury } => {
+            require!(treasury != Pubkey::default(), CustomError::InvalidParameter);
+            let old_treasury = config.treasury;
+            config.treasury = treasury;
+            emit!(TreasuryUpdated {
+                old_treasury,
+                new_treasury: treasury,
+            });
+        }
+        AdminAction::TransferOwnership { owner } => {
+            require!(!config.roles_enabled, CustomError::RolesEnabled);
+            require!(owner != Pubkey::default(), CustomError::InvalidParameter);
+            let previous_owner = config.owner;
+            config.owner = owner;
+            emit!(OwnershipTransferred {
+                previous_owner,
+                new_owner: owner,
+            });
+        }
+        AdminAction::SetAttestationRequired {
+            attestation_required,
+        } => {
+            config.attestation_required = attestation_required;
+            emit!(AttestationRequirementUpdated {
+                attestation_required,
+            });
+        }
+        AdminAction::SetChainId { chain_id } => {
+            require!(chain_id != 0, CustomError::InvalidParameter);
+            config.chain_id = chain_id;
+            emit!(ChainIdUpdated { chain_id });
+        }
+        AdminAction::SetMintAllowlistMode {
+            mint_allowlist_mode,
+        } => {
+            config.mint_allowlist_mode = mint_allowlist_mode;
+            emit!(MintAllowlistModeUpdated {
+                mint_allowlist_mode,
+            });
+        }
+        AdminAction::SetCancelGracePeriod {
+            cancel_grace_period,
+        } => {
+            require!(cancel_grace_period >= 0, CustomError::InvalidParameter);
+            config.cancel_grace_period = cancel_grace_period;
+            emit!(CancelGracePeriodUpdated {
+                cancel_grace_period,
+            });
+        }
+        AdminAction::SetBondConfig {
+            bond_multiple,
+            unbond_cooldown,
+            slash_window,
+        } => {
+            require!(
+                unbond_cooldown >= 0 && slash_window >= 0,
+                CustomError::InvalidParameter
+            );
+            // An executor must not be able to withdraw before its fills stop
+            // being slashable.
+            require!(
+                unbond_cooldown >= slash_window,
+                CustomError::InvalidParameter
+            );
+            config.bond_multiple = bond_multiple;
+            config.unbond_cooldown = unbond_cooldown;
+            config.slash_window = slash_window;
+            emit!(BondConfigUpdated {
+                bond_multiple,
+                unbond_cooldown,
+                slash_window,
+            });
+        }
+        AdminAction::SetSwapConfig {
+            swap_adapter,
+            fee_on_output,
+        } => {
+            let old_swap_adapter = config.swap_adapter;
+            config.swap_adapter = swap_adapter;
+            config.swap_fee_on_output = fee_on_output;
+            emit!(SwapConfigUpdated {
+                old_swap_adapter,
+                new_swap_adapter: swap_adapter,
+                fee_on_output,
+            });
+        }
+        AdminAction::SetOrderLifetime {
+            min_order_lifetime,
+            max_order_lifetime,
+        } => {
+            require!(
+                min_order_lifetime >= 0
+                    && (max_order_lifetime == 0 || max_order_lifetime >= min_order_lifetime),
+                CustomError::InvalidParameter
+            );
+            config.min_order_lifetime = min_order_lifetime;
+            config.max_order_lifetime = max_order_lifetime;
+            emit!(OrderLifetimeUpdated {
+                min_order_lifetime,
+                max_order_lifetime,
+            });
+        }
+        AdminAction::SetOrderMinimums {
+            min_sol_order_size,
+            min_notional,
+            max_price_age,
+        } => {
+            require!(max_price_age > 0, CustomError::InvalidParameter);
+            config.min_sol_order_size = min_sol_order_size;
+            config.min_notional = min_notional;
+            config.max_price_age = max_price_age;
+            emit!(OrderMinimumsUpdated {
+                min_sol_order_size,
+                min_notional,
+                max_price_age,
+            });
+        }
+        AdminAction::SetChainConfig { .. }
+        | AdminAction::SetTokenRoute { .. }
+        | AdminAction::SetMintPolicy { .. }
+        | AdminAction::InitGuardianSet { .. }
+        | AdminAction::RotateGuardianSet { .. }
+        | AdminAction::InitOrderBook { .. }
+        | AdminAction::ForceRefund { .. }
+        | AdminAction::MigrateConfig { .. }
+        | AdminAction::MigrateOrder { .. } => {
+            return err!(CustomError::ActionNeedsAccounts);
+        }
+        AdminAction::GrantRole { key, role } => {
+            let roles = roles.ok_or(CustomError::RolesRequired)?;
+            roles.grant(key, role)?;
+            emit!(RoleGranted { key, role });
+        }
+        AdminAction::RevokeRole { key, role } => {
+            let roles = roles.ok_or(CustomError::RolesRequired)?;
+            roles.revoke(&key, role);
+            // At least one Admin must remain, and enough of them to meet the
+            // threshold.
+            require!(
+                roles.count(Role::Admin) >= roles.admin_threshold.max(1) as usize,
+                CustomError::InvalidParameter
+            );
+            emit!(RoleRevoked { key, role });
+        }
+        AdminAction::SetAdminThreshold { threshold } => {
+            let roles = roles.ok_or(CustomError::RolesRequired)?;
+            require!(
+                threshold as usize <= roles.count(Role::Admin),
+                CustomError::InvalidParameter
+            );
+            roles.admin_threshold = threshold;
+            emit!(AdminThresholdUpdated { threshold });
+        }
+    }
+    Ok(())
+}
+
+/// Adds `order` to `order_book` when the opener passes one.
+fn list_order(
+    order_book: Option<&AccountLoader<OrderBook>>,
+    order: &mut Account<LimitOrder>,
+) -> Result<()> {
+    let Some(order_book) = order_book else {
+        return Ok(());
+    };
+    let mut book = order_book.load_mut()?;
+    require!(book.is_market_of(order), CustomError::InvalidOrderBook);
+    book.insert(OrderBookSlot {
+        order: order.key(),
+        amount_in: order.amount_in,
+        expiry: order.live_until(),
+    })?;
+    order.in_order_book = true;
+    Ok(())
+}
+
+/// Removes a listed `order` from its book before it is closed. The entry may
+/// already be gone if the book was pruned after the order expired.
+fn delist_order(
+    order_book: Option<&AccountLoader<OrderBook>>,
+    order: &Account<LimitOrder>,
+) -> Result<()> {
+    if !order.in_order_book {
+        return Ok(());
+    }
+    let order_book = order_book.ok_or(CustomError::OrderBookRequired)?;
+    let mut book = order_book.load_mut()?;
+    require!(book.is_market_of(order), CustomError::InvalidOrderBook);
+    book.remove(&order.key());
+    Ok(())
+}
+
+/// Reads the order book slot of one order in a batch. Orders outside any
+/// book fill it with this program's id, the way Anchor marks a missing
+/// optional account.
+fn batch_order_book<'info>(
+    account: &'info AccountInfo<'info>,
+) -> Result<Option<AccountLoader<'info, OrderBook>>> {
+    if account.key() == crate::ID {
+        return Ok(None);
+    }
+    AccountLoader::try_from(account).map(Some)
+}
+
+/// Checks that `account` is owned by this program and carries
+/// `discriminator`, without deserializing a possibly outdated layout.
+fn check_account_type(account: &AccountInfo, discriminator: &[u8]) -> Result<()> {
+    require_keys_eq!(
+        *account.owner,
+        crate::ID,
+        ErrorCode::AccountOwnedByWrongProgram
+    );
+    require!(
+        account.try_borrow_data()?.starts_with(discriminator),
+        ErrorCode::AccountDiscriminatorMismatch
+    );
+    Ok(())
+}
+
+/// Grows `account` to `new_len`, zero-filling the new bytes, with `payer`
+/// covering the rent for them. Only the rent difference between the two
+/// sizes is added, since SOL orders also hold their escrow as lamports.
+fn grow_account<'info>(
+    account: &AccountInfo<'info>,
+    new_len: usize,
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>,
+) -> Result<()> {
+    let old_len = account.data_len();
+    if old_len >= new_len {
+        return Ok(());
+    }
+
+    let rent = Rent::get()?;
+    let top_up = rent
+        .minimum_balance(new_len)
+        .saturating_sub(rent.minimum_balance(old_len));
+    system_program::transfer(
+        CpiContext::new(
+            system_program.to_account_info(),
+            system_program::Transfer {
+                from: payer.to_account_info(),
+                to: account.clone(),
+            },
+        ),
+        top_up,
+    )?;
+    account.realloc(new_len, true)?;
+    Ok(())
+}
+
+/// Checks shared by every `open_order_*` instruction; callers validate
+/// `from_token` against their own escrow type.
+fn validate_open_order(
+    params: &OpenOrderParams,
+    config: &GlobalConfig,
+    chain_config: &ChainConfig,
+    token_route: &TokenRoute,
+) -> Result<()> {
+    require!(!config.paused, CustomError::Paused);
+    if params.from_chain_id != config.chain_id
+        || params.amount_in == 0
+        || params.to_chain_id == 0
+        || params.to_token == [0u8; 32]
+        || params.recipient == [0u8; 32]
+    {
+        return Err(error!(CustomError::InvalidParameter));
+    }
+    let clock = Clock::get()?;
+    if params.expiry <= clock.unix_timestamp {
+        return Err(error!(CustomError::InvalidParameter));
+    }
+    validate_lifetime(config, params, clock.unix_timestamp)?;
+    validate_htlc_params(params, clock.unix_timestamp)?;
+    validate_exclusivity_params(params, clock.unix_timestamp)?;
+    validate_destination(config, chain_config, params)?;
+    validate_token_route(token_route, params)?;
+    Ok(())
+}
+
+/// Bounds how far out a regular order can expire. Good-till-cancelled orders
+/// use `expiry` only as a nonce, so they skip the bounds but can't be HTLCs,
+/// whose refund path depends on a deadline.
+fn validate_lifetime(config: &GlobalConfig, params: &OpenOrderParams, now: i64) -> Result<()> {
+    if params.good_till_cancelled {
+        require!(params.hashlock == [0u8; 32], CustomError::InvalidParameter);
+        return Ok(());
+    }
+    let lifetime = params.expiry.saturating_sub(now);
+    require!(
+        lifetime >= config.min_order_lifetime
+            && (config.max_order_lifetime == 0 || lifetime <= config.max_order_lifetime),
+        CustomError::InvalidLifetime
+    );
+    Ok(())
+}
+
+fn validate_htlc_params(params: &OpenOrderParams, now: i64) -> Result<()> {
+    // A zero hashlock opens a regular order, which must not carry a timelock
+    // or a solver.
+    if params.hashlock == [0u8; 32] {
+        require!(
+            params.timelock == 0 && params.htlc_solver == Pubkey::default(),
+            CustomError::InvalidParameter
+        );
+    } else {
+        require!(
+            params.timelock > now && params.htlc_solver != Pubkey::default(),
+            CustomError::InvalidParameter
+        );
+    }
+    Ok(())
+}
+
+fn validate_exclusivity_params(params: &OpenOrderParams, now: i64) -> Result<()> {
+    // A default executor opens the order to every registered executor.
+    if params.exclusive_executor == Pubkey::default() {
+        require!(
+            params.exclusivity_deadline == 0,
+            CustomError::InvalidParameter
+        );
+    } else {
+        require!(
+            params.hashlock == [0u8; 32]
+                && params.exclusivity_deadline > now
+                && params.exclusivity_deadline <= params.expiry,
+            CustomError::InvalidParameter
+        );
+    }
+    Ok(())
+}
+
+/// Requires the exclusive executor of an RFQ order to have signed a
+/// [`FillQuote`] matching `params` through an Ed25519 program instruction in
+/// the same transaction.
+fn verify_fill_quote(
+    params: &OpenOrderParams,
+    sender: &Pubkey,
+    instructions: Option<&UncheckedAccount>,
+) -> Result<()> {
+    if params.exclusive_executor == Pubkey::default() {
+        return Ok(());
+    }
+    let instructions = instructions.ok_or(CustomError::InvalidQuote)?;
+
+    let quote = FillQuote {
+        sender: *sender,
+        expiry: params.expiry,
+        from_token: params.from_token,
+        amount_in: params.amount_in,
+        to_chain_id: params.to_chain_id,
+        to_token: params.to_token,
+        recipient: params.recipient,
+        amount_out: params.amount_out,
+        exclusivity_deadline: params.exclusivity_deadline,
+    };
+    let signatures = load_ed25519_signatures(instructions)?;
+    require!(
+        signatures.iter().any(|(key, message)| {
+            *key == params.exclusive_executor
+                && FillQuote::from_message(message).is_some_and(|signed| signed == quote)
+        }),
+        CustomError::InvalidQuote
+    );
+    Ok(())
+}
+
+/// Only the quoting solver may fill an RFQ order before its exclusivity
+/// deadline.
+fn check_exclusivity(order: &LimitOrder, executor: &Pubkey, now: i64) -> Result<()> {
+    require!(
+        now >= order.exclusivity_deadline || *executor == order.exclusive_executor,
+        CustomError::ExclusivityActive
+    );
+    Ok(())
+}
+
+/// Splits `amount_in` into `(fee_amount, send_amount)` for a fee in basis
+/// points, rounding the fee up when `round_up` is set.
+fn calculate_fee(amount_in: u64, platform_fee: u16, round_up: bool) -> Result<(u64, u64)> {
+    let fee_product = amount_in
+        .checked_mul(platform_fee as u64)
+        .ok_or(CustomError::Overflow)?;
+    let fee_amount = if round_up {
+        fee_product.div_ceil(10000)
+    } else {
+        fee_product / 10000
+    };
+    let send_amount = amount_in
+        .checked_sub(fee_amount)
+        .ok_or(CustomError::Overflow)?;
+    require!(send_amount > 0, CustomError::InsufficientFunds);
+    Ok((fee_amount, send_amount))
+}
+
+fn validate_destination(
+    config: &GlobalConfig,
+    chain_config: &ChainConfig,
+    params: &OpenOrderParams,
+) -> Result<()> {
+    require!(chain_config.enabled, CustomError::ChainNotSupported);
+    require!(
+        chain_config.address_format.is_valid(&params.recipient),
+        CustomError::InvalidRecipient
+    );
+    if params.to_chain_id == config.chain_id {
+        // Same-chain orders settle through `fill_local_order`, which pays out
+        // `amount_out` of the `to_token` mint to the recipient's wallet.
+        require!(
+            params.to_token != params.from_token.to_bytes()
+                && params.hashlock == [0u8; 32]
+                && local_amount(&params.amount_out).is_some_and(|amount| amount > 0),
+            CustomError::InvalidParameter
+        );
+    }
+    require!(
+        params.amount_in >= chain_config.min_amount_in
+            && (chain_config.max_amount_in == 0 || params.amount_in <= chain_config.max_amount_in),
+        CustomError::AmountOutOfRange
+    );
+    Ok(())
+}
+
+/// Reads a big-endian 256-bit amount that has to fit a Solana token amount.
+fn local_amount(amount: &[u8; 32]) -> Option<u64> {
+    let (high, low) = amount.split_at(24);
+    high.iter()
+        .all(|byte| *byte == 0)
+        .then(|| u64::from_be_bytes(low.try_into().unwrap()))
+}
+
+fn local_amount_bytes(amount: u64) -> [u8; 32] {
+    let mut bytes = [0u8; 32];
+    bytes[24..].copy_from_slice(&amount.to_be_bytes());
+    bytes
+}
+
+fn validate_token_route(route: &TokenRoute, params: &OpenOrderParams) -> Result<()> {
+    require!(route.enabled, CustomError::TokenRouteNotSupported);
+    require!(
+        params.amount_in >= route.min_amount_in
+            && (route.max_amount_in == 0 || params.amount_in <= route.max_amount_in),
+        CustomError::AmountOutOfRange
+    );
+    Ok(())
+}
+
+/// Applies the optional `MintPolicy` PDA of `mint`. Without a policy account
+/// the mint is only accepted outside of allowlist mode.
+fn check_mint_policy(
+    config: &GlobalConfig,
+    mint_policy: &UncheckedAccount,
+    mint: &Account<Mint>,
+    amount_in: u64,
+) -> Result<()> {
+    if mint_policy.data_is_empty() {
+        require!(!config.mint_allowlist_mode, CustomError::MintNotAllowed);
+        return Ok(());
+    }
+
+    require_keys_eq!(*mint_policy.owner, crate::ID, CustomError::MintNotAllowed);
+    let policy = MintPolicy::try_deserialize(&mut &mint_policy.try_borrow_data()?[..])?;
+    require!(
+        policy.status == MintStatus::Allowed,
+        CustomError::MintNotAllowed
+    );
+    require!(
+        amount_in >= policy.min_order_size,
+        CustomError::AmountOutOfRange
+    );
+    if policy.require_no_freeze_authority {
+        require!(
+            mint.freeze_authority.is_none(),
+            CustomError::MintHasFreezeAuthority
+        );
+    }
+    Ok(())
+}
+
+/// Keeps out orders too small to be worth executing: SOL orders below
+/// `min_sol_order_size`, and orders worth less than `min_notional` when
+/// their `from_token` has a price. A stale price rejects the order rather
+/// than skipping the check.
+fn check_order_size(
+    config: &GlobalConfig,
+    price_feed: &UncheckedAccount,
+    params: &OpenOrderParams,
+) -> Result<()> {
+    if params.from_token == native_token() {
+        require!(
+            params.amount_in >= config.min_sol_order_size,
+            CustomError::AmountOutOfRange
+        );
+    }
+    if config.min_notional == 0 || price_feed.data_is_empty() {
+        return Ok(());
+    }
+
+    require_keys_eq!(*price_feed.owner, crate::ID, CustomError::InvalidParameter);
+    let feed = PriceFeed::try_deserialize(&mut &price_feed.try_borrow_data()?[..])?;
+    let age = Clock::get()?.unix_timestamp.saturating_sub(feed.updated_at);
+    require!(age <= config.max_price_age, CustomError::StalePrice);
+    require!(
+        feed.notional(params.amount_in) >= config.min_notional as u128,
+        CustomError::AmountOutOfRange
+    );
+    Ok(())
+}
+
+fn effective_platform_fee(config: &GlobalConfig, chain_config: &ChainConfig) -> u16 {
+    chain_config
+        .platform_fee_override
+        .unwrap_or(config.platform_fee)
+}
+
+/// Checks shared by every way of filling a same-chain order.
+fn check_local_fill(accounts: &FillLocalOrder, now: i64) -> Result<()> {
+    let order = &accounts.order;
+    require!(order.is_local(), CustomError::NotLocalOrder);
+    require!(!accounts.global_config.paused, CustomError::Paused);
+    require!(order.is_live(now), CustomError::ExpiryEarlier);
+    check_exclusivity(order, &accounts.taker.key(), now)?;
+    require_keys_eq!(
+        accounts.refund_receiver.key(),
+        order.refund_to(),
+        CustomError::InvalidRefundReceiver
+    );
+    Ok(())
+}
+
+/// Takes `amount_in` of the escrow for its share of `amount_out`, closing the
+/// order once nothing is left and shrinking it otherwise.
+fn take_local_order(accounts: &mut FillLocalOrder, amount_in: u64) -> Result<()> {
+    let order = &accounts.order;
+    let total_out = local_amount(&order.amount_out).ok_or(CustomError::InvalidParameter)?;
+    let remaining_in = order.amount_in - amount_in;
+    let amount_out =
+        (total_out as u128 * amount_in as u128).div_ceil(order.amount_in as u128) as u64;
+    // Rounding must not leave escrow behind that would be free to take.
+    require!(
+        remaining_in == 0 || amount_out < total_out,
+        CustomError::InvalidParameter
+    );
+
+    let fee_amount = settle_local_fill(accounts, amount_in, amount_out)?;
+    if remaining_in == 0 {
+        delist_order(accounts.order_book.as_ref(), &accounts.order)?;
+        close_filled_order(accounts)?;
+    } else {
+        let order = &mut accounts.order;
+        order.amount_in = remaining_in;
+        order.amount_out = local_amount_bytes(total_out - amount_out);
+        if order.in_order_book {
+            let order_book = accounts
+                .order_book
+                .as_ref()
+                .ok_or(CustomError::OrderBookRequired)?;
+            let mut book = order_book.load_mut()?;
+            require!(book.is_market_of(order), CustomError::InvalidOrderBook);
+            book.resize(&order.key(), remaining_in);
+        }
+    }
+
+    emit!(LocalOrderFilled {
+        order_pubkey: accounts.order.key(),
+        taker: accounts.taker.key(),
+        amount_in,
+        amount_out,
+        fee_amount,
+        remaining_amount_in: remaining_in,
+    });
+    if remaining_in == 0 {
+        emit!(OrderExecuted {
+            order_pubkey: accounts.order.key(),
+            by: accounts.taker.key(),
+            native_token_volume: 0,
+        });
+    }
+    Ok(())
+}
+
+/// Pays `amount_out` of the output mint from the taker to the recipient and
+/// `amount_in` of the escrow, less the platform fee, to the taker. Returns
+/// the fee.
+fn settle_local_fill(accounts: &FillLocalOrder, amount_in: u64, amount_out: u64) -> Result<u64> {
+    let order = &accounts.order;
+    let config = &accounts.global_config;
+    let platform_fee = effective_platform_fee(config, &accounts.chain_config);
+    let (fee_amount, send_amount) = calculate_fee(amount_in, platform_fee, config.round_fees_up)?;
+
+    let cpi_accounts = TokenTransfer {
+        from: accounts.taker_output_account.to_account_info(),
+        to: accounts.recipient_token_account.to_account_info(),
+        authority: accounts.taker.to_account_info(),
+    };
+    let cpi_ctx = CpiContext::new(accounts.token_program.to_account_info(), cpi_accounts);
+    token::transfer(cpi_ctx, amount_out)?;
+
+    if !order.uses_token_escrow() {
+        let order_info = order.to_account_info();
+        **order_info.try_borrow_mut_lamports()? -= amount_in;
+        **accounts.taker.try_borrow_mut_lamports()? += send_amount;
+        **accounts.treasury.try_borrow_mut_lamports()? += fee_amount;
+        return Ok(fee_amount);
+    }
+
+    let (Some(order_token_account), Some(taker_token_account), Some(treasury_token_account)) = (
+        &accounts.order_token_account,
+        &accounts.taker_token_account,
+        &accounts.treasury_token_account,
+    ) else {
+        return Err(error!(CustomError::InvalidEscrow));
+    };
+    let seeds = &[
+        b"limit_order",
+        order.sender.as_ref(),
+        &order.expiry.to_le_bytes(),
+        &[order.bump],
+    ];
+    let signer = &[&seeds[..]];
+    for (to, amount) in [
+        (taker_token_account, send_amount),
+        (treasury_token_account, fee_amount),
+    ] {
+        let cpi_accounts = TokenTransfer {
+            from: order_token_account.to_account_info(),
+            to: to.to_account_info(),
+            authority: order.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(
+            accounts.token_program.to_account_info(),
+            cpi_accounts,
+            signer,
+        );
+        token::transfer(cpi_ctx, amount)?;
+    }
+    Ok(fee_amount)
+}
+
+/// Closes a fully filled local order and its token escrow, if any, to the
+/// refund address.
+fn close_filled_order(accounts: &FillLocalOrder) -> Result<()> {
+    let order = &accounts.order;
+    if let Some(order_token_account) = &accounts.order_token_account {
+        let seeds = &[
+            b"limit_order",
+            order.sender.as_ref(),
+            &order.expiry.to_le_bytes(),
+            &[order.bump],
+        ];
+        let signer = &[&seeds[..]];
+        let close_cpi_accounts = CloseAccount {
+            account: order_token_account.to_account_info(),
+            destination: accounts.refund_receiver.to_account_info(),
+            authority: order.to_account_info(),
+        };
+        let close_cpi_ctx = CpiContext::new_with_signer(
+            accounts.token_program.to_account_info(),
+            close_cpi_accounts,
+            signer,
+        );
+        token::close_account(close_cpi_ctx)?;
+    }
+    order.close(accounts.refund_receiver.to_account_info())
+}
+
+/// Lets Executors execute freely and a bonded executor execute `order` as long
+/// as its open fills stay within `GlobalConfig::bond_multiple` times its bond.
+/// The fill is recorded on the bond so it can be slashed until
+/// `GlobalConfig::slash_window` has passed.
+fn authorize_executor(
+    config: &GlobalConfig,
+    roles: Option<&Account<Roles>>,
+    executor: &Pubkey,
+    executor_bond: Option<&mut Account<ExecutorBond>>,
+    order_key: &Pubkey,
+    order: &LimitOrder,
+    now: i64,
+) -> Result<()> {
+    require!(!config.paused, CustomError::Paused);
+    check_exclusivity(order, executor, now)?;
+    if has_role(config, roles, executor, Role::Executor) {
+        return Ok(());
+    }
+    let bond = executor_bond.ok_or(CustomError::OnlyOwnerCanExecute)?;
+    require!(config.bond_multiple > 0, CustomError::OnlyOwnerCanExecute);
+    require!(
+        bond.executor == *executor && bond.mint == order.from_token,
+        CustomError::InvalidBond
+    );
+
+    bond.release_settled(now, config.slash_window);
+    let exposure = bond
+        .exposure()?
+        .checked_add(order.amount_in)
+        .ok_or(CustomError::Overflow)?;
+    require!(
+        exposure <= bond.amount.saturating_mul(config.bond_multiple as u64)
+            && bond.pending.len() < ExecutorBond::MAX_PENDING,
+        CustomError::BondLimitExceeded
+    );
+
+    bond.pending.push(BondedExecution {
+        order: *order_key,
+        refund_to: order.refund_to(),
+        amount: order.amount_in,
+        executed_at: now,
+    });
+    Ok(())
+}
+
+/// Empties the unbonding amount once the cooldown has passed.
+fn take_unbonded(config: &GlobalConfig, bond: &mut ExecutorBond, now: i64) -> Result<u64> {
+    require!(bond.unbonding_amount > 0, CustomError::InvalidParameter);
+    require!(
+        now >= bond
+            .unbond_requested_at
+            .checked_add(config.unbond_cooldown)
+            .ok_or(CustomError::Overflow)?,
+        CustomError::UnbondCooldownActive
+    );
+
+    let amount = bond.unbonding_amount;
+    bond.unbonding_amount = 0;
+    Ok(amount)
+}
+
+/// Validates one order of `execute_orders_batch` without side effects and
+/// returns it with its `(fee_amount, send_amount)` split.
+fn prepare_batch_execution<'info>(
+    config: &GlobalConfig,
+    executor: &Pubkey,
+    guardian_set: Option<&Account<GuardianSet>>,
+    instructions: Option<&UncheckedAccount>,
+    accounts: &'info [AccountInfo<'info>],
+    now: i64,
+) -> Result<(Account<'info, LimitOrder>, u64, u64)> {
+    let order = Account::<LimitOrder>::try_from(&accounts[0])?;
+    require!(
+        order.to_account_info().is_writable,
+        CustomError::InvalidBatchAccounts
+    );
+    require!(!order.is_htlc(), CustomError::HtlcOrder);
+    require!(!order.is_local(), CustomError::LocalOrder);
+    require!(order.uses_token_escrow(), CustomError::InvalidEscrow);
+    require!(order.is_live(now), CustomError::ExpiryEarlier);
+    check_exclusivity(&order, executor, now)?;
+    if order.in_order_book {
+        let order_book = batch_order_book(&accounts[6])?.ok_or(CustomError::OrderBookRequired)?;
+        require!(
+            order_book.load()?.is_market_of(&order),
+            CustomError::InvalidOrderBook
+        );
+    }
+    require_keys_eq!(
+        accounts[4].key(),
+        order.refund_to(),
+        CustomError::InvalidRefundReceiver
+    );
+
+    let order_token_account = Account::<TokenAccount>::try_from(&accounts[1])?;
+    require_keys_eq!(
+        order_token_account.owner,
+        order.key(),
+        CustomError::InvalidEscrow
+    );
+
+    let treasury_token_account = Account::<TokenAccount>::try_from(&accounts[3])?;
+    require!(
+        treasury_token_account.owner == config.treasury
+            && treasury_token_account.mint == order.from_token
+            && accounts[3].key()
+                == get_associated_token_address(&config.treasury, &order.from_token),
+        CustomError::InvalidBatchAccounts
+    );
+
+    let chain_config = Account::<ChainConfig>::try_from(&accounts[5])?;
+    let chain_config_key = Pubkey::create_program_address(
+        &[
+            b"chain-config",
+            &order.to_chain_id.to_le_bytes(),
+            &[chain_config.bump],
+        ],
+        &crate::ID,
+    )
+    .map_err(|_| CustomError::InvalidBatchAccounts)?;
+    require_keys_eq!(
+        accounts[5].key(),
+        chain_config_key,
+        CustomError::InvalidBatchAccounts
+    );
+
+    if config.attestation_required {
+        verify_fill_attestation(
+            config,
+            guardian_set,
+            instructions,
+            &order.key(),
+            &order,
+            now,
+        )?;
+    }
+
+    let platform_fee = effective_platform_fee(config, &chain_config);
+    let (fee_amount, send_amount) =
+        calculate_fee(order.amount_in, platform_fee, config.round_fees_up)?;
+    Ok((order, fee_amount, send_amount))
+}
+
+/// Decides why `signer` may cancel `order`. The sender can cancel an expired
+/// order, or a live one once its cancel request has outlived the grace
+/// period; the owner can always cancel.
+fn cancel_reason(
+    config: &GlobalConfig,
+    roles: Option<&Account<Roles>>,
+    order: &LimitOrder,
+    signer: &Pubkey,
+    now: i64,
+) -> Result<CancelReason> {
+    if *signer == order.sender {
+        if !order.is_live(now) {
+            return Ok(CancelReason::Expired);
+        }
+        let grace_elapsed = order.cancel_requested_at != 0
+            && now
+                >= order
+                    .cancel_requested_at
+                    .saturating_add(config.cancel_grace_period);
+        if config.cancel_grace_period == 0 || grace_elapsed {
+            return Ok(CancelReason::Sender);
+        }
+    }
+    if is_direct_admin(config, roles, signer) {
+        return Ok(CancelReason::Owner);
+    }
+    if *signer == order.sender {
+        return err!(CustomError::CancelGracePeriodActive);
+    }
+    err!(CustomError::OnlySenderOrOwner)
+}
+
+/// Refunds every order in `accounts` to its refund address and returns the
+/// refunded orders. The number of accounts
+/// per order depends on how the order is escrowed:
+///
+/// - lamports: `[order, refund_receiver, order_book]`
+/// - wrapped SOL: `[order, order_token_account, refund_receiver, order_book]`
+/// - SPL: `[order, order_token_account, user_token_account, refund_receiver,
+///   order_book]`
+///
+/// Without `force_refund_code` every order must belong to `by` and pass the
+/// same checks as a single cancel. Listed orders are removed from the book
+/// passed with them, so one batch can span markets; see `batch_order_book`.
+fn refund_orders<'info>(
+    accounts: &'info [AccountInfo<'info>],
+    token_program: &Program<'info, Token>,
+    config: &GlobalConfig,
+    by: Pubkey,
+    force_refund_code: Option<u16>,
+) -> Result<Vec<Pubkey>> {
+    require!(!accounts.is_empty(), CustomError::InvalidBatchAccounts);
+
+    let clock = Clock::get()?;
+    let mut refunded = Vec::new();
+    let mut index = 0;
+    while index < accounts.len() {
+        let order_info = &accounts[index];
+        let order = Account::<LimitOrder>::try_from(order_info)?;
+        require!(order_info.is_writable, CustomError::InvalidBatchAccounts);
+        require!(!order.is_htlc(), CustomError::HtlcOrder);
+        let reason = match force_refund_code {
+            Some(_) => CancelReason::ForceRefund,
+            None => {
+                require_keys_eq!(by, order.sender, CustomError::OnlySenderOrOwner);
+                cancel_reason(config, None, &order, &by, clock.unix_timestamp)?
+            }
+        };
+
+        let count = if !order.uses_token_escrow() {
+            3
+        } else if order.wrapped_sol {
+            4
+        } else {
+            5
+        };
+        let order_accounts = accounts
+            .get(index..index + count)
+            .ok_or(CustomError::InvalidBatchAccounts)?;
+        index += count;
+
+        let refund_receiver = &order_accounts[count - 2];
+        require_keys_eq!(
+            refund_receiver.key(),
+            order.refund_to(),
+            CustomError::InvalidRefundReceiver
+        );
+        delist_order(
+            batch_order_book(&order_accounts[count - 1])?.as_ref(),
+            &order,
+        )?;
+
+        if order.uses_token_escrow() {
+            let seeds = &[
+                b"limit_order",
+                order.sender.as_ref(),
+                &order.expiry.to_le_bytes(),
+                &[order.bump],
+            ];
+            let signer = &[&seeds[..]];
+
+            let order_token_account = Account::<TokenAccount>::try_from(&order_accounts[1])?;
+            require_keys_eq!(
+                order_token_account.owner,
+                order.key(),
+                CustomError::InvalidEscrow
+            );
+
+            if !order.wrapped_sol {
+                let user_token_account = Account::<TokenAccount>::try_from(&order_accounts[2])?;
+                require!(
+                    user_token_account.owner == order.refund_to()
+                        && user_token_account.mint == order.from_token,
+                    CustomError::InvalidRefundReceiver
+                );
+
+                let cpi_accounts = TokenTransfer {
+                    from: order_accounts[1].clone(),
+                    to: order_accounts[2].clone(),
+                    authority: order_info.clone(),
+                };
+                let cpi_ctx = CpiContext::new_with_signer(
+                    token_program.to_account_info(),
+                    cpi_accounts,
+                    signer,
+                );
+                token::transfer(cpi_ctx, order.amount_in)?;
+            }
+
+            // Closing the escrow also unwraps wrapped SOL to the refund address.
+            let close_cpi_accounts = CloseAccount {
+                account: order_accounts[1].clone(),
+                destination: refund_receiver.clone(),
+                authority: order_info.clone(),
+            };
+            let close_cpi_ctx = CpiContext::new_with_signer(
+                token_program.to_account_info(),
+                close_cpi_accounts,
+                signer,
+            );
+            token::close_account(close_cpi_ctx)?;
+        }
+
+        // Lamport-escrowed orders hold `amount_in` on top of their rent, so
+        // closing the order refunds both.
+        order.close(refund_receiver.clone())?;
+
+        emit!(OrderCancelled {
+            order_pubkey: order_info.key(),
+            by,
+            reason,
+            reason_code: force_refund_code.unwrap_or(0),
+        });
+        refunded.push(order_info.key());
+    }
+    Ok(refunded)
+}
+
+fn error_code(err: &Error) -> u32 {
+    match err {
+        Error::AnchorError(err) => err.error_code_number,
+        Error::ProgramError(err) => u64::from(err.program_error.clone()) as u32,
+    }
+}
+
+fn validate_guardian_keys(keys: &[Pubkey], threshold: u8) -> Result<()> {
+    require!(
+        !keys.is_empty() && keys.len() <= GuardianSet::MAX_GUARDIANS,
+        CustomError::InvalidGuardianSet
+    );
+    require!(
+        threshold > 0 && threshold as usize <= keys.len(),
+        CustomError::InvalidGuardianSet
+    );
+    for (i, key) in keys.iter().enumerate() {
+        require!(
+            *key != Pubkey::default() && !keys[..i].contains(key),
+            CustomError::InvalidGuardianSet
+        );
+    }
+    Ok(())
+}
+
+/// Collects `(public_key, message)` pairs from every Ed25519 program
+/// instruction in the current transaction. Only signatures whose key,
+/// signature and message all live inside the Ed25519 instruction itself are
+/// accepted, so the data cannot be redirected to another instruction.
+fn load_ed25519_signatures(instructions: &AccountInfo) -> Result<Vec<(Pubkey, Vec<u8>)>> {
+    const OFFSETS_START: usize = 2;
+    const OFFSETS_SIZE: usize = 14;
+
+    let mut signatures = Vec::new();
+    let mut index = 0;
+    while let Ok(ix) = sysvar_instructions::load_instruction_at_checked(index, instructions) {
+        index += 1;
+        if ix.program_id != ed25519_program::ID || ix.data.len() < OFFSETS_START {
+            continue;
+        }
+
+        let data = &ix.data;
+        let read_u16 = |at: usize| -> Result<usize> {
+            let bytes = data
+                .get(at..at + 2)
+                .ok_or(CustomError::InvalidAttestation)?;
+            Ok(u16::from_le_bytes([bytes[0], bytes[1]]) as usize)
+        };
+
+        for i in 0..data[0] as usize {
+            let start = OFFSETS_START + i * OFFSETS_SIZE;
+            let signature_ix_index = read_u16(start + 2)?;
+            let public_key_offset = read_u16(start + 4)?;
+            let public_key_ix_index = read_u16(start + 6)?;
+            let message_offset = read_u16(start + 8)?;
+            let message_size = read_u16(start + 10)?;
+            let message_ix_index = read_u16(start + 12)?;
+
+            let this_ix = u16::MAX as usize;
+            require!(
+                signature_ix_index == this_ix
+                    && public_key_ix_index == this_ix
+                    && message_ix_index == this_ix,
+                CustomError::InvalidAttestation
+            );
+
+            let public_key = data
+                .get(public_key_offset..public_key_offset + 32)
+                .ok_or(CustomError::InvalidAttestation)?;
+            let message = data
+                .get(message_offset..message_offset + message_size)
+                .ok_or(CustomError::InvalidAttestation)?;
+            signatures.push((
+                Pubkey::try_from(public_key).map_err(|_| CustomError::InvalidAttestation)?,
+                message.to_vec(),
+            ));
+        }
+    }
+    Ok(signatures)
+}
+
+/// Counts the distinct guardians of `guardian_set` that signed `message`.
+fn count_guardian_signatures(
+    guardian_set: &GuardianSet,
+    signatures: &[(Pubkey, Vec<u8>)],
+    message: &[u8],
+) -> usize {
+    let mut signers: Vec<Pubkey> = Vec::new();
+    for (key, signed) in signatures {
+        if signed.as_slice() == message && guardian_set.keys.contains(key) && !signers.contains(key)
+        {
+            signers.push(*key);
+        }
+    }
+    signers.len()
+}
+
+fn check_guardian_set(config: &GlobalConfig, guardian_set: &GuardianSet, now: i64) -> Result<()> {
+    require!(
+        guardian_set.index == config.guardian_set_index || guardian_set.expiration_time > now,
+        CustomError::GuardianSetExpired
+    );
+    Ok(())
+}
+
+/// Requires a quorum of guardians to have signed a [`FillAttestation`] for
+/// `order` through Ed25519 program instructions in the same transaction.
+fn verify_fill_attestation(
+    config: &GlobalConfig,
+    guardian_set: Option<&Account<GuardianSet>>,
+    instructions: Option<&UncheckedAccount>,
+    order_key: &Pubkey,
+    order: &LimitOrder,
+    now: i64,
+) -> Result<FillAttestation> {
+    let guardian_set = guardian_set.ok_or(CustomError::AttestationRequired)?;
+    let instructions = instructions.ok_or(CustomError::AttestationRequired)?;
+    check_guardian_set(config, guardian_set, now)?;
+
+    let signatures = load_ed25519_signatures(instructions)?;
+    let (attestation, message) = signatures
+        .iter()
+        .find_map(|(_, message)| {
+            let attestation = FillAttestation::from_message(message)?;
+            (attestation.order == *order_key).then(|| (attestation, message.clone()))
+        })
+        .ok_or(CustomError::AttestationRequired)?;
+
+    require!(
+        attestation.recipient == order.recipient,
+        CustomError::InvalidAttestation
+    );
+    // Both amounts are big-endian uint256 values, so byte order compares numerically.
+    require!(
+        attestation.amount_delivered >= order.amount_out,
+        CustomError::InvalidAttestation
+    );
+    require!(
+        count_guardian_signatures(guardian_set, &signatures, &message)
+            >= guardian_set.threshold as usize,
+        CustomError::InsufficientAttestations
+    );
+
+    emit!(FillAttested {
+        order_pubkey: *order_key,
+        guardian_set_index: guardian_set.index,
+        dest_tx_hash: attestation.dest_tx_hash,
+        amount_delivered: attestation.amount_delivered,
+    });
+    Ok(attestation)
+}
+
+/// Slashing may be triggered by a Guardian or by a member of the guardian set.
+fn require_slasher(
+    config: &GlobalConfig,
+    roles: Option<&Account<Roles>>,
+    guardian_set: &GuardianSet,
+    authority: &Pubkey,
+) -> Result<()> {
+    require!(
+        has_role(config, roles, authority, Role::Guardian) || guardian_set.keys.contains(authority),
+        CustomError::OnlyOwnerOrGuardian
+    );
+    Ok(())
+}
+
+/// Removes the fill of `order_key` from `bond` and returns it with the amount
+/// to pay its sender, taken from the active bond first and then from funds
+/// still unbonding. A quorum of guardians must have signed a
+/// [`NonDeliveryAttestation`] for the fill.
+fn slash_bond(
+    config: &GlobalConfig,
+    bond: &mut ExecutorBond,
+    guardian_set: &Account<GuardianSet>,
+    instructions: &UncheckedAccount,
+    order_key: &Pubkey,
+    now: i64,
+) -> Result<(BondedExecution, u64)> {
+    check_guardian_set(config, guardian_set, now)?;
+
+    let signatures = load_ed25519_signatures(instructions)?;
+    let message = signatures
+        .iter()
+        .find_map(|(_, message)| {
+            let attestation = NonDeliveryAttestation::from_message(message)?;
+            (attestation.order == *order_key && attestation.executor == bond.executor)
+                .then(|| message.clone())
+        })
+        .ok_or(CustomError::AttestationRequired)?;
+    require!(
+        count_guardian_signatures(guardian_set, &signatures, &message)
+            >= guardian_set.threshold as usize,
+        CustomError::InsufficientAttestations
+    );
+
+    let position = bond
+        .pending
+        .iter()
+        .position(|execution| {
+            execution.order == *order_key
+                && execution.executed_at.saturating_add(config.slash_window) > now
+        })
+        .ok_or(CustomError::NotSlashable)?;
+    let execution = bond.pending.remove(position);
+
+    let from_bond = execution.amount.min(bond.amount);
+    bond.amount -= from_bond;
+    let from_unbonding = (execution.amount - from_bond).min(bond.unbonding_amount);
+    bond.unbonding_amount -= from_unbonding;
+    Ok((execution, from_bond + from_unbonding))
+}
+
+#[derive(Accounts)]
+pub struct Initialize<'info> {
+    #[account(
+        init,
+        seeds = [b"global-config"],
+        bump,
+        payer = signer,
+        space = 8 + GlobalConfig::INIT_SPACE
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    #[account(mut)]
+    pub signer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    #[account(
+        mut,
+        seeds = [b"global-config"],
+        bump,
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    /// Required once roles are enabled; each instruction checks its own role.
+    #[account(
+        mut,
+        seeds = [b"roles"],
+        bump = roles.bump,
+    )]
+    pub roles: Option<Account<'info, Roles>>,
+
+    pub authority: Signer<'info>,
+}
+
+/// Until `init_roles` runs the owner holds every role; afterwards the owner
+/// key has no special standing.
+#[derive(Accounts)]
+pub struct InitRoles<'info> {
+    #[account(
+        mut,
+        seeds = [b"global-config"],
+        bump,
+        has_one = owner
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    #[account(
+        init,
+        seeds = [b"roles"],
+        bump,
+        payer = owner,
+        space = 8 + Roles::INIT_SPACE
+    )]
+    pub roles: Account<'info, Roles>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ProposeAdminAction<'info> {
+    #[account(
+        seeds = [b"global-config"],
+        bump,
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    #[account(
+        mut,
+        seeds = [b"roles"],
+        bump = roles.bump,
+    )]
+    pub roles: Account<'info, Roles>,
+
+    #[account(
+        init,
+        seeds = [b"admin-proposal".as_ref(), roles.proposal_count.to_le_bytes().as_ref()],
+        bump,
+        payer = proposer,
+        space = 8 + AdminProposal::INIT_SPACE
+    )]
+    pub proposal: Account<'info, AdminProposal>,
+
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ApproveAdminAction<'info> {
+    #[account(
+        seeds = [b"global-config"],
+        bump,
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    #[account(
+        seeds = [b"roles"],
+        bump = roles.bump,
+    )]
+    pub roles: Account<'info, Roles>,
+
+    #[account(
+        mut,
+        seeds = [b"admin-proposal".as_ref(), proposal.id.to_le_bytes().as_ref()],
+        bump = proposal.bump,
+    )]
+    pub proposal: Account<'info, AdminProposal>,
+
+    pub approver: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteAdminAction<'info> {
+    #[account(
+        mut,
+        seeds = [b"global-config"],
+        bump,
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    #[account(
+        mut,
+        seeds = [b"roles"],
+        bump = roles.bump,
+    )]
+    pub roles: Account<'info, Roles>,
+
+    #[account(
+        mut,
+        seeds = [b"admin-proposal".as_ref(), proposal.id.to_le_bytes().as_ref()],
+        bump = proposal.bump,
+    )]
+    pub proposal: Account<'info, AdminProposal>,
+
+    pub executor: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateConfig<'info> {
+    /// CHECK: may predate the current layout; checked in `migrate_config`
+    #[account(
+        mut,
+        seeds = [b"global-config"],
+        bump,
+    )]
+    pub global_config: UncheckedAccount<'info>,
+
+    pub authority: Signer<'info>,
+
+    /// Required for role checks once roles are enabled.
+    #[account(
+        seeds = [b"roles"],
+        bump = roles.bump,
+    )]
+    pub roles: Option<Account<'info, Roles>>,
+
+    /// Approved proposal for this exact action; required once the admin
+    /// threshold is above one.
+    #[account(
+        mut,
+        seeds = [b"admin-proposal".as_ref(), proposal.id.to_le_bytes().as_ref()],
+        bump = proposal.bump,
+    )]
+    pub proposal: Option<Account<'info, AdminProposal>>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateOrder<'info> {
+    /// CHECK: may predate the current layout; checked in `migrate_order`
+    #[account(mut)]
+    pub order: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"global-config"],
+        bump
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    /// Required for role checks once roles are enabled.
+    #[account(
+        seeds = [b"roles"],
+        bump = roles.bump,
+    )]
+    pub roles: Option<Account<'info, Roles>>,
+
+    /// Approved proposal for this exact action; required once the admin
+    /// threshold is above one.
+    #[account(
+        mut,
+        seeds = [b"admin-proposal".as_ref(), proposal.id.to_le_bytes().as_ref()],
+        bump = proposal.bump,
+    )]
+    pub proposal: Option<Account<'info, AdminProposal>>,
+}
+
+#[derive(Accounts)]
+#[instruction(from_token: Pubkey, to_chain_id: u64)]
+pub struct InitOrderBook<'info> {
+    #[account(
+        init,
+        seeds = [b"order-book".as_ref(), from_token.as_ref(), &to_chain_id.to_le_bytes()],
+        bump,
+        payer = authority,
+        space = 8 + OrderBook::SIZE
+    )]
+    pub order_book: AccountLoader<'info, OrderBook>,
+
+    #[account(
+        seeds = [b"global-config"],
+        bump
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    /// Required for role checks once roles are enabled.
+    #[account(
+        seeds = [b"roles"],
+        bump = roles.bump,
+    )]
+    pub roles: Option<Account<'info, Roles>>,
+
+    /// Approved proposal for this exact action; required once the admin
+    /// threshold is above one.
+    #[account(
+        mut,
+        seeds = [b"admin-proposal".as_ref(), proposal.id.to_le_bytes().as_ref()],
+        bump = proposal.bump,
+    )]
+    pub proposal: Option<Account<'info, AdminProposal>>,
+}
+
+#[derive(Accounts)]
+pub struct PruneOrderBook<'info> {
+    #[account(mut)]
+    pub order_book: AccountLoader<'info, OrderBook>,
+}
+
+#[derive(Accounts)]
+pub struct InitGuardianSet<'info> {
+    #[account(
+        init,
+        seeds = [b"guardian-set".as_ref(), &0u32.to_le_bytes()],
+        bump,
+        payer = authority,
+        space = 8 + GuardianSet::INIT_SPACE
+    )]
+    pub guardian_set: Account<'info, GuardianSet>,
+
+    #[account(
+        mut,
+        seeds = [b"global-config"],
+        bump
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    /// Required for role checks once roles are enabled.
+    #[account(
+        seeds = [b"roles"],
+        bump = roles.bump,
+    )]
+    pub roles: Option<Account<'info, Roles>>,
+
+    /// Approved proposal for this exact action; required once the admin
+    /// threshold is above one.
+    #[account(
+        mut,
+        seeds = [b"admin-proposal".as_ref(), proposal.id.to_le_bytes().as_ref()],
+        bump = proposal.bump,
+    )]
+    pub proposal: Option<Account<'info, AdminProposal>>,
+}
+
+#[derive(Accounts)]
+pub struct RotateGuardianSet<'info> {
+    #[account(
+        mut,
+        seeds = [b"guardian-set".as_ref(), &global_config.guardian_set_index.to_le_bytes()],
+        bump = current_guardian_set.bump
+    )]
+    pub current_guardian_set: Account<'info, GuardianSet>,
+
+    #[account(
+        init,
+        seeds = [b"guardian-set".as_ref(), &(global_config.guardian_set_index + 1).to_le_bytes()],
+        bump,
+        payer = authority,
+        space = 8 + GuardianSet::INIT_SPACE
+    )]
+    pub new_guardian_set: Account<'info, GuardianSet>,
+
+    #[account(
+        mut,
+        seeds = [b"global-config"],
+        bump
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    /// Required for role checks once roles are enabled.
+    #[account(
+        seeds = [b"roles"],
+        bump = roles.bump,
+    )]
+    pub roles: Option<Account<'info, Roles>>,
+
+    /// Approved proposal for this exact action; required once the admin
+    /// threshold is above one.
+    #[account(
+        mut,
+        seeds = [b"admin-proposal".as_ref(), proposal.id.to_le_bytes().as_ref()],
+        bump = proposal.bump,
+    )]
+    pub proposal: Option<Account<'info, AdminProposal>>,
+}
+
+#[derive(Accounts)]
+#[instruction(chain_id: u64)]
+pub struct SetChainConfig<'info> {
+    #[account(
+        init_if_needed,
+        seeds = [b"chain-config".as_ref(), &chain_id.to_le_bytes()],
+        bump,
+        payer = authority,
+        space = 8 + ChainConfig::INIT_SPACE
+    )]
+    pub chain_config: Account<'info, ChainConfig>,
+
+    #[account(
+        seeds = [b"global-config"],
+        bump
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    /// Required for role checks once roles are enabled.
+    #[account(
+        seeds = [b"roles"],
+        bump = roles.bump,
+    )]
+    pub roles: Option<Account<'info, Roles>>,
+
+    /// Approved proposal for this exact action; required once the admin
+    /// threshold is above one.
+    #[account(
+        mut,
+        seeds = [b"admin-proposal".as_ref(), proposal.id.to_le_bytes().as_ref()],
+        bump = proposal.bump,
+    )]
+    pub proposal: Option<Account<'info, AdminProposal>>,
+}
+
+#[derive(Accounts)]
+#[instruction(from_token: Pubkey, to_chain_id: u64, to_token: [u8; 32])]
+pub struct SetTokenRoute<'info> {
+    #[account(
+        init_if_needed,
+        seeds = [b"token-route".as_ref(), from_token.as_ref(), &to_chain_id.to_le_bytes(), to_token.as_ref()],
+        bump,
+        payer = authority,
+        space = 8 + TokenRoute::INIT_SPACE
+    )]
+    pub token_route: Account<'info, TokenRoute>,
+
+    #[account(
+        seeds = [b"global-config"],
+        bump
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    /// Required for role checks once roles are enabled.
+    #[account(
+        seeds = [b"roles"],
+        bump = roles.bump,
+    )]
+    pub roles: Option<Account<'info, Roles>>,
+
+    /// Approved proposal for this exact action; required once the admin
+    /// threshold is above one.
+    #[account(
+        mut,
+        seeds = [b"admin-proposal".as_ref(), proposal.id.to_le_bytes().as_ref()],
+        bump = proposal.bump,
+    )]
+    pub proposal: Option<Account<'info, AdminProposal>>,
+}
+
+#[derive(Accounts)]
+#[instruction(mint: Pubkey)]
+pub struct SetMintPolicy<'info> {
+    #[account(
+        init_if_needed,
+        seeds = [b"mint-policy".as_ref(), mint.as_ref()],
+        bump,
+        payer = authority,
+        space = 8 + MintPolicy::INIT_SPACE
+    )]
+    pub mint_policy: Account<'info, MintPolicy>,
+
+    #[account(
+        seeds = [b"global-config"],
+        bump
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    /// Required for role checks once roles are enabled.
+    #[account(
+        seeds = [b"roles"],
+        bump = roles.bump,
+    )]
+    pub roles: Option<Account<'info, Roles>>,
+
+    /// Approved proposal for this exact action; required once the admin
+    /// threshold is above one.
+    #[account(
+        mut,
+        seeds = [b"admin-proposal".as_ref(), proposal.id.to_le_bytes().as_ref()],
+        bump = proposal.bump,
+    )]
+    pub proposal: Option<Account<'info, AdminProposal>>,
+}
+
+#[derive(Accounts)]
+#[instruction(mint: Pubkey)]
+pub struct SetPrice<'info> {
+    #[account(
+        init_if_needed,
+        seeds = [b"price-feed".as_ref(), mint.as_ref()],
+        bump,
+        payer = authority,
+        space = 8 + PriceFeed::INIT_SPACE
+    )]
+    pub price_feed: Account<'info, PriceFeed>,
+
+    #[account(
+        seeds = [b"global-config"],
+        bump,
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    /// Required once roles are enabled.
+    #[account(
+        seeds = [b"roles"],
+        bump = roles.bump,
+    )]
+    pub roles: Option<Account<'info, Roles>>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(params: OpenOrderParams)]
+pub struct OpenOrderSol<'info> {
+    #[account(
+        init,
+        seeds = [b"limit_order", order_sender(&user, &session_key).as_ref(), &params.expiry.to_le_bytes()],
+        bump,
+        payer = payer,
+        space = 8 + LimitOrder::INIT_SPACE,
+    )]
+    pub order: Account<'info, LimitOrder>,
+
+    /// Funds the order; `payer` covers the rent.
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        seeds = [b"global-config"],
+        bump,
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    #[account(
+        seeds = [b"chain-config".as_ref(), &params.to_chain_id.to_le_bytes()],
+        bump = chain_config.bump,
+    )]
+    pub chain_config: Account<'info, ChainConfig>,
+
+    #[account(
+        seeds = [b"token-route".as_ref(), params.from_token.as_ref(), &params.to_chain_id.to_le_bytes(), params.to_token.as_ref()],
+        bump = token_route.bump,
+    )]
+    pub token_route: Account<'info, TokenRoute>,
+
+    /// CHECK: may be uninitialized; deserialized in `check_order_size`
+    #[account(
+        seeds = [b"price-feed".as_ref(), params.from_token.as_ref()],
+        bump,
+    )]
+    pub price_feed: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    /// Required when `params.exclusive_executor` is set.
+    /// CHECK: address is checked against the instructions sysvar id
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: Option<UncheckedAccount<'info>>,
+
+    /// Market listing to keep in sync; required once the order is listed.
+    #[account(mut)]
+    pub order_book: Option<AccountLoader<'info, OrderBook>>,
+
+    /// Set when `user` is a session key opening the order for its wallet.
+    #[account(
+        mut,
+        seeds = [b"session-key".as_ref(), session_key.user.as_ref(), user.key().as_ref()],
+        bump = session_key.bump,
+    )]
+    pub session_key: Option<Account<'info, SessionKey>>,
+}
+
+#[derive(Accounts)]
+#[instruction(params: OpenOrderParams)]
+pub struct OpenOrderSpl<'info> {
+    #[account(
+        init,
+        seeds = [b"limit_order", order_sender(&user, &session_key).as_ref(), &params.expiry.to_le_bytes()],
+        bump,
+        payer = payer,
+        space = 8 + LimitOrder::INIT_SPACE,
+    )]
+    pub order: Account<'info, LimitOrder>,
+
+    /// Funds the order; `payer` covers the rent.
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(mut)]
+    pub user_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        init,
+        payer = payer,
+        associated_token::mint = token_mint,
+        associated_token::authority = order
+    )]
+    pub order_token_account: Account<'info, TokenAccount>,
+
+    pub token_mint: Account<'info, Mint>,
+
+    #[account(
+        seeds = [b"global-config"],
+        bump,
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    #[account(
+        seeds = [b"chain-config".as_ref(), &params.to_chain_id.to_le_bytes()],
+        bump = chain_config.bump,
+    )]
+    pub chain_config: Account<'info, ChainConfig>,
+
+    #[account(
+        seeds = [b"token-route".as_ref(), params.from_token.as_ref(), &params.to_chain_id.to_le_bytes(), params.to_token.as_ref()],
+        bump = token_route.bump,
+    )]
+    pub token_route: Account<'info, TokenRoute>,
+
+    /// CHECK: may be uninitialized; deserialized in `check_mint_policy`
+    #[account(
+        seeds = [b"mint-policy".as_ref(), token_mint.key().as_ref()],
+        bump,
+    )]
+    pub mint_policy: UncheckedAccount<'info>,
+
+    /// CHECK: may be uninitialized; deserialized in `check_order_size`
+    #[account(
+        seeds = [b"price-feed".as_ref(), params.from_token.as_ref()],
+        bump,
+    )]
+    pub price_feed: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token>,
+
+    pub system_program: Program<'info, System>,
+
+    pub associated_token_program: Program<'info, anchor_spl::associated_token::AssociatedToken>,
+
+    /// Required when `params.exclusive_executor` is set.
+    /// CHECK: address is checked against the instructions sysvar id
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: Option<UncheckedAccount<'info>>,
+
+    /// Market listing to keep in sync; required once the order is listed.
+    #[account(mut)]
+    pub order_book: Option<AccountLoader<'info, OrderBook>>,
+
+    /// Set when `user` is a session key opening the order for its wallet.
+    #[account(
+        mut,
+        seeds = [b"session-key".as_ref(), session_key.user.as_ref(), user.key().as_ref()],
+        bump = session_key.bump,
+    )]
+    pub session_key: Option<Account<'info, SessionKey>>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteOrderSpl<'info> {
+    #[account(
+        mut,
+        close = refund_receiver // refund rent to order.refund_to()
+    )]
+    pub order: Account<'info, LimitOrder>,
+
+    #[account(
+        mut,
+        constraint = order_token_account.owner == order.key()
+    )]
+    pub order_token_account: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub target_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = treasury_token_account.owner == global_config.treasury.key(),
+        constraint = treasury_token_account.mint == order.from_token,
+        constraint = treasury_token_account.key() == get_associated_token_address(&global_config.treasury.key(), &order.from_token)
+    )]
+    pub treasury_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        seeds = [b"global-config"],
+        bump,
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    #[account(
+        seeds = [b"chain-config".as_ref(), &order.to_chain_id.to_le_bytes()],
+        bump = chain_config.bump,
+    )]
+    pub chain_config: Account<'info, ChainConfig>,
+
+    #[account(mut)]
+    pub refund_receiver: SystemAccount<'info>,
+
+    pub executor: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+
+    /// Required while `global_config.attestation_required` is set.
+    pub guardian_set: Option<Account<'info, GuardianSet>>,
+
+    /// CHECK: address is checked against the instructions sysvar id
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: Option<UncheckedAccount<'info>>,
+
+    /// Required when `executor` is not the owner.
+    #[account(mut)]
+    pub executor_bond: Option<Account<'info, ExecutorBond>>,
+
+    /// Market listing to keep in sync; required once the order is listed.
+    #[account(mut)]
+    pub order_book: Option<AccountLoader<'info, OrderBook>>,
+
+    /// Required for role checks once roles are enabled.
+    #[account(
+        seeds = [b"roles"],
+        bump = roles.bump,
+    )]
+    pub roles: Option<Account<'info, Roles>>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteOrderSol<'info> {
+    #[account(
+        mut,
+        close = refund_receiver // refund rent to order.refund_to()
+    )]
+    pub order: Account<'info, LimitOrder>,
+
+    #[account(mut)]
+    pub target_sol: SystemAccount<'info>,
+
+    #[account(mut, address = global_config.treasury)]
+    pub treasury: SystemAccount<'info>,
+
+    pub executor: Signer<'info>,
+
+    #[account(
+        seeds = [b"global-config"],
+        bump,
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    #[account(
+        seeds = [b"chain-config".as_ref(), &order.to_chain_id.to_le_bytes()],
+        bump = chain_config.bump,
+    )]
+    pub chain_config: Account<'info, ChainConfig>,
+
+    #[account(mut)]
+    pub refund_receiver: SystemAccount<'info>,
+
+    /// Required while `global_config.attestation_required` is set.
+    pub guardian_set: Option<Account<'info, GuardianSet>>,
+
+    /// CHECK: address is checked against the instructions sysvar id
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: Option<UncheckedAccount<'info>>,
+
+    /// Required when `executor` is not the owner.
+    #[account(mut)]
+    pub executor_bond: Option<Account<'info, ExecutorBond>>,
+
+    /// Market listing to keep in sync; required once the order is listed.
+    #[account(mut)]
+    pub order_book: Option<AccountLoader<'info, OrderBook>>,
+
+    /// Required for role checks once roles are enabled.
+    #[account(
+        seeds = [b"roles"],
+        bump = roles.bump,
+    )]
+    pub roles: Option<Account<'info, Roles>>,
+}
+
+#[derive(Accounts)]
+pub struct CancelOrderSol<'info> {
+    #[account(
+        mut,
+        close = refund_receiver
+    )]
+    pub order: Account<'info, LimitOrder>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(mut)]
+    pub refund_receiver: SystemAccount<'info>,
+
+    #[account(
+        seeds = [b"global-config"],
+        bump,
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    pub system_program: Program<'info, System>,
+
+    /// Market listing to keep in sync; required once the order is listed.
+    #[account(mut)]
+    pub order_book: Option<AccountLoader<'info, OrderBook>>,
+
+    /// Set when `user` is a session key cancelling for its wallet.
+    #[account(
+        seeds = [b"session-key".as_ref(), session_key.user.as_ref(), user.key().as_ref()],
+        bump = session_key.bump,
+    )]
+    pub session_key: Option<Account<'info, SessionKey>>,
+
+    /// Required for role checks once roles are enabled.
+    #[account(
+        seeds = [b"roles"],
+        bump = roles.bump,
+    )]
+    pub roles: Option<Account<'info, Roles>>,
+}
+
+#[derive(Accounts)]
+pub struct CancelOrderSpl<'info> {
+    #[account(
+        mut,
+        seeds = [b"limit_order", order.sender.as_ref(), &order.expiry.to_le_bytes()],
+        bump = order.bump,
+        close = refund_receiver
+    )]
+    pub order: Account<'info, LimitOrder>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = user_token_account.owner == refund_receiver.key(),
+        constraint = user_token_account.mint == order.from_token
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = order_token_account.owner == order.key(),
+    )]
+    pub order_token_account: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub refund_receiver: SystemAccount<'info>,
+
+    #[account(
+        seeds = [b"global-config"],
+        bump
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    pub token_program: Program<'info, Token>,
+
+    /// Market listing to keep in sync; required once the order is listed.
+    #[account(mut)]
+    pub order_book: Option<AccountLoader<'info, OrderBook>>,
+
+    /// Set when `user` is a session key cancelling for its wallet.
+    #[account(
+        seeds = [b"session-key".as_ref(), session_key.user.as_ref(), user.key().as_ref()],
+        bump = session_key.bump,
+    )]
+    pub session_key: Option<Account<'info, SessionKey>>,
+
+    /// Required for role checks once roles are enabled.
+    #[account(
+        seeds = [b"roles"],
+        bump = roles.bump,
+    )]
+    pub roles: Option<Account<'info, Roles>>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteOrdersBatch<'info> {
+    #[account(
+        seeds = [b"global-config"],
+        bump,
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    pub executor: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+
+    /// Required while `global_config.attestation_required` is set.
+    pub guardian_set: Option<Account<'info, GuardianSet>>,
+
+    /// CHECK: address is checked against the instructions sysvar id
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: Option<UncheckedAccount<'info>>,
+
+    /// Required for role checks once roles are enabled.
+    #[account(
+        seeds = [b"roles"],
+        bump = roles.bump,
+    )]
+    pub roles: Option<Account<'info, Roles>>,
+}
+
+impl ExecuteOrdersBatch<'_> {
+    pub const ACCOUNTS_PER_ORDER: usize = 7;
+}
+
+#[derive(Accounts)]
+pub struct RequestCancel<'info> {
+    #[account(
+        mut,
+        seeds = [b"limit_order", order.sender.as_ref(), &order.expiry.to_le_bytes()],
+        bump = order.bump,
+    )]
+    pub order: Account<'info, LimitOrder>,
+
+    /// The order's sender, or a session key of it.
+    pub user: Signer<'info>,
+
+    #[account(
+        seeds = [b"global-config"],
+        bump
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    /// Set when `user` is a session key requesting for its wallet.
+    #[account(
+        seeds = [b"session-key".as_ref(), session_key.user.as_ref(), user.key().as_ref()],
+        bump = session_key.bump,
+    )]
+    pub session_key: Option<Account<'info, SessionKey>>,
+}
+
+#[derive(Accounts)]
+#[instruction(params: SessionKeyParams)]
+pub struct CreateSessionKey<'info> {
+    #[account(
+        init,
+        seeds = [b"session-key".as_ref(), user.key().as_ref(), params.key.as_ref()],
+        bump,
+        payer = user,
+        space = 8 + SessionKey::INIT_SPACE,
+    )]
+    pub session_key: Account<'info, SessionKey>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RevokeSessionKey<'info> {
+    #[account(
+        mut,
+        seeds = [b"session-key".as_ref(), user.key().as_ref(), session_key.key.as_ref()],
+        bump = session_key.bump,
+        close = user
+    )]
+    pub session_key: Account<'info, SessionKey>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CancelOrdersBatch<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        seeds = [b"global-config"],
+        bump
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct ForceRefundBatch<'info> {
+    #[account(
+        seeds = [b"global-config"],
+        bump
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    pub authority: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+
+    /// Required for role checks once roles are enabled.
+    #[account(
+        seeds = [b"roles"],
+        bump = roles.bump,
+    )]
+    pub roles: Option<Account<'info, Roles>>,
+
+    /// Approved proposal for this exact action; required once the admin
+    /// threshold is above one.
+    #[account(
+        mut,
+        seeds = [b"admin-proposal".as_ref(), proposal.id.to_le_bytes().as_ref()],
+        bump = proposal.bump,
+    )]
+    pub proposal: Option<Account<'info, AdminProposal>>,
+}
+
+#[derive(Accounts)]
+#[instruction(params: OpenOrderParams)]
+pub struct OpenOrderWsol<'info> {
+    #[account(
+        init,
+        seeds = [b"limit_order", order_sender(&user, &session_key).as_ref(), &params.expiry.to_le_bytes()],
+        bump,
+        payer = payer,
+        space = 8 + LimitOrder::INIT_SPACE,
+    )]
+    pub order: Account<'info, LimitOrder>,
+
+    /// Funds the order; `payer` covers the rent.
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        init,
+        payer = payer,
+        associated_token::mint = token_mint,
+        associated_token::authority = order
+    )]
+    pub order_token_account: Account<'info, TokenAccount>,
+
+    #[account(address = native_token())]
+    pub token_mint: Account<'info, Mint>,
+
+    #[account(
+        seeds = [b"global-config"],
+        bump,
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    #[account(
+        seeds = [b"chain-config".as_ref(), &params.to_chain_id.to_le_bytes()],
+        bump = chain_config.bump,
+    )]
+    pub chain_config: Account<'info, ChainConfig>,
+
+    #[account(
+        seeds = [b"token-route".as_ref(), params.from_token.as_ref(), &params.to_chain_id.to_le_bytes(), params.to_token.as_ref()],
+        bump = token_route.bump,
+    )]
+    pub token_route: Account<'info, TokenRoute>,
+
+    /// CHECK: may be uninitialized; deserialized in `check_order_size`
+    #[account(
+        seeds = [b"price-feed".as_ref(), params.from_token.as_ref()],
+        bump,
+    )]
+    pub price_feed: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token>,
+
+    pub system_program: Program<'info, System>,
+
+    pub associated_token_program: Program<'info, anchor_spl::associated_token::AssociatedToken>,
+
+    /// Required when `params.exclusive_executor` is set.
+    /// CHECK: address is checked against the instructions sysvar id
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: Option<UncheckedAccount<'info>>,
+
+    /// Market listing to keep in sync; required once the order is listed.
+    #[account(mut)]
+    pub order_book: Option<AccountLoader<'info, OrderBook>>,
+
+    /// Set when `user` is a session key opening the order for its wallet.
+    #[account(
+        mut,
+        seeds = [b"session-key".as_ref(), session_key.user.as_ref(), user.key().as_ref()],
+        bump = session_key.bump,
+    )]
+    pub session_key: Option<Account<'info, SessionKey>>,
+}
+
+#[derive(Accounts)]
+pub struct CancelOrderWsol<'info> {
+    #[account(
+        mut,
+        seeds = [b"limit_order", order.sender.as_ref(), &order.expiry.to_le_bytes()],
+        bump = order.bump,
+        close = refund_receiver
+    )]
+    pub order: Account<'info, LimitOrder>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = order_token_account.owner == order.key(),
+        constraint = order_token_account.mint == native_token()
+    )]
+    pub order_token_account: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub refund_receiver: SystemAccount<'info>,
+
+    #[account(
+        seeds = [b"global-config"],
+        bump
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    pub token_program: Program<'info, Token>,
+
+    /// Market listing to keep in sync; required once the order is listed.
+    #[account(mut)]
+    pub order_book: Option<AccountLoader<'info, OrderBook>>,
+
+    /// Set when `user` is a session key cancelling for its wallet.
+    #[account(
+        seeds = [b"session-key".as_ref(), session_key.user.as_ref(), user.key().as_ref()],
+        bump = session_key.bump,
+    )]
+    pub session_key: Option<Account<'info, SessionKey>>,
+
+    /// Required for role checks once roles are enabled.
+    #[account(
+        seeds = [b"roles"],
+        bump = roles.bump,
+    )]
+    pub roles: Option<Account<'info, Roles>>,
 }
 
 #[derive(Accounts)]
-pub struct Initialize<'info> {
+pub struct ExecuteOrderWsol<'info> {
+    #[account(
+        mut,
+        seeds = [b"limit_order", order.sender.as_ref(), &order.expiry.to_le_bytes()],
+        bump = order.bump,
+        close = refund_receiver // refund rent to order.refund_to()
+    )]
+    pub order: Account<'info, LimitOrder>,
+
+    #[account(
+        mut,
+        constraint = order_token_account.owner == order.key(),
+        constraint = order_token_account.mint == native_token()
+    )]
+    pub order_token_account: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub target_sol: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        constraint = treasury_token_account.owner == global_config.treasury.key(),
+        constraint = treasury_token_account.mint == order.from_token,
+        constraint = treasury_token_account.key() == get_associated_token_address(&global_config.treasury.key(), &order.from_token)
+    )]
+    pub treasury_token_account: Account<'info, TokenAccount>,
+
     #[account(
-        init,
         seeds = [b"global-config"],
         bump,
-        payer = signer,
-        space = 8 + GlobalConfig::SIZE
     )]
-    pub global_config: Account<'info, GlobalConfig>,
+    pub global_config: Account<'info, GlobalConfig>,
+
+    #[account(
+        seeds = [b"chain-config".as_ref(), &order.to_chain_id.to_le_bytes()],
+        bump = chain_config.bump,
+    )]
+    pub chain_config: Account<'info, ChainConfig>,
+
+    #[account(mut)]
+    pub refund_receiver: SystemAccount<'info>,
+
+    pub executor: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+
+    /// Required while `global_config.attestation_required` is set.
+    pub guardian_set: Option<Account<'info, GuardianSet>>,
+
+    /// CHECK: address is checked against the instructions sysvar id
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: Option<UncheckedAccount<'info>>,
+
+    /// Required when `executor` is not the owner.
+    #[account(mut)]
+    pub executor_bond: Option<Account<'info, ExecutorBond>>,
+
+    /// Market listing to keep in sync; required once the order is listed.
+    #[account(mut)]
+    pub order_book: Option<AccountLoader<'info, OrderBook>>,
+
+    /// Required for role checks once roles are enabled.
+    #[account(
+        seeds = [b"roles"],
+        bump = roles.bump,
+    )]
+    pub roles: Option<Account<'info, Roles>>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteOrderSwap<'info> {
+    #[account(
+        mut,
+        seeds = [b"limit_order", order.sender.as_ref(), &order.expiry.to_le_bytes()],
+        bump = order.bump,
+        close = refund_receiver // refund rent to order.refund_to()
+    )]
+    pub order: Account<'info, LimitOrder>,
+
+    #[account(
+        mut,
+        constraint = order_token_account.owner == order.key(),
+        constraint = order_token_account.mint == order.from_token
+    )]
+    pub order_token_account: Account<'info, TokenAccount>,
+
+    pub output_mint: Account<'info, Mint>,
+
+    /// Receives the swap output; closed to `executor` once it has settled.
+    #[account(
+        init_if_needed,
+        payer = executor,
+        associated_token::mint = output_mint,
+        associated_token::authority = order
+    )]
+    pub swap_output_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = settlement_vault.mint == output_mint.key()
+    )]
+    pub settlement_vault: Account<'info, TokenAccount>,
+
+    /// The treasury's account in whichever mint the fee is taken from.
+    #[account(
+        mut,
+        constraint = treasury_token_account.owner == global_config.treasury.key(),
+        constraint = treasury_token_account.key() == get_associated_token_address(
+            &global_config.treasury.key(),
+            &if global_config.swap_fee_on_output { output_mint.key() } else { order.from_token },
+        )
+    )]
+    pub treasury_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        seeds = [b"global-config"],
+        bump,
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    #[account(
+        seeds = [b"chain-config".as_ref(), &order.to_chain_id.to_le_bytes()],
+        bump = chain_config.bump,
+    )]
+    pub chain_config: Account<'info, ChainConfig>,
+
+    #[account(mut)]
+    pub refund_receiver: SystemAccount<'info>,
+
+    #[account(mut)]
+    pub executor: Signer<'info>,
+
+    /// CHECK: the configured swap adapter; only invoked
+    #[account(address = global_config.swap_adapter, executable)]
+    pub swap_program: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token>,
+
+    pub associated_token_program: Program<'info, anchor_spl::associated_token::AssociatedToken>,
+
+    pub system_program: Program<'info, System>,
+
+    /// Required while `global_config.attestation_required` is set.
+    pub guardian_set: Option<Account<'info, GuardianSet>>,
+
+    /// CHECK: address is checked against the instructions sysvar id
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: Option<UncheckedAccount<'info>>,
+
+    /// Required when `executor` is not the owner.
+    #[account(mut)]
+    pub executor_bond: Option<Account<'info, ExecutorBond>>,
+
+    /// Market listing to keep in sync; required once the order is listed.
+    #[account(mut)]
+    pub order_book: Option<AccountLoader<'info, OrderBook>>,
+
+    /// Required for role checks once roles are enabled.
+    #[account(
+        seeds = [b"roles"],
+        bump = roles.bump,
+    )]
+    pub roles: Option<Account<'info, Roles>>,
+}
+
+#[derive(Accounts)]
+pub struct FillLocalOrder<'info> {
+    #[account(
+        mut,
+        seeds = [b"limit_order", order.sender.as_ref(), &order.expiry.to_le_bytes()],
+        bump = order.bump,
+    )]
+    pub order: Account<'info, LimitOrder>,
+
+    /// Escrow of token orders; omitted for lamport escrows.
+    #[account(
+        mut,
+        constraint = order_token_account.owner == order.key(),
+        constraint = order_token_account.mint == order.from_token
+    )]
+    pub order_token_account: Option<Account<'info, TokenAccount>>,
+
+    /// Receives the escrow of token orders.
+    #[account(
+        mut,
+        constraint = taker_token_account.mint == order.from_token
+    )]
+    pub taker_token_account: Option<Account<'info, TokenAccount>>,
+
+    #[account(address = Pubkey::new_from_array(order.to_token))]
+    pub output_mint: Account<'info, Mint>,
+
+    /// Pays `amount_out` on behalf of the taker.
+    #[account(
+        mut,
+        constraint = taker_output_account.mint == output_mint.key()
+    )]
+    pub taker_output_account: Account<'info, TokenAccount>,
+
+    /// CHECK: the order's recipient, only used as the owner of
+    /// `recipient_token_account`
+    #[account(address = Pubkey::new_from_array(order.recipient))]
+    pub recipient: UncheckedAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = taker,
+        associated_token::mint = output_mint,
+        associated_token::authority = recipient
+    )]
+    pub recipient_token_account: Account<'info, TokenAccount>,
+
+    /// Receives the fee of lamport escrows.
+    #[account(mut, address = global_config.treasury)]
+    pub treasury: SystemAccount<'info>,
+
+    /// Receives the fee of token escrows.
+    #[account(
+        mut,
+        constraint = treasury_token_account.owner == global_config.treasury.key(),
+        constraint = treasury_token_account.mint == order.from_token,
+        constraint = treasury_token_account.key() == get_associated_token_address(&global_config.treasury.key(), &order.from_token)
+    )]
+    pub treasury_token_account: Option<Account<'info, TokenAccount>>,
+
+    #[account(
+        seeds = [b"global-config"],
+        bump,
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    #[account(
+        seeds = [b"chain-config".as_ref(), &order.to_chain_id.to_le_bytes()],
+        bump = chain_config.bump,
+    )]
+    pub chain_config: Account<'info, ChainConfig>,
+
+    #[account(mut)]
+    pub refund_receiver: SystemAccount<'info>,
+
+    #[account(mut)]
+    pub taker: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+
+    pub associated_token_program: Program<'info, anchor_spl::associated_token::AssociatedToken>,
+
+    pub system_program: Program<'info, System>,
+
+    /// Market listing to keep in sync; required once the order is listed.
+    #[account(mut)]
+    pub order_book: Option<AccountLoader<'info, OrderBook>>,
+
+    /// Required for role checks once roles are enabled.
+    #[account(
+        seeds = [b"roles"],
+        bump = roles.bump,
+    )]
+    pub roles: Option<Account<'info, Roles>>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimHtlcOrderSol<'info> {
+    #[account(
+        mut,
+        seeds = [b"limit_order", order.sender.as_ref(), &order.expiry.to_le_bytes()],
+        bump = order.bump,
+        close = refund_receiver
+    )]
+    pub order: Account<'info, LimitOrder>,
+
+    #[account(mut)]
+    pub solver: Signer<'info>,
+
+    #[account(mut, address = global_config.treasury)]
+    pub treasury: SystemAccount<'info>,
+
+    #[account(
+        seeds = [b"global-config"],
+        bump,
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    #[account(
+        seeds = [b"chain-config".as_ref(), &order.to_chain_id.to_le_bytes()],
+        bump = chain_config.bump,
+    )]
+    pub chain_config: Account<'info, ChainConfig>,
+
+    #[account(mut)]
+    pub refund_receiver: SystemAccount<'info>,
+
+    /// Market listing to keep in sync; required once the order is listed.
+    #[account(mut)]
+    pub order_book: Option<AccountLoader<'info, OrderBook>>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimHtlcOrderSpl<'info> {
+    #[account(
+        mut,
+        seeds = [b"limit_order", order.sender.as_ref(), &order.expiry.to_le_bytes()],
+        bump = order.bump,
+        close = refund_receiver
+    )]
+    pub order: Account<'info, LimitOrder>,
+
+    #[account(
+        mut,
+        constraint = order_token_account.owner == order.key()
+    )]
+    pub order_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = solver_token_account.owner == solver.key(),
+        constraint = solver_token_account.mint == order.from_token
+    )]
+    pub solver_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = treasury_token_account.owner == global_config.treasury.key(),
+        constraint = treasury_token_account.mint == order.from_token,
+        constraint = treasury_token_account.key() == get_associated_token_address(&global_config.treasury.key(), &order.from_token)
+    )]
+    pub treasury_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        seeds = [b"global-config"],
+        bump,
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    #[account(
+        seeds = [b"chain-config".as_ref(), &order.to_chain_id.to_le_bytes()],
+        bump = chain_config.bump,
+    )]
+    pub chain_config: Account<'info, ChainConfig>,
+
+    #[account(mut)]
+    pub refund_receiver: SystemAccount<'info>,
+
+    pub solver: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+
+    /// Market listing to keep in sync; required once the order is listed.
+    #[account(mut)]
+    pub order_book: Option<AccountLoader<'info, OrderBook>>,
+}
+
+#[derive(Accounts)]
+pub struct RefundHtlcOrderSol<'info> {
+    #[account(
+        mut,
+        seeds = [b"limit_order", order.sender.as_ref(), &order.expiry.to_le_bytes()],
+        bump = order.bump,
+        close = refund_receiver
+    )]
+    pub order: Account<'info, LimitOrder>,
 
     #[account(mut)]
-    pub signer: Signer<'info>,
+    pub refund_receiver: SystemAccount<'info>,
 
-    pub system_program: Program<'info, System>,
+    pub caller: Signer<'info>,
+
+    /// Market listing to keep in sync; required once the order is listed.
+    #[account(mut)]
+    pub order_book: Option<AccountLoader<'info, OrderBook>>,
 }
 
 #[derive(Accounts)]
-pub struct UpdateConfig<'info> {
+pub struct RefundHtlcOrderSpl<'info> {
     #[account(
         mut,
-        seeds = [b"global-config"],
-        bump,
-        has_one = owner
+        seeds = [b"limit_order", order.sender.as_ref(), &order.expiry.to_le_bytes()],
+        bump = order.bump,
+        close = refund_receiver
     )]
-    pub global_config: Account<'info, GlobalConfig>,
+    pub order: Account<'info, LimitOrder>,
 
-    pub owner: Signer<'info>,
+    #[account(
+        mut,
+        constraint = user_token_account.owner == refund_receiver.key(),
+        constraint = user_token_account.mint == order.from_token
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = order_token_account.owner == order.key(),
+    )]
+    pub order_token_account: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub refund_receiver: SystemAccount<'info>,
+
+    pub caller: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+
+    /// Market listing to keep in sync; required once the order is listed.
+    #[account(mut)]
+    pub order_book: Option<AccountLoader<'info, OrderBook>>,
 }
 
 #[derive(Accounts)]
-#[instruction(params: OpenOrderParams)]
-
-pub struct OpenOrderSol<'info> {
+pub struct BondExecutorSol<'info> {
     #[account(
-        init,
-        seeds = [b"limit_order", user.key().as_ref(),&params.expiry.to_le_bytes()],
+        init_if_needed,
+        seeds = [b"executor-bond".as_ref(), executor.key().as_ref(), spl_token::native_mint::ID.as_ref()],
         bump,
-        payer = user,
-        space = 8 + LimitOrder::SIZE,
+        payer = executor,
+        space = 8 + ExecutorBond::INIT_SPACE
     )]
-    pub order: Account<'info, LimitOrder>,
+    pub executor_bond: Account<'info, ExecutorBond>,
 
     #[account(mut)]
-    pub user: Signer<'info>,
+    pub executor: Signer<'info>,
 
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-#[instruction(params: OpenOrderParams)]
-pub struct OpenOrderSpl<'info> {
+pub struct BondExecutorSpl<'info> {
     #[account(
-        init,
-        seeds = [b"limit_order", user.key().as_ref(),&params.expiry.to_le_bytes()],
+        init_if_needed,
+        seeds = [b"executor-bond".as_ref(), executor.key().as_ref(), token_mint.key().as_ref()],
         bump,
-        payer = user,
-        space = 8 + LimitOrder::SIZE,
+        payer = executor,
+        space = 8 + ExecutorBond::INIT_SPACE
     )]
-    pub order: Account<'info, LimitOrder>,
-
-    #[account(mut)]
-    pub user: Signer<'info>,
-
-    #[account(mut)]
-    pub user_token_account: Account<'info, TokenAccount>,
+    pub executor_bond: Account<'info, ExecutorBond>,
 
     #[account(
-        init,
-        payer = user,
+        init_if_needed,
+        payer = executor,
         associated_token::mint = token_mint,
-        associated_token::authority = order
+        associated_token::authority = executor_bond
     )]
-    pub order_token_account: Account<'info, TokenAccount>,
+    pub bond_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = executor_token_account.mint == token_mint.key()
+    )]
+    pub executor_token_account: Account<'info, TokenAccount>,
 
     pub token_mint: Account<'info, Mint>,
 
+    #[account(mut)]
+    pub executor: Signer<'info>,
+
     pub token_program: Program<'info, Token>,
 
     pub system_program: Program<'info, System>,
@@ -479,59 +5321,90 @@ pub struct OpenOrderSpl<'info> {
 }
 
 #[derive(Accounts)]
-pub struct ExecuteOrderSpl<'info> {
+pub struct RequestUnbond<'info> {
     #[account(
         mut,
-        close = refund_receiver // refund rent to order.sender
+        seeds = [b"executor-bond".as_ref(), executor.key().as_ref(), executor_bond.mint.as_ref()],
+        bump = executor_bond.bump,
+        has_one = executor
     )]
-    pub order: Account<'info, LimitOrder>,
+    pub executor_bond: Account<'info, ExecutorBond>,
+
+    pub executor: Signer<'info>,
 
     #[account(
-        mut,
-        constraint = order_token_account.owner == order.key()
+        seeds = [b"global-config"],
+        bump,
     )]
-    pub order_token_account: Account<'info, TokenAccount>,
-
-    #[account(mut)]
-    pub target_token_account: Account<'info, TokenAccount>,
+    pub global_config: Account<'info, GlobalConfig>,
+}
 
+#[derive(Accounts)]
+pub struct WithdrawBondSol<'info> {
     #[account(
         mut,
-        constraint = treasury_token_account.owner == global_config.treasury.key(),
-        constraint = treasury_token_account.mint == order.from_token,
-        constraint = treasury_token_account.key() == get_associated_token_address(&global_config.treasury.key(), &order.from_token)
+        seeds = [b"executor-bond".as_ref(), executor.key().as_ref(), spl_token::native_mint::ID.as_ref()],
+        bump = executor_bond.bump,
+        has_one = executor
     )]
-    pub treasury_token_account: Account<'info, TokenAccount>,
+    pub executor_bond: Account<'info, ExecutorBond>,
+
+    #[account(mut)]
+    pub executor: Signer<'info>,
 
     #[account(
         seeds = [b"global-config"],
         bump,
     )]
     pub global_config: Account<'info, GlobalConfig>,
+}
 
-    #[account(mut)]
-    pub refund_receiver: SystemAccount<'info>,
+#[derive(Accounts)]
+pub struct WithdrawBondSpl<'info> {
+    #[account(
+        mut,
+        seeds = [b"executor-bond".as_ref(), executor.key().as_ref(), executor_bond.mint.as_ref()],
+        bump = executor_bond.bump,
+        has_one = executor,
+        constraint = executor_bond.mint != native_token() @ CustomError::InvalidBond
+    )]
+    pub executor_bond: Account<'info, ExecutorBond>,
+
+    #[account(
+        mut,
+        associated_token::mint = executor_bond.mint,
+        associated_token::authority = executor_bond
+    )]
+    pub bond_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = executor_token_account.mint == executor_bond.mint
+    )]
+    pub executor_token_account: Account<'info, TokenAccount>,
 
     pub executor: Signer<'info>,
 
+    #[account(
+        seeds = [b"global-config"],
+        bump,
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
     pub token_program: Program<'info, Token>,
 }
 
 #[derive(Accounts)]
-pub struct ExecuteOrderSol<'info> {
+pub struct SlashExecutorSol<'info> {
     #[account(
         mut,
-        close = refund_receiver // refund rent to order.sender
+        seeds = [b"executor-bond".as_ref(), executor_bond.executor.as_ref(), spl_token::native_mint::ID.as_ref()],
+        bump = executor_bond.bump,
     )]
-    pub order: Account<'info, LimitOrder>,
+    pub executor_bond: Account<'info, ExecutorBond>,
 
     #[account(mut)]
-    pub target_sol: SystemAccount<'info>,
-
-    #[account(mut, address = global_config.treasury)]
-    pub treasury: SystemAccount<'info>,
-
-    pub executor: Signer<'info>,
+    pub recipient: SystemAccount<'info>,
 
     #[account(
         seeds = [b"global-config"],
@@ -539,96 +5412,712 @@ pub struct ExecuteOrderSol<'info> {
     )]
     pub global_config: Account<'info, GlobalConfig>,
 
-    pub refund_receiver: SystemAccount<'info>,
+    pub authority: Signer<'info>,
+
+    pub guardian_set: Account<'info, GuardianSet>,
+
+    /// CHECK: address is checked against the instructions sysvar id
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+
+    /// Required for role checks once roles are enabled.
+    #[account(
+        seeds = [b"roles"],
+        bump = roles.bump,
+    )]
+    pub roles: Option<Account<'info, Roles>>,
 }
 
 #[derive(Accounts)]
-pub struct CancelOrderSol<'info> {
+pub struct SlashExecutorSpl<'info> {
     #[account(
         mut,
-        close = refund_receiver
+        seeds = [b"executor-bond".as_ref(), executor_bond.executor.as_ref(), executor_bond.mint.as_ref()],
+        bump = executor_bond.bump,
+        constraint = executor_bond.mint != native_token() @ CustomError::InvalidBond
     )]
-    pub order: Account<'info, LimitOrder>,
+    pub executor_bond: Account<'info, ExecutorBond>,
 
-    #[account(mut)]
-    pub user: Signer<'info>,
+    #[account(
+        mut,
+        associated_token::mint = executor_bond.mint,
+        associated_token::authority = executor_bond
+    )]
+    pub bond_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = recipient_token_account.mint == executor_bond.mint
+    )]
+    pub recipient_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        seeds = [b"global-config"],
+        bump,
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    pub authority: Signer<'info>,
+
+    pub guardian_set: Account<'info, GuardianSet>,
+
+    /// CHECK: address is checked against the instructions sysvar id
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token>,
+
+    /// Required for role checks once roles are enabled.
+    #[account(
+        seeds = [b"roles"],
+        bump = roles.bump,
+    )]
+    pub roles: Option<Account<'info, Roles>>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct OpenOrderParams {
+    pub from_token: Pubkey,
+    pub from_chain_id: u64,
+    pub amount_in: u64,
+    pub to_chain_id: u64,
+    pub to_token: [u8; 32],
+    pub recipient: [u8; 32],
+    pub expiry: i64,
+    pub amount_out: [u8; 32],
+    /// sha256 hash of the HTLC secret; all zeroes for a regular order.
+    pub hashlock: [u8; 32],
+    /// Unix timestamp after which an HTLC order can only be refunded.
+    pub timelock: i64,
+    /// Solver allowed to claim an HTLC order; the default key for a regular
+    /// order.
+    pub htlc_solver: Pubkey,
+    /// Solver holding the signed RFQ quote; the default key for an open order.
+    pub exclusive_executor: Pubkey,
+    /// Unix timestamp until which only `exclusive_executor` can fill.
+    pub exclusivity_deadline: i64,
+    /// Wallet receiving refunds; the default key refunds to the sender.
+    pub refund_address: Pubkey,
+    /// Keeps the order executable past `expiry` until it is cancelled.
+    pub good_till_cancelled: bool,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct SessionKeyParams {
+    pub key: Pubkey,
+    pub expires_at: i64,
+    /// Total `amount_in` the key may open, in base units of its one allowed
+    /// mint; zero makes a cancel-only key.
+    pub open_allowance: u64,
+    pub can_cancel: bool,
+    /// Mints the key may open orders in, the native mint for SOL. A key with
+    /// an allowance names exactly one.
+    pub allowed_mints: Vec<Pubkey>,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct GlobalConfig {
+    pub owner: Pubkey,
+    pub platform_fee: u16,
+    pub treasury: Pubkey,
+    pub paused: bool,
+    pub guardian_set_index: u32,
+    pub attestation_required: bool,
+    /// Chain id of this deployment; every order's `from_chain_id`.
+    pub chain_id: u64,
+    /// When set, only mints with an `Allowed` policy can be escrowed.
+    pub mint_allowlist_mode: bool,
+    /// Seconds between `request_cancel` and the sender being able to cancel;
+    /// zero lets senders cancel immediately.
+    pub cancel_grace_period: i64,
+    /// Multiple of its bond a third-party executor can have open to slashing;
+    /// zero restricts execution to the owner.
+    pub bond_multiple: u16,
+    /// Seconds an unbond request waits before it can be withdrawn.
+    pub unbond_cooldown: i64,
+    /// Seconds after a bonded fill during which it can be slashed.
+    pub slash_window: i64,
+    /// Layout version; zero for accounts created before versioning.
+    pub version: u8,
+    /// Set by `init_roles`; until then the owner holds every role.
+    pub roles_enabled: bool,
+    /// Program `execute_order_swap` swaps through; default disables it.
+    pub swap_adapter: Pubkey,
+    /// Whether swapped executions take the fee from the output rather than
+    /// the escrow.
+    pub swap_fee_on_output: bool,
+    /// Shortest time to expiry an order can be opened with, in seconds.
+    pub min_order_lifetime: i64,
+    /// Longest time to expiry an order can be opened with, in seconds; zero
+    /// leaves it unbounded. Good-till-cancelled orders are exempt from both.
+    pub max_order_lifetime: i64,
+    /// Smallest `amount_in` of SOL orders, in lamports; SPL orders use their
+    /// `MintPolicy::min_order_size`.
+    pub min_sol_order_size: u64,
+    /// Smallest notional value an order can be opened with, priced through
+    /// the `PriceFeed` of its `from_token`; zero disables the check. Mints
+    /// without a price skip it, while a stale price rejects the order.
+    pub min_notional: u64,
+    /// Seconds a `PriceFeed` price stays usable; always positive.
+    pub max_price_age: i64,
+    /// Rounds platform fees up, so a non-zero `platform_fee` never charges
+    /// nothing.
+    pub round_fees_up: bool,
+    pub reserved: [u8; 12], // Reserved space for future use
+}
+
+impl GlobalConfig {
+    /// Bumped with every layout change: 1 added `version`, 2 roles, 3 the
+    /// swap adapter, 4 order lifetime bounds and 5 order minimums and price
+    /// feeds.
+    pub const VERSION: u8 = 5;
+    /// `max_price_age` of new and migrated configs, in seconds.
+    pub const DEFAULT_MAX_PRICE_AGE: i64 = 3600;
+    /// Highest platform fee, in basis points.
+    pub const MAX_PLATFORM_FEE: u16 = 1000;
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct ChainConfig {
+    pub chain_id: u64,
+    pub enabled: bool,
+    pub address_format: AddressFormat,
+    pub min_amount_in: u64,
+    /// Zero means no upper bound.
+    pub max_amount_in: u64,
+    /// Replaces `GlobalConfig::platform_fee` for orders to this chain.
+    pub platform_fee_override: Option<u16>,
+    pub bump: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Clone, Copy, PartialEq, Eq)]
+pub enum AddressFormat {
+    /// 20-byte EVM address, left-padded with zeroes to 32 bytes.
+    Evm,
+    /// Any non-zero 32-byte address.
+    Bytes32,
+}
+
+impl AddressFormat {
+    pub fn is_valid(&self, recipient: &[u8; 32]) -> bool {
+        match self {
+            AddressFormat::Evm => recipient[..12] == [0u8; 12] && recipient[12..] != [0u8; 20],
+            AddressFormat::Bytes32 => *recipient != [0u8; 32],
+        }
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, InitSpace)]
+pub struct ChainConfigParams {
+    pub enabled: bool,
+    pub address_format: AddressFormat,
+    pub min_amount_in: u64,
+    pub max_amount_in: u64,
+    pub platform_fee_override: Option<u16>,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct MintPolicy {
+    pub mint: Pubkey,
+    pub status: MintStatus,
+    pub min_order_size: u64,
+    pub require_no_freeze_authority: bool,
+    pub bump: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Clone, Copy, PartialEq, Eq)]
+pub enum MintStatus {
+    Allowed,
+    Denied,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, InitSpace)]
+pub struct MintPolicyParams {
+    pub status: MintStatus,
+    pub min_order_size: u64,
+    pub require_no_freeze_authority: bool,
+}
+
+/// Notional value of a mint, set by holders of the PriceUpdater role rather
+/// than read from an oracle.
+#[account]
+#[derive(InitSpace)]
+pub struct PriceFeed {
+    pub mint: Pubkey,
+    /// Value of one base unit, scaled by `PRICE_SCALE`.
+    pub price: u64,
+    pub updated_at: i64,
+    pub bump: u8,
+}
+
+impl PriceFeed {
+    pub const PRICE_SCALE: u128 = 1_000_000_000;
+
+    pub fn notional(&self, amount: u64) -> u128 {
+        amount as u128 * self.price as u128 / Self::PRICE_SCALE
+    }
+}
+
+/// A supported `(from_token, to_chain_id, to_token)` path that solvers fill.
+#[account]
+#[derive(InitSpace)]
+pub struct TokenRoute {
+    pub from_token: Pubkey,
+    pub to_chain_id: u64,
+    pub to_token: [u8; 32],
+    pub enabled: bool,
+    pub from_decimals: u8,
+    pub to_decimals: u8,
+    pub min_amount_in: u64,
+    /// Zero means no upper bound.
+    pub max_amount_in: u64,
+    pub bump: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, InitSpace)]
+pub struct TokenRouteParams {
+    pub enabled: bool,
+    pub from_decimals: u8,
+    pub to_decimals: u8,
+    pub min_amount_in: u64,
+    pub max_amount_in: u64,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct GuardianSet {
+    pub index: u32,
+    #[max_len(19)]
+    pub keys: Vec<Pubkey>,
+    pub threshold: u8,
+    /// Unix timestamp after which a rotated-out set stops being accepted;
+    /// zero while the set is current.
+    pub expiration_time: i64,
+    pub bump: u8,
+}
+
+impl GuardianSet {
+    pub const MAX_GUARDIANS: usize = 19;
+}
+
+/// Message signed by guardians once the destination-chain leg of an order
+/// has been delivered. Amounts are big-endian uint256, like `amount_out`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct FillAttestation {
+    pub order: Pubkey,
+    pub dest_tx_hash: [u8; 32],
+    pub recipient: [u8; 32],
+    pub amount_delivered: [u8; 32],
+}
+
+impl FillAttestation {
+    pub const DOMAIN: &'static [u8] = b"interra-limit-order:fill:v1";
+
+    /// Parses a signed message of the form `DOMAIN || borsh(FillAttestation)`.
+    pub fn from_message(message: &[u8]) -> Option<Self> {
+        let body = message.strip_prefix(Self::DOMAIN)?;
+        Self::try_from_slice(body).ok()
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Role {
+    /// Config changes and role management.
+    Admin,
+    /// Platform fee changes.
+    FeeManager,
+    /// Pausing and unpausing.
+    Pauser,
+    /// Order execution without a bond.
+    Executor,
+    /// Slashing bonded executors.
+    Guardian,
+    /// Publishing `PriceFeed` prices.
+    PriceUpdater,
+}
+
+impl Role {
+    pub const ALL: u8 = 0b11_1111;
+
+    pub fn bit(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Clone)]
+pub struct RoleMember {
+    pub key: Pubkey,
+    /// Bit set of [`Role::bit`].
+    pub roles: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Roles {
+    #[max_len(16)]
+    pub members: Vec<RoleMember>,
+    /// Admin approvals needed for Admin actions; above one they must go
+    /// through a proposal.
+    pub admin_threshold: u8,
+    /// Id of the next proposal.
+    pub proposal_count: u64,
+    pub bump: u8,
+}
+
+impl Roles {
+    pub const MAX_MEMBERS: usize = 16;
+
+    pub fn has(&self, key: &Pubkey, role: Role) -> bool {
+        self.members
+            .iter()
+            .any(|member| member.key == *key && member.roles & role.bit() != 0)
+    }
+
+    pub fn grant(&mut self, key: Pubkey, role: Role) -> Result<()> {
+        if let Some(member) = self.members.iter_mut().find(|member| member.key == key) {
+            member.roles |= role.bit();
+            return Ok(());
+        }
+        require!(
+            self.members.len() < Self::MAX_MEMBERS,
+            CustomError::TooManyRoleMembers
+        );
+        self.members.push(RoleMember {
+            key,
+            roles: role.bit(),
+        });
+        Ok(())
+    }
+
+    pub fn revoke(&mut self, key: &Pubkey, role: Role) {
+        for member in self.members.iter_mut().filter(|member| member.key == *key) {
+            member.roles &= !role.bit();
+        }
+        self.members.retain(|member| member.roles != 0);
+    }
+
+    pub fn count(&self, role: Role) -> usize {
+        self.members
+            .iter()
+            .filter(|member| member.roles & role.bit() != 0)
+            .count()
+    }
+}
 
-    #[account(mut)]
-    pub refund_receiver: SystemAccount<'info>,
+/// Admin actions that can be taken directly or, under a multisig threshold,
+/// through `propose_admin_action`.
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Clone, PartialEq)]
+pub enum AdminAction {
+    SetTreasury {
+        treasury: Pubkey,
+    },
+    TransferOwnership {
+        owner: Pubkey,
+    },
+    SetAttestationRequired {
+        attestation_required: bool,
+    },
+    SetChainId {
+        chain_id: u64,
+    },
+    SetMintAllowlistMode {
+        mint_allowlist_mode: bool,
+    },
+    SetCancelGracePeriod {
+        cancel_grace_period: i64,
+    },
+    SetBondConfig {
+        bond_multiple: u16,
+        unbond_cooldown: i64,
+        slash_window: i64,
+    },
+    SetSwapConfig {
+        swap_adapter: Pubkey,
+        fee_on_output: bool,
+    },
+    SetOrderLifetime {
+        min_order_lifetime: i64,
+        max_order_lifetime: i64,
+    },
+    SetOrderMinimums {
+        min_sol_order_size: u64,
+        min_notional: u64,
+        max_price_age: i64,
+    },
+    // The actions below write accounts of their own, so a proposal for one
+    // is executed by passing it to the matching instruction.
+    SetChainConfig {
+        chain_id: u64,
+        params: ChainConfigParams,
+    },
+    SetTokenRoute {
+        from_token: Pubkey,
+        to_chain_id: u64,
+        to_token: [u8; 32],
+        params: TokenRouteParams,
+    },
+    SetMintPolicy {
+        mint: Pubkey,
+        params: MintPolicyParams,
+    },
+    InitGuardianSet {
+        #[max_len(19)]
+        keys: Vec<Pubkey>,
+        threshold: u8,
+    },
+    RotateGuardianSet {
+        #[max_len(19)]
+        keys: Vec<Pubkey>,
+        threshold: u8,
+        previous_set_ttl: i64,
+    },
+    InitOrderBook {
+        from_token: Pubkey,
+        to_chain_id: u64,
+    },
+    ForceRefund {
+        reason_code: u16,
+        #[max_len(16)]
+        orders: Vec<Pubkey>,
+    },
+    MigrateConfig {
+        chain_id: u64,
+    },
+    MigrateOrder {
+        order: Pubkey,
+    },
+    GrantRole {
+        key: Pubkey,
+        role: Role,
+    },
+    RevokeRole {
+        key: Pubkey,
+        role: Role,
+    },
+    SetAdminThreshold {
+        threshold: u8,
+    },
+}
 
-    #[account(
-        seeds = [b"global-config"],
-        bump,
-    )]
-    pub global_config: Account<'info, GlobalConfig>,
+#[account]
+#[derive(InitSpace)]
+pub struct AdminProposal {
+    pub id: u64,
+    pub proposer: Pubkey,
+    pub action: AdminAction,
+    /// Admins that approved; checked again against the current role set on
+    /// execution.
+    #[max_len(16)]
+    pub approvals: Vec<Pubkey>,
+    pub executed: bool,
+    pub bump: u8,
+}
 
-    pub system_program: Program<'info, System>,
+/// Open orders of one `(from_token, to_chain_id)` market, so keepers can
+/// read the whole market from a single account. Only `count` leading slots
+/// are in use; removal swaps the last slot into the gap.
+#[account(zero_copy)]
+pub struct OrderBook {
+    pub from_token: Pubkey,
+    pub to_chain_id: u64,
+    pub count: u32,
+    pub bump: u8,
+    pub padding: [u8; 3],
+    pub slots: [OrderBookSlot; 200],
 }
 
-#[derive(Accounts)]
-pub struct CancelOrderSpl<'info> {
-    #[account(
-        mut,
-        seeds = [b"limit_order", order.sender.as_ref(), &order.expiry.to_le_bytes()],
-        bump = order.bump,
-        close = refund_receiver
-    )]
-    pub order: Account<'info, LimitOrder>,
+impl OrderBook {
+    /// Keeps the account within the 10 KiB limit for creation through CPI.
+    pub const CAPACITY: usize = 200;
+    pub const SIZE: usize = std::mem::size_of::<OrderBook>();
 
-    #[account(mut)]
-    pub user: Signer<'info>,
+    pub fn is_market_of(&self, order: &LimitOrder) -> bool {
+        self.from_token == order.from_token && self.to_chain_id == order.to_chain_id
+    }
 
-    #[account(
-        mut,
-        constraint = user_token_account.owner == refund_receiver.key(),
-        constraint = user_token_account.mint == order.from_token
-    )]
-    pub user_token_account: Account<'info, TokenAccount>,
+    pub fn active(&self) -> &[OrderBookSlot] {
+        &self.slots[..self.count as usize]
+    }
 
-    #[account(
-        mut,
-        constraint = order_token_account.owner == order.key(),
-    )]
-    pub order_token_account: Account<'info, TokenAccount>,
+    pub fn insert(&mut self, slot: OrderBookSlot) -> Result<()> {
+        let count = self.count as usize;
+        require!(count < Self::CAPACITY, CustomError::OrderBookFull);
+        self.slots[count] = slot;
+        self.count += 1;
+        Ok(())
+    }
 
-    #[account(mut)]
-    pub refund_receiver: SystemAccount<'info>,
+    /// Removes the slot of `order`, returning whether it was listed.
+    pub fn remove(&mut self, order: &Pubkey) -> bool {
+        match self.active().iter().position(|slot| slot.order == *order) {
+            Some(index) => {
+                self.remove_at(index);
+                true
+            }
+            None => false,
+        }
+    }
 
-    #[account(
-        seeds = [b"global-config"],
-        bump
-    )]
-    pub global_config: Account<'info, GlobalConfig>,
+    /// Updates the listed amount of a partially filled `order`.
+    pub fn resize(&mut self, order: &Pubkey, amount_in: u64) {
+        let count = self.count as usize;
+        if let Some(slot) = self.slots[..count]
+            .iter_mut()
+            .find(|slot| slot.order == *order)
+        {
+            slot.amount_in = amount_in;
+        }
+    }
 
-    pub token_program: Program<'info, Token>,
+    /// Keeps the slots matching `keep`, returning how many were removed.
+    pub fn retain(&mut self, keep: impl Fn(&OrderBookSlot) -> bool) -> u32 {
+        let mut removed = 0;
+        let mut index = 0;
+        while index < self.count as usize {
+            if keep(&self.slots[index]) {
+                index += 1;
+            } else {
+                self.remove_at(index);
+                removed += 1;
+            }
+        }
+        removed
+    }
+
+    fn remove_at(&mut self, index: usize) {
+        let last = self.count as usize - 1;
+        self.slots[index] = self.slots[last];
+        self.slots[last] = OrderBookSlot::default();
+        self.count -= 1;
+    }
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize)]
-pub struct OpenOrderParams {
+#[zero_copy]
+#[derive(Default)]
+pub struct OrderBookSlot {
+    pub order: Pubkey,
+    pub amount_in: u64,
+    pub expiry: i64,
+}
+
+/// Quote signed by a solver for the exclusive right to fill an order until
+/// `exclusivity_deadline`. The order is identified by `sender` and `expiry`,
+/// like its PDA.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq)]
+pub struct FillQuote {
+    pub sender: Pubkey,
+    pub expiry: i64,
     pub from_token: Pubkey,
-    pub from_chain_id: u64,
     pub amount_in: u64,
     pub to_chain_id: u64,
     pub to_token: [u8; 32],
     pub recipient: [u8; 32],
-    pub expiry: i64,
     pub amount_out: [u8; 32],
+    pub exclusivity_deadline: i64,
+}
+
+impl FillQuote {
+    pub const DOMAIN: &'static [u8] = b"interra-limit-order:quote:v1";
+
+    /// Parses a signed message of the form `DOMAIN || borsh(FillQuote)`.
+    pub fn from_message(message: &[u8]) -> Option<Self> {
+        let body = message.strip_prefix(Self::DOMAIN)?;
+        Self::try_from_slice(body).ok()
+    }
+}
+
+/// Message signed by guardians when an order filled by a bonded executor was
+/// never delivered on the destination chain.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct NonDeliveryAttestation {
+    pub order: Pubkey,
+    pub executor: Pubkey,
+}
+
+impl NonDeliveryAttestation {
+    pub const DOMAIN: &'static [u8] = b"interra-limit-order:non-delivery:v1";
+
+    /// Parses a signed message of the form `DOMAIN || borsh(NonDeliveryAttestation)`.
+    pub fn from_message(message: &[u8]) -> Option<Self> {
+        let body = message.strip_prefix(Self::DOMAIN)?;
+        Self::try_from_slice(body).ok()
+    }
 }
 
 #[account]
-pub struct GlobalConfig {
-    pub owner: Pubkey,
-    pub platform_fee: u16,
-    pub treasury: Pubkey,
-    pub paused: bool,
-    pub reserved: [u8; 128], // Reserved space for future use
+#[derive(InitSpace)]
+pub struct ExecutorBond {
+    pub executor: Pubkey,
+    /// Bonded mint; the native mint for lamport bonds.
+    pub mint: Pubkey,
+    /// Active bond backing new fills.
+    pub amount: u64,
+    /// Unbonded amount waiting out the cooldown; still slashable.
+    pub unbonding_amount: u64,
+    pub unbond_requested_at: i64,
+    /// Fills that are still within the slash window.
+    #[max_len(32)]
+    pub pending: Vec<BondedExecution>,
+    pub bump: u8,
 }
 
-impl GlobalConfig {
-    pub const SIZE: usize = 32 + 2 + 32 + 1 + 128;
+impl ExecutorBond {
+    pub const MAX_PENDING: usize = 32;
+
+    pub fn exposure(&self) -> Result<u64> {
+        self.pending
+            .iter()
+            .try_fold(0u64, |total, execution| total.checked_add(execution.amount))
+            .ok_or_else(|| error!(CustomError::Overflow))
+    }
+
+    /// Drops fills whose slash window has passed.
+    pub fn release_settled(&mut self, now: i64, slash_window: i64) {
+        self.pending
+            .retain(|execution| execution.executed_at.saturating_add(slash_window) > now);
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct BondedExecution {
+    pub order: Pubkey,
+    /// Receives a slash of this fill; the order's refund address.
+    pub refund_to: Pubkey,
+    pub amount: u64,
+    pub executed_at: i64,
+}
+
+/// An ephemeral key allowed to open and cancel orders for `user`.
+#[account]
+#[derive(InitSpace)]
+pub struct SessionKey {
+    pub user: Pubkey,
+    pub key: Pubkey,
+    pub expires_at: i64,
+    /// Remaining `amount_in` the key may open, in base units of its allowed
+    /// mint; zero for a cancel-only key.
+    pub open_allowance: u64,
+    pub can_cancel: bool,
+    /// A single mint for keys created with an allowance; empty allows every
+    /// mint.
+    #[max_len(8)]
+    pub allowed_mints: Vec<Pubkey>,
+    pub bump: u8,
+}
+
+impl SessionKey {
+    pub const MAX_ALLOWED_MINTS: usize = 8;
+
+    pub fn allows_mint(&self, mint: &Pubkey) -> bool {
+        self.allowed_mints.is_empty() || self.allowed_mints.contains(mint)
+    }
 }
 
 #[account]
+#[derive(InitSpace)]
 pub struct LimitOrder {
     pub from_token: Pubkey,
     pub from_chain_id: u64,
@@ -640,10 +6129,73 @@ pub struct LimitOrder {
     pub expiry: i64,
     pub amount_out: [u8; 32],
     pub bump: u8,
+    pub hashlock: [u8; 32],
+    pub timelock: i64,
+    /// The only signer that can claim the order with the preimage, so a
+    /// revealed secret can't be used to take the escrow from the solver.
+    pub htlc_solver: Pubkey,
+    /// SOL escrowed as WSOL in the order's token account instead of as lamports.
+    pub wrapped_sol: bool,
+    /// Unix timestamp of the sender's `request_cancel`; zero if none.
+    pub cancel_requested_at: i64,
+    pub exclusive_executor: Pubkey,
+    pub exclusivity_deadline: i64,
+    /// Layout version; zero for orders opened before versioning. New fields
+    /// are appended after it so older orders migrate by zero-filling.
+    pub version: u8,
+    /// Listed in the `OrderBook` of its market.
+    pub in_order_book: bool,
+    /// Wallet receiving the escrow and rent back; the default key for orders
+    /// migrated from before refund addresses, which refund to `sender`.
+    pub refund_address: Pubkey,
+    /// Executable past `expiry`, which then only identifies the order.
+    pub good_till_cancelled: bool,
 }
 
 impl LimitOrder {
-    pub const SIZE: usize = 32 + 8 + 8 + 8 + 32 + 32 + 32 + 8 + 32 + 1;
+    pub const VERSION: u8 = 4;
+
+    /// Wallet that cancels, reclaims and executions refund to. Token refunds
+    /// go to a token account it owns.
+    pub fn refund_to(&self) -> Pubkey {
+        if self.refund_address == Pubkey::default() {
+            self.sender
+        } else {
+            self.refund_address
+        }
+    }
+
+    pub fn is_htlc(&self) -> bool {
+        self.hashlock != [0u8; 32]
+    }
+
+    /// Time until which the order can be executed; good-till-cancelled
+    /// orders never lapse.
+    pub fn live_until(&self) -> i64 {
+        if self.good_till_cancelled {
+            i64::MAX
+        } else {
+            self.expiry
+        }
+    }
+
+    /// Whether the order can still be executed at `now`. Past that the sender
+    /// can cancel without a grace period and keepers can prune its listing.
+    pub fn is_live(&self, now: i64) -> bool {
+        now < self.live_until()
+    }
+
+    /// Whether the order settles on this chain, where `to_token` is a mint and
+    /// `recipient` a wallet.
+    pub fn is_local(&self) -> bool {
+        self.to_chain_id == self.from_chain_id
+    }
+
+    /// Whether the escrow lives in a token account owned by the order rather
+    /// than as lamports on the order itself.
+    pub fn uses_token_escrow(&self) -> bool {
+        self.from_token != native_token() || self.wrapped_sol
+    }
 }
 
 #[event]
@@ -652,14 +6204,71 @@ pub struct Initialized {
     pub platform_fee: u16,
     pub treasury: Pubkey,
     pub paused: bool,
+    pub chain_id: u64,
 }
 
 #[event]
-pub struct ConfigUpdated {
-    pub owner: Pubkey,
-    pub platform_fee: u16,
-    pub treasury: Pubkey,
-    pub paused: bool,
+pub struct PlatformFeeUpdated {
+    pub old_platform_fee: u16,
+    pub new_platform_fee: u16,
+}
+
+#[event]
+pub struct TreasuryUpdated {
+    pub old_treasury: Pubkey,
+    pub new_treasury: Pubkey,
+}
+
+#[event]
+pub struct PausedUpdated {
+    pub old_paused: bool,
+    pub new_paused: bool,
+}
+
+#[event]
+pub struct OwnershipTransferred {
+    pub previous_owner: Pubkey,
+    pub new_owner: Pubkey,
+}
+
+#[event]
+pub struct ChainIdUpdated {
+    pub chain_id: u64,
+}
+
+#[event]
+pub struct ChainConfigUpdated {
+    pub chain_id: u64,
+    pub enabled: bool,
+    pub address_format: AddressFormat,
+    pub min_amount_in: u64,
+    pub max_amount_in: u64,
+    pub platform_fee_override: Option<u16>,
+}
+
+#[event]
+pub struct TokenRouteUpdated {
+    pub from_token: Pubkey,
+    pub to_chain_id: u64,
+    pub to_token: [u8; 32],
+    pub enabled: bool,
+    pub from_decimals: u8,
+    pub to_decimals: u8,
+    pub min_amount_in: u64,
+    pub max_amount_in: u64,
+}
+
+#[event]
+pub struct MintPolicyUpdated {
+    pub mint: Pubkey,
+    pub status: MintStatus,
+    pub min_order_size: u64,
+    pub require_no_freeze_authority: bool,
+}
+
+#[event]
+pub struct MintAllowlistModeUpdated {
+    pub mint_allowlist_mode: bool,
 }
 
 #[event]
@@ -671,6 +6280,48 @@ pub struct OrderOpened {
 pub struct OrderCancelled {
     pub order_pubkey: Pubkey,
     pub by: Pubkey,
+    pub reason: CancelReason,
+    /// Zero for regular cancels; set by the owner on `force_refund_batch`.
+    pub reason_code: u16,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum CancelReason {
+    /// Cancelled by the sender.
+    Sender,
+    /// Cancelled by an Admin through a regular cancel.
+    Owner,
+    /// Reclaimed after the order expired.
+    Expired,
+    /// Refunded by an Admin through `force_refund_batch`.
+    ForceRefund,
+}
+
+#[event]
+pub struct CancelRequested {
+    pub order_pubkey: Pubkey,
+    pub cancellable_at: i64,
+}
+
+#[event]
+pub struct SessionKeyCreated {
+    pub user: Pubkey,
+    pub key: Pubkey,
+    pub expires_at: i64,
+    pub open_allowance: u64,
+    pub can_cancel: bool,
+    pub allowed_mints: Vec<Pubkey>,
+}
+
+#[event]
+pub struct SessionKeyRevoked {
+    pub user: Pubkey,
+    pub key: Pubkey,
+}
+
+#[event]
+pub struct CancelGracePeriodUpdated {
+    pub cancel_grace_period: i64,
 }
 #[event]
 pub struct OrderExecuted {
@@ -679,9 +6330,205 @@ pub struct OrderExecuted {
     pub native_token_volume: u64,
 }
 
+#[event]
+pub struct BatchOrderResult {
+    pub order_pubkey: Pubkey,
+    pub executed: bool,
+    /// Error code that caused the order to be skipped; zero when executed.
+    pub error_code: u32,
+}
+
+#[event]
+pub struct GuardianSetUpdated {
+    pub index: u32,
+    pub keys: Vec<Pubkey>,
+    pub threshold: u8,
+    pub previous_set_expiration_time: i64,
+}
+
+#[event]
+pub struct AttestationRequirementUpdated {
+    pub attestation_required: bool,
+}
+
+#[event]
+pub struct FillAttested {
+    pub order_pubkey: Pubkey,
+    pub guardian_set_index: u32,
+    pub dest_tx_hash: [u8; 32],
+    pub amount_delivered: [u8; 32],
+}
+
+#[event]
+pub struct BondConfigUpdated {
+    pub bond_multiple: u16,
+    pub unbond_cooldown: i64,
+    pub slash_window: i64,
+}
+
+#[event]
+pub struct LocalOrderFilled {
+    pub order_pubkey: Pubkey,
+    pub taker: Pubkey,
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub fee_amount: u64,
+    /// Escrow left to take; zero once the order is filled and closed.
+    pub remaining_amount_in: u64,
+}
+
+#[event]
+pub struct SwapConfigUpdated {
+    pub old_swap_adapter: Pubkey,
+    pub new_swap_adapter: Pubkey,
+    pub fee_on_output: bool,
+}
+
+#[event]
+pub struct OrderLifetimeUpdated {
+    pub min_order_lifetime: i64,
+    pub max_order_lifetime: i64,
+}
+
+#[event]
+pub struct OrderMinimumsUpdated {
+    pub min_sol_order_size: u64,
+    pub min_notional: u64,
+    pub max_price_age: i64,
+}
+
+#[event]
+pub struct FeeRoundingUpdated {
+    pub round_fees_up: bool,
+}
+
+#[event]
+pub struct PriceUpdated {
+    pub mint: Pubkey,
+    pub price: u64,
+    pub updated_at: i64,
+}
+
+#[event]
+pub struct OrderSwapped {
+    pub order_pubkey: Pubkey,
+    pub output_mint: Pubkey,
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub fee_amount: u64,
+    pub fee_mint: Pubkey,
+}
+
+#[event]
+pub struct ExecutorBonded {
+    pub executor: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub total: u64,
+}
+
+#[event]
+pub struct UnbondRequested {
+    pub executor: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub withdrawable_at: i64,
+}
+
+#[event]
+pub struct BondWithdrawn {
+    pub executor: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct ExecutorSlashed {
+    pub executor: Pubkey,
+    pub mint: Pubkey,
+    pub order_pubkey: Pubkey,
+    pub recipient: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct AccountMigrated {
+    pub account: Pubkey,
+    pub from_version: u8,
+    pub to_version: u8,
+}
+
+#[event]
+pub struct OrderBookInitialized {
+    pub order_book: Pubkey,
+    pub from_token: Pubkey,
+    pub to_chain_id: u64,
+}
+
+#[event]
+pub struct OrderBookPruned {
+    pub order_book: Pubkey,
+    pub removed: u32,
+}
+
+#[event]
+pub struct RolesInitialized {
+    pub admin: Pubkey,
+}
+
+#[event]
+pub struct RoleGranted {
+    pub key: Pubkey,
+    pub role: Role,
+}
+
+#[event]
+pub struct RoleRevoked {
+    pub key: Pubkey,
+    pub role: Role,
+}
+
+#[event]
+pub struct AdminThresholdUpdated {
+    pub threshold: u8,
+}
+
+#[event]
+pub struct AdminActionProposed {
+    pub proposal: Pubkey,
+    pub id: u64,
+    pub proposer: Pubkey,
+}
+
+#[event]
+pub struct AdminActionApproved {
+    pub proposal: Pubkey,
+    pub approver: Pubkey,
+    pub approvals: u8,
+}
+
+#[event]
+pub struct AdminActionExecuted {
+    pub proposal: Pubkey,
+    pub id: u64,
+}
+
+#[event]
+pub struct HtlcClaimed {
+    pub order_pubkey: Pubkey,
+    pub by: Pubkey,
+    pub preimage: [u8; 32],
+}
+
+#[event]
+pub struct HtlcRefunded {
+    pub order_pubkey: Pubkey,
+    pub by: Pubkey,
+}
+
 #[error_code]
 pub enum CustomError {
-    #[msg("Platform fee must be between 0 and 10000.")]
+    #[msg("Platform fee exceeds the maximum.")]
     InvalidPlatformFee,
     #[msg("Invalid parameter")]
     InvalidParameter,
@@ -697,4 +6544,112 @@ pub enum CustomError {
     InvalidRefundReceiver,
     #[msg("Only owner can execute.")]
     OnlyOwnerCanExecute,
+    #[msg("HTLC orders can only be claimed or refunded.")]
+    HtlcOrder,
+    #[msg("Order is not an HTLC order.")]
+    NotHtlcOrder,
+    #[msg("Preimage does not match the hashlock.")]
+    InvalidPreimage,
+    #[msg("Only the order's HTLC solver can claim it.")]
+    NotHtlcSolver,
+    #[msg("HTLC timelock has expired.")]
+    TimelockExpired,
+    #[msg("HTLC timelock has not expired yet.")]
+    TimelockNotExpired,
+    #[msg("Invalid guardian set.")]
+    InvalidGuardianSet,
+    #[msg("Guardian set has expired.")]
+    GuardianSetExpired,
+    #[msg("A guardian fill attestation is required.")]
+    AttestationRequired,
+    #[msg("Invalid fill attestation.")]
+    InvalidAttestation,
+    #[msg("Not enough guardian signatures.")]
+    InsufficientAttestations,
+    #[msg("Destination chain is not supported.")]
+    ChainNotSupported,
+    #[msg("Recipient does not match the destination chain address format.")]
+    InvalidRecipient,
+    #[msg("Order amount is outside the allowed range.")]
+    AmountOutOfRange,
+    #[msg("Token route is not supported.")]
+    TokenRouteNotSupported,
+    #[msg("Mint is not allowed.")]
+    MintNotAllowed,
+    #[msg("Mint has a freeze authority.")]
+    MintHasFreezeAuthority,
+    #[msg("Order escrow does not match the instruction.")]
+    InvalidEscrow,
+    #[msg("Batch accounts do not match the expected layout.")]
+    InvalidBatchAccounts,
+    #[msg("Cancel has already been requested.")]
+    CancelAlreadyRequested,
+    #[msg("Cancel grace period has not elapsed.")]
+    CancelGracePeriodActive,
+    #[msg("Executor bond does not match the order.")]
+    InvalidBond,
+    #[msg("Executor bond does not cover this fill.")]
+    BondLimitExceeded,
+    #[msg("Unbond cooldown has not elapsed.")]
+    UnbondCooldownActive,
+    #[msg("No slashable fill for this order.")]
+    NotSlashable,
+    #[msg("Only the owner or a guardian can do this.")]
+    OnlyOwnerOrGuardian,
+    #[msg("Missing or invalid solver quote.")]
+    InvalidQuote,
+    #[msg("Order is exclusive to its quoting solver.")]
+    ExclusivityActive,
+    #[msg("Account already uses the current layout.")]
+    AlreadyMigrated,
+    #[msg("Order book does not match the order's market.")]
+    InvalidOrderBook,
+    #[msg("Order book is full.")]
+    OrderBookFull,
+    #[msg("Listed orders must be removed from their order book.")]
+    OrderBookRequired,
+    #[msg("Signer lacks the required role.")]
+    MissingRole,
+    #[msg("Admin actions require multisig approval.")]
+    MultisigRequired,
+    #[msg("Role-based access control is not enabled.")]
+    RolesRequired,
+    #[msg("Too many role members.")]
+    TooManyRoleMembers,
+    #[msg("Proposal has already been executed.")]
+    ProposalExecuted,
+    #[msg("Proposal already approved by this admin.")]
+    AlreadyApproved,
+    #[msg("Not enough admin approvals.")]
+    InsufficientApprovals,
+    #[msg("Protocol is paused.")]
+    Paused,
+    #[msg("Proposal approves a different action.")]
+    ProposalMismatch,
+    #[msg("Action runs through its own instruction with the proposal attached.")]
+    ActionNeedsAccounts,
+    #[msg("Session key has expired.")]
+    SessionKeyExpired,
+    #[msg("Session key does not permit this.")]
+    SessionNotPermitted,
+    #[msg("Order exceeds the session key's allowance.")]
+    SessionAllowanceExceeded,
+    #[msg("Session key does not belong to the order's sender.")]
+    InvalidSessionKey,
+    #[msg("No swap adapter is configured.")]
+    SwapNotConfigured,
+    #[msg("Swap output is below the minimum.")]
+    SwapOutputTooLow,
+    #[msg("Swap adapter did not swap the escrow as requested.")]
+    InvalidSwap,
+    #[msg("Same-chain orders can only be filled on-chain.")]
+    LocalOrder,
+    #[msg("Order is not a same-chain order.")]
+    NotLocalOrder,
+    #[msg("Order lifetime is outside the allowed range.")]
+    InvalidLifetime,
+    #[msg("Price feed is older than the maximum price age.")]
+    StalePrice,
+    #[msg("Roles are enabled; grant and revoke roles instead.")]
+    RolesEnabled,
 }
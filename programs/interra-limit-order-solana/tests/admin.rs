@@ -0,0 +1,704 @@
+//! Configuration, roles, admin multisig, migrations and order books.
+
+mod common;
+
+use anchor_lang::solana_program::instruction::AccountMeta;
+use anchor_lang::system_program;
+use anchor_lang::{AccountSerialize, Space};
+use anchor_spl::token::spl_token;
+use common::*;
+use limit_order::{
+    accounts, instruction, AccountMigrated, AddressFormat, AdminAction, AdminProposal,
+    CancelReason, ChainConfigParams, CustomError, GlobalConfig, LimitOrder, OrderBook,
+    OrderCancelled, PlatformFeeUpdated, Role, Roles,
+};
+
+#[test]
+fn initialize_caps_the_platform_fee() {
+    let mut svm = Svm::new();
+    let owner = new_signer();
+    svm.airdrop(&owner, SOL);
+    let initialize = |svm: &mut Svm, platform_fee, chain_id| {
+        svm.process(
+            &[ix(
+                accounts::Initialize {
+                    global_config: global_config_pda(),
+                    signer: owner,
+                    system_program: system_program::ID,
+                },
+                instruction::Initialize {
+                    platform_fee,
+                    treasury: owner,
+                    chain_id,
+                },
+            )],
+            &[owner],
+        )
+    };
+    assert_error(
+        initialize(&mut svm, GlobalConfig::MAX_PLATFORM_FEE + 1, CHAIN_ID),
+        CustomError::InvalidPlatformFee,
+    );
+    assert_error(initialize(&mut svm, 30, 0), CustomError::InvalidParameter);
+    assert_ok(initialize(
+        &mut svm,
+        GlobalConfig::MAX_PLATFORM_FEE,
+        CHAIN_ID,
+    ));
+
+    let config: GlobalConfig = svm.fetch(&global_config_pda());
+    assert_eq!(config.version, GlobalConfig::VERSION);
+    assert!(!config.roles_enabled);
+    assert_eq!(config.max_price_age, GlobalConfig::DEFAULT_MAX_PRICE_AGE);
+}
+
+#[test]
+fn platform_fee_updates() {
+    let mut env = Env::new(30);
+    let stranger = env.user();
+    let accounts = env.update_config(stranger);
+    assert_error(
+        env.send(
+            accounts,
+            instruction::SetPlatformFee { platform_fee: 50 },
+            &[stranger],
+        ),
+        CustomError::MissingRole,
+    );
+    assert_error(
+        env.admin(instruction::SetPlatformFee { platform_fee: 1001 }),
+        CustomError::InvalidPlatformFee,
+    );
+
+    assert_ok(env.admin(instruction::SetPlatformFee { platform_fee: 50 }));
+    let events = env.svm.events::<PlatformFeeUpdated>();
+    assert_eq!(events[0].old_platform_fee, 30);
+    assert_eq!(events[0].new_platform_fee, 50);
+    assert_eq!(env.config().platform_fee, 50);
+}
+
+#[test]
+fn admin_setters_validate_their_values() {
+    let mut env = Env::new(30);
+    let invalid = CustomError::InvalidParameter;
+    assert_error(
+        env.admin(instruction::SetTreasury {
+            treasury: Pubkey::default(),
+        }),
+        invalid,
+    );
+    assert_error(
+        env.admin(instruction::TransferOwnership {
+            new_owner: Pubkey::default(),
+        }),
+        invalid,
+    );
+    assert_error(env.admin(instruction::SetChainId { chain_id: 0 }), invalid);
+    assert_error(
+        env.admin(instruction::SetCancelGracePeriod {
+            cancel_grace_period: -1,
+        }),
+        invalid,
+    );
+    // Fills must stay slashable until the bond behind them can be withdrawn.
+    assert_error(
+        env.admin(instruction::SetBondConfig {
+            bond_multiple: 2,
+            unbond_cooldown: 60,
+            slash_window: 61,
+        }),
+        invalid,
+    );
+}
+
+#[test]
+fn ownership_transfer_hands_over_every_admin_right() {
+    let mut env = Env::new(30);
+    let new_owner = env.user();
+    assert_ok(env.admin(instruction::TransferOwnership { new_owner }));
+    assert_eq!(env.config().owner, new_owner);
+
+    assert_error(
+        env.admin(instruction::SetPaused { paused: true }),
+        CustomError::MissingRole,
+    );
+    assert_error(
+        env.set_chain_config(DEST_CHAIN, chain_params()),
+        CustomError::MissingRole,
+    );
+
+    env.owner = new_owner;
+    assert_ok(env.admin(instruction::SetPaused { paused: true }));
+    assert_ok(env.set_chain_config(DEST_CHAIN, chain_params()));
+
+    // Once roles are enabled, admin rights only move through role grants.
+    assert_ok(env.init_roles());
+    let next_owner = env.user();
+    assert_error(
+        env.admin(instruction::TransferOwnership {
+            new_owner: next_owner,
+        }),
+        CustomError::RolesEnabled,
+    );
+    assert_eq!(env.config().owner, new_owner);
+}
+
+fn chain_params() -> ChainConfigParams {
+    ChainConfigParams {
+        enabled: true,
+        address_format: AddressFormat::Bytes32,
+        min_amount_in: 0,
+        max_amount_in: 0,
+        platform_fee_override: None,
+    }
+}
+
+#[test]
+fn roles_split_the_owner_permissions() {
+    let mut env = Env::new(30);
+    let pauser = env.user();
+    assert_error(
+        env.grant_role(pauser, Role::Pauser),
+        CustomError::RolesRequired,
+    );
+
+    assert_ok(env.init_roles());
+    let roles: Roles = env.svm.fetch(&roles_pda());
+    assert!(roles.has(&env.owner, Role::Admin));
+    assert!(env.config().roles_enabled);
+
+    assert_ok(env.grant_role(pauser, Role::Pauser));
+    let send_as = |env: &mut Env, signer: Pubkey, args: instruction::SetPaused| {
+        let accounts = env.update_config(signer);
+        env.send(accounts, args, &[signer])
+    };
+    assert_ok(send_as(
+        &mut env,
+        pauser,
+        instruction::SetPaused { paused: true },
+    ));
+    assert!(env.config().paused);
+
+    // Each role only unlocks its own instructions.
+    let accounts = env.update_config(pauser);
+    assert_error(
+        env.send(
+            accounts,
+            instruction::SetPlatformFee { platform_fee: 40 },
+            &[pauser],
+        ),
+        CustomError::MissingRole,
+    );
+
+    // Without the roles account only the owner fallback applies, which no
+    // longer holds once roles are enabled.
+    let mut accounts = env.update_config(pauser);
+    accounts.roles = None;
+    assert_error(
+        env.send(
+            accounts,
+            instruction::SetPaused { paused: false },
+            &[pauser],
+        ),
+        CustomError::MissingRole,
+    );
+
+    let owner = env.owner;
+    assert_ok(env.admin(instruction::RevokeRole {
+        key: pauser,
+        role: Role::Pauser,
+    }));
+    assert_error(
+        send_as(&mut env, pauser, instruction::SetPaused { paused: false }),
+        CustomError::MissingRole,
+    );
+    // The last Admin cannot be removed.
+    assert_error(
+        env.admin(instruction::RevokeRole {
+            key: owner,
+            role: Role::Admin,
+        }),
+        CustomError::InvalidParameter,
+    );
+}
+
+#[test]
+fn registries_and_cancels_follow_roles() {
+    let mut env = Env::new(30);
+    let owner = env.owner;
+    let admin = env.user();
+    assert_ok(env.init_roles());
+    assert_ok(env.grant_role(admin, Role::Admin));
+    env.owner = admin;
+    assert_ok(env.admin(instruction::RevokeRole {
+        key: owner,
+        role: Role::Admin,
+    }));
+
+    // The config owner keeps no registry or cancel rights of its own.
+    env.owner = owner;
+    assert_error(
+        env.set_chain_config(DEST_CHAIN, chain_params()),
+        CustomError::MissingRole,
+    );
+    let user = env.user();
+    let order = env.open_sol(user, SOL, 3600);
+    assert_error(env.cancel_sol(order, owner), CustomError::OnlySenderOrOwner);
+
+    env.owner = admin;
+    assert_ok(env.set_chain_config(DEST_CHAIN, chain_params()));
+    // A per-chain fee override also needs FeeManager.
+    let with_override = ChainConfigParams {
+        platform_fee_override: Some(10),
+        ..chain_params()
+    };
+    assert_error(
+        env.set_chain_config(DEST_CHAIN, with_override.clone()),
+        CustomError::MissingRole,
+    );
+    assert_ok(env.grant_role(admin, Role::FeeManager));
+    assert_ok(env.set_chain_config(DEST_CHAIN, with_override));
+
+    assert_ok(env.cancel_sol(order, admin));
+    let events = env.svm.events::<OrderCancelled>();
+    assert!(events[0].reason == CancelReason::Owner);
+}
+
+#[test]
+fn role_members_are_bounded() {
+    let mut env = Env::new(30);
+    assert_ok(env.init_roles());
+    // The owner is the first member.
+    for _ in 1..Roles::MAX_MEMBERS {
+        assert_ok(env.grant_role(Pubkey::new_unique(), Role::Executor));
+    }
+    assert_error(
+        env.grant_role(Pubkey::new_unique(), Role::Executor),
+        CustomError::TooManyRoleMembers,
+    );
+    // Existing members can still gain roles.
+    let owner = env.owner;
+    assert_ok(env.grant_role(owner, Role::Executor));
+}
+
+fn propose(env: &mut Env, proposer: Pubkey, action: AdminAction) -> (TxResult, Pubkey) {
+    let roles: Roles = env.svm.fetch(&roles_pda());
+    let proposal = admin_proposal_pda(roles.proposal_count);
+    let result = env.send(
+        accounts::ProposeAdminAction {
+            global_config: global_config_pda(),
+            roles: roles_pda(),
+            proposal,
+            proposer,
+            system_program: system_program::ID,
+        },
+        instruction::ProposeAdminAction { action },
+        &[proposer],
+    );
+    (result, proposal)
+}
+
+fn approve(env: &mut Env, proposal: Pubkey, approver: Pubkey) -> TxResult {
+    env.send(
+        accounts::ApproveAdminAction {
+            global_config: global_config_pda(),
+            roles: roles_pda(),
+            proposal,
+            approver,
+        },
+        instruction::ApproveAdminAction {},
+        &[approver],
+    )
+}
+
+fn execute(env: &mut Env, proposal: Pubkey, executor: Pubkey) -> TxResult {
+    env.send(
+        accounts::ExecuteAdminAction {
+            global_config: global_config_pda(),
+            roles: roles_pda(),
+            proposal,
+            executor,
+        },
+        instruction::ExecuteAdminAction {},
+        &[executor],
+    )
+}
+
+#[test]
+fn admin_multisig() {
+    let mut env = Env::new(30);
+    let owner = env.owner;
+    let second = env.user();
+    let third = env.user();
+    assert_ok(env.init_roles());
+    assert_ok(env.grant_role(second, Role::Admin));
+    assert_ok(env.grant_role(third, Role::Admin));
+    assert_error(
+        env.admin(instruction::SetAdminThreshold { threshold: 4 }),
+        CustomError::InvalidParameter,
+    );
+    assert_ok(env.admin(instruction::SetAdminThreshold { threshold: 2 }));
+
+    assert_error(
+        env.admin(instruction::SetChainId { chain_id: 7 }),
+        CustomError::MultisigRequired,
+    );
+    let stranger = env.user();
+    let (result, _) = propose(&mut env, stranger, AdminAction::SetChainId { chain_id: 7 });
+    assert_error(result, CustomError::MissingRole);
+
+    let (result, proposal) = propose(&mut env, owner, AdminAction::SetChainId { chain_id: 7 });
+    assert_ok(result);
+    assert_error(
+        approve(&mut env, proposal, owner),
+        CustomError::AlreadyApproved,
+    );
+    assert_error(
+        approve(&mut env, proposal, stranger),
+        CustomError::MissingRole,
+    );
+    assert_error(
+        execute(&mut env, proposal, owner),
+        CustomError::InsufficientApprovals,
+    );
+
+    assert_ok(approve(&mut env, proposal, second));
+    assert_ok(execute(&mut env, proposal, third));
+    assert_eq!(env.config().chain_id, 7);
+    let executed: AdminProposal = env.svm.fetch(&proposal);
+    assert!(executed.executed);
+    assert_error(
+        execute(&mut env, proposal, owner),
+        CustomError::ProposalExecuted,
+    );
+    assert_error(
+        approve(&mut env, proposal, third),
+        CustomError::ProposalExecuted,
+    );
+}
+
+fn set_chain_config_with(
+    env: &mut Env,
+    authority: Pubkey,
+    proposal: Option<Pubkey>,
+    params: ChainConfigParams,
+) -> TxResult {
+    env.send(
+        accounts::SetChainConfig {
+            chain_config: chain_config_pda(DEST_CHAIN),
+            global_config: global_config_pda(),
+            authority,
+            system_program: system_program::ID,
+            roles: Some(roles_pda()),
+            proposal,
+        },
+        instruction::SetChainConfig {
+            chain_id: DEST_CHAIN,
+            params,
+        },
+        &[authority],
+    )
+}
+
+#[test]
+fn multisig_covers_instructions_with_their_own_accounts() {
+    let mut env = Env::new(30);
+    let owner = env.owner;
+    let second = env.user();
+    assert_ok(env.init_roles());
+    assert_ok(env.grant_role(second, Role::Admin));
+    assert_ok(env.admin(instruction::SetAdminThreshold { threshold: 2 }));
+
+    assert_error(
+        env.set_chain_config(DEST_CHAIN, chain_params()),
+        CustomError::MultisigRequired,
+    );
+    let action = AdminAction::SetChainConfig {
+        chain_id: DEST_CHAIN,
+        params: chain_params(),
+    };
+    let (result, proposal) = propose(&mut env, owner, action.clone());
+    assert_ok(result);
+    assert_error(
+        set_chain_config_with(&mut env, owner, Some(proposal), chain_params()),
+        CustomError::InsufficientApprovals,
+    );
+    assert_ok(approve(&mut env, proposal, second));
+    // Such proposals run through their own instruction, for exactly the
+    // approved action.
+    assert_error(
+        execute(&mut env, proposal, owner),
+        CustomError::ActionNeedsAccounts,
+    );
+    let disabled = ChainConfigParams {
+        enabled: false,
+        ..chain_params()
+    };
+    assert_error(
+        set_chain_config_with(&mut env, owner, Some(proposal), disabled),
+        CustomError::ProposalMismatch,
+    );
+    assert_ok(set_chain_config_with(
+        &mut env,
+        owner,
+        Some(proposal),
+        chain_params(),
+    ));
+    assert!(env.svm.fetch::<AdminProposal>(&proposal).executed);
+    assert_error(
+        set_chain_config_with(&mut env, second, Some(proposal), chain_params()),
+        CustomError::ProposalExecuted,
+    );
+
+    // Force refunds name the orders the proposal covers.
+    let user = env.user();
+    let covered = env.open_sol(user, SOL, 3600);
+    let other = env.open_sol(user, SOL, 7200);
+    let (result, proposal) = propose(
+        &mut env,
+        second,
+        AdminAction::ForceRefund {
+            reason_code: 7,
+            orders: vec![covered],
+        },
+    );
+    assert_ok(result);
+    assert_ok(approve(&mut env, proposal, owner));
+    let force_refund = |env: &mut Env, order: Pubkey| {
+        let mut ix = ix(
+            accounts::ForceRefundBatch {
+                global_config: global_config_pda(),
+                authority: owner,
+                token_program: spl_token::ID,
+                roles: Some(roles_pda()),
+                proposal: Some(proposal),
+            },
+            instruction::ForceRefundBatch { reason_code: 7 },
+        );
+        ix.accounts.extend([
+            AccountMeta::new(order, false),
+            AccountMeta::new(user, false),
+            order_book_meta(None),
+        ]);
+        env.svm.process(&[ix], &[owner])
+    };
+    assert_error(force_refund(&mut env, other), CustomError::ProposalMismatch);
+    assert_ok(force_refund(&mut env, covered));
+    assert!(env.svm.account(&covered).is_none());
+    assert!(env.svm.account(&other).is_some());
+}
+
+#[test]
+fn approvals_of_removed_admins_do_not_count() {
+    let mut env = Env::new(30);
+    let owner = env.owner;
+    let second = env.user();
+    let third = env.user();
+    assert_ok(env.init_roles());
+    assert_ok(env.grant_role(second, Role::Admin));
+    assert_ok(env.grant_role(third, Role::Admin));
+    assert_ok(env.admin(instruction::SetAdminThreshold { threshold: 2 }));
+
+    let (result, stale) = propose(&mut env, second, AdminAction::SetChainId { chain_id: 7 });
+    assert_ok(result);
+    assert_ok(approve(&mut env, stale, owner));
+
+    let (result, removal) = propose(
+        &mut env,
+        owner,
+        AdminAction::RevokeRole {
+            key: second,
+            role: Role::Admin,
+        },
+    );
+    assert_ok(result);
+    assert_ok(approve(&mut env, removal, third));
+    assert_ok(execute(&mut env, removal, third));
+
+    assert_error(
+        execute(&mut env, stale, owner),
+        CustomError::InsufficientApprovals,
+    );
+    assert_ok(approve(&mut env, stale, third));
+    assert_ok(execute(&mut env, stale, owner));
+}
+
+#[test]
+fn migrates_legacy_accounts() {
+    let mut env = Env::new(30);
+    let owner = env.owner;
+    let migrate_config = |env: &mut Env, owner: Pubkey, chain_id: u64| {
+        env.send(
+            accounts::MigrateConfig {
+                global_config: global_config_pda(),
+                authority: owner,
+                roles: None,
+                proposal: None,
+            },
+            instruction::MigrateConfig { chain_id },
+            &[owner],
+        )
+    };
+    assert_error(
+        migrate_config(&mut env, owner, CHAIN_ID),
+        CustomError::AlreadyMigrated,
+    );
+
+    // A version 4 config predates the order minimums and price feeds, and
+    // only moves up to the chain it is already on.
+    let rewind = |env: &mut Env, version: u8, chain_id: u64| {
+        let config_pda = global_config_pda();
+        let mut legacy = env.config();
+        legacy.version = version;
+        legacy.chain_id = chain_id;
+        legacy.max_price_age = 0;
+        let mut account = env.svm.account(&config_pda).unwrap();
+        account.data.clear();
+        legacy.try_serialize(&mut account.data).unwrap();
+        env.svm.set_account(config_pda, account);
+    };
+    rewind(&mut env, 4, CHAIN_ID);
+    assert_error(
+        migrate_config(&mut env, owner, CHAIN_ID + 1),
+        CustomError::InvalidParameter,
+    );
+    assert_ok(migrate_config(&mut env, owner, CHAIN_ID));
+    let events = env.svm.events::<AccountMigrated>();
+    assert_eq!((events[0].from_version, events[0].to_version), (4, 5));
+    assert_eq!(
+        env.config().max_price_age,
+        GlobalConfig::DEFAULT_MAX_PRICE_AGE
+    );
+
+    // A version 0 config may predate the chain registry too, and picks up
+    // its chain id on migration.
+    rewind(&mut env, 0, 0);
+    let stranger = env.user();
+    assert_error(
+        migrate_config(&mut env, stranger, CHAIN_ID),
+        CustomError::MissingRole,
+    );
+    assert_error(
+        migrate_config(&mut env, owner, 0),
+        CustomError::InvalidParameter,
+    );
+    assert_ok(migrate_config(&mut env, owner, CHAIN_ID));
+    let events = env.svm.events::<AccountMigrated>();
+    assert_eq!(
+        (events[0].from_version, events[0].to_version),
+        (0, GlobalConfig::VERSION)
+    );
+    let config = env.config();
+    assert_eq!(config.version, GlobalConfig::VERSION);
+    assert_eq!(config.chain_id, CHAIN_ID);
+    assert_eq!(config.max_price_age, GlobalConfig::DEFAULT_MAX_PRICE_AGE);
+
+    // A version 0 order predates `version`, `in_order_book`, `refund_address`
+    // and `good_till_cancelled`.
+    let user = env.user();
+    let order = env.open_sol(user, SOL, 3600);
+    let mut legacy = env.svm.account(&order).unwrap().clone();
+    legacy
+        .data
+        .truncate(8 + LimitOrder::INIT_SPACE - 2 - 32 - 1);
+    env.svm.set_account(order, legacy);
+    let migrate_order = |env: &mut Env, owner: Pubkey| {
+        env.send(
+            accounts::MigrateOrder {
+                order,
+                global_config: global_config_pda(),
+                authority: owner,
+                system_program: system_program::ID,
+                roles: None,
+                proposal: None,
+            },
+            instruction::MigrateOrder {},
+            &[owner],
+        )
+    };
+    assert_error(migrate_order(&mut env, stranger), CustomError::MissingRole);
+    assert_ok(migrate_order(&mut env, owner));
+    assert_eq!(env.order(&order).version, LimitOrder::VERSION);
+    assert_eq!(
+        env.svm.account(&order).unwrap().data.len(),
+        8 + LimitOrder::INIT_SPACE
+    );
+    assert_error(migrate_order(&mut env, owner), CustomError::AlreadyMigrated);
+
+    // The migrated order still holds its escrow and refunds its sender.
+    assert_eq!(env.order(&order).refund_to(), user);
+    let before = env.svm.lamports(&user);
+    assert_ok(env.cancel_sol(order, user));
+    assert!(env.svm.lamports(&user) > before + SOL);
+}
+
+#[test]
+fn order_books_track_open_orders() {
+    let mut env = Env::new(30);
+    let book = env.init_order_book(native_mint());
+    let user = env.user();
+    let open = |env: &mut Env, ttl: i64, order_book: Pubkey| {
+        let params = env.params(native_mint(), SOL / 10, env.svm.now() + ttl);
+        let mut accounts = env.open_sol_accounts(user, &params);
+        accounts.order_book = Some(order_book);
+        let order = accounts.order;
+        env.send(accounts, instruction::OpenOrderSol { params }, &[user])
+            .map(|()| order)
+    };
+    let listed = |env: &Env| {
+        let account = env.svm.account(&book).unwrap();
+        let book: &OrderBook = bytemuck::from_bytes(&account.data[8..]);
+        book.active()
+            .iter()
+            .map(|slot| slot.order)
+            .collect::<Vec<_>>()
+    };
+
+    let first = open(&mut env, 3600, book).unwrap();
+    let second = open(&mut env, 60, book).unwrap();
+    assert_eq!(listed(&env), [first, second]);
+    assert!(env.order(&first).in_order_book);
+
+    // Listed orders must keep the book in sync.
+    assert_error(env.cancel_sol(first, user), CustomError::OrderBookRequired);
+    let mut accounts = env.cancel_sol_accounts(first, user);
+    accounts.order_book = Some(book);
+    assert_ok(env.send(accounts, instruction::CancelOrderSol {}, &[user]));
+    assert_eq!(listed(&env), [second]);
+
+    // A book only takes orders of its own market.
+    let mint = env.create_mint(6);
+    let other_book = env.init_order_book(mint);
+    assert_error(
+        open(&mut env, 7200, other_book).map(|_| ()),
+        CustomError::InvalidOrderBook,
+    );
+
+    env.svm.warp(60);
+    assert_ok(env.send(
+        accounts::PruneOrderBook { order_book: book },
+        instruction::PruneOrderBook {},
+        &[],
+    ));
+    assert!(listed(&env).is_empty());
+}
+
+#[test]
+fn order_books_are_bounded() {
+    let mut env = Env::new(30);
+    let book = env.init_order_book(native_mint());
+    let user = env.user();
+    env.svm.airdrop(&user, 1_000 * SOL);
+    let capacity = OrderBook::CAPACITY as i64;
+    for ttl in 0..=capacity {
+        let params = env.params(native_mint(), 1, env.svm.now() + 3600 + ttl);
+        let mut accounts = env.open_sol_accounts(user, &params);
+        accounts.order_book = Some(book);
+        let result = env.send(accounts, instruction::OpenOrderSol { params }, &[user]);
+        if ttl < capacity {
+            assert_ok(result);
+        } else {
+            assert_error(result, CustomError::OrderBookFull);
+        }
+    }
+}
@@ -0,0 +1,290 @@
+//! Substituted PDAs, token accounts and forged orders.
+
+mod common;
+
+use anchor_lang::error::ErrorCode;
+use anchor_lang::system_program;
+use anchor_spl::associated_token::get_associated_token_address;
+use anchor_spl::token::spl_token;
+use common::*;
+use limit_order::{accounts, instruction, AddressFormat, ChainConfigParams, CustomError};
+
+const OTHER_CHAIN: u64 = DEST_CHAIN + 1;
+
+fn execute_sol_with(env: &mut Env, edit: impl FnOnce(&mut accounts::ExecuteOrderSol)) -> TxResult {
+    let user = env.user();
+    let order = env.open_sol(user, SOL, 3600);
+    let owner = env.owner;
+    let mut accounts = env.execute_sol_accounts(order, owner, Pubkey::new_unique());
+    edit(&mut accounts);
+    env.send(
+        accounts,
+        instruction::ExecuteOrderSol {
+            native_token_volume: 0,
+        },
+        &[owner],
+    )
+}
+
+fn execute_spl_with(
+    env: &mut Env,
+    edit: impl FnOnce(&mut Env, &mut accounts::ExecuteOrderSpl),
+) -> TxResult {
+    let mint = env.create_mint(9);
+    let user = env.user();
+    env.create_token_account(mint, user, 10_000);
+    env.create_token_account(mint, env.treasury, 0);
+    let target = env.create_token_account(mint, Pubkey::new_unique(), 0);
+    let order = env.open_spl(user, mint, 10_000, 3600);
+    let owner = env.owner;
+    let mut accounts = env.execute_spl_accounts(order, owner, target);
+    edit(env, &mut accounts);
+    env.send(
+        accounts,
+        instruction::ExecuteOrderSpl {
+            native_token_volume: 0,
+        },
+        &[owner],
+    )
+}
+
+fn enable_other_chain(env: &mut Env) {
+    let params = ChainConfigParams {
+        enabled: true,
+        address_format: AddressFormat::Bytes32,
+        min_amount_in: 0,
+        max_amount_in: 0,
+        platform_fee_override: None,
+    };
+    assert_ok(env.set_chain_config(OTHER_CHAIN, params));
+}
+
+/// Copies a live order's account to `address` under another owner.
+fn forge_order(env: &mut Env, order: &Pubkey, address: Pubkey, owner: Pubkey) {
+    let mut forged = env.svm.account(order).expect("order exists").clone();
+    forged.owner = owner;
+    env.svm.set_account(address, forged);
+}
+
+#[test]
+fn open_rejects_substituted_pdas() {
+    let mut env = Env::new(30);
+    let user = env.user();
+    let expiry = env.svm.now() + 3600;
+    let open = |env: &mut Env, edit: &dyn Fn(&mut accounts::OpenOrderSol)| {
+        let params = env.params(native_mint(), SOL, expiry);
+        let mut accounts = env.open_sol_accounts(user, &params);
+        edit(&mut accounts);
+        env.send(accounts, instruction::OpenOrderSol { params }, &[user])
+    };
+
+    assert_error(
+        open(&mut env, &|accounts| accounts.order = Pubkey::new_unique()),
+        ErrorCode::ConstraintSeeds,
+    );
+    enable_other_chain(&mut env);
+    assert_error(
+        open(&mut env, &|accounts| {
+            accounts.chain_config = chain_config_pda(OTHER_CHAIN)
+        }),
+        ErrorCode::ConstraintSeeds,
+    );
+    let mint = env.create_mint(9);
+    assert_error(
+        open(&mut env, &|accounts| {
+            accounts.token_route = token_route_pda(&mint, DEST_CHAIN, &TO_TOKEN)
+        }),
+        ErrorCode::ConstraintSeeds,
+    );
+    assert_ok(open(&mut env, &|_| {}));
+}
+
+#[test]
+fn open_spl_rejects_a_borrowed_mint_policy() {
+    let mut env = Env::new(30);
+    assert_ok(env.admin(instruction::SetMintAllowlistMode {
+        mint_allowlist_mode: true,
+    }));
+    let allowed = env.create_mint(9);
+    assert_ok(env.set_mint_policy(
+        allowed,
+        limit_order::MintPolicyParams {
+            status: limit_order::MintStatus::Allowed,
+            min_order_size: 0,
+            require_no_freeze_authority: false,
+        },
+    ));
+
+    let mint = env.create_mint(9);
+    let user = env.user();
+    env.create_token_account(mint, user, 1_000);
+    let params = env.params(mint, 1_000, env.svm.now() + 3600);
+    let mut accounts = env.open_spl_accounts(user, &params);
+    accounts.mint_policy = mint_policy_pda(&allowed);
+    assert_error(
+        env.send(accounts, instruction::OpenOrderSpl { params }, &[user]),
+        ErrorCode::ConstraintSeeds,
+    );
+}
+
+#[test]
+fn execute_sol_rejects_substituted_accounts() {
+    let mut env = Env::new(30);
+    let attacker = env.user();
+    assert_error(
+        execute_sol_with(&mut env, |accounts| accounts.treasury = attacker),
+        ErrorCode::ConstraintAddress,
+    );
+    assert_error(
+        execute_sol_with(&mut env, |accounts| accounts.refund_receiver = attacker),
+        CustomError::InvalidRefundReceiver,
+    );
+    enable_other_chain(&mut env);
+    assert_error(
+        execute_sol_with(&mut env, |accounts| {
+            accounts.chain_config = chain_config_pda(OTHER_CHAIN)
+        }),
+        ErrorCode::ConstraintSeeds,
+    );
+    assert_error(
+        execute_sol_with(&mut env, |accounts| accounts.global_config = roles_pda()),
+        ErrorCode::AccountNotInitialized,
+    );
+}
+
+#[test]
+fn execute_spl_rejects_substituted_token_accounts() {
+    let mut env = Env::new(30);
+
+    // The fee must land in the treasury's associated token account.
+    let attacker = env.user();
+    assert_error(
+        execute_spl_with(&mut env, |env, accounts| {
+            let mint = env
+                .svm
+                .fetch::<anchor_spl::token::TokenAccount>(&accounts.order_token_account)
+                .mint;
+            accounts.treasury_token_account = env.create_token_account(mint, attacker, 0);
+        }),
+        ErrorCode::ConstraintRaw,
+    );
+    assert_error(
+        execute_spl_with(&mut env, |env, accounts| {
+            let mint = env
+                .svm
+                .fetch::<anchor_spl::token::TokenAccount>(&accounts.order_token_account)
+                .mint;
+            let treasury = env.treasury;
+            let side_account = Pubkey::new_unique();
+            env.create_token_account_at(side_account, mint, treasury, 0);
+            accounts.treasury_token_account = side_account;
+        }),
+        ErrorCode::ConstraintRaw,
+    );
+
+    // Another order's escrow cannot be drained through this one.
+    assert_error(
+        execute_spl_with(&mut env, |env, accounts| {
+            let mint = env
+                .svm
+                .fetch::<anchor_spl::token::TokenAccount>(&accounts.order_token_account)
+                .mint;
+            let user = env.user();
+            env.create_token_account(mint, user, 10_000);
+            let other = env.open_spl(user, mint, 10_000, 7200);
+            accounts.order_token_account = get_associated_token_address(&other, &mint);
+        }),
+        ErrorCode::ConstraintRaw,
+    );
+
+    // The token program refuses a target for a different mint.
+    assert_error(
+        execute_spl_with(&mut env, |env, accounts| {
+            let other_mint = env.create_mint(9);
+            accounts.target_token_account =
+                env.create_token_account(other_mint, Pubkey::new_unique(), 0);
+        }),
+        spl_token::error::TokenError::MintMismatch as u32,
+    );
+}
+
+#[test]
+fn cancel_refunds_only_to_the_sender() {
+    let mut env = Env::new(30);
+    let mint = env.create_mint(9);
+    let user = env.user();
+    let attacker = env.user();
+    let user_ata = env.create_token_account(mint, user, 10_000);
+    let attacker_ata = env.create_token_account(mint, attacker, 0);
+    let order = env.open_spl(user, mint, 10_000, 3600);
+    let owner = env.owner;
+
+    let mut accounts = env.cancel_spl_accounts(order, owner);
+    accounts.user_token_account = attacker_ata;
+    assert_error(
+        env.send(accounts, instruction::CancelOrderSpl {}, &[owner]),
+        ErrorCode::ConstraintRaw,
+    );
+    let mut accounts = env.cancel_spl_accounts(order, owner);
+    accounts.user_token_account = attacker_ata;
+    accounts.refund_receiver = attacker;
+    assert_error(
+        env.send(accounts, instruction::CancelOrderSpl {}, &[owner]),
+        CustomError::InvalidRefundReceiver,
+    );
+
+    assert_error(
+        env.send(
+            accounts::RequestCancel {
+                order,
+                user: attacker,
+                global_config: global_config_pda(),
+                session_key: None,
+            },
+            instruction::RequestCancel {},
+            &[attacker],
+        ),
+        CustomError::OnlySenderOrOwner,
+    );
+
+    assert_ok(env.cancel_spl(order, owner));
+    assert_eq!(env.token_balance(&user_ata), 10_000);
+    assert_eq!(env.token_balance(&attacker_ata), 0);
+}
+
+#[test]
+fn forged_orders_are_rejected() {
+    let mut env = Env::new(30);
+    let user = env.user();
+    let order = env.open_sol(user, SOL, 3600);
+
+    // Only this program can create accounts it owns, so a copy must be
+    // owned by someone else.
+    let spoofed = Pubkey::new_unique();
+    forge_order(&mut env, &order, spoofed, system_program::ID);
+    let target = Pubkey::new_unique();
+    assert_error(
+        env.execute_sol(spoofed, target),
+        ErrorCode::AccountOwnedByWrongProgram,
+    );
+    assert_error(
+        env.cancel_sol(spoofed, user),
+        ErrorCode::AccountOwnedByWrongProgram,
+    );
+
+    // Other program accounts don't pass for orders.
+    let owner = env.owner;
+    let mut accounts = env.execute_sol_accounts(order, owner, target);
+    accounts.order = global_config_pda();
+    assert_error(
+        env.send(
+            accounts,
+            instruction::ExecuteOrderSol {
+                native_token_volume: 0,
+            },
+            &[owner],
+        ),
+        ErrorCode::AccountDiscriminatorMismatch,
+    );
+    assert_ok(env.execute_sol(order, target));
+}
@@ -0,0 +1,884 @@
+//! Fixtures shared by the in-process SVM tests.
+
+#![allow(dead_code)]
+
+pub mod svm;
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::ed25519_program;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::program_pack::Pack;
+use anchor_lang::system_program;
+use anchor_lang::{InstructionData, ToAccountMetas};
+use anchor_spl::associated_token::{get_associated_token_address, spl_associated_token_account};
+use anchor_spl::token::spl_token;
+use ed25519_dalek::{Signer, SigningKey};
+use limit_order::{
+    accounts, instruction, AddressFormat, ChainConfigParams, FillAttestation, LimitOrder,
+    MintPolicyParams, OpenOrderParams, OrderBook, Role, TokenRouteParams,
+};
+use solana_sdk::signature::Keypair;
+
+pub use anchor_lang::prelude::Pubkey;
+pub use svm::{AccountState, Svm, TxError, TxFailure, TxResult};
+
+pub const SOL: u64 = 1_000_000_000;
+/// Chain id of the deployment under test.
+pub const CHAIN_ID: u64 = 10002;
+/// Destination chain registered by [`Env::new`].
+pub const DEST_CHAIN: u64 = 2;
+pub const TO_TOKEN: [u8; 32] = [7; 32];
+pub const RECIPIENT: [u8; 32] = [9; 32];
+
+pub fn native_mint() -> Pubkey {
+    spl_token::native_mint::ID
+}
+
+pub fn global_config_pda() -> Pubkey {
+    Pubkey::find_program_address(&[b"global-config"], &limit_order::ID).0
+}
+
+pub fn roles_pda() -> Pubkey {
+    Pubkey::find_program_address(&[b"roles"], &limit_order::ID).0
+}
+
+pub fn chain_config_pda(chain_id: u64) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"chain-config", &chain_id.to_le_bytes()],
+        &limit_order::ID,
+    )
+    .0
+}
+
+pub fn token_route_pda(from_token: &Pubkey, to_chain_id: u64, to_token: &[u8; 32]) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            b"token-route",
+            from_token.as_ref(),
+            &to_chain_id.to_le_bytes(),
+            to_token,
+        ],
+        &limit_order::ID,
+    )
+    .0
+}
+
+pub fn mint_policy_pda(mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"mint-policy", mint.as_ref()], &limit_order::ID).0
+}
+
+pub fn price_feed_pda(mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"price-feed", mint.as_ref()], &limit_order::ID).0
+}
+
+pub fn order_pda(sender: &Pubkey, expiry: i64) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"limit_order", sender.as_ref(), &expiry.to_le_bytes()],
+        &limit_order::ID,
+    )
+    .0
+}
+
+pub fn guardian_set_pda(index: u32) -> Pubkey {
+    Pubkey::find_program_address(&[b"guardian-set", &index.to_le_bytes()], &limit_order::ID).0
+}
+
+pub fn executor_bond_pda(executor: &Pubkey, mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"executor-bond", executor.as_ref(), mint.as_ref()],
+        &limit_order::ID,
+    )
+    .0
+}
+
+pub fn order_book_pda(from_token: &Pubkey, to_chain_id: u64) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            b"order-book",
+            from_token.as_ref(),
+            &to_chain_id.to_le_bytes(),
+        ],
+        &limit_order::ID,
+    )
+    .0
+}
+
+/// The order book slot of an order in a batch; the program id stands in for
+/// orders outside any book.
+pub fn order_book_meta(order_book: Option<Pubkey>) -> AccountMeta {
+    match order_book {
+        Some(order_book) => AccountMeta::new(order_book, false),
+        None => AccountMeta::new_readonly(limit_order::ID, false),
+    }
+}
+
+pub fn session_key_pda(user: &Pubkey, key: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"session-key", user.as_ref(), key.as_ref()],
+        &limit_order::ID,
+    )
+    .0
+}
+
+pub fn admin_proposal_pda(id: u64) -> Pubkey {
+    Pubkey::find_program_address(&[b"admin-proposal", &id.to_le_bytes()], &limit_order::ID).0
+}
+
+fn keyring() -> &'static Mutex<HashMap<Pubkey, SigningKey>> {
+    static KEYRING: OnceLock<Mutex<HashMap<Pubkey, SigningKey>>> = OnceLock::new();
+    KEYRING.get_or_init(Default::default)
+}
+
+/// The keypair behind a [`new_signer`] key, for signing transactions.
+pub fn keypair(key: &Pubkey) -> Keypair {
+    let signing_key = keyring().lock().unwrap().get(key).cloned();
+    let signing_key = signing_key.unwrap_or_else(|| panic!("no signing key for {key}"));
+    Keypair::new_from_array(signing_key.to_bytes())
+}
+
+/// A fresh Ed25519 key that [`ed25519_ix`] can sign with.
+pub fn new_signer() -> Pubkey {
+    static SEED: AtomicU64 = AtomicU64::new(1);
+    let mut secret = [0; 32];
+    secret[..8].copy_from_slice(&SEED.fetch_add(1, Ordering::Relaxed).to_le_bytes());
+    let signing_key = SigningKey::from_bytes(&secret);
+    let key = Pubkey::new_from_array(signing_key.verifying_key().to_bytes());
+    keyring().lock().unwrap().insert(key, signing_key);
+    key
+}
+
+/// An Ed25519 program instruction carrying `signatures` as `(signer,
+/// message)` pairs, each with its key, signature and message stored inline.
+/// Every signer must come from [`new_signer`].
+pub fn ed25519_ix(signatures: &[(Pubkey, Vec<u8>)]) -> Instruction {
+    const OFFSETS_START: usize = 2;
+    const OFFSETS_SIZE: usize = 14;
+
+    let mut offsets = vec![signatures.len() as u8, 0];
+    let mut payload = Vec::new();
+    let mut at = OFFSETS_START + OFFSETS_SIZE * signatures.len();
+    for (key, message) in signatures {
+        let public_key_offset = at;
+        let signature_offset = public_key_offset + 32;
+        let message_offset = signature_offset + 64;
+        at = message_offset + message.len();
+        for value in [
+            signature_offset,
+            u16::MAX as usize,
+            public_key_offset,
+            u16::MAX as usize,
+            message_offset,
+            message.len(),
+            u16::MAX as usize,
+        ] {
+            offsets.extend_from_slice(&(value as u16).to_le_bytes());
+        }
+        let signature = keyring()
+            .lock()
+            .unwrap()
+            .get(key)
+            .unwrap_or_else(|| panic!("no signing key for {key}"))
+            .sign(message);
+        payload.extend_from_slice(key.as_ref());
+        payload.extend_from_slice(&signature.to_bytes());
+        payload.extend_from_slice(message);
+    }
+    offsets.extend(payload);
+    Instruction {
+        program_id: ed25519_program::ID,
+        accounts: Vec::new(),
+        data: offsets,
+    }
+}
+
+/// Flips a bit of the first signature carried by an [`ed25519_ix`]
+/// instruction.
+pub fn forge_signature(ed25519: &mut Instruction) {
+    let offset = u16::from_le_bytes([ed25519.data[2], ed25519.data[3]]) as usize;
+    ed25519.data[offset] ^= 1;
+}
+
+/// `DOMAIN || borsh(body)`, the layout of every signed message.
+pub fn signed_message(domain: &[u8], body: impl AnchorSerialize) -> Vec<u8> {
+    let mut message = domain.to_vec();
+    body.serialize(&mut message).expect("message serializes");
+    message
+}
+
+/// A fill attestation delivering exactly what `order` asks for.
+pub fn fill_attestation(order: &Pubkey, order_account: &LimitOrder) -> Vec<u8> {
+    signed_message(
+        FillAttestation::DOMAIN,
+        FillAttestation {
+            order: *order,
+            dest_tx_hash: [3; 32],
+            recipient: order_account.recipient,
+            amount_delivered: order_account.amount_out,
+        },
+    )
+}
+
+pub fn ix(accounts: impl ToAccountMetas, args: impl InstructionData) -> Instruction {
+    Instruction {
+        program_id: limit_order::ID,
+        accounts: accounts.to_account_metas(None),
+        data: args.data(),
+    }
+}
+
+/// Asserts that `result` failed with the program error `code`, printing the
+/// transaction logs otherwise.
+#[track_caller]
+pub fn assert_error(result: TxResult, code: impl Into<u32>) {
+    let code = code.into();
+    match result {
+        Err(TxFailure {
+            error: TxError::Program(ProgramError::Custom(actual)),
+            ..
+        }) if actual == code => {}
+        Err(failure) => panic!(
+            "expected error {code}, got {:?}\n{}",
+            failure.error,
+            failure.logs.join("\n")
+        ),
+        Ok(()) => panic!("expected error {code}, but the transaction succeeded"),
+    }
+}
+
+/// Asserts that `result` was rejected by Ed25519 signature verification.
+#[track_caller]
+pub fn assert_invalid_signature(result: TxResult) {
+    match result {
+        Err(TxFailure {
+            error: TxError::InvalidSignature,
+            ..
+        }) => {}
+        Err(failure) => panic!("expected an invalid signature, got {:?}", failure.error),
+        Ok(()) => panic!("expected an invalid signature, but the transaction succeeded"),
+    }
+}
+
+#[track_caller]
+pub fn assert_ok(result: TxResult) {
+    if let Err(failure) = result {
+        panic!("{:?}\n{}", failure.error, failure.logs.join("\n"));
+    }
+}
+
+/// A deployment with one destination chain and a SOL route to it.
+pub struct Env {
+    pub svm: Svm,
+    pub owner: Pubkey,
+    pub treasury: Pubkey,
+}
+
+impl Env {
+    pub fn new(platform_fee: u16) -> Self {
+        let mut svm = Svm::new();
+        let owner = new_signer();
+        let treasury = Pubkey::new_unique();
+        svm.airdrop(&owner, 100 * SOL);
+        svm.airdrop(&treasury, SOL);
+
+        let mut env = Self {
+            svm,
+            owner,
+            treasury,
+        };
+        let mut native_mint_data = vec![0; spl_token::state::Mint::LEN];
+        spl_token::state::Mint {
+            decimals: 9,
+            is_initialized: true,
+            ..Default::default()
+        }
+        .pack_into_slice(&mut native_mint_data);
+        env.set_token_program_account(native_mint(), native_mint_data);
+        assert_ok(env.send(
+            accounts::Initialize {
+                global_config: global_config_pda(),
+                signer: owner,
+                system_program: system_program::ID,
+            },
+            instruction::Initialize {
+                platform_fee,
+                treasury,
+                chain_id: CHAIN_ID,
+            },
+            &[owner],
+        ));
+        assert_ok(env.set_chain_config(
+            DEST_CHAIN,
+            ChainConfigParams {
+                enabled: true,
+                address_format: AddressFormat::Bytes32,
+                min_amount_in: 0,
+                max_amount_in: 0,
+                platform_fee_override: None,
+            },
+        ));
+        assert_ok(env.set_token_route(native_mint(), 9));
+        env
+    }
+
+    pub fn send(
+        &mut self,
+        accounts: impl ToAccountMetas,
+        args: impl InstructionData,
+        signers: &[Pubkey],
+    ) -> TxResult {
+        self.svm.process(&[ix(accounts, args)], signers)
+    }
+
+    /// A fresh funded wallet.
+    pub fn user(&mut self) -> Pubkey {
+        let user = new_signer();
+        self.svm.airdrop(&user, 10 * SOL);
+        user
+    }
+
+    pub fn config(&self) -> limit_order::GlobalConfig {
+        self.svm.fetch(&global_config_pda())
+    }
+
+    pub fn order(&self, order: &Pubkey) -> LimitOrder {
+        self.svm.fetch(order)
+    }
+
+    /// The roles account to pass to role-gated instructions, once enabled.
+    pub fn roles(&self) -> Option<Pubkey> {
+        self.config().roles_enabled.then(roles_pda)
+    }
+
+    /// `UpdateConfig` accounts for `authority`, passing the roles account
+    /// once roles are enabled.
+    pub fn update_config(&self, authority: Pubkey) -> accounts::UpdateConfig {
+        accounts::UpdateConfig {
+            global_config: global_config_pda(),
+            roles: self.roles(),
+            authority,
+        }
+    }
+
+    /// Sends an `UpdateConfig` instruction signed by the owner.
+    pub fn admin(&mut self, args: impl InstructionData) -> TxResult {
+        let owner = self.owner;
+        let accounts = self.update_config(owner);
+        self.send(accounts, args, &[owner])
+    }
+
+    pub fn init_roles(&mut self) -> TxResult {
+        let owner = self.owner;
+        self.send(
+            accounts::InitRoles {
+                global_config: global_config_pda(),
+                roles: roles_pda(),
+                owner,
+                system_program: system_program::ID,
+            },
+            instruction::InitRoles {},
+            &[owner],
+        )
+    }
+
+    pub fn grant_role(&mut self, key: Pubkey, role: Role) -> TxResult {
+        self.admin(instruction::GrantRole { key, role })
+    }
+
+    /// Creates the first guardian set and turns on fill attestations.
+    pub fn init_guardian_set(&mut self, keys: Vec<Pubkey>, threshold: u8) -> TxResult {
+        let owner = self.owner;
+        self.send(
+            accounts::InitGuardianSet {
+                guardian_set: guardian_set_pda(0),
+                global_config: global_config_pda(),
+                authority: owner,
+                system_program: system_program::ID,
+                roles: self.roles(),
+                proposal: None,
+            },
+            instruction::InitGuardianSet { keys, threshold },
+            &[owner],
+        )
+    }
+
+    pub fn set_mint_policy(&mut self, mint: Pubkey, params: MintPolicyParams) -> TxResult {
+        let owner = self.owner;
+        self.send(
+            accounts::SetMintPolicy {
+                mint_policy: mint_policy_pda(&mint),
+                global_config: global_config_pda(),
+                authority: owner,
+                system_program: system_program::ID,
+                roles: self.roles(),
+                proposal: None,
+            },
+            instruction::SetMintPolicy { mint, params },
+            &[owner],
+        )
+    }
+
+    pub fn init_order_book(&mut self, from_token: Pubkey) -> Pubkey {
+        let owner = self.owner;
+        let order_book = order_book_pda(&from_token, DEST_CHAIN);
+        assert_ok(self.send(
+            accounts::InitOrderBook {
+                order_book,
+                global_config: global_config_pda(),
+                authority: owner,
+                system_program: system_program::ID,
+                roles: self.roles(),
+                proposal: None,
+            },
+            instruction::InitOrderBook {
+                from_token,
+                to_chain_id: DEST_CHAIN,
+            },
+            &[owner],
+        ));
+        order_book
+    }
+
+    /// Orders currently listed in `order_book`.
+    pub fn listed_orders(&self, order_book: &Pubkey) -> Vec<Pubkey> {
+        let account = self.svm.account(order_book).expect("order book exists");
+        let book: &OrderBook = bytemuck::from_bytes(&account.data[8..]);
+        book.active().iter().map(|slot| slot.order).collect()
+    }
+
+    pub fn set_chain_config(&mut self, chain_id: u64, params: ChainConfigParams) -> TxResult {
+        let owner = self.owner;
+        self.send(
+            accounts::SetChainConfig {
+                chain_config: chain_config_pda(chain_id),
+                global_config: global_config_pda(),
+                authority: owner,
+                system_program: system_program::ID,
+                roles: self.roles(),
+                proposal: None,
+            },
+            instruction::SetChainConfig { chain_id, params },
+            &[owner],
+        )
+    }
+
+    pub fn set_token_route(&mut self, from_token: Pubkey, from_decimals: u8) -> TxResult {
+        self.set_route(
+            from_token,
+            DEST_CHAIN,
+            TokenRouteParams {
+                enabled: true,
+                from_decimals,
+                to_decimals: 18,
+                min_amount_in: 0,
+                max_amount_in: 0,
+            },
+        )
+    }
+
+    pub fn set_route(
+        &mut self,
+        from_token: Pubkey,
+        to_chain_id: u64,
+        params: TokenRouteParams,
+    ) -> TxResult {
+        let owner = self.owner;
+        self.send(
+            accounts::SetTokenRoute {
+                token_route: token_route_pda(&from_token, to_chain_id, &TO_TOKEN),
+                global_config: global_config_pda(),
+                authority: owner,
+                system_program: system_program::ID,
+                roles: self.roles(),
+                proposal: None,
+            },
+            instruction::SetTokenRoute {
+                from_token,
+                to_chain_id,
+                to_token: TO_TOKEN,
+                params,
+            },
+            &[owner],
+        )
+    }
+
+    /// Parameters of a regular order to [`DEST_CHAIN`].
+    pub fn params(&self, from_token: Pubkey, amount_in: u64, expiry: i64) -> OpenOrderParams {
+        OpenOrderParams {
+            from_token,
+            from_chain_id: CHAIN_ID,
+            amount_in,
+            to_chain_id: DEST_CHAIN,
+            to_token: TO_TOKEN,
+            recipient: RECIPIENT,
+            expiry,
+            amount_out: [1; 32],
+            hashlock: [0; 32],
+            timelock: 0,
+            htlc_solver: Pubkey::default(),
+            exclusive_executor: Pubkey::default(),
+            exclusivity_deadline: 0,
+            refund_address: Pubkey::default(),
+            good_till_cancelled: false,
+        }
+    }
+
+    pub fn open_sol_accounts(
+        &self,
+        user: Pubkey,
+        params: &OpenOrderParams,
+    ) -> accounts::OpenOrderSol {
+        accounts::OpenOrderSol {
+            order: order_pda(&user, params.expiry),
+            user,
+            payer: user,
+            global_config: global_config_pda(),
+            chain_config: chain_config_pda(params.to_chain_id),
+            token_route: token_route_pda(&params.from_token, params.to_chain_id, &params.to_token),
+            price_feed: price_feed_pda(&params.from_token),
+            system_program: system_program::ID,
+            instructions: None,
+            order_book: None,
+            session_key: None,
+        }
+    }
+
+    pub fn open_sol_with(&mut self, user: Pubkey, params: OpenOrderParams) -> TxResult {
+        let accounts = self.open_sol_accounts(user, &params);
+        self.send(accounts, instruction::OpenOrderSol { params }, &[user])
+    }
+
+    /// Opens a SOL order expiring `ttl` seconds from now and returns its PDA.
+    pub fn open_sol(&mut self, user: Pubkey, amount_in: u64, ttl: i64) -> Pubkey {
+        let expiry = self.svm.now() + ttl;
+        let params = self.params(native_mint(), amount_in, expiry);
+        assert_ok(self.open_sol_with(user, params));
+        order_pda(&user, expiry)
+    }
+
+    pub fn execute_sol_accounts(
+        &self,
+        order: Pubkey,
+        executor: Pubkey,
+        target: Pubkey,
+    ) -> accounts::ExecuteOrderSol {
+        let order_account: limit_order::LimitOrder = self.svm.fetch(&order);
+        accounts::ExecuteOrderSol {
+            order,
+            target_sol: target,
+            treasury: self.treasury,
+            executor,
+            global_config: global_config_pda(),
+            chain_config: chain_config_pda(order_account.to_chain_id),
+            refund_receiver: order_account.refund_to(),
+            guardian_set: None,
+            instructions: None,
+            executor_bond: None,
+            order_book: None,
+            roles: None,
+        }
+    }
+
+    pub fn execute_sol(&mut self, order: Pubkey, target: Pubkey) -> TxResult {
+        let owner = self.owner;
+        let accounts = self.execute_sol_accounts(order, owner, target);
+        self.send(
+            accounts,
+            instruction::ExecuteOrderSol {
+                native_token_volume: 0,
+            },
+            &[owner],
+        )
+    }
+
+    pub fn cancel_sol_accounts(&self, order: Pubkey, user: Pubkey) -> accounts::CancelOrderSol {
+        let order_account: limit_order::LimitOrder = self.svm.fetch(&order);
+        accounts::CancelOrderSol {
+            order,
+            user,
+            refund_receiver: order_account.refund_to(),
+            global_config: global_config_pda(),
+            system_program: system_program::ID,
+            order_book: None,
+            session_key: None,
+            roles: self.roles(),
+        }
+    }
+
+    pub fn cancel_sol(&mut self, order: Pubkey, user: Pubkey) -> TxResult {
+        let accounts = self.cancel_sol_accounts(order, user);
+        self.send(accounts, instruction::CancelOrderSol {}, &[user])
+    }
+
+    /// Creates a mint with `decimals` and registers its route.
+    pub fn create_mint(&mut self, decimals: u8) -> Pubkey {
+        self.create_mint_with(decimals, None)
+    }
+
+    pub fn create_mint_with(&mut self, decimals: u8, freeze_authority: Option<Pubkey>) -> Pubkey {
+        let mint = Pubkey::new_unique();
+        let mut data = vec![0; spl_token::state::Mint::LEN];
+        spl_token::state::Mint {
+            mint_authority: Some(self.owner).into(),
+            supply: u64::MAX / 2,
+            decimals,
+            is_initialized: true,
+            freeze_authority: freeze_authority.into(),
+        }
+        .pack_into_slice(&mut data);
+        self.set_token_program_account(mint, data);
+        assert_ok(self.set_token_route(mint, decimals));
+        mint
+    }
+
+    /// Creates the associated token account of `owner` for `mint` holding
+    /// `amount` and returns its address.
+    pub fn create_token_account(&mut self, mint: Pubkey, owner: Pubkey, amount: u64) -> Pubkey {
+        let address = get_associated_token_address(&owner, &mint);
+        self.create_token_account_at(address, mint, owner, amount);
+        address
+    }
+
+    pub fn create_token_account_at(
+        &mut self,
+        address: Pubkey,
+        mint: Pubkey,
+        owner: Pubkey,
+        amount: u64,
+    ) {
+        let rent = Rent::default().minimum_balance(spl_token::state::Account::LEN);
+        let is_native = mint == native_mint();
+        let mut data = vec![0; spl_token::state::Account::LEN];
+        spl_token::state::Account {
+            mint,
+            owner,
+            amount,
+            state: spl_token::state::AccountState::Initialized,
+            is_native: is_native.then_some(rent).into(),
+            ..Default::default()
+        }
+        .pack_into_slice(&mut data);
+        self.set_token_program_account(address, data);
+        if is_native {
+            self.svm.airdrop(&address, amount);
+        }
+    }
+
+    fn set_token_program_account(&mut self, address: Pubkey, data: Vec<u8>) {
+        let lamports = Rent::default().minimum_balance(data.len());
+        self.svm.set_account(
+            address,
+            AccountState {
+                lamports,
+                data,
+                owner: spl_token::ID,
+                executable: false,
+            },
+        );
+    }
+
+    /// Lets `delegate` transfer up to `amount` out of a token account, as an
+    /// SPL `approve` would.
+    pub fn approve_delegate(&mut self, address: Pubkey, delegate: Pubkey, amount: u64) {
+        let mut account = self
+            .svm
+            .account(&address)
+            .expect("token account exists")
+            .clone();
+        let mut state =
+            spl_token::state::Account::unpack(&account.data).expect("token account unpacks");
+        state.delegate = Some(delegate).into();
+        state.delegated_amount = amount;
+        state.pack_into_slice(&mut account.data);
+        self.svm.set_account(address, account);
+    }
+
+    pub fn token_balance(&self, address: &Pubkey) -> u64 {
+        let account = self.svm.account(address).expect("token account exists");
+        spl_token::state::Account::unpack(&account.data)
+            .expect("token account unpacks")
+            .amount
+    }
+
+    pub fn open_spl_accounts(
+        &self,
+        user: Pubkey,
+        params: &OpenOrderParams,
+    ) -> accounts::OpenOrderSpl {
+        let order = order_pda(&user, params.expiry);
+        accounts::OpenOrderSpl {
+            order,
+            user,
+            payer: user,
+            user_token_account: get_associated_token_address(&user, &params.from_token),
+            order_token_account: get_associated_token_address(&order, &params.from_token),
+            token_mint: params.from_token,
+            global_config: global_config_pda(),
+            chain_config: chain_config_pda(params.to_chain_id),
+            token_route: token_route_pda(&params.from_token, params.to_chain_id, &params.to_token),
+            mint_policy: mint_policy_pda(&params.from_token),
+            price_feed: price_feed_pda(&params.from_token),
+            token_program: spl_token::ID,
+            system_program: system_program::ID,
+            associated_token_program: spl_associated_token_account::ID,
+            instructions: None,
+            order_book: None,
+            session_key: None,
+        }
+    }
+
+    pub fn open_spl_with(&mut self, user: Pubkey, params: OpenOrderParams) -> TxResult {
+        let accounts = self.open_spl_accounts(user, &params);
+        self.send(accounts, instruction::OpenOrderSpl { params }, &[user])
+    }
+
+    /// Opens an order escrowing `amount_in` of `mint` from the user's
+    /// associated token account and returns its PDA.
+    pub fn open_spl(&mut self, user: Pubkey, mint: Pubkey, amount_in: u64, ttl: i64) -> Pubkey {
+        let expiry = self.svm.now() + ttl;
+        let params = self.params(mint, amount_in, expiry);
+        assert_ok(self.open_spl_with(user, params));
+        order_pda(&user, expiry)
+    }
+
+    pub fn execute_spl_accounts(
+        &self,
+        order: Pubkey,
+        executor: Pubkey,
+        target_token_account: Pubkey,
+    ) -> accounts::ExecuteOrderSpl {
+        let order_account: limit_order::LimitOrder = self.svm.fetch(&order);
+        accounts::ExecuteOrderSpl {
+            order,
+            order_token_account: get_associated_token_address(&order, &order_account.from_token),
+            target_token_account,
+            treasury_token_account: get_associated_token_address(
+                &self.treasury,
+                &order_account.from_token,
+            ),
+            global_config: global_config_pda(),
+            chain_config: chain_config_pda(order_account.to_chain_id),
+            refund_receiver: order_account.refund_to(),
+            executor,
+            token_program: spl_token::ID,
+            guardian_set: None,
+            instructions: None,
+            executor_bond: None,
+            order_book: None,
+            roles: None,
+        }
+    }
+
+    pub fn execute_spl(&mut self, order: Pubkey, target_token_account: Pubkey) -> TxResult {
+        let owner = self.owner;
+        let accounts = self.execute_spl_accounts(order, owner, target_token_account);
+        self.send(
+            accounts,
+            instruction::ExecuteOrderSpl {
+                native_token_volume: 0,
+            },
+            &[owner],
+        )
+    }
+
+    pub fn cancel_spl_accounts(&self, order: Pubkey, user: Pubkey) -> accounts::CancelOrderSpl {
+        let order_account: limit_order::LimitOrder = self.svm.fetch(&order);
+        accounts::CancelOrderSpl {
+            order,
+            user,
+            user_token_account: get_associated_token_address(
+                &order_account.refund_to(),
+                &order_account.from_token,
+            ),
+            order_token_account: get_associated_token_address(&order, &order_account.from_token),
+            refund_receiver: order_account.refund_to(),
+            global_config: global_config_pda(),
+            token_program: spl_token::ID,
+            order_book: None,
+            session_key: None,
+            roles: self.roles(),
+        }
+    }
+
+    pub fn cancel_spl(&mut self, order: Pubkey, user: Pubkey) -> TxResult {
+        let accounts = self.cancel_spl_accounts(order, user);
+        self.send(accounts, instruction::CancelOrderSpl {}, &[user])
+    }
+
+    pub fn open_wsol_accounts(
+        &self,
+        user: Pubkey,
+        params: &OpenOrderParams,
+    ) -> accounts::OpenOrderWsol {
+        let order = order_pda(&user, params.expiry);
+        accounts::OpenOrderWsol {
+            order,
+            user,
+            payer: user,
+            order_token_account: get_associated_token_address(&order, &native_mint()),
+            token_mint: native_mint(),
+            global_config: global_config_pda(),
+            chain_config: chain_config_pda(params.to_chain_id),
+            token_route: token_route_pda(&params.from_token, params.to_chain_id, &params.to_token),
+            price_feed: price_feed_pda(&params.from_token),
+            token_program: spl_token::ID,
+            system_program: system_program::ID,
+            associated_token_program: spl_associated_token_account::ID,
+            instructions: None,
+            order_book: None,
+            session_key: None,
+        }
+    }
+
+    /// Opens a SOL order escrowed as WSOL and returns its PDA.
+    pub fn open_wsol(&mut self, user: Pubkey, amount_in: u64, ttl: i64) -> Pubkey {
+        let expiry = self.svm.now() + ttl;
+        let params = self.params(native_mint(), amount_in, expiry);
+        let accounts = self.open_wsol_accounts(user, &params);
+        assert_ok(self.send(accounts, instruction::OpenOrderWsol { params }, &[user]));
+        order_pda(&user, expiry)
+    }
+
+    pub fn execute_wsol_accounts(
+        &self,
+        order: Pubkey,
+        executor: Pubkey,
+        target: Pubkey,
+    ) -> accounts::ExecuteOrderWsol {
+        let order_account = self.order(&order);
+        accounts::ExecuteOrderWsol {
+            order,
+            order_token_account: get_associated_token_address(&order, &native_mint()),
+            target_sol: target,
+            treasury_token_account: get_associated_token_address(&self.treasury, &native_mint()),
+            global_config: global_config_pda(),
+            chain_config: chain_config_pda(order_account.to_chain_id),
+            refund_receiver: order_account.refund_to(),
+            executor,
+            token_program: spl_token::ID,
+            guardian_set: None,
+            instructions: None,
+            executor_bond: None,
+            order_book: None,
+            roles: None,
+        }
+    }
+
+    pub fn cancel_wsol_accounts(&self, order: Pubkey, user: Pubkey) -> accounts::CancelOrderWsol {
+        let order_account = self.order(&order);
+        accounts::CancelOrderWsol {
+            order,
+            user,
+            order_token_account: get_associated_token_address(&order, &native_mint()),
+            refund_receiver: order_account.refund_to(),
+            global_config: global_config_pda(),
+            token_program: spl_token::ID,
+            order_book: None,
+            session_key: None,
+            roles: self.roles(),
+        }
+    }
+}
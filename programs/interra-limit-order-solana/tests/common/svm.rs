@@ -0,0 +1,480 @@
+//! The test validator: a `solana-program-test` bank the tests drive
+//! synchronously. `limit_order` and the mock swap adapter run from their SBF
+//! builds when `target/deploy` (or `SBF_OUT_DIR`) holds them, as after
+//! `anchor build` or `cargo build-sbf`, and natively otherwise; SPL Token and
+//! the associated token program are the bank's own SBF programs. Either way
+//! the runtime checks ownership, rent, signatures, writability and Ed25519
+//! precompile instructions, but compute units are only metered for SBF
+//! programs.
+
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::Once;
+use std::time::Duration;
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::entrypoint::ProgramResult;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction, InstructionError};
+use anchor_lang::solana_program::program_stubs::{self, SyscallStubs};
+use anchor_lang::solana_program::{bpf_loader, ed25519_program, sysvar};
+use anchor_lang::{Discriminator, Event};
+use base64::Engine;
+use solana_program_test::{processor, ProgramTest, ProgramTestContext};
+use solana_sdk::account::{Account, AccountSharedData};
+use solana_sdk::hash::Hash;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::transaction::{Transaction, TransactionError};
+use tokio::runtime::Runtime;
+
+thread_local! {
+    static EVENTS: RefCell<Vec<Vec<u8>>> = const { RefCell::new(Vec::new()) };
+    static CALLERS: RefCell<Vec<&'static [AccountInfo<'static>]>> = const { RefCell::new(Vec::new()) };
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AccountState {
+    pub lamports: u64,
+    pub data: Vec<u8>,
+    pub owner: Pubkey,
+    pub executable: bool,
+}
+
+impl From<Account> for AccountState {
+    fn from(account: Account) -> Self {
+        Self {
+            lamports: account.lamports,
+            data: account.data,
+            owner: account.owner,
+            executable: account.executable,
+        }
+    }
+}
+
+/// Why a transaction was rejected.
+#[derive(Debug, PartialEq, Eq)]
+pub enum TxError {
+    /// An instruction returned an error.
+    Program(ProgramError),
+    /// The runtime rejected what an instruction did, e.g. debiting or writing
+    /// an account the program doesn't own.
+    Instruction(InstructionError),
+    /// The transaction as a whole was rejected, e.g. for leaving an account
+    /// below its rent-exempt minimum.
+    Transaction(TransactionError),
+    /// An Ed25519 program instruction failed to verify.
+    InvalidSignature,
+}
+
+#[derive(Debug)]
+pub struct TxFailure {
+    pub error: TxError,
+    pub logs: Vec<String>,
+}
+
+pub type TxResult = std::result::Result<(), TxFailure>;
+
+pub struct Svm {
+    runtime: Runtime,
+    context: ProgramTestContext,
+    clock: Clock,
+    /// Every account a test or transaction has touched; the fee payer is
+    /// kept out so fees don't show up in [`Svm::total_lamports`].
+    touched: BTreeSet<Pubkey>,
+    last_blockhash: Hash,
+    sbf: bool,
+    logs: Vec<String>,
+    /// Events native programs emitted, which the bank's stubs don't log.
+    native_events: Vec<Vec<u8>>,
+    compute_units: u64,
+}
+
+impl Default for Svm {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Svm {
+    pub fn new() -> Self {
+        let mut program_test = ProgramTest::default();
+        // Builds are picked up below, so `add_program` only adds natives.
+        program_test.prefer_bpf(false);
+        // Transaction logs are kept per transaction; don't echo them too.
+        solana_logger::setup_with_default("off");
+        let sbf = add_sbf_program(&mut program_test, "limit_order", limit_order::ID);
+        if !sbf {
+            program_test.add_program(
+                "limit_order",
+                limit_order::ID,
+                processor!(process_limit_order),
+            );
+        }
+        if !add_sbf_program(&mut program_test, "mock_swap", mock_swap::ID) {
+            program_test.add_program("mock_swap", mock_swap::ID, processor!(process_mock_swap));
+        }
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .expect("runtime starts");
+        let context = runtime.block_on(program_test.start_with_context());
+        // `start_with_context` installs the bank's stubs for native programs
+        // the first time it runs; wrap them before any transaction does.
+        static STUBS: Once = Once::new();
+        STUBS.call_once(|| {
+            let bank_stubs = program_stubs::set_syscall_stubs(Box::new(NoStubs));
+            program_stubs::set_syscall_stubs(Box::new(NativeStubs(bank_stubs)));
+        });
+        let svm = Self {
+            runtime,
+            last_blockhash: context.last_blockhash,
+            context,
+            clock: Clock {
+                slot: 1,
+                unix_timestamp: 1_700_000_000,
+                ..Clock::default()
+            },
+            touched: BTreeSet::new(),
+            sbf,
+            logs: Vec::new(),
+            native_events: Vec::new(),
+            compute_units: 0,
+        };
+        svm.context.set_sysvar(&svm.clock);
+        svm
+    }
+
+    /// Whether `limit_order` runs from its SBF build, so that
+    /// [`Svm::compute_units`] is meaningful.
+    pub fn runs_sbf(&self) -> bool {
+        self.sbf
+    }
+
+    pub fn now(&self) -> i64 {
+        self.clock.unix_timestamp
+    }
+
+    /// Moves the clock `seconds` forward.
+    pub fn warp(&mut self, seconds: i64) {
+        self.clock.unix_timestamp += seconds;
+        self.clock.slot += (seconds.max(0) as u64).max(1);
+        self.context.set_sysvar(&self.clock);
+    }
+
+    pub fn set_account(&mut self, key: Pubkey, account: AccountState) {
+        self.touched.insert(key);
+        self.context.set_account(
+            &key,
+            &AccountSharedData::from(Account {
+                lamports: account.lamports,
+                data: account.data,
+                owner: account.owner,
+                executable: account.executable,
+                rent_epoch: 0,
+            }),
+        );
+    }
+
+    pub fn account(&self, key: &Pubkey) -> Option<AccountState> {
+        self.runtime
+            .block_on(self.context.banks_client.get_account(*key))
+            .expect("bank answers")
+            .map(AccountState::from)
+    }
+
+    pub fn lamports(&self, key: &Pubkey) -> u64 {
+        self.account(key).map_or(0, |account| account.lamports)
+    }
+
+    /// Lamports held across every account tests have touched; only airdrops
+    /// change it. Programs and sysvars the bank ships with are left out.
+    pub fn total_lamports(&self) -> u64 {
+        self.touched
+            .iter()
+            .filter_map(|key| self.account(key))
+            .filter(|account| !account.executable && account.owner != sysvar::ID)
+            .map(|account| account.lamports)
+            .sum()
+    }
+
+    pub fn airdrop(&mut self, key: &Pubkey, lamports: u64) {
+        let mut account = self.account(key).unwrap_or_default();
+        account.lamports += lamports;
+        self.set_account(*key, account);
+    }
+
+    /// Deserializes an Anchor account, panicking if it is missing.
+    pub fn fetch<T: AccountDeserialize>(&self, key: &Pubkey) -> T {
+        let account = self
+            .account(key)
+            .unwrap_or_else(|| panic!("account {key} does not exist"));
+        T::try_deserialize(&mut account.data.as_slice()).expect("account deserializes")
+    }
+
+    /// Logs of the last processed transaction.
+    pub fn logs(&self) -> &[String] {
+        &self.logs
+    }
+
+    /// Compute units the last processed transaction consumed. Native
+    /// programs are charged next to nothing, so only read this when
+    /// [`Svm::runs_sbf`].
+    pub fn compute_units(&self) -> u64 {
+        self.compute_units
+    }
+
+    /// Events of type `E` emitted by the last processed transaction.
+    pub fn events<E: Event + AnchorDeserialize + Discriminator>(&self) -> Vec<E> {
+        self.logs
+            .iter()
+            .filter_map(|log| log.strip_prefix("Program data: "))
+            .flat_map(str::split_whitespace)
+            .filter_map(|field| base64::engine::general_purpose::STANDARD.decode(field).ok())
+            .chain(self.native_events.iter().cloned())
+            .filter_map(|data| {
+                let mut body = data.strip_prefix(E::DISCRIMINATOR)?;
+                E::deserialize(&mut body).ok()
+            })
+            .collect()
+    }
+
+    /// Runs `instructions` as one transaction paid for by the bank's own fee
+    /// payer. Every key in `signers` signs; accounts an instruction marks as
+    /// signers but `signers` leaves out are sent unsigned, so the program
+    /// sees a missing signature.
+    pub fn process(&mut self, instructions: &[Instruction], signers: &[Pubkey]) -> TxResult {
+        let instructions: Vec<Instruction> = instructions
+            .iter()
+            .cloned()
+            .map(|mut ix| {
+                for meta in &mut ix.accounts {
+                    meta.is_signer &= signers.contains(&meta.pubkey);
+                    self.touched.insert(meta.pubkey);
+                }
+                ix
+            })
+            .collect();
+        let keypairs: Vec<Keypair> = signers
+            .iter()
+            .filter(|signer| {
+                instructions
+                    .iter()
+                    .flat_map(|ix| &ix.accounts)
+                    .any(|meta| meta.is_signer && meta.pubkey == **signer)
+            })
+            .map(super::keypair)
+            .collect();
+        let blockhash = self.new_blockhash();
+        let payer = &self.context.payer;
+        let transaction = Transaction::new_signed_with_payer(
+            &instructions,
+            Some(&payer.pubkey()),
+            &std::iter::once(payer)
+                .chain(&keypairs)
+                .collect::<Vec<&Keypair>>(),
+            blockhash,
+        );
+
+        EVENTS.with(|events| events.borrow_mut().clear());
+        let processed = self
+            .runtime
+            .block_on(
+                self.context
+                    .banks_client
+                    .process_transaction_with_metadata(transaction),
+            )
+            .expect("bank answers");
+        self.native_events = EVENTS.with(|events| events.take());
+        (self.logs, self.compute_units) = processed
+            .metadata
+            .map(|metadata| (metadata.log_messages, metadata.compute_units_consumed))
+            .unwrap_or_default();
+        processed.result.map_err(|error| TxFailure {
+            error: match error {
+                TransactionError::InstructionError(index, _)
+                    if instructions[index as usize].program_id == ed25519_program::ID =>
+                {
+                    TxError::InvalidSignature
+                }
+                TransactionError::InstructionError(_, error) => {
+                    ProgramError::try_from(error.clone())
+                        .map_or(TxError::Instruction(error), TxError::Program)
+                }
+                error => TxError::Transaction(error),
+            },
+            logs: self.logs.clone(),
+        })
+    }
+
+    /// A blockhash no earlier transaction used, so repeating a transaction
+    /// isn't rejected as already processed.
+    fn new_blockhash(&mut self) -> Hash {
+        loop {
+            let blockhash = self
+                .runtime
+                .block_on(self.context.banks_client.get_latest_blockhash())
+                .expect("bank answers");
+            if blockhash != self.last_blockhash {
+                self.last_blockhash = blockhash;
+                return blockhash;
+            }
+            self.runtime
+                .block_on(async { tokio::time::sleep(Duration::from_millis(1)).await });
+        }
+    }
+}
+
+/// Adds `name` from its SBF build if there is one, returning whether it did.
+fn add_sbf_program(program_test: &mut ProgramTest, name: &str, id: Pubkey) -> bool {
+    let out_dir = std::env::var("SBF_OUT_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| Path::new(env!("CARGO_MANIFEST_DIR")).join("../../target/deploy"));
+    match std::fs::read(out_dir.join(format!("{name}.so"))) {
+        Ok(elf) => {
+            program_test.add_account(
+                id,
+                Account {
+                    lamports: Rent::default().minimum_balance(elf.len()),
+                    data: elf,
+                    owner: bpf_loader::ID,
+                    executable: true,
+                    rent_epoch: 0,
+                },
+            );
+            true
+        }
+        Err(_) => false,
+    }
+}
+
+// Anchor entrypoints borrow their accounts for as long as the accounts'
+// data, which the runtime's frame outlives.
+fn process_limit_order(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    with_caller(accounts, |accounts| {
+        limit_order::entry(program_id, accounts, data)
+    })
+}
+
+fn process_mock_swap(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    with_caller(accounts, |accounts| {
+        mock_swap::entry(program_id, accounts, data)
+    })
+}
+
+/// Runs a native entrypoint with its accounts leaked to `'static`, as the
+/// Anchor entrypoints want, and remembered for the CPIs it makes.
+fn with_caller(
+    accounts: &[AccountInfo],
+    entry: impl FnOnce(&'static [AccountInfo<'static>]) -> ProgramResult,
+) -> ProgramResult {
+    // SAFETY: the infos are leaked below, and the bank keeps the account
+    // memory they point at alive for the whole transaction.
+    let accounts: &'static [AccountInfo<'static>] = unsafe {
+        std::mem::transmute::<&[AccountInfo], &'static [AccountInfo<'static>]>(Box::leak(
+            accounts.to_vec().into_boxed_slice(),
+        ))
+    };
+    CALLERS.with(|callers| callers.borrow_mut().push(accounts));
+    let result = entry(accounts);
+    CALLERS.with(|callers| callers.borrow_mut().pop());
+    result
+}
+
+struct NoStubs;
+
+impl SyscallStubs for NoStubs {}
+
+/// The bank's syscalls for native programs, filling in what they leave out
+/// next to the SBF runtime.
+struct NativeStubs(Box<dyn SyscallStubs>);
+
+impl SyscallStubs for NativeStubs {
+    fn sol_log(&self, message: &str) {
+        self.0.sol_log(message)
+    }
+
+    fn sol_log_data(&self, fields: &[&[u8]]) {
+        EVENTS.with(|events| {
+            events
+                .borrow_mut()
+                .extend(fields.iter().map(|field| field.to_vec()))
+        });
+    }
+
+    /// The SBF runtime syncs every account of the caller before a CPI, but
+    /// the bank only syncs the ones the CPI names, so a caller that moved
+    /// lamports between other accounts would look unbalanced. Naming the
+    /// rest read-only syncs them too; the bank's own programs ignore
+    /// trailing accounts, unlike the mock swap adapter.
+    fn sol_invoke_signed(
+        &self,
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        let mut instruction = instruction.clone();
+        let mut account_infos = account_infos.to_vec();
+        let caller = CALLERS.with(|callers| callers.borrow().last().copied());
+        if instruction.program_id != mock_swap::ID {
+            for info in caller.unwrap_or_default() {
+                if info.is_writable
+                    && !instruction
+                        .accounts
+                        .iter()
+                        .any(|meta| meta.pubkey == *info.key)
+                {
+                    instruction
+                        .accounts
+                        .push(AccountMeta::new_readonly(*info.key, false));
+                    // SAFETY: the caller's infos outlive every CPI it makes.
+                    account_infos.push(unsafe {
+                        std::mem::transmute::<AccountInfo<'static>, AccountInfo>(info.clone())
+                    });
+                }
+            }
+        }
+        self.0
+            .sol_invoke_signed(&instruction, &account_infos, signers_seeds)
+    }
+
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_clock_sysvar(var_addr)
+    }
+
+    fn sol_get_epoch_schedule_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_epoch_schedule_sysvar(var_addr)
+    }
+
+    fn sol_get_epoch_rewards_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_epoch_rewards_sysvar(var_addr)
+    }
+
+    fn sol_get_fees_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_fees_sysvar(var_addr)
+    }
+
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_rent_sysvar(var_addr)
+    }
+
+    fn sol_get_last_restart_slot(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_last_restart_slot(var_addr)
+    }
+
+    fn sol_get_return_data(&self) -> Option<(Pubkey, Vec<u8>)> {
+        self.0.sol_get_return_data()
+    }
+
+    fn sol_set_return_data(&self, data: &[u8]) {
+        self.0.sol_set_return_data(data)
+    }
+
+    fn sol_get_stack_height(&self) -> u64 {
+        self.0.sol_get_stack_height()
+    }
+}
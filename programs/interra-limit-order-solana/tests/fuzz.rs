@@ -0,0 +1,471 @@
+//! Random instruction sequences checked against a model of who owns what.
+//!
+//! Every user's value is their wallet plus whatever is still escrowed in
+//! their open orders (amount and rent alike). Opening and cancelling must
+//! leave it unchanged; an execution must move exactly `amount_in` out of it,
+//! split between the treasury and the target by the fee in force at the time.
+
+mod common;
+
+use anchor_lang::system_program;
+use anchor_spl::associated_token::get_associated_token_address;
+use anchor_spl::token::spl_token;
+use common::*;
+use limit_order::{accounts, instruction, AddressFormat, ChainConfigParams};
+use proptest::prelude::*;
+
+const USERS: usize = 3;
+const TOKENS: u64 = 1_000_000_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Asset {
+    Sol,
+    Spl,
+}
+
+#[derive(Clone, Debug)]
+enum Op {
+    Open {
+        user: usize,
+        asset: Asset,
+        amount_in: u64,
+        ttl: i64,
+    },
+    Execute {
+        order: usize,
+    },
+    Cancel {
+        order: usize,
+        by_owner: bool,
+    },
+    RequestCancel {
+        order: usize,
+    },
+    Warp {
+        seconds: i64,
+    },
+    SetPlatformFee {
+        platform_fee: u16,
+    },
+    SetFeeOverride {
+        platform_fee_override: Option<u16>,
+    },
+    SetCancelGracePeriod {
+        cancel_grace_period: i64,
+    },
+    SetPaused {
+        paused: bool,
+    },
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    let asset = prop_oneof![Just(Asset::Sol), Just(Asset::Spl)];
+    prop_oneof![
+        4 => (0..USERS, asset, 1..=3 * SOL, 1..7_200i64).prop_map(|(user, asset, amount_in, ttl)| {
+            Op::Open {
+                user,
+                asset,
+                amount_in,
+                ttl,
+            }
+        }),
+        3 => any::<usize>().prop_map(|order| Op::Execute { order }),
+        2 => (any::<usize>(), any::<bool>())
+            .prop_map(|(order, by_owner)| Op::Cancel { order, by_owner }),
+        1 => any::<usize>().prop_map(|order| Op::RequestCancel { order }),
+        2 => (0..4_000i64).prop_map(|seconds| Op::Warp { seconds }),
+        1 => (0..=1_000u16).prop_map(|platform_fee| Op::SetPlatformFee { platform_fee }),
+        1 => proptest::option::of(0..=1_000u16)
+            .prop_map(|platform_fee_override| Op::SetFeeOverride {
+                platform_fee_override
+            }),
+        1 => (0..600i64).prop_map(|cancel_grace_period| Op::SetCancelGracePeriod {
+            cancel_grace_period
+        }),
+        1 => any::<bool>().prop_map(|paused| Op::SetPaused { paused }),
+    ]
+}
+
+struct Order {
+    key: Pubkey,
+    sender: usize,
+    asset: Asset,
+    amount_in: u64,
+    closed: bool,
+}
+
+struct Harness {
+    env: Env,
+    mint: Pubkey,
+    users: Vec<Pubkey>,
+    target: Pubkey,
+    target_ata: Pubkey,
+    treasury_ata: Pubkey,
+    orders: Vec<Order>,
+    platform_fee: u16,
+    platform_fee_override: Option<u16>,
+    /// Expected lamport and token value of each user.
+    lamports: Vec<u64>,
+    tokens: Vec<u64>,
+    treasury_lamports: u64,
+    treasury_tokens: u64,
+    target_lamports: u64,
+    target_tokens: u64,
+    total_lamports: u64,
+}
+
+impl Harness {
+    fn new(platform_fee: u16) -> Self {
+        let mut env = Env::new(platform_fee);
+        let mint = env.create_mint(9);
+        let users: Vec<Pubkey> = (0..USERS).map(|_| env.user()).collect();
+        for user in &users {
+            env.create_token_account(mint, *user, TOKENS);
+        }
+        let target = env.user();
+        let target_ata = env.create_token_account(mint, target, 0);
+        let treasury_ata = env.create_token_account(mint, env.treasury, 0);
+
+        let mut harness = Self {
+            mint,
+            target,
+            target_ata,
+            treasury_ata,
+            orders: Vec::new(),
+            platform_fee,
+            platform_fee_override: None,
+            lamports: Vec::new(),
+            tokens: Vec::new(),
+            treasury_lamports: env.svm.lamports(&env.treasury),
+            treasury_tokens: 0,
+            target_lamports: env.svm.lamports(&target),
+            target_tokens: 0,
+            total_lamports: env.svm.total_lamports(),
+            users,
+            env,
+        };
+        harness.lamports = (0..USERS).map(|user| harness.lamport_value(user)).collect();
+        harness.tokens = (0..USERS).map(|user| harness.token_value(user)).collect();
+        harness
+    }
+
+    fn escrow(&self, order: &Order) -> Option<Pubkey> {
+        (order.asset == Asset::Spl).then(|| get_associated_token_address(&order.key, &self.mint))
+    }
+
+    fn open_orders(&self, user: usize) -> impl Iterator<Item = &Order> {
+        self.orders
+            .iter()
+            .filter(move |order| order.sender == user && !order.closed)
+    }
+
+    fn lamport_value(&self, user: usize) -> u64 {
+        let svm = &self.env.svm;
+        let escrowed: u64 = self
+            .open_orders(user)
+            .map(|order| {
+                svm.lamports(&order.key)
+                    + self.escrow(order).map_or(0, |escrow| svm.lamports(&escrow))
+            })
+            .sum();
+        svm.lamports(&self.users[user]) + escrowed
+    }
+
+    fn token_value(&self, user: usize) -> u64 {
+        let escrowed: u64 = self
+            .open_orders(user)
+            .filter_map(|order| self.escrow(order))
+            .map(|escrow| self.env.token_balance(&escrow))
+            .sum();
+        let wallet = get_associated_token_address(&self.users[user], &self.mint);
+        self.env.token_balance(&wallet) + escrowed
+    }
+
+    fn pick(&self, index: usize) -> Option<usize> {
+        (!self.orders.is_empty()).then(|| index % self.orders.len())
+    }
+
+    fn apply(&mut self, op: &Op) {
+        match *op {
+            Op::Open {
+                user,
+                asset,
+                amount_in,
+                ttl,
+            } => self.open(user, asset, amount_in, ttl),
+            Op::Execute { order } => {
+                if let Some(order) = self.pick(order) {
+                    self.execute(order);
+                }
+            }
+            Op::Cancel { order, by_owner } => {
+                if let Some(order) = self.pick(order) {
+                    self.cancel(order, by_owner);
+                }
+            }
+            Op::RequestCancel { order } => {
+                if let Some(index) = self.pick(order) {
+                    let order = &self.orders[index];
+                    let sender = self.users[order.sender];
+                    let result = self.env.send(
+                        accounts::RequestCancel {
+                            order: order.key,
+                            user: sender,
+                            global_config: global_config_pda(),
+                            session_key: None,
+                        },
+                        instruction::RequestCancel {},
+                        &[sender],
+                    );
+                    assert!(
+                        !order.closed || result.is_err(),
+                        "closed order accepted a request"
+                    );
+                }
+            }
+            Op::Warp { seconds } => self.env.svm.warp(seconds),
+            Op::SetPlatformFee { platform_fee } => {
+                if self
+                    .env
+                    .admin(instruction::SetPlatformFee { platform_fee })
+                    .is_ok()
+                {
+                    self.platform_fee = platform_fee;
+                }
+            }
+            Op::SetFeeOverride {
+                platform_fee_override,
+            } => {
+                let params = ChainConfigParams {
+                    enabled: true,
+                    address_format: AddressFormat::Bytes32,
+                    min_amount_in: 0,
+                    max_amount_in: 0,
+                    platform_fee_override,
+                };
+                if self.env.set_chain_config(DEST_CHAIN, params).is_ok() {
+                    self.platform_fee_override = platform_fee_override;
+                }
+            }
+            Op::SetCancelGracePeriod {
+                cancel_grace_period,
+            } => {
+                let _ = self.env.admin(instruction::SetCancelGracePeriod {
+                    cancel_grace_period,
+                });
+            }
+            Op::SetPaused { paused } => {
+                let _ = self.env.admin(instruction::SetPaused { paused });
+            }
+        }
+    }
+
+    fn open(&mut self, user: usize, asset: Asset, amount_in: u64, ttl: i64) {
+        let sender = self.users[user];
+        let from_token = match asset {
+            Asset::Sol => native_mint(),
+            Asset::Spl => self.mint,
+        };
+        let params = self
+            .env
+            .params(from_token, amount_in, self.env.svm.now() + ttl);
+        let key = order_pda(&sender, params.expiry);
+        let existed = self.env.svm.account(&key).is_some();
+        let result = match asset {
+            Asset::Sol => self.env.open_sol_with(sender, params),
+            Asset::Spl => self.env.open_spl_with(sender, params),
+        };
+        if result.is_ok() {
+            assert!(!existed, "an open order was overwritten");
+            // A closed order's address is free again for a later order with
+            // the same expiry, which takes over its entry.
+            self.orders.retain(|order| order.key != key);
+            self.orders.push(Order {
+                key,
+                sender: user,
+                asset,
+                amount_in,
+                closed: false,
+            });
+        }
+    }
+
+    fn execute(&mut self, index: usize) {
+        let order = &self.orders[index];
+        let owner = self.env.owner;
+        let sender = self.users[order.sender];
+        // Closed orders can't be fetched to build the accounts, so every
+        // execution passes the accounts the order was opened with.
+        let result = match order.asset {
+            Asset::Sol => self.env.send(
+                accounts::ExecuteOrderSol {
+                    order: order.key,
+                    target_sol: self.target,
+                    treasury: self.env.treasury,
+                    executor: owner,
+                    global_config: global_config_pda(),
+                    chain_config: chain_config_pda(DEST_CHAIN),
+                    refund_receiver: sender,
+                    guardian_set: None,
+                    instructions: None,
+                    executor_bond: None,
+                    order_book: None,
+                    roles: None,
+                },
+                instruction::ExecuteOrderSol {
+                    native_token_volume: 0,
+                },
+                &[owner],
+            ),
+            Asset::Spl => self.env.send(
+                accounts::ExecuteOrderSpl {
+                    order: order.key,
+                    order_token_account: self.escrow(order).expect("spl escrow"),
+                    target_token_account: self.target_ata,
+                    treasury_token_account: self.treasury_ata,
+                    global_config: global_config_pda(),
+                    chain_config: chain_config_pda(DEST_CHAIN),
+                    refund_receiver: sender,
+                    executor: owner,
+                    token_program: spl_token::ID,
+                    guardian_set: None,
+                    instructions: None,
+                    executor_bond: None,
+                    order_book: None,
+                    roles: None,
+                },
+                instruction::ExecuteOrderSpl {
+                    native_token_volume: 0,
+                },
+                &[owner],
+            ),
+        };
+        if order.closed {
+            assert!(result.is_err(), "order executed twice");
+            return;
+        }
+        if result.is_err() {
+            return;
+        }
+
+        let fee_bps = self.platform_fee_override.unwrap_or(self.platform_fee);
+        let fee = (order.amount_in as u128 * fee_bps as u128 / 10_000) as u64;
+        let (sender, amount_in, asset) = (order.sender, order.amount_in, order.asset);
+        self.orders[index].closed = true;
+        match asset {
+            Asset::Sol => {
+                self.lamports[sender] -= amount_in;
+                self.treasury_lamports += fee;
+                self.target_lamports += amount_in - fee;
+            }
+            Asset::Spl => {
+                self.tokens[sender] -= amount_in;
+                self.treasury_tokens += fee;
+                self.target_tokens += amount_in - fee;
+            }
+        }
+    }
+
+    fn cancel(&mut self, index: usize, by_owner: bool) {
+        let order = &self.orders[index];
+        let signer = if by_owner {
+            self.env.owner
+        } else {
+            self.users[order.sender]
+        };
+        let result = match order.asset {
+            Asset::Sol => self.env.send(
+                accounts::CancelOrderSol {
+                    order: order.key,
+                    user: signer,
+                    refund_receiver: self.users[order.sender],
+                    global_config: global_config_pda(),
+                    system_program: system_program::ID,
+                    order_book: None,
+                    session_key: None,
+                    roles: None,
+                },
+                instruction::CancelOrderSol {},
+                &[signer],
+            ),
+            Asset::Spl => self.env.send(
+                accounts::CancelOrderSpl {
+                    order: order.key,
+                    user: signer,
+                    user_token_account: get_associated_token_address(
+                        &self.users[order.sender],
+                        &self.mint,
+                    ),
+                    order_token_account: self.escrow(order).expect("spl escrow"),
+                    refund_receiver: self.users[order.sender],
+                    global_config: global_config_pda(),
+                    token_program: spl_token::ID,
+                    order_book: None,
+                    session_key: None,
+                    roles: None,
+                },
+                instruction::CancelOrderSpl {},
+                &[signer],
+            ),
+        };
+        if order.closed {
+            assert!(result.is_err(), "order cancelled twice");
+        } else if result.is_ok() {
+            self.orders[index].closed = true;
+        }
+    }
+
+    fn check(&self) {
+        let svm = &self.env.svm;
+        assert_eq!(
+            svm.total_lamports(),
+            self.total_lamports,
+            "lamports minted or burned"
+        );
+        for user in 0..USERS {
+            assert_eq!(
+                self.lamport_value(user),
+                self.lamports[user],
+                "user {user} lamports"
+            );
+            assert_eq!(
+                self.token_value(user),
+                self.tokens[user],
+                "user {user} tokens"
+            );
+        }
+        assert_eq!(svm.lamports(&self.env.treasury), self.treasury_lamports);
+        assert_eq!(
+            self.env.token_balance(&self.treasury_ata),
+            self.treasury_tokens
+        );
+        assert_eq!(svm.lamports(&self.target), self.target_lamports);
+        assert_eq!(self.env.token_balance(&self.target_ata), self.target_tokens);
+
+        for order in &self.orders {
+            let open = svm.account(&order.key).is_some();
+            assert_eq!(open, !order.closed, "order {} state", order.key);
+            if let Some(escrow) = self.escrow(order) {
+                assert_eq!(
+                    svm.account(&escrow).is_some(),
+                    open,
+                    "order {} and its escrow closed apart",
+                    order.key
+                );
+            }
+        }
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn value_is_conserved(platform_fee in 0..=1_000u16, ops in prop::collection::vec(op(), 1..48)) {
+        let mut harness = Harness::new(platform_fee);
+        for op in &ops {
+            harness.apply(op);
+            harness.check();
+        }
+    }
+}
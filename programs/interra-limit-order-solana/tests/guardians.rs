@@ -0,0 +1,658 @@
+//! Guardian fill attestations, executor bonds and slashing.
+
+mod common;
+
+use anchor_lang::error::ErrorCode;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::sysvar::instructions as sysvar_instructions;
+use anchor_lang::system_program;
+use anchor_spl::associated_token::{get_associated_token_address, spl_associated_token_account};
+use anchor_spl::token::spl_token;
+use common::*;
+use limit_order::{
+    accounts, instruction, CustomError, ExecutorBond, FillAttestation, FillAttested,
+    NonDeliveryAttestation,
+};
+
+fn guardians(count: usize) -> Vec<Pubkey> {
+    (0..count).map(|_| new_signer()).collect()
+}
+
+/// Executes the SOL `order` as the owner, attested by `signatures`.
+fn execute_attested(
+    env: &mut Env,
+    order: Pubkey,
+    guardian_set: Pubkey,
+    signatures: &[(Pubkey, Vec<u8>)],
+) -> TxResult {
+    execute_after(env, order, guardian_set, ed25519_ix(signatures))
+}
+
+/// Executes the SOL `order` as the owner right after `ed25519`.
+fn execute_after(
+    env: &mut Env,
+    order: Pubkey,
+    guardian_set: Pubkey,
+    ed25519: Instruction,
+) -> TxResult {
+    let owner = env.owner;
+    let mut accounts = env.execute_sol_accounts(order, owner, Pubkey::new_unique());
+    accounts.guardian_set = Some(guardian_set);
+    accounts.instructions = Some(sysvar_instructions::ID);
+    let execute = ix(
+        accounts,
+        instruction::ExecuteOrderSol {
+            native_token_volume: 0,
+        },
+    );
+    env.svm.process(&[ed25519, execute], &[owner])
+}
+
+#[test]
+fn guardian_sets_are_validated() {
+    let mut env = Env::new(30);
+    let keys = guardians(3);
+    let invalid = CustomError::InvalidGuardianSet;
+    assert_error(env.init_guardian_set(Vec::new(), 1), invalid);
+    assert_error(env.init_guardian_set(keys.clone(), 0), invalid);
+    assert_error(env.init_guardian_set(keys.clone(), 4), invalid);
+    assert_error(env.init_guardian_set(vec![keys[0], keys[0]], 1), invalid);
+    assert_error(
+        env.init_guardian_set(vec![keys[0], Pubkey::default()], 1),
+        invalid,
+    );
+    assert_error(env.init_guardian_set(guardians(20), 1), invalid);
+
+    let stranger = env.user();
+    let owner = env.owner;
+    env.owner = stranger;
+    assert_error(
+        env.init_guardian_set(keys.clone(), 2),
+        CustomError::MissingRole,
+    );
+    env.owner = owner;
+    assert_ok(env.init_guardian_set(keys, 2));
+    assert!(env.config().attestation_required);
+}
+
+#[test]
+fn executions_need_a_guardian_quorum() {
+    let mut env = Env::new(30);
+    let keys = guardians(3);
+    assert_ok(env.init_guardian_set(keys.clone(), 2));
+    let set = guardian_set_pda(0);
+    let user = env.user();
+    let order = env.open_sol(user, SOL, 3600);
+    let order_account = env.order(&order);
+    let message = fill_attestation(&order, &order_account);
+
+    let target = Pubkey::new_unique();
+    assert_error(
+        env.execute_sol(order, target),
+        CustomError::AttestationRequired,
+    );
+    assert_error(
+        execute_attested(&mut env, order, set, &[]),
+        CustomError::AttestationRequired,
+    );
+    // An attestation for another order does not count.
+    let other = fill_attestation(&Pubkey::new_unique(), &order_account);
+    assert_error(
+        execute_attested(
+            &mut env,
+            order,
+            set,
+            &[(keys[0], other.clone()), (keys[1], other)],
+        ),
+        CustomError::AttestationRequired,
+    );
+
+    let insufficient = CustomError::InsufficientAttestations;
+    assert_error(
+        execute_attested(&mut env, order, set, &[(keys[0], message.clone())]),
+        insufficient,
+    );
+    assert_error(
+        execute_attested(
+            &mut env,
+            order,
+            set,
+            &[(keys[0], message.clone()), (keys[0], message.clone())],
+        ),
+        insufficient,
+    );
+    assert_error(
+        execute_attested(
+            &mut env,
+            order,
+            set,
+            &[(keys[0], message.clone()), (new_signer(), message.clone())],
+        ),
+        insufficient,
+    );
+
+    // Signatures are checked before the program counts them.
+    let mut forged = ed25519_ix(&[(keys[0], message.clone()), (keys[2], message.clone())]);
+    forge_signature(&mut forged);
+    assert_invalid_signature(execute_after(&mut env, order, set, forged));
+
+    assert_ok(execute_attested(
+        &mut env,
+        order,
+        set,
+        &[(keys[0], message.clone()), (keys[2], message)],
+    ));
+    let attested = env.svm.events::<FillAttested>();
+    assert_eq!(attested[0].order_pubkey, order);
+    assert_eq!(attested[0].guardian_set_index, 0);
+}
+
+#[test]
+fn attestations_must_match_the_order() {
+    let mut env = Env::new(30);
+    let keys = guardians(1);
+    assert_ok(env.init_guardian_set(keys.clone(), 1));
+    let set = guardian_set_pda(0);
+    let user = env.user();
+    let order = env.open_sol(user, SOL, 3600);
+    let order_account = env.order(&order);
+    let attestation = |recipient, amount_delivered| {
+        signed_message(
+            FillAttestation::DOMAIN,
+            FillAttestation {
+                order,
+                dest_tx_hash: [3; 32],
+                recipient,
+                amount_delivered,
+            },
+        )
+    };
+
+    let wrong_recipient = attestation([8; 32], order_account.amount_out);
+    assert_error(
+        execute_attested(&mut env, order, set, &[(keys[0], wrong_recipient)]),
+        CustomError::InvalidAttestation,
+    );
+    let mut short = order_account.amount_out;
+    short[31] -= 1;
+    assert_error(
+        execute_attested(
+            &mut env,
+            order,
+            set,
+            &[(keys[0], attestation(RECIPIENT, short))],
+        ),
+        CustomError::InvalidAttestation,
+    );
+
+    // Signature data must live in the Ed25519 instruction itself, even when
+    // the instruction it points at verifies.
+    let owner = env.owner;
+    let mut accounts = env.execute_sol_accounts(order, owner, Pubkey::new_unique());
+    accounts.guardian_set = Some(set);
+    accounts.instructions = Some(sysvar_instructions::ID);
+    let ed25519 = ed25519_ix(&[(keys[0], fill_attestation(&order, &order_account))]);
+    let mut redirected = ed25519.clone();
+    redirected.data[14..16].copy_from_slice(&0u16.to_le_bytes());
+    let execute = ix(
+        accounts,
+        instruction::ExecuteOrderSol {
+            native_token_volume: 0,
+        },
+    );
+    assert_error(
+        env.svm
+            .process(&[ed25519, redirected.clone(), execute.clone()], &[owner]),
+        CustomError::InvalidAttestation,
+    );
+    // Pointing at an instruction that holds no signature fails verification.
+    redirected.data[14..16].copy_from_slice(&1u16.to_le_bytes());
+    assert_invalid_signature(env.svm.process(&[redirected, execute.clone()], &[owner]));
+
+    // Nor can another account stand in for the instructions sysvar.
+    let mut spoofed = execute;
+    let fake = Pubkey::new_unique();
+    env.svm.airdrop(&fake, SOL);
+    for meta in &mut spoofed.accounts {
+        if meta.pubkey == sysvar_instructions::ID {
+            meta.pubkey = fake;
+        }
+    }
+    assert_error(
+        env.svm.process(&[spoofed], &[owner]),
+        ErrorCode::ConstraintAddress,
+    );
+
+    // A larger delivery is fine.
+    let mut more = order_account.amount_out;
+    more[0] += 1;
+    assert_ok(execute_attested(
+        &mut env,
+        order,
+        set,
+        &[(keys[0], attestation(RECIPIENT, more))],
+    ));
+}
+
+#[test]
+fn rotated_guardian_sets_expire() {
+    let mut env = Env::new(30);
+    let old_keys = guardians(1);
+    let new_keys = guardians(1);
+    assert_ok(env.init_guardian_set(old_keys.clone(), 1));
+    let owner = env.owner;
+    let rotate = |env: &mut Env, keys: Vec<Pubkey>, previous_set_ttl| {
+        let index = env.config().guardian_set_index;
+        env.send(
+            accounts::RotateGuardianSet {
+                current_guardian_set: guardian_set_pda(index),
+                new_guardian_set: guardian_set_pda(index + 1),
+                global_config: global_config_pda(),
+                authority: owner,
+                system_program: system_program::ID,
+                roles: None,
+                proposal: None,
+            },
+            instruction::RotateGuardianSet {
+                keys,
+                threshold: 1,
+                previous_set_ttl,
+            },
+            &[owner],
+        )
+    };
+    assert_error(
+        rotate(&mut env, new_keys.clone(), -1),
+        CustomError::InvalidParameter,
+    );
+    assert_ok(rotate(&mut env, new_keys.clone(), 600));
+    assert_eq!(env.config().guardian_set_index, 1);
+
+    let user = env.user();
+    let first = env.open_sol(user, SOL, 3600);
+    let second = env.open_sol(user, SOL, 7200);
+    let message = fill_attestation(&first, &env.order(&first));
+    assert_ok(execute_attested(
+        &mut env,
+        first,
+        guardian_set_pda(0),
+        &[(old_keys[0], message)],
+    ));
+
+    env.svm.warp(600);
+    let message = fill_attestation(&second, &env.order(&second));
+    assert_error(
+        execute_attested(
+            &mut env,
+            second,
+            guardian_set_pda(0),
+            &[(old_keys[0], message.clone())],
+        ),
+        CustomError::GuardianSetExpired,
+    );
+    // The old set's keys mean nothing to the new set.
+    assert_error(
+        execute_attested(
+            &mut env,
+            second,
+            guardian_set_pda(1),
+            &[(old_keys[0], message.clone())],
+        ),
+        CustomError::InsufficientAttestations,
+    );
+    assert_ok(execute_attested(
+        &mut env,
+        second,
+        guardian_set_pda(1),
+        &[(new_keys[0], message)],
+    ));
+}
+
+fn set_bond_config(env: &mut Env, bond_multiple: u16) {
+    assert_ok(env.admin(instruction::SetBondConfig {
+        bond_multiple,
+        unbond_cooldown: 3600,
+        slash_window: 1800,
+    }));
+}
+
+fn bond_sol(env: &mut Env, executor: Pubkey, amount: u64) -> TxResult {
+    env.send(
+        accounts::BondExecutorSol {
+            executor_bond: executor_bond_pda(&executor, &native_mint()),
+            executor,
+            system_program: system_program::ID,
+        },
+        instruction::BondExecutorSol { amount },
+        &[executor],
+    )
+}
+
+fn execute_bonded(
+    env: &mut Env,
+    order: Pubkey,
+    executor: Pubkey,
+    bond: Option<Pubkey>,
+) -> TxResult {
+    let mut accounts = env.execute_sol_accounts(order, executor, Pubkey::new_unique());
+    accounts.executor_bond = bond;
+    env.send(
+        accounts,
+        instruction::ExecuteOrderSol {
+            native_token_volume: 0,
+        },
+        &[executor],
+    )
+}
+
+fn request_unbond(env: &mut Env, executor: Pubkey, amount: u64) -> TxResult {
+    env.send(
+        accounts::RequestUnbond {
+            executor_bond: executor_bond_pda(&executor, &native_mint()),
+            executor,
+            global_config: global_config_pda(),
+        },
+        instruction::RequestUnbond { amount },
+        &[executor],
+    )
+}
+
+fn withdraw_sol(env: &mut Env, executor: Pubkey) -> TxResult {
+    env.send(
+        accounts::WithdrawBondSol {
+            executor_bond: executor_bond_pda(&executor, &native_mint()),
+            executor,
+            global_config: global_config_pda(),
+        },
+        instruction::WithdrawBondSol {},
+        &[executor],
+    )
+}
+
+fn slash_sol_ix(
+    executor: Pubkey,
+    order: Pubkey,
+    recipient: Pubkey,
+    authority: Pubkey,
+) -> Instruction {
+    ix(
+        accounts::SlashExecutorSol {
+            executor_bond: executor_bond_pda(&executor, &native_mint()),
+            recipient,
+            global_config: global_config_pda(),
+            authority,
+            guardian_set: guardian_set_pda(0),
+            instructions: sysvar_instructions::ID,
+            roles: None,
+        },
+        instruction::SlashExecutorSol { order },
+    )
+}
+
+fn slash_sol(
+    env: &mut Env,
+    executor: Pubkey,
+    order: Pubkey,
+    recipient: Pubkey,
+    authority: Pubkey,
+    signatures: &[(Pubkey, Vec<u8>)],
+) -> TxResult {
+    let slash = slash_sol_ix(executor, order, recipient, authority);
+    let instructions: Vec<Instruction> = if signatures.is_empty() {
+        vec![slash]
+    } else {
+        vec![ed25519_ix(signatures), slash]
+    };
+    env.svm.process(&instructions, &[authority])
+}
+
+fn non_delivery(order: Pubkey, executor: Pubkey) -> Vec<u8> {
+    signed_message(
+        NonDeliveryAttestation::DOMAIN,
+        NonDeliveryAttestation { order, executor },
+    )
+}
+
+#[test]
+fn bonded_executors_fill_up_to_their_limit() {
+    let mut env = Env::new(30);
+    let executor = env.user();
+    let bond = executor_bond_pda(&executor, &native_mint());
+    let user = env.user();
+    let order = env.open_sol(user, SOL + SOL / 2, 3600);
+
+    assert_error(
+        execute_bonded(&mut env, order, executor, None),
+        CustomError::OnlyOwnerCanExecute,
+    );
+    assert_error(
+        bond_sol(&mut env, executor, 0),
+        CustomError::InvalidParameter,
+    );
+    assert_ok(bond_sol(&mut env, executor, SOL));
+    // Bonds are only accepted once a bond multiple is configured.
+    assert_error(
+        execute_bonded(&mut env, order, executor, Some(bond)),
+        CustomError::OnlyOwnerCanExecute,
+    );
+
+    set_bond_config(&mut env, 2);
+    assert_ok(execute_bonded(&mut env, order, executor, Some(bond)));
+    let bond_account: ExecutorBond = env.svm.fetch(&bond);
+    assert_eq!(bond_account.pending.len(), 1);
+    assert_eq!(bond_account.exposure().unwrap(), SOL + SOL / 2);
+
+    let order = env.open_sol(user, SOL, 7200);
+    assert_error(
+        execute_bonded(&mut env, order, executor, Some(bond)),
+        CustomError::BondLimitExceeded,
+    );
+    // Fills stop counting once their slash window has passed.
+    env.svm.warp(1800);
+    assert_ok(execute_bonded(&mut env, order, executor, Some(bond)));
+}
+
+#[test]
+fn bonds_belong_to_their_executor_and_mint() {
+    let mut env = Env::new(30);
+    set_bond_config(&mut env, 2);
+    let executor = env.user();
+    let other = env.user();
+    assert_ok(bond_sol(&mut env, other, SOL));
+    let user = env.user();
+    let order = env.open_sol(user, SOL / 2, 3600);
+    assert_error(
+        execute_bonded(
+            &mut env,
+            order,
+            executor,
+            Some(executor_bond_pda(&other, &native_mint())),
+        ),
+        CustomError::InvalidBond,
+    );
+
+    // Lamport bonds cover SOL orders, so no token bond is kept for WSOL.
+    env.create_token_account(native_mint(), executor, SOL);
+    let bond = executor_bond_pda(&executor, &native_mint());
+    assert_error(
+        env.send(
+            accounts::BondExecutorSpl {
+                executor_bond: bond,
+                bond_token_account: get_associated_token_address(&bond, &native_mint()),
+                executor_token_account: get_associated_token_address(&executor, &native_mint()),
+                token_mint: native_mint(),
+                executor,
+                token_program: spl_token::ID,
+                system_program: system_program::ID,
+                associated_token_program: spl_associated_token_account::ID,
+            },
+            instruction::BondExecutorSpl { amount: SOL },
+            &[executor],
+        ),
+        CustomError::InvalidBond,
+    );
+
+    // A token bond does not back orders in another mint.
+    let mint = env.create_mint(6);
+    let executor_ata = env.create_token_account(mint, executor, 1_000);
+    let token_bond = executor_bond_pda(&executor, &mint);
+    assert_ok(env.send(
+        accounts::BondExecutorSpl {
+            executor_bond: token_bond,
+            bond_token_account: get_associated_token_address(&token_bond, &mint),
+            executor_token_account: executor_ata,
+            token_mint: mint,
+            executor,
+            token_program: spl_token::ID,
+            system_program: system_program::ID,
+            associated_token_program: spl_associated_token_account::ID,
+        },
+        instruction::BondExecutorSpl { amount: 1_000 },
+        &[executor],
+    ));
+    assert_error(
+        execute_bonded(&mut env, order, executor, Some(token_bond)),
+        CustomError::InvalidBond,
+    );
+}
+
+#[test]
+fn unbonding_waits_out_the_cooldown() {
+    let mut env = Env::new(30);
+    set_bond_config(&mut env, 2);
+    let executor = env.user();
+    let bond = executor_bond_pda(&executor, &native_mint());
+    assert_ok(bond_sol(&mut env, executor, 2 * SOL));
+    let user = env.user();
+    let order = env.open_sol(user, 3 * SOL, 3600);
+    assert_ok(execute_bonded(&mut env, order, executor, Some(bond)));
+
+    assert_error(
+        request_unbond(&mut env, executor, 3 * SOL),
+        CustomError::InvalidParameter,
+    );
+    // 1 SOL left would only cover 2 SOL of open fills.
+    assert_error(
+        request_unbond(&mut env, executor, SOL),
+        CustomError::BondLimitExceeded,
+    );
+    assert_error(
+        withdraw_sol(&mut env, executor),
+        CustomError::InvalidParameter,
+    );
+
+    assert_ok(request_unbond(&mut env, executor, SOL / 2));
+    assert_error(
+        withdraw_sol(&mut env, executor),
+        CustomError::UnbondCooldownActive,
+    );
+    env.svm.warp(3599);
+    assert_error(
+        withdraw_sol(&mut env, executor),
+        CustomError::UnbondCooldownActive,
+    );
+    env.svm.warp(1);
+    let before = env.svm.lamports(&executor);
+    assert_ok(withdraw_sol(&mut env, executor));
+    assert_eq!(env.svm.lamports(&executor), before + SOL / 2);
+}
+
+#[test]
+fn guardians_slash_undelivered_fills() {
+    let mut env = Env::new(30);
+    set_bond_config(&mut env, 2);
+    let keys = guardians(2);
+    assert_ok(env.init_guardian_set(keys.clone(), 2));
+    assert_ok(env.admin(instruction::SetAttestationRequired {
+        attestation_required: false,
+    }));
+
+    let executor = env.user();
+    let bond = executor_bond_pda(&executor, &native_mint());
+    assert_ok(bond_sol(&mut env, executor, SOL));
+    let user = env.user();
+    // Slashes follow the order's refund address rather than its sender.
+    let refund_address = Pubkey::new_unique();
+    let expiry = env.svm.now() + 3600;
+    let mut params = env.params(native_mint(), SOL + SOL / 2, expiry);
+    params.refund_address = refund_address;
+    assert_ok(env.open_sol_with(user, params));
+    let order = order_pda(&user, expiry);
+    let late = env.open_sol(user, SOL / 4, 7200);
+    assert_ok(execute_bonded(&mut env, order, executor, Some(bond)));
+
+    let message = non_delivery(order, executor);
+    let quorum = [(keys[0], message.clone()), (keys[1], message.clone())];
+    let stranger = env.user();
+    assert_error(
+        slash_sol(&mut env, executor, order, user, stranger, &quorum),
+        CustomError::OnlyOwnerOrGuardian,
+    );
+    let guardian = keys[0];
+    env.svm.airdrop(&guardian, SOL);
+    assert_error(
+        slash_sol(&mut env, executor, order, user, guardian, &[]),
+        CustomError::AttestationRequired,
+    );
+    assert_error(
+        slash_sol(&mut env, executor, order, user, guardian, &quorum[..1]),
+        CustomError::InsufficientAttestations,
+    );
+    // The attestation names the executor it blames.
+    let blamed_other = non_delivery(order, Pubkey::new_unique());
+    assert_error(
+        slash_sol(
+            &mut env,
+            executor,
+            order,
+            user,
+            guardian,
+            &[(keys[0], blamed_other.clone()), (keys[1], blamed_other)],
+        ),
+        CustomError::AttestationRequired,
+    );
+    assert_error(
+        slash_sol(&mut env, executor, order, user, guardian, &quorum),
+        CustomError::InvalidRefundReceiver,
+    );
+    let mut forged = ed25519_ix(&quorum);
+    forge_signature(&mut forged);
+    let slash = slash_sol_ix(executor, order, refund_address, guardian);
+    assert_invalid_signature(env.svm.process(&[forged, slash], &[guardian]));
+
+    let before = env.svm.lamports(&refund_address);
+    let owner = env.owner;
+    assert_ok(slash_sol(
+        &mut env,
+        executor,
+        order,
+        refund_address,
+        owner,
+        &quorum,
+    ));
+    // The whole bond was slashed; the rest of the fill is uncovered.
+    assert_eq!(env.svm.lamports(&refund_address), before + SOL);
+    let bond_account: ExecutorBond = env.svm.fetch(&bond);
+    assert_eq!(bond_account.amount, 0);
+    assert!(bond_account.pending.is_empty());
+    assert_error(
+        slash_sol(&mut env, executor, order, refund_address, guardian, &quorum),
+        CustomError::NotSlashable,
+    );
+
+    assert_ok(bond_sol(&mut env, executor, SOL));
+    assert_ok(execute_bonded(&mut env, late, executor, Some(bond)));
+    env.svm.warp(1800);
+    let message = non_delivery(late, executor);
+    assert_error(
+        slash_sol(
+            &mut env,
+            executor,
+            late,
+            user,
+            guardian,
+            &[(keys[0], message.clone()), (keys[1], message)],
+        ),
+        CustomError::NotSlashable,
+    );
+}
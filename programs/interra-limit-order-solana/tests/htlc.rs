@@ -0,0 +1,384 @@
+//! Hashed-timelock orders and RFQ exclusivity.
+
+mod common;
+
+use anchor_lang::solana_program::hash::hash;
+use anchor_lang::solana_program::sysvar::instructions as sysvar_instructions;
+use anchor_spl::associated_token::get_associated_token_address;
+use anchor_spl::token::spl_token;
+use common::*;
+use limit_order::{accounts, instruction, CustomError, FillQuote, HtlcClaimed, OpenOrderParams};
+
+const PREIMAGE: [u8; 32] = [42; 32];
+
+fn htlc_params(
+    env: &Env,
+    from_token: Pubkey,
+    amount_in: u64,
+    timelock_ttl: i64,
+    solver: Pubkey,
+) -> OpenOrderParams {
+    let now = env.svm.now();
+    let mut params = env.params(from_token, amount_in, now + 7200);
+    params.hashlock = hash(&PREIMAGE).to_bytes();
+    params.timelock = now + timelock_ttl;
+    params.htlc_solver = solver;
+    params
+}
+
+fn open_htlc_sol(env: &mut Env, user: Pubkey, solver: Pubkey, amount_in: u64) -> Pubkey {
+    let params = htlc_params(env, native_mint(), amount_in, 3600, solver);
+    let order = order_pda(&user, params.expiry);
+    assert_ok(env.open_sol_with(user, params));
+    order
+}
+
+fn claim_sol_accounts(env: &Env, order: Pubkey, solver: Pubkey) -> accounts::ClaimHtlcOrderSol {
+    let order_account = env.order(&order);
+    accounts::ClaimHtlcOrderSol {
+        order,
+        solver,
+        treasury: env.treasury,
+        global_config: global_config_pda(),
+        chain_config: chain_config_pda(order_account.to_chain_id),
+        refund_receiver: order_account.refund_to(),
+        order_book: None,
+    }
+}
+
+fn claim_sol(env: &mut Env, order: Pubkey, solver: Pubkey, preimage: [u8; 32]) -> TxResult {
+    let accounts = claim_sol_accounts(env, order, solver);
+    env.send(
+        accounts,
+        instruction::ClaimHtlcOrderSol { preimage },
+        &[solver],
+    )
+}
+
+fn refund_sol_accounts(env: &Env, order: Pubkey, caller: Pubkey) -> accounts::RefundHtlcOrderSol {
+    accounts::RefundHtlcOrderSol {
+        order,
+        refund_receiver: env.order(&order).refund_to(),
+        caller,
+        order_book: None,
+    }
+}
+
+fn refund_sol(env: &mut Env, order: Pubkey, caller: Pubkey) -> TxResult {
+    let accounts = refund_sol_accounts(env, order, caller);
+    env.send(accounts, instruction::RefundHtlcOrderSol {}, &[caller])
+}
+
+#[test]
+fn solvers_claim_with_the_preimage() {
+    let mut env = Env::new(30);
+    let user = env.user();
+    let solver = env.user();
+    let order = open_htlc_sol(&mut env, user, solver, SOL);
+
+    assert_error(
+        claim_sol(&mut env, order, solver, [41; 32]),
+        CustomError::InvalidPreimage,
+    );
+    // Once the preimage is public, anyone else holding it still can't claim.
+    let watcher = env.user();
+    assert_error(
+        claim_sol(&mut env, order, watcher, PREIMAGE),
+        CustomError::NotHtlcSolver,
+    );
+    // Claims move the escrow, so they stop while paused.
+    assert_ok(env.admin(instruction::SetPaused { paused: true }));
+    assert_error(
+        claim_sol(&mut env, order, solver, PREIMAGE),
+        CustomError::Paused,
+    );
+    assert_ok(env.admin(instruction::SetPaused { paused: false }));
+    let solver_before = env.svm.lamports(&solver);
+    let treasury_before = env.svm.lamports(&env.treasury);
+    assert_ok(claim_sol(&mut env, order, solver, PREIMAGE));
+
+    let fee = SOL * 30 / 10_000;
+    assert_eq!(env.svm.lamports(&solver), solver_before + SOL - fee);
+    assert_eq!(env.svm.lamports(&env.treasury), treasury_before + fee);
+    let claimed = env.svm.events::<HtlcClaimed>();
+    assert_eq!(claimed[0].preimage, PREIMAGE);
+    assert!(env.svm.account(&order).is_none());
+}
+
+#[test]
+fn senders_refund_after_the_timelock() {
+    let mut env = Env::new(30);
+    let user = env.user();
+    let solver = env.user();
+    let before = env.svm.lamports(&user);
+    let order = open_htlc_sol(&mut env, user, solver, SOL);
+
+    // Anyone may trigger the refund, but only once the timelock has passed.
+    assert_error(
+        refund_sol(&mut env, order, solver),
+        CustomError::TimelockNotExpired,
+    );
+    env.svm.warp(3600);
+    assert_error(
+        claim_sol(&mut env, order, solver, PREIMAGE),
+        CustomError::TimelockExpired,
+    );
+    assert_ok(refund_sol(&mut env, order, solver));
+    assert_eq!(env.svm.lamports(&user), before);
+}
+
+#[test]
+fn settled_htlc_orders_leave_the_order_book() {
+    let mut env = Env::new(30);
+    let book = env.init_order_book(native_mint());
+    let user = env.user();
+    let solver = env.user();
+    let open_listed = |env: &mut Env, expiry_offset: i64| {
+        let mut params = htlc_params(env, native_mint(), SOL, 3600, solver);
+        params.expiry += expiry_offset;
+        let mut accounts = env.open_sol_accounts(user, &params);
+        accounts.order_book = Some(book);
+        let order = accounts.order;
+        assert_ok(env.send(accounts, instruction::OpenOrderSol { params }, &[user]));
+        order
+    };
+    let claimed = open_listed(&mut env, 0);
+    let refunded = open_listed(&mut env, 1);
+    assert_eq!(env.listed_orders(&book), [claimed, refunded]);
+
+    assert_error(
+        claim_sol(&mut env, claimed, solver, PREIMAGE),
+        CustomError::OrderBookRequired,
+    );
+    let mut accounts = claim_sol_accounts(&env, claimed, solver);
+    accounts.order_book = Some(book);
+    assert_ok(env.send(
+        accounts,
+        instruction::ClaimHtlcOrderSol { preimage: PREIMAGE },
+        &[solver],
+    ));
+    assert_eq!(env.listed_orders(&book), [refunded]);
+
+    env.svm.warp(3600);
+    assert_error(
+        refund_sol(&mut env, refunded, solver),
+        CustomError::OrderBookRequired,
+    );
+    let mut accounts = refund_sol_accounts(&env, refunded, solver);
+    accounts.order_book = Some(book);
+    assert_ok(env.send(accounts, instruction::RefundHtlcOrderSol {}, &[solver]));
+    assert!(env.listed_orders(&book).is_empty());
+}
+
+#[test]
+fn htlc_and_regular_orders_use_their_own_instructions() {
+    let mut env = Env::new(30);
+    let user = env.user();
+    let solver = env.user();
+    let htlc = open_htlc_sol(&mut env, user, solver, SOL);
+    let regular = env.open_sol(user, SOL, 3600);
+
+    let target = Pubkey::new_unique();
+    assert_error(env.execute_sol(htlc, target), CustomError::HtlcOrder);
+    assert_error(env.cancel_sol(htlc, user), CustomError::HtlcOrder);
+    assert_error(
+        env.send(
+            accounts::RequestCancel {
+                order: htlc,
+                user,
+                global_config: global_config_pda(),
+                session_key: None,
+            },
+            instruction::RequestCancel {},
+            &[user],
+        ),
+        CustomError::HtlcOrder,
+    );
+    assert_error(
+        claim_sol(&mut env, regular, solver, PREIMAGE),
+        CustomError::NotHtlcOrder,
+    );
+    assert_error(
+        refund_sol(&mut env, regular, solver),
+        CustomError::NotHtlcOrder,
+    );
+}
+
+#[test]
+fn spl_htlc_orders() {
+    let mut env = Env::new(30);
+    let user = env.user();
+    let solver = env.user();
+    let mint = env.create_mint(6);
+    let user_ata = env.create_token_account(mint, user, 20_000);
+    let treasury_ata = env.create_token_account(mint, env.treasury, 0);
+    let solver_ata = env.create_token_account(mint, solver, 0);
+
+    let params = htlc_params(&env, mint, 10_000, 3600, solver);
+    let claimed = order_pda(&user, params.expiry);
+    assert_ok(env.open_spl_with(user, params));
+    env.svm.warp(1);
+    let params = htlc_params(&env, mint, 10_000, 3600, solver);
+    let refunded = order_pda(&user, params.expiry);
+    assert_ok(env.open_spl_with(user, params));
+
+    let claim = |env: &mut Env, solver: Pubkey, solver_token_account| {
+        env.send(
+            accounts::ClaimHtlcOrderSpl {
+                order: claimed,
+                order_token_account: get_associated_token_address(&claimed, &mint),
+                solver_token_account,
+                treasury_token_account: treasury_ata,
+                global_config: global_config_pda(),
+                chain_config: chain_config_pda(DEST_CHAIN),
+                refund_receiver: user,
+                solver,
+                token_program: spl_token::ID,
+                order_book: None,
+            },
+            instruction::ClaimHtlcOrderSpl { preimage: PREIMAGE },
+            &[solver],
+        )
+    };
+    // Proceeds only go to the solver's own account.
+    let other_ata = env.create_token_account(mint, Pubkey::new_unique(), 0);
+    assert_error(
+        claim(&mut env, solver, other_ata),
+        anchor_lang::error::ErrorCode::ConstraintRaw,
+    );
+    let watcher = env.user();
+    let watcher_ata = env.create_token_account(mint, watcher, 0);
+    assert_error(
+        claim(&mut env, watcher, watcher_ata),
+        CustomError::NotHtlcSolver,
+    );
+    assert_ok(claim(&mut env, solver, solver_ata));
+    assert_eq!(env.token_balance(&solver_ata), 10_000 - 30);
+    assert_eq!(env.token_balance(&treasury_ata), 30);
+
+    env.svm.warp(3600);
+    let refund = accounts::RefundHtlcOrderSpl {
+        order: refunded,
+        user_token_account: user_ata,
+        order_token_account: get_associated_token_address(&refunded, &mint),
+        refund_receiver: user,
+        caller: solver,
+        token_program: spl_token::ID,
+        order_book: None,
+    };
+    assert_ok(env.send(refund, instruction::RefundHtlcOrderSpl {}, &[solver]));
+    assert_eq!(env.token_balance(&user_ata), 10_000);
+}
+
+fn quote(user: Pubkey, params: &OpenOrderParams) -> Vec<u8> {
+    signed_message(
+        FillQuote::DOMAIN,
+        FillQuote {
+            sender: user,
+            expiry: params.expiry,
+            from_token: params.from_token,
+            amount_in: params.amount_in,
+            to_chain_id: params.to_chain_id,
+            to_token: params.to_token,
+            recipient: params.recipient,
+            amount_out: params.amount_out,
+            exclusivity_deadline: params.exclusivity_deadline,
+        },
+    )
+}
+
+fn rfq_params(env: &Env, solver: Pubkey) -> OpenOrderParams {
+    let now = env.svm.now();
+    let mut params = env.params(native_mint(), SOL, now + 3600);
+    params.exclusive_executor = solver;
+    params.exclusivity_deadline = now + 600;
+    params
+}
+
+fn open_rfq(
+    env: &mut Env,
+    user: Pubkey,
+    params: OpenOrderParams,
+    quote: Option<(Pubkey, Vec<u8>)>,
+) -> TxResult {
+    let mut accounts = env.open_sol_accounts(user, &params);
+    accounts.instructions = Some(sysvar_instructions::ID);
+    let open = ix(accounts, instruction::OpenOrderSol { params });
+    match quote {
+        Some(signature) => env.svm.process(&[ed25519_ix(&[signature]), open], &[user]),
+        None => env.svm.process(&[open], &[user]),
+    }
+}
+
+#[test]
+fn rfq_orders_need_the_solver_quote() {
+    let mut env = Env::new(30);
+    let user = env.user();
+    let solver = env.owner;
+    let params = rfq_params(&env, solver);
+    let signed = quote(user, &params);
+
+    let unquoted = rfq_params(&env, solver);
+    assert_error(
+        open_rfq(&mut env, user, unquoted, None),
+        CustomError::InvalidQuote,
+    );
+    let other_signer = (new_signer(), signed.clone());
+    let same = rfq_params(&env, solver);
+    assert_error(
+        open_rfq(&mut env, user, same, Some(other_signer)),
+        CustomError::InvalidQuote,
+    );
+    let mut worse = rfq_params(&env, solver);
+    worse.amount_out = [0; 32];
+    assert_error(
+        open_rfq(&mut env, user, worse, Some((solver, signed.clone()))),
+        CustomError::InvalidQuote,
+    );
+    let params_without_sysvar = rfq_params(&env, solver);
+    assert_error(
+        env.open_sol_with(user, params_without_sysvar),
+        CustomError::InvalidQuote,
+    );
+    let mut forged = ed25519_ix(&[(solver, signed.clone())]);
+    forge_signature(&mut forged);
+    let same = rfq_params(&env, solver);
+    let mut accounts = env.open_sol_accounts(user, &same);
+    accounts.instructions = Some(sysvar_instructions::ID);
+    let open = ix(accounts, instruction::OpenOrderSol { params: same });
+    assert_invalid_signature(env.svm.process(&[forged, open], &[user]));
+    assert_ok(open_rfq(&mut env, user, params, Some((solver, signed))));
+}
+
+#[test]
+fn only_the_quoting_solver_fills_before_the_deadline() {
+    let mut env = Env::new(30);
+    let executor = env.user();
+    assert_ok(env.init_roles());
+    assert_ok(env.grant_role(executor, limit_order::Role::Executor));
+
+    let user = env.user();
+    let solver = env.owner;
+    let params = rfq_params(&env, solver);
+    let signed = quote(user, &params);
+    let order = order_pda(&user, params.expiry);
+    assert_ok(open_rfq(&mut env, user, params, Some((solver, signed))));
+
+    let execute_as = |env: &mut Env, executor: Pubkey| {
+        let mut accounts = env.execute_sol_accounts(order, executor, Pubkey::new_unique());
+        accounts.roles = Some(roles_pda());
+        env.send(
+            accounts,
+            instruction::ExecuteOrderSol {
+                native_token_volume: 0,
+            },
+            &[executor],
+        )
+    };
+    assert_error(
+        execute_as(&mut env, executor),
+        CustomError::ExclusivityActive,
+    );
+    env.svm.warp(600);
+    assert_ok(execute_as(&mut env, executor));
+}
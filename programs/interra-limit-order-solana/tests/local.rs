@@ -0,0 +1,449 @@
+//! Same-chain orders, settled by paying the recipient on Solana.
+
+mod common;
+
+use anchor_lang::error::ErrorCode;
+use anchor_lang::prelude::Rent;
+use anchor_lang::solana_program::program_pack::Pack;
+use anchor_lang::system_program;
+use anchor_spl::associated_token::{get_associated_token_address, spl_associated_token_account};
+use anchor_spl::token::spl_token;
+use common::*;
+use limit_order::{
+    accounts, instruction, AddressFormat, ChainConfigParams, CustomError, LocalOrderFilled,
+    OpenOrderParams, OrderBook, OrderExecuted, TokenRouteParams,
+};
+
+/// `amount` as the big-endian 256-bit `amount_out` of an order.
+fn u256(amount: u64) -> [u8; 32] {
+    let mut bytes = [0; 32];
+    bytes[24..].copy_from_slice(&amount.to_be_bytes());
+    bytes
+}
+
+type ParamsEdit = Box<dyn FnOnce(&mut OpenOrderParams)>;
+
+fn ata_rent() -> u64 {
+    Rent::default().minimum_balance(spl_token::state::Account::LEN)
+}
+
+/// Enables same-chain orders and the market from `from_token` into `output`.
+fn enable_local(env: &mut Env, from_token: Pubkey, output: Pubkey) {
+    assert_ok(env.set_chain_config(
+        CHAIN_ID,
+        ChainConfigParams {
+            enabled: true,
+            address_format: AddressFormat::Bytes32,
+            min_amount_in: 0,
+            max_amount_in: 0,
+            platform_fee_override: None,
+        },
+    ));
+    set_mint_route(env, from_token, CHAIN_ID, output);
+}
+
+fn set_mint_route(env: &mut Env, from_token: Pubkey, to_chain_id: u64, output: Pubkey) {
+    let owner = env.owner;
+    let to_token = output.to_bytes();
+    assert_ok(env.send(
+        accounts::SetTokenRoute {
+            token_route: token_route_pda(&from_token, to_chain_id, &to_token),
+            global_config: global_config_pda(),
+            authority: owner,
+            system_program: system_program::ID,
+            roles: None,
+            proposal: None,
+        },
+        instruction::SetTokenRoute {
+            from_token,
+            to_chain_id,
+            to_token,
+            params: TokenRouteParams {
+                enabled: true,
+                from_decimals: 9,
+                to_decimals: 6,
+                min_amount_in: 0,
+                max_amount_in: 0,
+            },
+        },
+        &[owner],
+    ));
+}
+
+/// An order selling `amount_in` of `from_token` for `amount_out` of `output`,
+/// paid to `recipient`.
+fn local_params(
+    env: &Env,
+    from_token: Pubkey,
+    amount_in: u64,
+    output: Pubkey,
+    amount_out: u64,
+    recipient: Pubkey,
+) -> OpenOrderParams {
+    let mut params = env.params(from_token, amount_in, env.svm.now() + 3600);
+    params.to_chain_id = CHAIN_ID;
+    params.to_token = output.to_bytes();
+    params.recipient = recipient.to_bytes();
+    params.amount_out = u256(amount_out);
+    params
+}
+
+fn fill_accounts(env: &Env, order: Pubkey, taker: Pubkey) -> accounts::FillLocalOrder {
+    let order_account = env.order(&order);
+    let from_token = order_account.from_token;
+    let output_mint = Pubkey::new_from_array(order_account.to_token);
+    let recipient = Pubkey::new_from_array(order_account.recipient);
+    let token_escrow = order_account.uses_token_escrow();
+    accounts::FillLocalOrder {
+        order,
+        order_token_account: token_escrow
+            .then(|| get_associated_token_address(&order, &from_token)),
+        taker_token_account: token_escrow
+            .then(|| get_associated_token_address(&taker, &from_token)),
+        output_mint,
+        taker_output_account: get_associated_token_address(&taker, &output_mint),
+        recipient,
+        recipient_token_account: get_associated_token_address(&recipient, &output_mint),
+        treasury: env.treasury,
+        treasury_token_account: token_escrow
+            .then(|| get_associated_token_address(&env.treasury, &from_token)),
+        global_config: global_config_pda(),
+        chain_config: chain_config_pda(order_account.to_chain_id),
+        refund_receiver: order_account.refund_to(),
+        taker,
+        token_program: spl_token::ID,
+        associated_token_program: spl_associated_token_account::ID,
+        system_program: system_program::ID,
+        order_book: None,
+        roles: None,
+    }
+}
+
+fn fill(env: &mut Env, order: Pubkey, taker: Pubkey) -> TxResult {
+    let accounts = fill_accounts(env, order, taker);
+    env.send(accounts, instruction::FillLocalOrder {}, &[taker])
+}
+
+fn take(env: &mut Env, order: Pubkey, taker: Pubkey, amount_in: u64) -> TxResult {
+    let accounts = fill_accounts(env, order, taker);
+    env.send(accounts, instruction::TakeOrder { amount_in }, &[taker])
+}
+
+#[test]
+fn fills_a_local_sol_order() {
+    let mut env = Env::new(30);
+    let usdc = env.create_mint(6);
+    enable_local(&mut env, native_mint(), usdc);
+    let user = env.user();
+    let owner = env.owner;
+    env.create_token_account(usdc, owner, 500_000_000);
+
+    let params = local_params(&env, native_mint(), SOL, usdc, 150_000_000, user);
+    let order = order_pda(&user, params.expiry);
+    assert_ok(env.open_sol_with(user, params));
+    let order_rent = env.svm.lamports(&order) - SOL;
+    let user_before = env.svm.lamports(&user);
+    let owner_before = env.svm.lamports(&owner);
+    let treasury_before = env.svm.lamports(&env.treasury);
+
+    assert_ok(fill(&mut env, order, owner));
+    let fee = SOL * 30 / 10_000;
+    let user_usdc = get_associated_token_address(&user, &usdc);
+    assert_eq!(env.token_balance(&user_usdc), 150_000_000);
+    assert_eq!(
+        env.token_balance(&get_associated_token_address(&owner, &usdc)),
+        350_000_000
+    );
+    assert_eq!(
+        env.svm.lamports(&owner),
+        owner_before + SOL - fee - ata_rent()
+    );
+    assert_eq!(env.svm.lamports(&env.treasury), treasury_before + fee);
+    assert_eq!(env.svm.lamports(&user), user_before + order_rent);
+    assert!(env.svm.account(&order).is_none());
+
+    let events = env.svm.events::<LocalOrderFilled>();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].order_pubkey, order);
+    assert_eq!(events[0].taker, owner);
+    assert_eq!(events[0].amount_in, SOL);
+    assert_eq!(events[0].amount_out, 150_000_000);
+    assert_eq!(events[0].fee_amount, fee);
+}
+
+#[test]
+fn fills_a_local_spl_order() {
+    let mut env = Env::new(30);
+    let mint = env.create_mint(9);
+    let usdc = env.create_mint(6);
+    enable_local(&mut env, mint, usdc);
+    let user = env.user();
+    let owner = env.owner;
+    env.create_token_account(mint, user, 2_000_000);
+    let treasury_ata = env.create_token_account(mint, env.treasury, 0);
+    let taker_ata = env.create_token_account(mint, owner, 0);
+    env.create_token_account(usdc, owner, 500);
+    // The recipient may be any wallet, and may already hold the output.
+    let recipient = Pubkey::new_unique();
+    let recipient_usdc = env.create_token_account(usdc, recipient, 7);
+
+    let params = local_params(&env, mint, 1_000_000, usdc, 300, recipient);
+    let order = order_pda(&user, params.expiry);
+    assert_ok(env.open_spl_with(user, params));
+
+    assert_ok(fill(&mut env, order, owner));
+    assert_eq!(env.token_balance(&recipient_usdc), 307);
+    assert_eq!(env.token_balance(&taker_ata), 1_000_000 - 3_000);
+    assert_eq!(env.token_balance(&treasury_ata), 3_000);
+    assert!(env.svm.account(&order).is_none());
+    assert!(env
+        .svm
+        .account(&get_associated_token_address(&order, &mint))
+        .is_none());
+}
+
+#[test]
+fn checks_local_orders_when_opened() {
+    let mut env = Env::new(30);
+    let usdc = env.create_mint(6);
+    enable_local(&mut env, native_mint(), usdc);
+    let user = env.user();
+
+    let now = env.svm.now();
+    let cases: Vec<ParamsEdit> = vec![
+        // `amount_out` has to be a non-zero token amount.
+        Box::new(|params| params.amount_out = [1; 32]),
+        Box::new(|params| params.amount_out = [0; 32]),
+        // Local orders settle atomically, so they can't be HTLCs.
+        Box::new(move |params| {
+            params.hashlock = [3; 32];
+            params.timelock = now + 600;
+            params.htlc_solver = Pubkey::new_unique();
+        }),
+    ];
+    for edit in cases {
+        let mut params = local_params(&env, native_mint(), SOL, usdc, 100, user);
+        edit(&mut params);
+        assert_error(
+            env.open_sol_with(user, params),
+            CustomError::InvalidParameter,
+        );
+    }
+
+    // Selling a mint for itself isn't an order.
+    let mint = env.create_mint(6);
+    enable_local(&mut env, mint, mint);
+    env.create_token_account(mint, user, 1_000);
+    let params = local_params(&env, mint, 1_000, mint, 1_000, user);
+    assert_error(
+        env.open_spl_with(user, params),
+        CustomError::InvalidParameter,
+    );
+}
+
+#[test]
+fn local_orders_only_settle_on_chain() {
+    let mut env = Env::new(30);
+    let usdc = env.create_mint(6);
+    enable_local(&mut env, native_mint(), usdc);
+    let user = env.user();
+    let owner = env.owner;
+    env.create_token_account(usdc, owner, 100);
+
+    let params = local_params(&env, native_mint(), SOL, usdc, 150, user);
+    let order = order_pda(&user, params.expiry);
+    assert_ok(env.open_sol_with(user, params));
+
+    // Cross-chain executions would release the escrow without paying out.
+    assert_error(
+        env.execute_sol(order, Pubkey::new_unique()),
+        CustomError::LocalOrder,
+    );
+    // Nor can a cross-chain order be filled here, even into a Solana mint.
+    set_mint_route(&mut env, native_mint(), DEST_CHAIN, usdc);
+    let mut params = local_params(&env, native_mint(), SOL, usdc, 150, user);
+    params.to_chain_id = DEST_CHAIN;
+    params.expiry += 1;
+    let cross_chain = order_pda(&user, params.expiry);
+    assert_ok(env.open_sol_with(user, params));
+    assert_error(
+        fill(&mut env, cross_chain, owner),
+        CustomError::NotLocalOrder,
+    );
+
+    // The output goes to the order's recipient, in the order's mint.
+    let mut accounts = fill_accounts(&env, order, owner);
+    let stranger = Pubkey::new_unique();
+    accounts.recipient = stranger;
+    accounts.recipient_token_account = get_associated_token_address(&stranger, &usdc);
+    assert_error(
+        env.send(accounts, instruction::FillLocalOrder {}, &[owner]),
+        ErrorCode::ConstraintAddress,
+    );
+    let other = env.create_mint(6);
+    let mut accounts = fill_accounts(&env, order, owner);
+    accounts.output_mint = other;
+    accounts.taker_output_account = env.create_token_account(other, owner, 1_000);
+    accounts.recipient_token_account = get_associated_token_address(&user, &other);
+    assert_error(
+        env.send(accounts, instruction::FillLocalOrder {}, &[owner]),
+        ErrorCode::ConstraintAddress,
+    );
+
+    // Takers need the Executor role and the full output.
+    let taker = env.user();
+    env.create_token_account(usdc, taker, 1_000);
+    assert_error(
+        fill(&mut env, order, taker),
+        CustomError::OnlyOwnerCanExecute,
+    );
+    assert_error(
+        fill(&mut env, order, owner),
+        spl_token::error::TokenError::InsufficientFunds as u32,
+    );
+}
+
+#[test]
+fn anyone_takes_local_orders_in_parts() {
+    let mut env = Env::new(30);
+    let usdc = env.create_mint(6);
+    enable_local(&mut env, native_mint(), usdc);
+    let user = env.user();
+    let params = local_params(&env, native_mint(), SOL, usdc, 1_000, user);
+    let order = order_pda(&user, params.expiry);
+    assert_ok(env.open_sol_with(user, params));
+    let user_before = env.svm.lamports(&user);
+    let order_rent = env.svm.lamports(&order) - SOL;
+
+    // A third of the escrow costs a third of the output, rounded up.
+    let first = env.user();
+    env.create_token_account(usdc, first, 1_000);
+    let first_before = env.svm.lamports(&first);
+    assert_ok(take(&mut env, order, first, SOL / 3));
+    let fee = SOL / 3 * 30 / 10_000;
+    assert_eq!(
+        env.svm.lamports(&first),
+        first_before + SOL / 3 - fee - ata_rent()
+    );
+    let user_usdc = get_associated_token_address(&user, &usdc);
+    assert_eq!(env.token_balance(&user_usdc), 334);
+    let order_account = env.order(&order);
+    assert_eq!(order_account.amount_in, SOL - SOL / 3);
+    assert_eq!(order_account.amount_out, u256(666));
+    assert_eq!(env.svm.lamports(&order), order_rent + SOL - SOL / 3);
+    let events = env.svm.events::<LocalOrderFilled>();
+    assert_eq!(events[0].amount_out, 334);
+    assert_eq!(events[0].remaining_amount_in, SOL - SOL / 3);
+    assert!(env.svm.events::<OrderExecuted>().is_empty());
+
+    let second = env.user();
+    env.create_token_account(usdc, second, 1_000);
+    assert_ok(take(&mut env, order, second, SOL - SOL / 3));
+    assert_eq!(env.token_balance(&user_usdc), 1_000);
+    assert!(env.svm.account(&order).is_none());
+    assert_eq!(env.svm.lamports(&user), user_before + order_rent);
+
+    let events = env.svm.events::<LocalOrderFilled>();
+    assert_eq!(
+        (events[0].taker, events[0].remaining_amount_in),
+        (second, 0)
+    );
+    assert_eq!(env.svm.events::<OrderExecuted>().len(), 1);
+}
+
+#[test]
+fn takes_stay_within_the_order() {
+    let mut env = Env::new(30);
+    let usdc = env.create_mint(6);
+    enable_local(&mut env, native_mint(), usdc);
+    let user = env.user();
+    let taker = env.user();
+    env.create_token_account(usdc, taker, 1_000);
+
+    let params = local_params(&env, native_mint(), SOL, usdc, 1, user);
+    let order = order_pda(&user, params.expiry);
+    assert_ok(env.open_sol_with(user, params));
+    for amount_in in [0, SOL + 1] {
+        assert_error(
+            take(&mut env, order, taker, amount_in),
+            CustomError::InvalidParameter,
+        );
+    }
+    // Paying the whole output for part of the escrow would leave the rest
+    // free to take.
+    assert_error(
+        take(&mut env, order, taker, SOL / 2),
+        CustomError::InvalidParameter,
+    );
+
+    env.svm.warp(3600);
+    assert_error(
+        take(&mut env, order, taker, SOL),
+        CustomError::ExpiryEarlier,
+    );
+}
+
+#[test]
+fn partial_takes_keep_the_order_book_in_sync() {
+    let mut env = Env::new(30);
+    let usdc = env.create_mint(6);
+    enable_local(&mut env, native_mint(), usdc);
+    let owner = env.owner;
+    let book = order_book_pda(&native_mint(), CHAIN_ID);
+    assert_ok(env.send(
+        accounts::InitOrderBook {
+            order_book: book,
+            global_config: global_config_pda(),
+            authority: owner,
+            system_program: system_program::ID,
+            roles: None,
+            proposal: None,
+        },
+        instruction::InitOrderBook {
+            from_token: native_mint(),
+            to_chain_id: CHAIN_ID,
+        },
+        &[owner],
+    ));
+    let listed = |env: &Env| {
+        let account = env.svm.account(&book).unwrap();
+        let book: &OrderBook = bytemuck::from_bytes(&account.data[8..]);
+        book.active()
+            .iter()
+            .map(|slot| (slot.order, slot.amount_in))
+            .collect::<Vec<_>>()
+    };
+
+    let user = env.user();
+    let params = local_params(&env, native_mint(), SOL, usdc, 1_000, user);
+    let order = order_pda(&user, params.expiry);
+    let mut accounts = env.open_sol_accounts(user, &params);
+    accounts.order_book = Some(book);
+    assert_ok(env.send(accounts, instruction::OpenOrderSol { params }, &[user]));
+
+    let taker = env.user();
+    env.create_token_account(usdc, taker, 1_000);
+    assert_error(
+        take(&mut env, order, taker, SOL / 4),
+        CustomError::OrderBookRequired,
+    );
+    let mut accounts = fill_accounts(&env, order, taker);
+    accounts.order_book = Some(book);
+    assert_ok(env.send(
+        accounts,
+        instruction::TakeOrder { amount_in: SOL / 4 },
+        &[taker],
+    ));
+    assert_eq!(listed(&env), [(order, SOL - SOL / 4)]);
+
+    let mut accounts = fill_accounts(&env, order, taker);
+    accounts.order_book = Some(book);
+    assert_ok(env.send(
+        accounts,
+        instruction::TakeOrder {
+            amount_in: SOL - SOL / 4,
+        },
+        &[taker],
+    ));
+    assert!(listed(&env).is_empty());
+}
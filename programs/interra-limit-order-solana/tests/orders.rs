@@ -0,0 +1,1015 @@
+//! Order lifecycles, fee math and expiry.
+
+mod common;
+
+use anchor_lang::error::ErrorCode;
+use anchor_lang::prelude::Rent;
+use anchor_lang::solana_program::instruction::AccountMeta;
+use anchor_lang::solana_program::program_pack::Pack;
+use anchor_lang::Space;
+use anchor_spl::associated_token::get_associated_token_address;
+use anchor_spl::token::spl_token;
+use common::*;
+use limit_order::{
+    accounts, instruction, AddressFormat, BatchOrderResult, CancelReason, ChainConfigParams,
+    CustomError, LimitOrder, OrderBook, OrderCancelled, OrderLifetimeUpdated,
+};
+
+fn rent(len: usize) -> u64 {
+    Rent::default().minimum_balance(len)
+}
+
+#[test]
+fn opens_and_executes_a_sol_order() {
+    let mut env = Env::new(30);
+    let user = env.user();
+    let treasury_before = env.svm.lamports(&env.treasury);
+    let order = env.open_sol(user, SOL, 3600);
+    let order_rent = env.svm.lamports(&order) - SOL;
+    let user_after_open = env.svm.lamports(&user);
+
+    let target = Pubkey::new_unique();
+    assert_ok(env.execute_sol(order, target));
+
+    let fee = SOL * 30 / 10_000;
+    assert_eq!(env.svm.lamports(&target), SOL - fee);
+    assert_eq!(env.svm.lamports(&env.treasury), treasury_before + fee);
+    assert_eq!(env.svm.lamports(&user), user_after_open + order_rent);
+    assert!(env.svm.account(&order).is_none());
+}
+
+#[test]
+fn opens_and_executes_an_spl_order() {
+    let mut env = Env::new(30);
+    let user = env.user();
+    let mint = env.create_mint(6);
+    let user_ata = env.create_token_account(mint, user, 5_000_000);
+    let treasury_ata = env.create_token_account(mint, env.treasury, 0);
+    let target = env.create_token_account(mint, Pubkey::new_unique(), 0);
+
+    let order = env.open_spl(user, mint, 2_000_000, 3600);
+    let escrow = get_associated_token_address(&order, &mint);
+    assert_eq!(env.token_balance(&user_ata), 3_000_000);
+    assert_eq!(env.token_balance(&escrow), 2_000_000);
+
+    assert_ok(env.execute_spl(order, target));
+    assert_eq!(env.token_balance(&target), 2_000_000 - 6_000);
+    assert_eq!(env.token_balance(&treasury_ata), 6_000);
+    assert!(env.svm.account(&order).is_none());
+    assert!(env.svm.account(&escrow).is_none());
+}
+
+#[test]
+fn opens_and_executes_a_wsol_order() {
+    let mut env = Env::new(50);
+    let user = env.user();
+    let treasury_ata = env.create_token_account(native_mint(), env.treasury, 0);
+    let order = env.open_wsol(user, SOL, 3600);
+    let escrow = get_associated_token_address(&order, &native_mint());
+    assert_eq!(env.token_balance(&escrow), SOL);
+
+    let target = Pubkey::new_unique();
+    let owner = env.owner;
+    let accounts = env.execute_wsol_accounts(order, owner, target);
+    assert_ok(env.send(
+        accounts,
+        instruction::ExecuteOrderWsol {
+            native_token_volume: 0,
+        },
+        &[owner],
+    ));
+
+    let fee = SOL * 50 / 10_000;
+    assert_eq!(env.svm.lamports(&target), SOL - fee);
+    assert_eq!(env.token_balance(&treasury_ata), fee);
+    assert!(env.svm.account(&order).is_none());
+    assert!(env.svm.account(&escrow).is_none());
+}
+
+#[test]
+fn cancelling_returns_every_lamport_and_token() {
+    let mut env = Env::new(30);
+    let user = env.user();
+    let mint = env.create_mint(6);
+    let user_ata = env.create_token_account(mint, user, 1_000);
+    let before = env.svm.lamports(&user);
+
+    let sol_order = env.open_sol(user, SOL, 3600);
+    let spl_order = env.open_spl(user, mint, 1_000, 7200);
+    let wsol_order = env.open_wsol(user, SOL, 10_800);
+    assert_eq!(env.token_balance(&user_ata), 0);
+
+    assert_ok(env.cancel_sol(sol_order, user));
+    assert_ok(env.cancel_spl(spl_order, user));
+    let accounts = env.cancel_wsol_accounts(wsol_order, user);
+    assert_ok(env.send(accounts, instruction::CancelOrderWsol {}, &[user]));
+
+    assert_eq!(env.svm.lamports(&user), before);
+    assert_eq!(env.token_balance(&user_ata), 1_000);
+    let reasons: Vec<CancelReason> = env
+        .svm
+        .events::<OrderCancelled>()
+        .into_iter()
+        .map(|event| event.reason)
+        .collect();
+    assert!(reasons == [CancelReason::Sender]);
+}
+
+#[test]
+fn payers_cover_the_rent_of_orders_they_open() {
+    let mut env = Env::new(30);
+    let user = env.user();
+    let payer = env.user();
+    let mint = env.create_mint(6);
+    env.create_token_account(mint, user, 1_000);
+    let user_before = env.svm.lamports(&user);
+    let payer_before = env.svm.lamports(&payer);
+
+    let expiry = env.svm.now() + 3600;
+    let params = env.params(native_mint(), SOL, expiry);
+    let mut accounts = env.open_sol_accounts(user, &params);
+    accounts.payer = payer;
+    assert_ok(env.send(
+        accounts,
+        instruction::OpenOrderSol { params },
+        &[user, payer],
+    ));
+    let params = env.params(mint, 1_000, expiry + 1);
+    let mut accounts = env.open_spl_accounts(user, &params);
+    accounts.payer = payer;
+    assert_ok(env.send(
+        accounts,
+        instruction::OpenOrderSpl { params },
+        &[user, payer],
+    ));
+
+    let order_rent = rent(8 + LimitOrder::INIT_SPACE);
+    let escrow_rent = rent(spl_token::state::Account::LEN);
+    assert_eq!(env.svm.lamports(&user), user_before - SOL);
+    assert_eq!(
+        env.svm.lamports(&payer),
+        payer_before - 2 * order_rent - escrow_rent
+    );
+    assert_eq!(env.order(&order_pda(&user, expiry)).sender, user);
+}
+
+#[test]
+fn refunds_go_to_the_refund_address() {
+    let mut env = Env::new(30);
+    let user = env.user();
+    let refund_address = Pubkey::new_unique();
+    let mint = env.create_mint(6);
+    env.create_token_account(mint, user, 1_000);
+    let refund_ata = env.create_token_account(mint, refund_address, 0);
+
+    let expiry = env.svm.now() + 3600;
+    let mut params = env.params(native_mint(), SOL, expiry);
+    params.refund_address = refund_address;
+    assert_ok(env.open_sol_with(user, params));
+    let sol_order = order_pda(&user, expiry);
+    assert_eq!(env.order(&sol_order).refund_address, refund_address);
+    let mut params = env.params(mint, 1_000, expiry + 1);
+    params.refund_address = refund_address;
+    assert_ok(env.open_spl_with(user, params));
+    let spl_order = order_pda(&user, expiry + 1);
+
+    // The sender still cancels, but can't take the refund itself.
+    let mut accounts = env.cancel_sol_accounts(sol_order, user);
+    accounts.refund_receiver = user;
+    assert_error(
+        env.send(accounts, instruction::CancelOrderSol {}, &[user]),
+        CustomError::InvalidRefundReceiver,
+    );
+    let order_rent = env.svm.lamports(&sol_order) - SOL;
+    assert_ok(env.cancel_sol(sol_order, user));
+    assert_eq!(env.svm.lamports(&refund_address), SOL + order_rent);
+
+    // Reclaiming an expired order refunds there too.
+    env.svm.warp(3601);
+    let refund_before = env.svm.lamports(&refund_address);
+    let spl_rent = env.svm.lamports(&spl_order)
+        + env
+            .svm
+            .lamports(&get_associated_token_address(&spl_order, &mint));
+    assert_ok(env.cancel_spl(spl_order, user));
+    assert_eq!(env.token_balance(&refund_ata), 1_000);
+    assert_eq!(env.svm.lamports(&refund_address), refund_before + spl_rent);
+
+    // Executions return the order rent to it.
+    let expiry = env.svm.now() + 3600;
+    let mut params = env.params(native_mint(), SOL, expiry);
+    params.refund_address = refund_address;
+    assert_ok(env.open_sol_with(user, params));
+    let order = order_pda(&user, expiry);
+    let refund_before = env.svm.lamports(&refund_address);
+    let order_rent = env.svm.lamports(&order) - SOL;
+    let target = Pubkey::new_unique();
+    let owner = env.owner;
+    let mut accounts = env.execute_sol_accounts(order, owner, target);
+    accounts.refund_receiver = user;
+    assert_error(
+        env.send(
+            accounts,
+            instruction::ExecuteOrderSol {
+                native_token_volume: 0,
+            },
+            &[owner],
+        ),
+        CustomError::InvalidRefundReceiver,
+    );
+    assert_ok(env.execute_sol(order, target));
+    assert_eq!(
+        env.svm.lamports(&refund_address),
+        refund_before + order_rent
+    );
+}
+
+#[test]
+fn fee_rounds_down() {
+    let mut env = Env::new(30);
+    let user = env.user();
+    let treasury_before = env.svm.lamports(&env.treasury);
+
+    // 10_001 * 30 / 10_000 = 30.003
+    let order = env.open_sol(user, 10_001, 3600);
+    let target = Pubkey::new_unique();
+    env.svm.airdrop(&target, SOL);
+    assert_ok(env.execute_sol(order, target));
+    assert_eq!(env.svm.lamports(&target), SOL + 10_001 - 30);
+    assert_eq!(env.svm.lamports(&env.treasury), treasury_before + 30);
+}
+
+#[test]
+fn dust_orders_pay_no_fee() {
+    let mut env = Env::new(30);
+    let user = env.user();
+    let mint = env.create_mint(0);
+    env.create_token_account(mint, user, 1_000);
+    let treasury_ata = env.create_token_account(mint, env.treasury, 0);
+    let target = env.create_token_account(mint, Pubkey::new_unique(), 0);
+
+    // 333 * 30 < 10_000, so the whole amount is delivered.
+    let order = env.open_spl(user, mint, 333, 3600);
+    assert_ok(env.execute_spl(order, target));
+    assert_eq!(env.token_balance(&target), 333);
+    assert_eq!(env.token_balance(&treasury_ata), 0);
+
+    let order = env.open_spl(user, mint, 1, 7200);
+    assert_ok(env.execute_spl(order, target));
+    assert_eq!(env.token_balance(&target), 334);
+}
+
+#[test]
+fn fees_can_round_up() {
+    let mut env = Env::new(30);
+    let stranger = env.user();
+    let accounts = env.update_config(stranger);
+    assert_error(
+        env.send(
+            accounts,
+            instruction::SetFeeRounding {
+                round_fees_up: true,
+            },
+            &[stranger],
+        ),
+        CustomError::MissingRole,
+    );
+    assert_ok(env.admin(instruction::SetFeeRounding {
+        round_fees_up: true,
+    }));
+    assert!(env.config().round_fees_up);
+
+    let user = env.user();
+    let mint = env.create_mint(0);
+    env.create_token_account(mint, user, 1_000);
+    let treasury_ata = env.create_token_account(mint, env.treasury, 0);
+    let target = env.create_token_account(mint, Pubkey::new_unique(), 0);
+
+    // 333 * 30 / 10_000 = 0.999, which used to round down to nothing.
+    let order = env.open_spl(user, mint, 333, 3600);
+    assert_ok(env.execute_spl(order, target));
+    assert_eq!(env.token_balance(&treasury_ata), 1);
+    assert_eq!(env.token_balance(&target), 332);
+
+    // A single unit only covers its own fee.
+    let order = env.open_spl(user, mint, 1, 7200);
+    assert_error(
+        env.execute_spl(order, target),
+        CustomError::InsufficientFunds,
+    );
+}
+
+#[test]
+fn maximum_fee_takes_a_tenth() {
+    let mut env = Env::new(1000);
+    let user = env.user();
+    let mint = env.create_mint(6);
+    env.create_token_account(mint, user, 10_009);
+    let treasury_ata = env.create_token_account(mint, env.treasury, 0);
+    let target = env.create_token_account(mint, Pubkey::new_unique(), 0);
+
+    let order = env.open_spl(user, mint, 10_009, 3600);
+    assert_ok(env.execute_spl(order, target));
+    assert_eq!(env.token_balance(&treasury_ata), 1_000);
+    assert_eq!(env.token_balance(&target), 9_009);
+}
+
+#[test]
+fn chain_fee_override_replaces_the_platform_fee() {
+    let mut env = Env::new(30);
+    let params = |platform_fee_override| ChainConfigParams {
+        enabled: true,
+        address_format: AddressFormat::Bytes32,
+        min_amount_in: 0,
+        max_amount_in: 0,
+        platform_fee_override,
+    };
+    assert_error(
+        env.set_chain_config(DEST_CHAIN, params(Some(1001))),
+        CustomError::InvalidPlatformFee,
+    );
+
+    assert_ok(env.set_chain_config(DEST_CHAIN, params(Some(0))));
+    let user = env.user();
+    let order = env.open_sol(user, SOL, 3600);
+    let target = Pubkey::new_unique();
+    assert_ok(env.execute_sol(order, target));
+    assert_eq!(env.svm.lamports(&target), SOL);
+
+    // The override applies at execution time, not when the order is opened.
+    let order = env.open_sol(user, SOL, 7200);
+    assert_ok(env.set_chain_config(DEST_CHAIN, params(Some(1000))));
+    let target = Pubkey::new_unique();
+    assert_ok(env.execute_sol(order, target));
+    assert_eq!(env.svm.lamports(&target), SOL - SOL / 10);
+}
+
+#[test]
+fn fee_overflow_is_rejected() {
+    let mut env = Env::new(30);
+    let user = env.user();
+    let mint = env.create_mint(0);
+    let amount = u64::MAX / 2;
+    env.create_token_account(mint, user, amount);
+    env.create_token_account(mint, env.treasury, 0);
+    let target = env.create_token_account(mint, Pubkey::new_unique(), 0);
+
+    let order = env.open_spl(user, mint, amount, 3600);
+    assert_error(env.execute_spl(order, target), CustomError::Overflow);
+    // The escrow is untouched and can still be cancelled.
+    assert_ok(env.cancel_spl(order, user));
+}
+
+#[test]
+fn expired_orders_cannot_be_executed() {
+    let mut env = Env::new(30);
+    let user = env.user();
+    let order = env.open_sol(user, SOL, 3600);
+
+    env.svm.warp(3599);
+    let target = Pubkey::new_unique();
+    env.svm.airdrop(&target, SOL);
+    assert_ok(env.execute_sol(order, target));
+
+    let order = env.open_sol(user, SOL, 60);
+    env.svm.warp(60);
+    assert_error(env.execute_sol(order, target), CustomError::ExpiryEarlier);
+}
+
+#[test]
+fn expired_orders_are_cancelled_as_expired() {
+    let mut env = Env::new(30);
+    assert_ok(env.admin(instruction::SetCancelGracePeriod {
+        cancel_grace_period: 600,
+    }));
+    let user = env.user();
+    let order = env.open_sol(user, SOL, 60);
+    assert_error(
+        env.cancel_sol(order, user),
+        CustomError::CancelGracePeriodActive,
+    );
+
+    // Expiry lifts the grace period.
+    env.svm.warp(60);
+    assert_ok(env.cancel_sol(order, user));
+    let events = env.svm.events::<OrderCancelled>();
+    assert!(events[0].reason == CancelReason::Expired);
+}
+
+#[test]
+fn order_lifetimes_are_bounded() {
+    let mut env = Env::new(30);
+    for (min_order_lifetime, max_order_lifetime) in [(-1, 0), (600, 599)] {
+        assert_error(
+            env.admin(instruction::SetOrderLifetime {
+                min_order_lifetime,
+                max_order_lifetime,
+            }),
+            CustomError::InvalidParameter,
+        );
+    }
+    assert_ok(env.admin(instruction::SetOrderLifetime {
+        min_order_lifetime: 600,
+        max_order_lifetime: 86_400,
+    }));
+    let config = env.config();
+    assert_eq!(
+        (config.min_order_lifetime, config.max_order_lifetime),
+        (600, 86_400)
+    );
+    let events = env.svm.events::<OrderLifetimeUpdated>();
+    assert_eq!(events[0].min_order_lifetime, 600);
+
+    let user = env.user();
+    for ttl in [599, 86_401] {
+        let params = env.params(native_mint(), SOL, env.svm.now() + ttl);
+        assert_error(
+            env.open_sol_with(user, params),
+            CustomError::InvalidLifetime,
+        );
+    }
+    for ttl in [600, 86_400] {
+        let params = env.params(native_mint(), SOL, env.svm.now() + ttl);
+        assert_ok(env.open_sol_with(user, params));
+    }
+    // Good-till-cancelled orders only use the expiry as a nonce.
+    let mut params = env.params(native_mint(), SOL, env.svm.now() + 1);
+    params.good_till_cancelled = true;
+    assert_ok(env.open_sol_with(user, params));
+}
+
+#[test]
+fn good_till_cancelled_orders_outlive_their_expiry() {
+    let mut env = Env::new(30);
+    assert_ok(env.admin(instruction::SetCancelGracePeriod {
+        cancel_grace_period: 600,
+    }));
+    let book = env.init_order_book(native_mint());
+    let user = env.user();
+    let open_gtc = |env: &mut Env, expiry: i64| {
+        let mut params = env.params(native_mint(), SOL, expiry);
+        params.good_till_cancelled = true;
+        let mut accounts = env.open_sol_accounts(user, &params);
+        accounts.order_book = Some(book);
+        let order = accounts.order;
+        assert_ok(env.send(accounts, instruction::OpenOrderSol { params }, &[user]));
+        order
+    };
+    let listed = |env: &Env| {
+        let account = env.svm.account(&book).unwrap();
+        bytemuck::from_bytes::<OrderBook>(&account.data[8..])
+            .active()
+            .len()
+    };
+
+    // HTLC refunds depend on a deadline, so those orders can't be open-ended.
+    let now = env.svm.now();
+    let mut params = env.params(native_mint(), SOL, now + 60);
+    params.good_till_cancelled = true;
+    params.hashlock = [1; 32];
+    params.timelock = now + 3600;
+    params.htlc_solver = Pubkey::new_unique();
+    assert_error(
+        env.open_sol_with(user, params),
+        CustomError::InvalidParameter,
+    );
+
+    let first = open_gtc(&mut env, now + 60);
+    let second = open_gtc(&mut env, now + 61);
+    assert!(env.order(&first).good_till_cancelled);
+    env.svm.warp(3600);
+
+    // Keepers keep them listed and solvers can still fill them.
+    assert_ok(env.send(
+        accounts::PruneOrderBook { order_book: book },
+        instruction::PruneOrderBook {},
+        &[],
+    ));
+    assert_eq!(listed(&env), 2);
+    let target = Pubkey::new_unique();
+    env.svm.airdrop(&target, SOL);
+    let mut accounts = env.execute_sol_accounts(first, env.owner, target);
+    accounts.order_book = Some(book);
+    assert_ok(env.send(
+        accounts,
+        instruction::ExecuteOrderSol {
+            native_token_volume: 0,
+        },
+        &[env.owner],
+    ));
+    assert_eq!(listed(&env), 1);
+
+    // Past its expiry the sender still goes through the grace period.
+    let cancel = |env: &mut Env| {
+        let mut accounts = env.cancel_sol_accounts(second, user);
+        accounts.order_book = Some(book);
+        env.send(accounts, instruction::CancelOrderSol {}, &[user])
+    };
+    assert_error(cancel(&mut env), CustomError::CancelGracePeriodActive);
+    assert_ok(env.send(
+        accounts::RequestCancel {
+            order: second,
+            user,
+            global_config: global_config_pda(),
+            session_key: None,
+        },
+        instruction::RequestCancel {},
+        &[user],
+    ));
+    env.svm.warp(600);
+    assert_ok(cancel(&mut env));
+    let events = env.svm.events::<OrderCancelled>();
+    assert!(events[0].reason == CancelReason::Sender);
+    assert_eq!(listed(&env), 0);
+}
+
+#[test]
+fn cancel_grace_period() {
+    let mut env = Env::new(30);
+    assert_ok(env.admin(instruction::SetCancelGracePeriod {
+        cancel_grace_period: 600,
+    }));
+    let user = env.user();
+    let order = env.open_sol(user, SOL, 3600);
+    let request_cancel = |env: &mut Env| {
+        env.send(
+            accounts::RequestCancel {
+                order,
+                user,
+                global_config: global_config_pda(),
+                session_key: None,
+            },
+            instruction::RequestCancel {},
+            &[user],
+        )
+    };
+
+    assert_ok(request_cancel(&mut env));
+    assert_eq!(env.order(&order).cancel_requested_at, env.svm.now());
+    assert_error(
+        request_cancel(&mut env),
+        CustomError::CancelAlreadyRequested,
+    );
+
+    env.svm.warp(599);
+    assert_error(
+        env.cancel_sol(order, user),
+        CustomError::CancelGracePeriodActive,
+    );
+    // Solvers can still fill during the grace period, so leave one open.
+    env.svm.warp(1);
+    assert_ok(env.cancel_sol(order, user));
+    let events = env.svm.events::<OrderCancelled>();
+    assert!(events[0].reason == CancelReason::Sender);
+}
+
+#[test]
+fn owner_can_cancel_any_order_and_strangers_none() {
+    let mut env = Env::new(30);
+    assert_ok(env.admin(instruction::SetCancelGracePeriod {
+        cancel_grace_period: 600,
+    }));
+    let user = env.user();
+    let stranger = env.user();
+    let order = env.open_sol(user, SOL, 3600);
+    assert_error(
+        env.cancel_sol(order, stranger),
+        CustomError::OnlySenderOrOwner,
+    );
+
+    let user_before = env.svm.lamports(&user);
+    let order_lamports = env.svm.lamports(&order);
+    let owner = env.owner;
+    assert_ok(env.cancel_sol(order, owner));
+    assert_eq!(env.svm.lamports(&user), user_before + order_lamports);
+    let events = env.svm.events::<OrderCancelled>();
+    assert!(events[0].reason == CancelReason::Owner);
+}
+
+#[test]
+fn cancel_checks_the_escrow_type() {
+    let mut env = Env::new(30);
+    let user = env.user();
+    let wsol_order = env.open_wsol(user, SOL, 3600);
+    assert_error(env.cancel_sol(wsol_order, user), CustomError::InvalidEscrow);
+
+    // A lamport order has no escrow account to pass in its place.
+    let sol_order = env.open_sol(user, SOL, 7200);
+    let mut accounts = env.cancel_wsol_accounts(wsol_order, user);
+    accounts.order = sol_order;
+    assert_error(
+        env.send(accounts, instruction::CancelOrderWsol {}, &[user]),
+        ErrorCode::ConstraintRaw,
+    );
+}
+
+/// An order's accounts in `execute_orders_batch`, naming the book of its
+/// market when it is listed.
+fn batch_metas(env: &Env, order: Pubkey, target: Pubkey) -> Vec<AccountMeta> {
+    let order_account = env.order(&order);
+    let mint = order_account.from_token;
+    let order_book = order_account
+        .in_order_book
+        .then(|| order_book_pda(&mint, order_account.to_chain_id));
+    vec![
+        AccountMeta::new(order, false),
+        AccountMeta::new(get_associated_token_address(&order, &mint), false),
+        AccountMeta::new(target, false),
+        AccountMeta::new(get_associated_token_address(&env.treasury, &mint), false),
+        AccountMeta::new(order_account.refund_to(), false),
+        AccountMeta::new_readonly(chain_config_pda(order_account.to_chain_id), false),
+        order_book_meta(order_book),
+    ]
+}
+
+fn execute_batch(
+    env: &mut Env,
+    metas: Vec<AccountMeta>,
+    volumes: Vec<u64>,
+    skip: bool,
+) -> TxResult {
+    let owner = env.owner;
+    let mut ix = ix(
+        accounts::ExecuteOrdersBatch {
+            global_config: global_config_pda(),
+            executor: owner,
+            token_program: spl_token::ID,
+            guardian_set: None,
+            instructions: None,
+            roles: None,
+        },
+        instruction::ExecuteOrdersBatch {
+            native_token_volumes: volumes,
+            skip_failed: skip,
+        },
+    );
+    ix.accounts.extend(metas);
+    env.svm.process(&[ix], &[owner])
+}
+
+#[test]
+fn executes_a_batch_of_orders() {
+    let mut env = Env::new(30);
+    let user = env.user();
+    let mint = env.create_mint(6);
+    env.create_token_account(mint, user, 20_000);
+    let treasury_ata = env.create_token_account(mint, env.treasury, 0);
+    let target = env.create_token_account(mint, Pubkey::new_unique(), 0);
+    let first = env.open_spl(user, mint, 10_000, 3600);
+    let second = env.open_spl(user, mint, 10_000, 7200);
+
+    let mut metas = batch_metas(&env, first, target);
+    metas.extend(batch_metas(&env, second, target));
+    assert_error(
+        execute_batch(&mut env, metas.clone(), vec![0], false),
+        CustomError::InvalidBatchAccounts,
+    );
+    assert_error(
+        execute_batch(&mut env, Vec::new(), Vec::new(), false),
+        CustomError::InvalidBatchAccounts,
+    );
+
+    // Lamport orders hold no token escrow and cannot ride a token batch.
+    let sol_order = env.open_sol(user, SOL, 10_800);
+    let sol_metas = batch_metas(&env, sol_order, target);
+    assert_error(
+        execute_batch(&mut env, sol_metas, vec![0], false),
+        CustomError::InvalidEscrow,
+    );
+
+    assert_ok(execute_batch(&mut env, metas, vec![1, 2], false));
+    assert_eq!(env.token_balance(&target), 2 * (10_000 - 30));
+    assert_eq!(env.token_balance(&treasury_ata), 60);
+    assert!(env.svm.account(&first).is_none());
+    assert!(env.svm.account(&second).is_none());
+}
+
+#[test]
+fn batch_skips_failed_orders_when_asked() {
+    let mut env = Env::new(30);
+    let user = env.user();
+    let mint = env.create_mint(6);
+    env.create_token_account(mint, user, 20_000);
+    env.create_token_account(mint, env.treasury, 0);
+    let target = env.create_token_account(mint, Pubkey::new_unique(), 0);
+    let expiring = env.open_spl(user, mint, 10_000, 60);
+    let live = env.open_spl(user, mint, 10_000, 7200);
+    env.svm.warp(60);
+
+    let mut metas = batch_metas(&env, expiring, target);
+    metas.extend(batch_metas(&env, live, target));
+    assert_error(
+        execute_batch(&mut env, metas.clone(), vec![0, 0], false),
+        CustomError::ExpiryEarlier,
+    );
+
+    assert_ok(execute_batch(&mut env, metas, vec![0, 0], true));
+    let results = env.svm.events::<BatchOrderResult>();
+    assert_eq!(results.len(), 2);
+    assert!(!results[0].executed);
+    assert_eq!(results[0].error_code, u32::from(CustomError::ExpiryEarlier));
+    assert!(results[1].executed);
+    assert!(env.svm.account(&expiring).is_some());
+    assert!(env.svm.account(&live).is_none());
+}
+
+/// Compute units each order adds to a token batch on top of the batch's
+/// fixed cost; `execute_orders_batch`'s doc comment quotes this figure.
+const BATCH_UNITS_PER_ORDER: u64 = 30_000;
+
+#[test]
+fn batches_stay_within_their_per_order_compute_budget() {
+    let mut env = Env::new(30);
+    let user = env.user();
+    let mint = env.create_mint(6);
+    env.create_token_account(mint, user, 50_000);
+    env.create_token_account(mint, env.treasury, 0);
+    let target = env.create_token_account(mint, Pubkey::new_unique(), 0);
+    let batch_units = |env: &mut Env, orders: i64| {
+        let mut metas = Vec::new();
+        for ttl in 1..=orders {
+            let order = env.open_spl(user, mint, 10_000, 3600 * ttl);
+            metas.extend(batch_metas(env, order, target));
+        }
+        assert_ok(execute_batch(env, metas, vec![0; orders as usize], false));
+        env.svm.compute_units()
+    };
+
+    let single = batch_units(&mut env, 1);
+    // Four orders is as many as fit a legacy transaction.
+    let per_order = (batch_units(&mut env, 4) - single) / 3;
+    println!("{per_order} compute units per batched order");
+    if env.svm.runs_sbf() {
+        assert!(
+            per_order <= BATCH_UNITS_PER_ORDER,
+            "{per_order} compute units per order, over the budget of {BATCH_UNITS_PER_ORDER}"
+        );
+    }
+}
+
+#[test]
+fn batches_keep_the_order_book_in_sync() {
+    let mut env = Env::new(30);
+    let user = env.user();
+    let mint = env.create_mint(6);
+    let user_ata = env.create_token_account(mint, user, 40_000);
+    env.create_token_account(mint, env.treasury, 0);
+    let target = env.create_token_account(mint, Pubkey::new_unique(), 0);
+    let book = env.init_order_book(mint);
+    let open_listed = |env: &mut Env, ttl: i64| {
+        let params = env.params(mint, 10_000, env.svm.now() + ttl);
+        let mut accounts = env.open_spl_accounts(user, &params);
+        accounts.order_book = Some(book);
+        let order = accounts.order;
+        assert_ok(env.send(accounts, instruction::OpenOrderSpl { params }, &[user]));
+        order
+    };
+    let executed = open_listed(&mut env, 3600);
+    let cancelled = open_listed(&mut env, 7200);
+    let refunded = open_listed(&mut env, 10_800);
+    let kept = open_listed(&mut env, 14_400);
+    assert_eq!(
+        env.listed_orders(&book),
+        [executed, cancelled, refunded, kept]
+    );
+
+    let metas = batch_metas(&env, executed, target);
+    let mut unbooked = metas.clone();
+    unbooked[6] = order_book_meta(None);
+    assert_error(
+        execute_batch(&mut env, unbooked.clone(), vec![0], false),
+        CustomError::OrderBookRequired,
+    );
+    // Skipped orders stay listed.
+    assert_ok(execute_batch(&mut env, unbooked, vec![0], true));
+    assert_eq!(env.listed_orders(&book).len(), 4);
+    assert_ok(execute_batch(&mut env, metas, vec![0], false));
+    assert_eq!(env.listed_orders(&book), [kept, cancelled, refunded]);
+
+    let refund_metas = |order: Pubkey, order_book: Option<Pubkey>| {
+        vec![
+            AccountMeta::new(order, false),
+            AccountMeta::new(get_associated_token_address(&order, &mint), false),
+            AccountMeta::new(user_ata, false),
+            AccountMeta::new(user, false),
+            order_book_meta(order_book),
+        ]
+    };
+    let cancel = |env: &mut Env, order_book: Option<Pubkey>| {
+        let mut ix = ix(
+            accounts::CancelOrdersBatch {
+                user,
+                global_config: global_config_pda(),
+                token_program: spl_token::ID,
+            },
+            instruction::CancelOrdersBatch {},
+        );
+        ix.accounts.extend(refund_metas(cancelled, order_book));
+        env.svm.process(&[ix], &[user])
+    };
+    assert_error(cancel(&mut env, None), CustomError::OrderBookRequired);
+    assert_ok(cancel(&mut env, Some(book)));
+    assert_eq!(env.listed_orders(&book), [kept, refunded]);
+
+    let owner = env.owner;
+    let mut ix = ix(
+        accounts::ForceRefundBatch {
+            global_config: global_config_pda(),
+            authority: owner,
+            token_program: spl_token::ID,
+            roles: None,
+            proposal: None,
+        },
+        instruction::ForceRefundBatch { reason_code: 7 },
+    );
+    ix.accounts.extend(refund_metas(refunded, Some(book)));
+    assert_ok(env.svm.process(&[ix], &[owner]));
+    assert_eq!(env.listed_orders(&book), [kept]);
+    assert_eq!(env.token_balance(&user_ata), 20_000);
+}
+
+#[test]
+fn batches_span_markets() {
+    let mut env = Env::new(30);
+    let user = env.user();
+    let markets: Vec<_> = (0..2)
+        .map(|_| {
+            let mint = env.create_mint(6);
+            let user_ata = env.create_token_account(mint, user, 30_000);
+            env.create_token_account(mint, env.treasury, 0);
+            let target = env.create_token_account(mint, Pubkey::new_unique(), 0);
+            (mint, user_ata, target, env.init_order_book(mint))
+        })
+        .collect();
+    let open_listed = |env: &mut Env, mint: Pubkey, book: Pubkey, ttl: i64| {
+        let params = env.params(mint, 10_000, env.svm.now() + ttl);
+        let mut accounts = env.open_spl_accounts(user, &params);
+        accounts.order_book = Some(book);
+        let order = accounts.order;
+        assert_ok(env.send(accounts, instruction::OpenOrderSpl { params }, &[user]));
+        order
+    };
+    let (first_mint, first_ata, first_target, first_book) = markets[0];
+    let (second_mint, second_ata, second_target, second_book) = markets[1];
+    let first = open_listed(&mut env, first_mint, first_book, 3600);
+    let second = open_listed(&mut env, second_mint, second_book, 7200);
+    let unlisted = env.open_spl(user, first_mint, 10_000, 10_800);
+    let first_kept = open_listed(&mut env, first_mint, first_book, 14_400);
+    let second_kept = open_listed(&mut env, second_mint, second_book, 18_000);
+
+    let mut metas = batch_metas(&env, first, first_target);
+    metas.extend(batch_metas(&env, second, second_target));
+    metas.extend(batch_metas(&env, unlisted, first_target));
+    let mut crossed = metas.clone();
+    crossed[6] = order_book_meta(Some(second_book));
+    assert_error(
+        execute_batch(&mut env, crossed, vec![0; 3], false),
+        CustomError::InvalidOrderBook,
+    );
+    assert_ok(execute_batch(&mut env, metas, vec![0; 3], false));
+    assert_eq!(env.listed_orders(&first_book), [first_kept]);
+    assert_eq!(env.listed_orders(&second_book), [second_kept]);
+    assert_eq!(env.token_balance(&first_target), 2 * (10_000 - 30));
+    assert_eq!(env.token_balance(&second_target), 10_000 - 30);
+
+    let mut ix = ix(
+        accounts::CancelOrdersBatch {
+            user,
+            global_config: global_config_pda(),
+            token_program: spl_token::ID,
+        },
+        instruction::CancelOrdersBatch {},
+    );
+    for (order, mint, user_ata, book) in [
+        (first_kept, first_mint, first_ata, first_book),
+        (second_kept, second_mint, second_ata, second_book),
+    ] {
+        ix.accounts.extend([
+            AccountMeta::new(order, false),
+            AccountMeta::new(get_associated_token_address(&order, &mint), false),
+            AccountMeta::new(user_ata, false),
+            AccountMeta::new(user, false),
+            order_book_meta(Some(book)),
+        ]);
+    }
+    assert_ok(env.svm.process(&[ix], &[user]));
+    assert!(env.listed_orders(&first_book).is_empty());
+    assert!(env.listed_orders(&second_book).is_empty());
+    assert_eq!(env.token_balance(&first_ata), 10_000);
+    assert_eq!(env.token_balance(&second_ata), 20_000);
+}
+
+#[test]
+fn cancels_and_force_refunds_batches() {
+    let mut env = Env::new(30);
+    let user = env.user();
+    let mint = env.create_mint(6);
+    let user_ata = env.create_token_account(mint, user, 1_000);
+    let before = env.svm.lamports(&user);
+    let sol_order = env.open_sol(user, SOL, 3600);
+    let wsol_order = env.open_wsol(user, SOL, 7200);
+    let spl_order = env.open_spl(user, mint, 1_000, 10_800);
+
+    let metas = vec![
+        AccountMeta::new(sol_order, false),
+        AccountMeta::new(user, false),
+        order_book_meta(None),
+        AccountMeta::new(wsol_order, false),
+        AccountMeta::new(
+            get_associated_token_address(&wsol_order, &native_mint()),
+            false,
+        ),
+        AccountMeta::new(user, false),
+        order_book_meta(None),
+        AccountMeta::new(spl_order, false),
+        AccountMeta::new(get_associated_token_address(&spl_order, &mint), false),
+        AccountMeta::new(user_ata, false),
+        AccountMeta::new(user, false),
+        order_book_meta(None),
+    ];
+
+    let owner = env.owner;
+    let force_refund = |env: &mut Env, reason_code: u16, metas: Vec<AccountMeta>| {
+        let mut ix = ix(
+            accounts::ForceRefundBatch {
+                global_config: global_config_pda(),
+                authority: owner,
+                token_program: spl_token::ID,
+                roles: None,
+                proposal: None,
+            },
+            instruction::ForceRefundBatch { reason_code },
+        );
+        ix.accounts.extend(metas);
+        env.svm.process(&[ix], &[owner])
+    };
+    assert_error(
+        force_refund(&mut env, 0, metas.clone()),
+        CustomError::InvalidParameter,
+    );
+    // A truncated layout for the last order is rejected as a whole.
+    assert_error(
+        force_refund(&mut env, 7, metas[..11].to_vec()),
+        CustomError::InvalidBatchAccounts,
+    );
+
+    let stranger = env.user();
+    let mut ix = ix(
+        accounts::CancelOrdersBatch {
+            user: stranger,
+            global_config: global_config_pda(),
+            token_program: spl_token::ID,
+        },
+        instruction::CancelOrdersBatch {},
+    );
+    ix.accounts.extend(metas.clone());
+    assert_error(
+        env.svm.process(&[ix.clone()], &[stranger]),
+        CustomError::OnlySenderOrOwner,
+    );
+
+    ix.accounts[0].pubkey = user;
+    assert_ok(env.svm.process(&[ix], &[user]));
+    assert_eq!(env.svm.lamports(&user), before);
+    assert_eq!(env.token_balance(&user_ata), 1_000);
+    assert_eq!(env.svm.events::<OrderCancelled>().len(), 3);
+
+    let order = env.open_sol(user, SOL, 3600);
+    assert_ok(force_refund(
+        &mut env,
+        7,
+        vec![
+            AccountMeta::new(order, false),
+            AccountMeta::new(user, false),
+            order_book_meta(None),
+        ],
+    ));
+    let events = env.svm.events::<OrderCancelled>();
+    assert!(events[0].reason == CancelReason::ForceRefund);
+    assert_eq!(events[0].reason_code, 7);
+}
+
+#[test]
+fn sol_orders_need_the_funds() {
+    let mut env = Env::new(30);
+    let user = new_signer();
+    env.svm.airdrop(&user, SOL);
+    let expiry = env.svm.now() + 3600;
+    let params = env.params(native_mint(), SOL, expiry);
+    assert_error(
+        env.open_sol_with(user, params),
+        CustomError::InsufficientFunds,
+    );
+    let params = env.params(native_mint(), SOL, expiry);
+    let accounts = env.open_wsol_accounts(user, &params);
+    assert_error(
+        env.send(accounts, instruction::OpenOrderWsol { params }, &[user]),
+        CustomError::InsufficientFunds,
+    );
+
+    // The order's own rent is paid on top of `amount_in`.
+    let amount = SOL - rent(8 + LimitOrder::INIT_SPACE);
+    let params = env.params(native_mint(), amount, expiry);
+    assert_ok(env.open_sol_with(user, params));
+    assert_eq!(env.svm.lamports(&user), 0);
+}
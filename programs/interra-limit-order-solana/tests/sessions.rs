@@ -0,0 +1,354 @@
+//! Session keys opening and cancelling orders on behalf of their wallet.
+
+mod common;
+
+use anchor_lang::error::ErrorCode;
+use anchor_lang::system_program;
+use anchor_spl::associated_token::get_associated_token_address;
+use common::*;
+use limit_order::{
+    accounts, instruction, CustomError, OpenOrderParams, SessionKey, SessionKeyCreated,
+    SessionKeyParams,
+};
+
+fn session_params(env: &Env, key: Pubkey, open_allowance: u64) -> SessionKeyParams {
+    SessionKeyParams {
+        key,
+        expires_at: env.svm.now() + 3600,
+        open_allowance,
+        can_cancel: true,
+        allowed_mints: vec![native_mint()],
+    }
+}
+
+fn create_session(env: &mut Env, user: Pubkey, params: SessionKeyParams) -> TxResult {
+    env.send(
+        accounts::CreateSessionKey {
+            session_key: session_key_pda(&user, &params.key),
+            user,
+            system_program: system_program::ID,
+        },
+        instruction::CreateSessionKey { params },
+        &[user],
+    )
+}
+
+/// A funded ephemeral key that may open up to `open_allowance` lamports for
+/// `user`.
+fn session(env: &mut Env, user: Pubkey, open_allowance: u64) -> Pubkey {
+    let params = session_params(env, new_signer(), open_allowance);
+    session_with(env, user, params)
+}
+
+/// A funded ephemeral key authorised by `user` through `params`.
+fn session_with(env: &mut Env, user: Pubkey, params: SessionKeyParams) -> Pubkey {
+    let key = params.key;
+    env.svm.airdrop(&key, SOL);
+    assert_ok(create_session(env, user, params));
+    key
+}
+
+fn open_sol_as(env: &mut Env, user: Pubkey, key: Pubkey, params: OpenOrderParams) -> TxResult {
+    let mut accounts = env.open_sol_accounts(user, &params);
+    accounts.user = key;
+    accounts.payer = key;
+    accounts.session_key = Some(session_key_pda(&user, &key));
+    env.send(accounts, instruction::OpenOrderSol { params }, &[key])
+}
+
+fn cancel_sol_as(env: &mut Env, order: Pubkey, key: Pubkey, user: Pubkey) -> TxResult {
+    let mut accounts = env.cancel_sol_accounts(order, key);
+    accounts.session_key = Some(session_key_pda(&user, &key));
+    env.send(accounts, instruction::CancelOrderSol {}, &[key])
+}
+
+#[test]
+fn users_create_and_revoke_session_keys() {
+    let mut env = Env::new(30);
+    let user = env.user();
+    let key = Pubkey::new_unique();
+
+    let mut params = session_params(&env, user, SOL);
+    assert_error(
+        create_session(&mut env, user, params),
+        CustomError::InvalidParameter,
+    );
+    params = session_params(&env, key, SOL);
+    params.expires_at = env.svm.now();
+    assert_error(
+        create_session(&mut env, user, params),
+        CustomError::InvalidParameter,
+    );
+    params = session_params(&env, key, SOL);
+    params.allowed_mints = vec![Pubkey::new_unique(); SessionKey::MAX_ALLOWED_MINTS + 1];
+    assert_error(
+        create_session(&mut env, user, params),
+        CustomError::InvalidParameter,
+    );
+    // An allowance is counted in a single mint's base units.
+    for allowed_mints in [vec![], vec![native_mint(), Pubkey::new_unique()]] {
+        params = session_params(&env, key, SOL);
+        params.allowed_mints = allowed_mints;
+        assert_error(
+            create_session(&mut env, user, params),
+            CustomError::InvalidParameter,
+        );
+    }
+
+    let user_before = env.svm.lamports(&user);
+    params = session_params(&env, key, SOL);
+    params.allowed_mints = vec![native_mint()];
+    assert_ok(create_session(&mut env, user, params));
+    let session_key = session_key_pda(&user, &key);
+    let session: SessionKey = env.svm.fetch(&session_key);
+    assert_eq!(session.user, user);
+    assert_eq!(session.key, key);
+    assert_eq!(session.open_allowance, SOL);
+    assert_eq!(session.allowed_mints, vec![native_mint()]);
+    let events = env.svm.events::<SessionKeyCreated>();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].key, key);
+
+    // Only the user can revoke, and revoking refunds the rent.
+    let stranger = env.user();
+    assert_error(
+        env.send(
+            accounts::RevokeSessionKey {
+                session_key,
+                user: stranger,
+            },
+            instruction::RevokeSessionKey {},
+            &[stranger],
+        ),
+        ErrorCode::ConstraintSeeds,
+    );
+    assert_ok(env.send(
+        accounts::RevokeSessionKey { session_key, user },
+        instruction::RevokeSessionKey {},
+        &[user],
+    ));
+    assert!(env.svm.account(&session_key).is_none());
+    assert_eq!(env.svm.lamports(&user), user_before);
+}
+
+#[test]
+fn session_keys_open_orders_owned_by_their_wallet() {
+    let mut env = Env::new(30);
+    let user = env.user();
+    let key = session(&mut env, user, 3 * SOL);
+    // Session keys pay for what they open; the wallet keeps the refunds.
+    env.svm.airdrop(&key, 3 * SOL);
+
+    let expiry = env.svm.now() + 3600;
+    let params = env.params(native_mint(), 2 * SOL, expiry);
+    assert_ok(open_sol_as(&mut env, user, key, params));
+
+    let order = env.order(&order_pda(&user, expiry));
+    assert_eq!(order.sender, user);
+    let session: SessionKey = env.svm.fetch(&session_key_pda(&user, &key));
+    assert_eq!(session.open_allowance, SOL);
+
+    let params = env.params(native_mint(), SOL + 1, expiry + 1);
+    assert_error(
+        open_sol_as(&mut env, user, key, params),
+        CustomError::SessionAllowanceExceeded,
+    );
+    let params = env.params(native_mint(), SOL, expiry + 1);
+    assert_ok(open_sol_as(&mut env, user, key, params));
+    let session: SessionKey = env.svm.fetch(&session_key_pda(&user, &key));
+    assert_eq!(session.open_allowance, 0);
+}
+
+#[test]
+fn session_keys_stay_within_their_scope() {
+    let mut env = Env::new(30);
+    let user = env.user();
+    let mint = env.create_mint(6);
+    let mut params = session_params(&env, new_signer(), SOL);
+    params.allowed_mints = vec![mint];
+    let mint_only = session_with(&mut env, user, params);
+    let cancel_only = session(&mut env, user, 0);
+
+    let expiry = env.svm.now() + 3600;
+    let params = env.params(native_mint(), SOL / 2, expiry);
+    assert_error(
+        open_sol_as(&mut env, user, mint_only, params),
+        CustomError::SessionNotPermitted,
+    );
+    // Refunds of session orders can't be sent anywhere but the wallet.
+    let key = session(&mut env, user, SOL);
+    let mut params = env.params(native_mint(), SOL / 2, expiry);
+    params.refund_address = key;
+    assert_error(
+        open_sol_as(&mut env, user, key, params),
+        CustomError::SessionNotPermitted,
+    );
+    let params = env.params(native_mint(), 1, expiry);
+    assert_error(
+        open_sol_as(&mut env, user, cancel_only, params),
+        CustomError::SessionAllowanceExceeded,
+    );
+
+    // A session of another wallet doesn't let its key open for this one.
+    let other = env.user();
+    let other_key = session(&mut env, other, SOL);
+    let params = env.params(native_mint(), SOL / 2, expiry);
+    let mut accounts = env.open_sol_accounts(user, &params);
+    accounts.user = other_key;
+    accounts.payer = other_key;
+    accounts.session_key = Some(session_key_pda(&other, &other_key));
+    assert_error(
+        env.send(accounts, instruction::OpenOrderSol { params }, &[other_key]),
+        ErrorCode::ConstraintSeeds,
+    );
+    // Nor does signing with a key the session wasn't created for.
+    let params = env.params(native_mint(), SOL / 2, expiry);
+    let mut accounts = env.open_sol_accounts(user, &params);
+    accounts.user = cancel_only;
+    accounts.payer = cancel_only;
+    accounts.session_key = Some(session_key_pda(&user, &mint_only));
+    assert_error(
+        env.send(
+            accounts,
+            instruction::OpenOrderSol { params },
+            &[cancel_only],
+        ),
+        ErrorCode::ConstraintSeeds,
+    );
+
+    let key = session(&mut env, user, SOL);
+    env.svm.warp(3600);
+    let params = env.params(native_mint(), SOL / 2, env.svm.now() + 3600);
+    assert_error(
+        open_sol_as(&mut env, user, key, params),
+        CustomError::SessionKeyExpired,
+    );
+}
+
+#[test]
+fn session_cancels_refund_the_wallet() {
+    let mut env = Env::new(30);
+    let user = env.user();
+    let key = session(&mut env, user, SOL);
+    let mut params = session_params(&env, new_signer(), SOL);
+    params.can_cancel = false;
+    let open_only = session_with(&mut env, user, params);
+
+    let order = env.open_sol(user, SOL, 3600);
+    assert_error(
+        cancel_sol_as(&mut env, order, open_only, user),
+        CustomError::SessionNotPermitted,
+    );
+
+    // The refund can't be redirected to the session key.
+    let mut accounts = env.cancel_sol_accounts(order, key);
+    accounts.refund_receiver = key;
+    accounts.session_key = Some(session_key_pda(&user, &key));
+    assert_error(
+        env.send(accounts, instruction::CancelOrderSol {}, &[key]),
+        CustomError::InvalidRefundReceiver,
+    );
+
+    let user_before = env.svm.lamports(&user);
+    let key_before = env.svm.lamports(&key);
+    let rent = env.svm.lamports(&order) - SOL;
+    assert_ok(cancel_sol_as(&mut env, order, key, user));
+    assert_eq!(env.svm.lamports(&user), user_before + SOL + rent);
+    assert_eq!(env.svm.lamports(&key), key_before);
+
+    // Sessions only act for their own wallet's orders.
+    let other = env.user();
+    let other_order = env.open_sol(other, SOL, 3600);
+    assert_error(
+        cancel_sol_as(&mut env, other_order, key, user),
+        CustomError::InvalidSessionKey,
+    );
+
+    let order = env.open_sol(user, SOL, 3600);
+    env.svm.warp(3600);
+    assert_error(
+        cancel_sol_as(&mut env, order, key, user),
+        CustomError::SessionKeyExpired,
+    );
+}
+
+#[test]
+fn session_keys_open_spl_orders_as_token_delegates() {
+    let mut env = Env::new(30);
+    let user = env.user();
+    let mint = env.create_mint(6);
+    let user_token_account = env.create_token_account(mint, user, 1_000_000);
+    let mut params = session_params(&env, new_signer(), 600_000);
+    params.allowed_mints = vec![mint];
+    let key = session_with(&mut env, user, params);
+    env.approve_delegate(user_token_account, key, 600_000);
+
+    let expiry = env.svm.now() + 3600;
+    let params = env.params(mint, 400_000, expiry);
+    let mut accounts = env.open_spl_accounts(user, &params);
+    accounts.user = key;
+    accounts.payer = key;
+    accounts.session_key = Some(session_key_pda(&user, &key));
+    assert_ok(env.send(accounts, instruction::OpenOrderSpl { params }, &[key]));
+
+    let order = order_pda(&user, expiry);
+    assert_eq!(env.order(&order).sender, user);
+    assert_eq!(env.token_balance(&user_token_account), 600_000);
+
+    let mut accounts = env.cancel_spl_accounts(order, key);
+    accounts.session_key = Some(session_key_pda(&user, &key));
+    assert_ok(env.send(accounts, instruction::CancelOrderSpl {}, &[key]));
+    assert_eq!(env.token_balance(&user_token_account), 1_000_000);
+    assert!(env
+        .svm
+        .account(&get_associated_token_address(&order, &mint))
+        .is_none());
+}
+
+#[test]
+fn session_keys_request_grace_period_cancels() {
+    let mut env = Env::new(30);
+    assert_ok(env.admin(instruction::SetCancelGracePeriod {
+        cancel_grace_period: 600,
+    }));
+    let user = env.user();
+    let key = session(&mut env, user, 0);
+    let mut params = session_params(&env, new_signer(), SOL);
+    params.can_cancel = false;
+    let open_only = session_with(&mut env, user, params);
+    let order = env.open_sol(user, SOL, 3600);
+
+    let request_cancel_as = |env: &mut Env, key: Pubkey, wallet: Pubkey| {
+        env.send(
+            accounts::RequestCancel {
+                order,
+                user: key,
+                global_config: global_config_pda(),
+                session_key: Some(session_key_pda(&wallet, &key)),
+            },
+            instruction::RequestCancel {},
+            &[key],
+        )
+    };
+    assert_error(
+        request_cancel_as(&mut env, open_only, user),
+        CustomError::SessionNotPermitted,
+    );
+    let other = env.user();
+    let other_key = session(&mut env, other, 0);
+    assert_error(
+        request_cancel_as(&mut env, other_key, other),
+        CustomError::InvalidSessionKey,
+    );
+    assert_error(
+        cancel_sol_as(&mut env, order, key, user),
+        CustomError::CancelGracePeriodActive,
+    );
+
+    assert_ok(request_cancel_as(&mut env, key, user));
+    assert_eq!(env.order(&order).cancel_requested_at, env.svm.now());
+    env.svm.warp(600);
+    let before = env.svm.lamports(&user);
+    assert_ok(cancel_sol_as(&mut env, order, key, user));
+    assert!(env.svm.lamports(&user) > before + SOL);
+}
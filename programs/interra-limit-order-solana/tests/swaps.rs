@@ -0,0 +1,381 @@
+//! Executions that swap the escrow through the configured adapter before
+//! settling, against the mock constant-product pool.
+
+mod common;
+
+use anchor_lang::error::ErrorCode;
+use anchor_lang::prelude::{AccountSerialize, Rent};
+use anchor_lang::solana_program::instruction::{AccountMeta, InstructionError};
+use anchor_lang::system_program;
+use anchor_spl::associated_token::{get_associated_token_address, spl_associated_token_account};
+use anchor_spl::token::spl_token;
+use common::*;
+use limit_order::{accounts, instruction, CustomError, OrderExecuted, OrderSwapped};
+use mock_swap::{PoolMode, SwapMode};
+
+const POOL_DEPTH: u64 = 1_000_000_000;
+
+fn pool_authority() -> Pubkey {
+    Pubkey::find_program_address(&[b"pool"], &mock_swap::ID).0
+}
+
+fn pool_mode() -> Pubkey {
+    Pubkey::find_program_address(&[b"mode"], &mock_swap::ID).0
+}
+
+/// Makes the mock pool pull amounts other than the one it was asked to swap.
+fn set_pool_mode(env: &mut Env, mode: SwapMode) {
+    let mut data = Vec::new();
+    PoolMode { mode }
+        .try_serialize(&mut data)
+        .expect("mode serializes");
+    env.svm.set_account(
+        pool_mode(),
+        AccountState {
+            lamports: Rent::default().minimum_balance(data.len()),
+            data,
+            owner: mock_swap::ID,
+            executable: false,
+        },
+    );
+}
+
+/// What the mock pool pays out for `amount_in`.
+fn quote(amount_in: u64) -> u64 {
+    (POOL_DEPTH as u128 * amount_in as u128 / (POOL_DEPTH + amount_in) as u128) as u64
+}
+
+/// Two fresh mints with an equally deep pool between them.
+fn pool(env: &mut Env) -> (Pubkey, Pubkey) {
+    let input = env.create_mint(6);
+    let output = env.create_mint(6);
+    env.create_token_account(input, pool_authority(), POOL_DEPTH);
+    env.create_token_account(output, pool_authority(), POOL_DEPTH);
+    (input, output)
+}
+
+fn set_swap_config(env: &mut Env, swap_adapter: Pubkey, fee_on_output: bool) {
+    assert_ok(env.admin(instruction::SetSwapConfig {
+        swap_adapter,
+        fee_on_output,
+    }));
+}
+
+fn swap_accounts(
+    env: &Env,
+    order: Pubkey,
+    output_mint: Pubkey,
+    settlement_vault: Pubkey,
+) -> accounts::ExecuteOrderSwap {
+    let order_account = env.order(&order);
+    let fee_mint = if env.config().swap_fee_on_output {
+        output_mint
+    } else {
+        order_account.from_token
+    };
+    accounts::ExecuteOrderSwap {
+        order,
+        order_token_account: get_associated_token_address(&order, &order_account.from_token),
+        output_mint,
+        swap_output_account: get_associated_token_address(&order, &output_mint),
+        settlement_vault,
+        treasury_token_account: get_associated_token_address(&env.treasury, &fee_mint),
+        global_config: global_config_pda(),
+        chain_config: chain_config_pda(order_account.to_chain_id),
+        refund_receiver: order_account.refund_to(),
+        executor: env.owner,
+        swap_program: mock_swap::ID,
+        token_program: spl_token::ID,
+        associated_token_program: spl_associated_token_account::ID,
+        system_program: system_program::ID,
+        guardian_set: None,
+        instructions: None,
+        executor_bond: None,
+        order_book: None,
+        roles: None,
+    }
+}
+
+fn send_swap(env: &mut Env, accounts: accounts::ExecuteOrderSwap, min_amount_out: u64) -> TxResult {
+    send_swap_with(env, accounts, min_amount_out, None)
+}
+
+/// Sends the swap, passing the pool's `mode` account when given.
+fn send_swap_with(
+    env: &mut Env,
+    accounts: accounts::ExecuteOrderSwap,
+    min_amount_out: u64,
+    mode: Option<Pubkey>,
+) -> TxResult {
+    let pool_source =
+        get_associated_token_address(&pool_authority(), &env.order(&accounts.order).from_token);
+    let pool_destination = get_associated_token_address(&pool_authority(), &accounts.output_mint);
+    let mut ix = ix(
+        accounts,
+        instruction::ExecuteOrderSwap {
+            min_amount_out,
+            native_token_volume: 0,
+        },
+    );
+    ix.accounts.extend([
+        AccountMeta::new(pool_source, false),
+        AccountMeta::new(pool_destination, false),
+        AccountMeta::new_readonly(pool_authority(), false),
+    ]);
+    if let Some(mode) = mode {
+        ix.accounts.push(AccountMeta::new_readonly(mode, false));
+    }
+    let owner = env.owner;
+    env.svm.process(&[ix], &[owner])
+}
+
+fn execute_swap(
+    env: &mut Env,
+    order: Pubkey,
+    output_mint: Pubkey,
+    settlement_vault: Pubkey,
+    min_amount_out: u64,
+) -> TxResult {
+    let accounts = swap_accounts(env, order, output_mint, settlement_vault);
+    send_swap(env, accounts, min_amount_out)
+}
+
+#[test]
+fn swaps_take_the_fee_from_the_escrow_by_default() {
+    let mut env = Env::new(30);
+    let (input, output) = pool(&mut env);
+    set_swap_config(&mut env, mock_swap::ID, false);
+    let user = env.user();
+    env.create_token_account(input, user, 1_000_000);
+    let treasury_ata = env.create_token_account(input, env.treasury, 0);
+    let vault = env.create_token_account(output, Pubkey::new_unique(), 0);
+
+    let order = env.open_spl(user, input, 1_000_000, 3600);
+    let user_before = env.svm.lamports(&user);
+    let escrow_rent = env
+        .svm
+        .lamports(&get_associated_token_address(&order, &input));
+    let order_rent = env.svm.lamports(&order);
+    let amount_out = quote(997_000);
+    assert_ok(execute_swap(&mut env, order, output, vault, amount_out));
+
+    assert_eq!(env.token_balance(&vault), amount_out);
+    assert_eq!(env.token_balance(&treasury_ata), 3_000);
+    assert_eq!(
+        env.token_balance(&get_associated_token_address(&pool_authority(), &input)),
+        POOL_DEPTH + 997_000
+    );
+    assert!(env.svm.account(&order).is_none());
+    assert!(env
+        .svm
+        .account(&get_associated_token_address(&order, &input))
+        .is_none());
+    assert!(env
+        .svm
+        .account(&get_associated_token_address(&order, &output))
+        .is_none());
+    assert_eq!(
+        env.svm.lamports(&user),
+        user_before + escrow_rent + order_rent
+    );
+
+    let events = env.svm.events::<OrderSwapped>();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].order_pubkey, order);
+    assert_eq!(events[0].output_mint, output);
+    assert_eq!(events[0].amount_in, 997_000);
+    assert_eq!(events[0].amount_out, amount_out);
+    assert_eq!(events[0].fee_amount, 3_000);
+    assert_eq!(events[0].fee_mint, input);
+    assert_eq!(env.svm.events::<OrderExecuted>().len(), 1);
+}
+
+#[test]
+fn swaps_can_take_the_fee_from_the_output() {
+    let mut env = Env::new(30);
+    let (input, output) = pool(&mut env);
+    set_swap_config(&mut env, mock_swap::ID, true);
+    let user = env.user();
+    env.create_token_account(input, user, 1_000_000);
+    let treasury_ata = env.create_token_account(output, env.treasury, 0);
+    let vault = env.create_token_account(output, Pubkey::new_unique(), 0);
+
+    let order = env.open_spl(user, input, 1_000_000, 3600);
+    let amount_out = quote(1_000_000);
+    assert_ok(execute_swap(&mut env, order, output, vault, amount_out));
+
+    let fee = amount_out * 30 / 10_000;
+    assert_eq!(env.token_balance(&treasury_ata), fee);
+    assert_eq!(env.token_balance(&vault), amount_out - fee);
+    let events = env.svm.events::<OrderSwapped>();
+    assert_eq!(events[0].amount_in, 1_000_000);
+    assert_eq!(events[0].fee_amount, fee);
+    assert_eq!(events[0].fee_mint, output);
+}
+
+#[test]
+fn swaps_enforce_the_minimum_output() {
+    let mut env = Env::new(30);
+    let (input, output) = pool(&mut env);
+    set_swap_config(&mut env, mock_swap::ID, false);
+    let user = env.user();
+    env.create_token_account(input, user, 1_000_000);
+    env.create_token_account(input, env.treasury, 0);
+    let vault = env.create_token_account(output, Pubkey::new_unique(), 0);
+    let order = env.open_spl(user, input, 1_000_000, 3600);
+
+    let amount_out = quote(997_000);
+    assert_error(
+        execute_swap(&mut env, order, output, vault, amount_out + 1),
+        CustomError::SwapOutputTooLow,
+    );
+    assert_error(
+        execute_swap(&mut env, order, output, vault, 0),
+        CustomError::InvalidParameter,
+    );
+    // Swapping into the escrowed mint is no swap at all.
+    let same_mint_vault = env.create_token_account(input, Pubkey::new_unique(), 0);
+    assert_error(
+        execute_swap(&mut env, order, input, same_mint_vault, 1),
+        CustomError::InvalidParameter,
+    );
+    let stray_vault = env.create_token_account(input, Pubkey::new_unique(), 0);
+    assert_error(
+        execute_swap(&mut env, order, output, stray_vault, amount_out),
+        ErrorCode::ConstraintRaw,
+    );
+
+    assert_ok(execute_swap(&mut env, order, output, vault, amount_out));
+    assert_eq!(env.token_balance(&vault), amount_out);
+}
+
+#[test]
+fn swaps_must_pull_exactly_the_swap_amount() {
+    let mut env = Env::new(30);
+    let (input, output) = pool(&mut env);
+    set_swap_config(&mut env, mock_swap::ID, false);
+    let user = env.user();
+    env.create_token_account(input, user, 1_000_000);
+    env.create_token_account(input, env.treasury, 0);
+    let vault = env.create_token_account(output, Pubkey::new_unique(), 0);
+    let order = env.open_spl(user, input, 1_000_000, 3600);
+    let escrow = get_associated_token_address(&order, &input);
+
+    // A stray deposit gives the pool something extra to pull.
+    env.create_token_account_at(escrow, input, order, 1_000_001);
+    set_pool_mode(&mut env, SwapMode::PullExtra);
+    let accounts = swap_accounts(&env, order, output, vault);
+    assert_error(
+        send_swap_with(&mut env, accounts, 1, Some(pool_mode())),
+        CustomError::InvalidSwap,
+    );
+    env.create_token_account_at(escrow, input, order, 1_000_000);
+    set_pool_mode(&mut env, SwapMode::PullShort);
+    let accounts = swap_accounts(&env, order, output, vault);
+    assert_error(
+        send_swap_with(&mut env, accounts, 1, Some(pool_mode())),
+        CustomError::InvalidSwap,
+    );
+
+    set_pool_mode(&mut env, SwapMode::Honest);
+    let accounts = swap_accounts(&env, order, output, vault);
+    assert_ok(send_swap_with(&mut env, accounts, 1, Some(pool_mode())));
+    assert_eq!(env.token_balance(&vault), quote(997_000));
+}
+
+#[test]
+fn the_runtime_stops_adapters_debiting_accounts_they_do_not_own() {
+    let mut env = Env::new(30);
+    let (input, output) = pool(&mut env);
+    set_swap_config(&mut env, mock_swap::ID, false);
+    let user = env.user();
+    env.create_token_account(input, user, 1_000_000);
+    env.create_token_account(input, env.treasury, 0);
+    let vault = env.create_token_account(output, Pubkey::new_unique(), 0);
+    let order = env.open_spl(user, input, 1_000_000, 3600);
+    let escrow = get_associated_token_address(&order, &input);
+    let escrow_lamports = env.svm.lamports(&escrow);
+
+    set_pool_mode(&mut env, SwapMode::SkimLamports);
+    let accounts = swap_accounts(&env, order, output, vault);
+    // Natively the bank panics on the debit rather than returning it.
+    let expected = if env.svm.runs_sbf() {
+        InstructionError::ExternalAccountLamportSpend
+    } else {
+        InstructionError::ProgramFailedToComplete
+    };
+    match send_swap_with(&mut env, accounts, 1, Some(pool_mode())) {
+        Err(TxFailure {
+            error: TxError::Instruction(error),
+            ..
+        }) if error == expected => {}
+        other => panic!("expected the runtime to reject the debit, got {other:?}"),
+    }
+    assert_eq!(env.svm.lamports(&escrow), escrow_lamports);
+    assert_eq!(env.token_balance(&escrow), 1_000_000);
+}
+
+#[test]
+fn swaps_only_go_through_the_configured_adapter() {
+    let mut env = Env::new(30);
+    let (input, output) = pool(&mut env);
+    let user = env.user();
+    env.create_token_account(input, user, 1_000_000);
+    env.create_token_account(input, env.treasury, 0);
+    let vault = env.create_token_account(output, Pubkey::new_unique(), 0);
+    let order = env.open_spl(user, input, 1_000_000, 3600);
+
+    assert_error(
+        execute_swap(&mut env, order, output, vault, 1),
+        ErrorCode::ConstraintAddress,
+    );
+    // The unset adapter is the system program's address.
+    let mut accounts = swap_accounts(&env, order, output, vault);
+    accounts.swap_program = system_program::ID;
+    assert_error(
+        send_swap(&mut env, accounts, 1),
+        CustomError::SwapNotConfigured,
+    );
+
+    set_swap_config(&mut env, mock_swap::ID, false);
+    let mut accounts = swap_accounts(&env, order, output, vault);
+    accounts.swap_program = spl_token::ID;
+    assert_error(
+        send_swap(&mut env, accounts, 1),
+        ErrorCode::ConstraintAddress,
+    );
+
+    // Only the admin sets the adapter.
+    let stranger = env.user();
+    let accounts = env.update_config(stranger);
+    assert_error(
+        env.send(
+            accounts,
+            instruction::SetSwapConfig {
+                swap_adapter: spl_token::ID,
+                fee_on_output: false,
+            },
+            &[stranger],
+        ),
+        CustomError::MissingRole,
+    );
+    assert_ok(execute_swap(&mut env, order, output, vault, 1));
+}
+
+#[test]
+fn swaps_need_a_token_escrow() {
+    let mut env = Env::new(30);
+    let (_, output) = pool(&mut env);
+    set_swap_config(&mut env, mock_swap::ID, false);
+    let user = env.user();
+    let vault = env.create_token_account(output, Pubkey::new_unique(), 0);
+    env.create_token_account(native_mint(), env.treasury, 0);
+    let order = env.open_sol(user, SOL, 3600);
+    // Lamport escrows have no token account to swap from.
+    env.create_token_account(native_mint(), order, 0);
+
+    assert_error(
+        execute_swap(&mut env, order, output, vault, 1),
+        CustomError::InvalidEscrow,
+    );
+}
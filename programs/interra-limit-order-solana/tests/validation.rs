@@ -0,0 +1,315 @@
+//! Checks shared by the `open_order_*` instructions.
+
+mod common;
+
+use anchor_lang::system_program;
+use common::*;
+use limit_order::{
+    accounts, instruction, AddressFormat, ChainConfigParams, CustomError, MintPolicyParams,
+    MintStatus, OpenOrderParams, PriceFeed, TokenRouteParams,
+};
+
+fn chain(
+    address_format: AddressFormat,
+    min_amount_in: u64,
+    max_amount_in: u64,
+) -> ChainConfigParams {
+    ChainConfigParams {
+        enabled: true,
+        address_format,
+        min_amount_in,
+        max_amount_in,
+        platform_fee_override: None,
+    }
+}
+
+fn route(enabled: bool, min_amount_in: u64, max_amount_in: u64) -> TokenRouteParams {
+    TokenRouteParams {
+        enabled,
+        from_decimals: 9,
+        to_decimals: 18,
+        min_amount_in,
+        max_amount_in,
+    }
+}
+
+type ParamsEdit = Box<dyn FnOnce(&mut OpenOrderParams)>;
+
+fn open_sol_expecting(env: &mut Env, code: CustomError, edit: impl FnOnce(&mut OpenOrderParams)) {
+    let user = env.user();
+    let mut params = env.params(native_mint(), SOL, env.svm.now() + 3600);
+    edit(&mut params);
+    assert_error(env.open_sol_with(user, params), code);
+}
+
+#[test]
+fn rejects_malformed_parameters() {
+    let mut env = Env::new(30);
+    let now = env.svm.now();
+    let cases: Vec<ParamsEdit> = vec![
+        Box::new(|params| params.from_chain_id = CHAIN_ID + 1),
+        Box::new(|params| params.amount_in = 0),
+        Box::new(|params| params.recipient = [0; 32]),
+        Box::new(move |params| params.expiry = now),
+        Box::new(|params| params.timelock = 1),
+        Box::new(move |params| {
+            params.hashlock = [1; 32];
+            params.timelock = now;
+            params.htlc_solver = Pubkey::new_unique();
+        }),
+        // HTLC orders are claimed by the solver they bind, and only by it.
+        Box::new(move |params| {
+            params.hashlock = [1; 32];
+            params.timelock = now + 60;
+        }),
+        Box::new(|params| params.htlc_solver = Pubkey::new_unique()),
+        Box::new(move |params| params.exclusivity_deadline = now + 60),
+        Box::new(move |params| {
+            params.exclusive_executor = Pubkey::new_unique();
+            params.exclusivity_deadline = params.expiry + 1;
+        }),
+        Box::new(move |params| {
+            params.exclusive_executor = Pubkey::new_unique();
+            params.hashlock = [1; 32];
+            params.timelock = now + 60;
+            params.htlc_solver = params.exclusive_executor;
+            params.exclusivity_deadline = now + 60;
+        }),
+    ];
+    for edit in cases {
+        open_sol_expecting(&mut env, CustomError::InvalidParameter, edit);
+    }
+}
+
+#[test]
+fn sol_orders_must_escrow_the_native_mint() {
+    let mut env = Env::new(30);
+    let mint = env.create_mint(9);
+    open_sol_expecting(&mut env, CustomError::InvalidParameter, |params| {
+        params.from_token = mint
+    });
+}
+
+#[test]
+fn rejects_unsupported_chains() {
+    let mut env = Env::new(30);
+    let mut disabled = chain(AddressFormat::Bytes32, 0, 0);
+    disabled.enabled = false;
+    assert_ok(env.set_chain_config(DEST_CHAIN, disabled));
+    open_sol_expecting(&mut env, CustomError::ChainNotSupported, |_| {});
+
+    // Same-chain orders need this deployment's own chain to be enabled too.
+    let mut local = chain(AddressFormat::Bytes32, 0, 0);
+    local.enabled = false;
+    assert_ok(env.set_chain_config(CHAIN_ID, local));
+    assert_ok(env.set_route(native_mint(), CHAIN_ID, route(true, 0, 0)));
+    open_sol_expecting(&mut env, CustomError::ChainNotSupported, |params| {
+        params.to_chain_id = CHAIN_ID
+    });
+}
+
+#[test]
+fn checks_the_recipient_format() {
+    let mut env = Env::new(30);
+    assert_ok(env.set_chain_config(DEST_CHAIN, chain(AddressFormat::Evm, 0, 0)));
+    open_sol_expecting(&mut env, CustomError::InvalidRecipient, |_| {});
+    open_sol_expecting(&mut env, CustomError::InvalidRecipient, |params| {
+        params.recipient = [0; 32];
+        params.recipient[..12].copy_from_slice(&[1; 12]);
+    });
+
+    let user = env.user();
+    let mut params = env.params(native_mint(), SOL, env.svm.now() + 3600);
+    params.recipient = [0; 32];
+    params.recipient[12..].copy_from_slice(&[0xab; 20]);
+    assert_ok(env.open_sol_with(user, params));
+}
+
+#[test]
+fn enforces_chain_and_route_amount_limits() {
+    let mut env = Env::new(30);
+    assert_ok(env.set_chain_config(DEST_CHAIN, chain(AddressFormat::Bytes32, 100, 1_000)));
+    let bounds = |amount_in| move |params: &mut OpenOrderParams| params.amount_in = amount_in;
+    open_sol_expecting(&mut env, CustomError::AmountOutOfRange, bounds(99));
+    open_sol_expecting(&mut env, CustomError::AmountOutOfRange, bounds(1_001));
+    let user = env.user();
+    let params = env.params(native_mint(), 1_000, env.svm.now() + 3600);
+    assert_ok(env.open_sol_with(user, params));
+
+    assert_error(
+        env.set_chain_config(DEST_CHAIN, chain(AddressFormat::Bytes32, 100, 99)),
+        CustomError::InvalidParameter,
+    );
+    assert_error(
+        env.set_chain_config(0, chain(AddressFormat::Bytes32, 0, 0)),
+        CustomError::InvalidParameter,
+    );
+
+    let mint = env.create_mint(9);
+    assert_ok(env.set_route(mint, DEST_CHAIN, route(true, 500, 0)));
+    let user = env.user();
+    env.create_token_account(mint, user, 1_000);
+    let params = env.params(mint, 499, env.svm.now() + 3600);
+    assert_error(
+        env.open_spl_with(user, params),
+        CustomError::AmountOutOfRange,
+    );
+    let params = env.params(mint, 500, env.svm.now() + 3600);
+    assert_ok(env.open_spl_with(user, params));
+}
+
+#[test]
+fn rejects_disabled_routes() {
+    let mut env = Env::new(30);
+    let mint = env.create_mint(9);
+    assert_ok(env.set_route(mint, DEST_CHAIN, route(false, 0, 0)));
+    let user = env.user();
+    env.create_token_account(mint, user, 1_000);
+    let params = env.params(mint, 1_000, env.svm.now() + 3600);
+    assert_error(
+        env.open_spl_with(user, params),
+        CustomError::TokenRouteNotSupported,
+    );
+
+    assert_error(
+        env.set_route(mint, DEST_CHAIN, route(true, 10, 9)),
+        CustomError::InvalidParameter,
+    );
+
+    // A route registered with the wrong decimals would misprice the order.
+    let mint = env.create_mint(6);
+    assert_ok(env.set_route(mint, DEST_CHAIN, route(true, 0, 0)));
+    env.create_token_account(mint, user, 1_000);
+    let params = env.params(mint, 1_000, env.svm.now() + 3600);
+    assert_error(
+        env.open_spl_with(user, params),
+        CustomError::TokenRouteNotSupported,
+    );
+}
+
+#[test]
+fn applies_mint_policies() {
+    let mut env = Env::new(30);
+    let mint = env.create_mint_with(6, Some(Pubkey::new_unique()));
+    let user = env.user();
+    env.create_token_account(mint, user, 10_000);
+    let open = |env: &mut Env, amount_in: u64| {
+        let params = env.params(mint, amount_in, env.svm.now() + 3600);
+        env.open_spl_with(user, params)
+    };
+
+    // Without a policy a mint is accepted until allowlist mode is turned on.
+    assert_ok(env.admin(instruction::SetMintAllowlistMode {
+        mint_allowlist_mode: true,
+    }));
+    assert_error(open(&mut env, 1_000), CustomError::MintNotAllowed);
+
+    let policy = |status, require_no_freeze_authority| MintPolicyParams {
+        status,
+        min_order_size: 100,
+        require_no_freeze_authority,
+    };
+    assert_ok(env.set_mint_policy(mint, policy(MintStatus::Denied, false)));
+    assert_error(open(&mut env, 1_000), CustomError::MintNotAllowed);
+
+    assert_ok(env.set_mint_policy(mint, policy(MintStatus::Allowed, true)));
+    assert_error(open(&mut env, 1_000), CustomError::MintHasFreezeAuthority);
+
+    assert_ok(env.set_mint_policy(mint, policy(MintStatus::Allowed, false)));
+    assert_error(open(&mut env, 99), CustomError::AmountOutOfRange);
+    env.svm.warp(1);
+    assert_ok(open(&mut env, 100));
+}
+
+fn set_price(env: &mut Env, authority: Pubkey, mint: Pubkey, price: u64) -> TxResult {
+    env.send(
+        accounts::SetPrice {
+            price_feed: price_feed_pda(&mint),
+            global_config: global_config_pda(),
+            roles: None,
+            authority,
+            system_program: system_program::ID,
+        },
+        instruction::SetPrice { mint, price },
+        &[authority],
+    )
+}
+
+#[test]
+fn enforces_order_minimums() {
+    let mut env = Env::new(30);
+    // Prices always go stale eventually.
+    for max_price_age in [-1, 0] {
+        assert_error(
+            env.admin(instruction::SetOrderMinimums {
+                min_sol_order_size: 0,
+                min_notional: 0,
+                max_price_age,
+            }),
+            CustomError::InvalidParameter,
+        );
+    }
+    assert_ok(env.admin(instruction::SetOrderMinimums {
+        min_sol_order_size: SOL / 10,
+        min_notional: 1_000_000,
+        max_price_age: 600,
+    }));
+    let config = env.config();
+    assert_eq!(config.min_sol_order_size, SOL / 10);
+    assert_eq!(config.min_notional, 1_000_000);
+
+    open_sol_expecting(&mut env, CustomError::AmountOutOfRange, |params| {
+        params.amount_in = SOL / 10 - 1;
+    });
+    let user = env.user();
+    let params = env.params(native_mint(), SOL / 10, env.svm.now() + 3600);
+    assert_ok(env.open_sol_with(user, params));
+
+    // Without a price there is no notional to check.
+    let mint = env.create_mint(6);
+    env.create_token_account(mint, user, 10_000_000);
+    let open = |env: &mut Env, amount_in: u64| {
+        // Each open needs its own expiry.
+        env.svm.warp(1);
+        let params = env.params(mint, amount_in, env.svm.now() + 3600);
+        env.open_spl_with(user, params)
+    };
+    assert_ok(open(&mut env, 1));
+
+    let stranger = env.user();
+    assert_error(
+        set_price(&mut env, stranger, mint, 1),
+        CustomError::MissingRole,
+    );
+    let owner = env.owner;
+    assert_error(
+        set_price(&mut env, owner, mint, 0),
+        CustomError::InvalidParameter,
+    );
+    // One notional unit per base unit.
+    assert_ok(set_price(&mut env, owner, mint, 1_000_000_000));
+    let feed: PriceFeed = env.svm.fetch(&price_feed_pda(&mint));
+    assert_eq!(feed.notional(1_000_000), 1_000_000);
+    assert_error(open(&mut env, 999_999), CustomError::AmountOutOfRange);
+    assert_ok(open(&mut env, 1_000_000));
+
+    // A stale price blocks opens until it is refreshed.
+    env.svm.warp(600);
+    assert_error(open(&mut env, 1_000_000), CustomError::StalePrice);
+    assert_ok(set_price(&mut env, owner, mint, 1_000_000_000));
+    assert_ok(open(&mut env, 1_000_000));
+}
+
+#[test]
+fn paused_deployments_reject_orders() {
+    let mut env = Env::new(30);
+    let user = env.user();
+    let order = env.open_sol(user, SOL, 3600);
+    assert_ok(env.admin(instruction::SetPaused { paused: true }));
+
+    open_sol_expecting(&mut env, CustomError::Paused, |_| {});
+    let target = Pubkey::new_unique();
+    assert_error(env.execute_sol(order, target), CustomError::Paused);
+    // Funds stay withdrawable while paused.
+    assert_ok(env.cancel_sol(order, user));
+}
@@ -0,0 +1,135 @@
+// Constant-product AMM implementing the limit order swap adapter interface.
+// Only meant for tests: there is a single pool per token pair, funded
+// directly into token accounts owned by the pool authority PDA.
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Token, TokenAccount, Transfer};
+
+declare_id!("CXv8PfQtY329DC67QTPjq2C8Nu7s48PLsjdLca1wEqDF");
+
+#[program]
+pub mod mock_swap {
+    use super::*;
+
+    /// Swaps `amount_in` of `source` into `destination` at the pool's
+    /// constant-product price. `min_amount_out` is left for the caller to
+    /// enforce, so tests can exercise the caller's own check. A [`PoolMode`]
+    /// passed after the adapter's accounts makes the pool pull the wrong
+    /// amount instead.
+    pub fn swap<'info>(
+        ctx: Context<'_, '_, 'info, 'info, Swap<'info>>,
+        amount_in: u64,
+        _min_amount_out: u64,
+    ) -> Result<()> {
+        let reserve_in = ctx.accounts.pool_source.amount as u128;
+        let reserve_out = ctx.accounts.pool_destination.amount as u128;
+        let amount_out = reserve_out
+            .checked_mul(amount_in as u128)
+            .and_then(|product| product.checked_div(reserve_in + amount_in as u128))
+            .ok_or(SwapError::EmptyPool)? as u64;
+        require!(amount_out > 0, SwapError::EmptyPool);
+        let mode = match ctx.remaining_accounts.first() {
+            Some(account) => Account::<PoolMode>::try_from(account)?.mode,
+            None => SwapMode::Honest,
+        };
+        let amount_pulled = match mode {
+            SwapMode::PullExtra => amount_in + 1,
+            SwapMode::PullShort => amount_in - 1,
+            SwapMode::Honest | SwapMode::SkimLamports => amount_in,
+        };
+        if mode == SwapMode::SkimLamports {
+            **ctx
+                .accounts
+                .source
+                .to_account_info()
+                .try_borrow_mut_lamports()? -= 1;
+            **ctx
+                .accounts
+                .destination
+                .to_account_info()
+                .try_borrow_mut_lamports()? += 1;
+        }
+
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.source.to_account_info(),
+                    to: ctx.accounts.pool_source.to_account_info(),
+                    authority: ctx.accounts.authority.to_account_info(),
+                },
+            ),
+            amount_pulled,
+        )?;
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.pool_destination.to_account_info(),
+                    to: ctx.accounts.destination.to_account_info(),
+                    authority: ctx.accounts.pool_authority.to_account_info(),
+                },
+                &[&[b"pool", &[ctx.bumps.pool_authority]]],
+            ),
+            amount_out,
+        )?;
+        Ok(())
+    }
+}
+
+/// The swap adapter interface: `authority` owns `source`, and the adapter's
+/// own accounts follow `token_program`.
+#[derive(Accounts)]
+pub struct Swap<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(mut)]
+    pub source: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub destination: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+
+    #[account(
+        mut,
+        constraint = pool_source.owner == pool_authority.key(),
+        constraint = pool_source.mint == source.mint
+    )]
+    pub pool_source: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = pool_destination.owner == pool_authority.key(),
+        constraint = pool_destination.mint == destination.mint
+    )]
+    pub pool_destination: Account<'info, TokenAccount>,
+
+    /// CHECK: PDA owning the pool's token accounts
+    #[account(seeds = [b"pool"], bump)]
+    pub pool_authority: UncheckedAccount<'info>,
+}
+
+/// How much of `source` the pool pulls for a swap of `amount_in`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum SwapMode {
+    Honest,
+    /// One more than `amount_in`.
+    PullExtra,
+    /// One less than `amount_in`.
+    PullShort,
+    /// Also moves a lamport out of `source`, which only the token program
+    /// may debit.
+    SkimLamports,
+}
+
+/// Written directly into test state; there is no instruction to set it.
+#[account]
+pub struct PoolMode {
+    pub mode: SwapMode,
+}
+
+#[error_code]
+pub enum SwapError {
+    #[msg("Pool cannot fill this swap.")]
+    EmptyPool,
+}
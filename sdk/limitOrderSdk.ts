@@ -0,0 +1,161 @@
+import * as anchor from "@coral-xyz/anchor";
+import { PublicKey } from "@solana/web3.js";
+
+export type TokenRoute = {
+  publicKey: PublicKey;
+  fromToken: PublicKey;
+  toChainId: anchor.BN;
+  toToken: number[];
+  enabled: boolean;
+  fromDecimals: number;
+  toDecimals: number;
+  minAmountIn: anchor.BN;
+  maxAmountIn: anchor.BN;
+};
+
+export type OrderBookEntry = {
+  order: PublicKey;
+  amountIn: anchor.BN;
+  expiry: anchor.BN;
+};
+
+// TokenRoute 账户布局: discriminator(8) | from_token(32) | to_chain_id(8) | ...
+const TOKEN_ROUTE_FROM_TOKEN_OFFSET = 8;
+const TOKEN_ROUTE_TO_CHAIN_ID_OFFSET = 8 + 32;
+
+export function globalConfigPda(program: anchor.Program): PublicKey {
+  return PublicKey.findProgramAddressSync(
+    [Buffer.from("global-config")],
+    program.programId
+  )[0];
+}
+
+export function chainConfigPda(
+  program: anchor.Program,
+  chainId: number | anchor.BN
+): PublicKey {
+  return PublicKey.findProgramAddressSync(
+    [
+      Buffer.from("chain-config"),
+      new anchor.BN(chainId).toArrayLike(Buffer, "le", 8),
+    ],
+    program.programId
+  )[0];
+}
+
+export function tokenRoutePda(
+  program: anchor.Program,
+  fromToken: PublicKey,
+  toChainId: number | anchor.BN,
+  toToken: Uint8Array | number[]
+): PublicKey {
+  return PublicKey.findProgramAddressSync(
+    [
+      Buffer.from("token-route"),
+      fromToken.toBuffer(),
+      new anchor.BN(toChainId).toArrayLike(Buffer, "le", 8),
+      Buffer.from(toToken),
+    ],
+    program.programId
+  )[0];
+}
+
+export function mintPolicyPda(
+  program: anchor.Program,
+  mint: PublicKey
+): PublicKey {
+  return PublicKey.findProgramAddressSync(
+    [Buffer.from("mint-policy"), mint.toBuffer()],
+    program.programId
+  )[0];
+}
+
+export function priceFeedPda(
+  program: anchor.Program,
+  mint: PublicKey
+): PublicKey {
+  return PublicKey.findProgramAddressSync(
+    [Buffer.from("price-feed"), mint.toBuffer()],
+    program.programId
+  )[0];
+}
+
+export function sessionKeyPda(
+  program: anchor.Program,
+  user: PublicKey,
+  key: PublicKey
+): PublicKey {
+  return PublicKey.findProgramAddressSync(
+    [Buffer.from("session-key"), user.toBuffer(), key.toBuffer()],
+    program.programId
+  )[0];
+}
+
+export function orderBookPda(
+  program: anchor.Program,
+  fromToken: PublicKey,
+  toChainId: number | anchor.BN
+): PublicKey {
+  return PublicKey.findProgramAddressSync(
+    [
+      Buffer.from("order-book"),
+      fromToken.toBuffer(),
+      new anchor.BN(toChainId).toArrayLike(Buffer, "le", 8),
+    ],
+    program.programId
+  )[0];
+}
+
+/**
+ * Reads the listed orders of a `(fromToken, toChainId)` market with a single
+ * account fetch. Entries can include expired orders until the book is pruned.
+ */
+export async function fetchOrderBook(
+  program: anchor.Program,
+  fromToken: PublicKey,
+  toChainId: number | anchor.BN
+): Promise<OrderBookEntry[]> {
+  const book = await program.account["orderBook"].fetch(
+    orderBookPda(program, fromToken, toChainId)
+  );
+  return (book.slots as OrderBookEntry[]).slice(0, book.count as number);
+}
+
+/**
+ * Lists registered token routes, optionally narrowed to a source token
+ * and/or destination chain. Disabled routes are skipped unless
+ * `includeDisabled` is set.
+ */
+export async function listTokenRoutes(
+  program: anchor.Program,
+  filter: {
+    fromToken?: PublicKey;
+    toChainId?: number | anchor.BN;
+    includeDisabled?: boolean;
+  } = {}
+): Promise<TokenRoute[]> {
+  const filters = [];
+  if (filter.fromToken) {
+    filters.push({
+      memcmp: {
+        offset: TOKEN_ROUTE_FROM_TOKEN_OFFSET,
+        bytes: filter.fromToken.toBase58(),
+      },
+    });
+  }
+  if (filter.toChainId !== undefined) {
+    filters.push({
+      memcmp: {
+        offset: TOKEN_ROUTE_TO_CHAIN_ID_OFFSET,
+        bytes: anchor.utils.bytes.bs58.encode(
+          new anchor.BN(filter.toChainId).toArrayLike(Buffer, "le", 8)
+        ),
+      },
+    });
+  }
+
+  const accounts = await program.account["tokenRoute"].all(filters);
+  return accounts
+    .map(({ publicKey, account }) => ({ publicKey, ...account } as TokenRoute))
+    .filter((route) => filter.includeDisabled || route.enabled);
+}
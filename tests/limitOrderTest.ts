@@ -1,7 +1,13 @@
 import * as anchor from "@coral-xyz/anchor";
 import { AnchorError } from "@coral-xyz/anchor";
 
-import { PublicKey, SystemProgram } from "@solana/web3.js";
+import {
+  ComputeBudgetProgram,
+  Ed25519Program,
+  PublicKey,
+  SystemProgram,
+  SYSVAR_INSTRUCTIONS_PUBKEY,
+} from "@solana/web3.js";
 import {
   createMint,
   getOrCreateAssociatedTokenAccount,
@@ -10,9 +16,25 @@ import {
   getAccount,
   getAssociatedTokenAddress,
   createAssociatedTokenAccount,
+  NATIVE_MINT,
 } from "@solana/spl-token";
-import { createSolOrder, createSplOrder } from "./limitOrderTestHelpers";
+import {
+  createSolOrder,
+  createSplOrder,
+  createWsolOrder,
+} from "./limitOrderTestHelpers";
+import {
+  chainConfigPda,
+  fetchOrderBook,
+  listTokenRoutes,
+  mintPolicyPda,
+  orderBookPda,
+  priceFeedPda,
+  sessionKeyPda,
+  tokenRoutePda,
+} from "../sdk/limitOrderSdk";
 import { expect } from "chai";
+import { createHash } from "crypto";
 
 describe("globalConfig test", () => {
   const provider = anchor.AnchorProvider.local();
@@ -47,7 +69,7 @@ describe("globalConfig test", () => {
     );
 
     await program.methods
-      .initialize(platformFee, user)
+      .initialize(platformFee, user, new anchor.BN(10002))
       .accounts({
         global_config: globalConfigPda,
         signer: user,
@@ -64,6 +86,9 @@ describe("globalConfig test", () => {
     expect(config.platformFee).to.equal(platformFee);
     expect(config.treasury.toBase58()).to.equal(user.toBase58());
     expect(config.paused).to.equal(false);
+    expect(config.chainId.toNumber()).to.equal(10002);
+    expect(config.version).to.equal(5);
+    expect(config.maxPriceAge.toNumber()).to.equal(3600);
   });
 
   it("should fail if non-owner tries to update global config", async () => {
@@ -78,10 +103,11 @@ describe("globalConfig test", () => {
     let caughtError = null;
     try {
       await program.methods
-        .updateConfig(nonOwner.publicKey, 50, nonOwner.publicKey, false)
+        .setPlatformFee(50)
         .accounts({
           global_config: globalConfigPda,
-          owner: nonOwner.publicKey,
+          roles: null,
+          authority: nonOwner.publicKey,
         })
         .signers([nonOwner])
         .rpc();
@@ -89,20 +115,18 @@ describe("globalConfig test", () => {
       caughtError = err;
     }
     const anchorError = caughtError as AnchorError;
-    expect(anchorError.error.errorCode.code).to.equal("ConstraintHasOne");
+    expect(anchorError.error.errorCode.code).to.equal("MissingRole");
   });
 
   it("should success if owner tries to update global config", async () => {
     const platformFee = 50;
 
     const listener = await program.addEventListener(
-      "ConfigUpdated",
+      "PlatformFeeUpdated",
       (event: any, slot) => {
         try {
-          expect(event.owner.toBase58()).to.equal(user.toBase58());
-          expect(event.platform_fee.toNumber()).to.equal(platformFee);
-          expect(event.treasury.toBase58()).to.equal(user.toBase58());
-          expect(event.paused()).to.equal(false);
+          expect(event.oldPlatformFee).to.equal(30);
+          expect(event.newPlatformFee).to.equal(platformFee);
         } catch (e) {
           console.error("Event assertion failed:", e);
           throw e;
@@ -111,10 +135,19 @@ describe("globalConfig test", () => {
     );
 
     await program.methods
-      .updateConfig(user, platformFee, user, false)
+      .setPlatformFee(platformFee)
       .accounts({
         global_config: globalConfigPda,
-        owner: user,
+        roles: null,
+        authority: user,
+      })
+      .rpc();
+    await program.methods
+      .setTreasury(user)
+      .accounts({
+        global_config: globalConfigPda,
+        roles: null,
+        authority: user,
       })
       .rpc();
 
@@ -128,6 +161,114 @@ describe("globalConfig test", () => {
     expect(config.treasury.toBase58()).to.equal(user.toBase58());
     expect(config.paused).to.equal(false);
   });
+
+  it("should fail if the platform fee exceeds the ceiling", async () => {
+    let caughtError = null;
+    try {
+      await program.methods
+        .setPlatformFee(1001)
+        .accounts({
+          global_config: globalConfigPda,
+          roles: null,
+          authority: user,
+        })
+        .rpc();
+    } catch (err) {
+      caughtError = err;
+    }
+    const anchorError = caughtError as AnchorError;
+    expect(anchorError.error.errorCode.code).to.equal("InvalidPlatformFee");
+  });
+
+  it("registers destination chains", async () => {
+    await program.methods
+      .setChainConfig(new anchor.BN(2), {
+        enabled: true,
+        addressFormat: { bytes32: {} },
+        minAmountIn: new anchor.BN(0),
+        maxAmountIn: new anchor.BN(0),
+        platformFeeOverride: null,
+      })
+      .accounts({
+        chainConfig: chainConfigPda(program, 2),
+        globalConfig: globalConfigPda,
+        authority: user,
+        systemProgram: SystemProgram.programId,
+        roles: null,
+        proposal: null,
+      })
+      .rpc();
+
+    await program.methods
+      .setChainConfig(new anchor.BN(5), {
+        enabled: true,
+        addressFormat: { evm: {} },
+        minAmountIn: new anchor.BN(1_000),
+        maxAmountIn: new anchor.BN(1_000_000_000),
+        platformFeeOverride: 10,
+      })
+      .accounts({
+        chainConfig: chainConfigPda(program, 5),
+        globalConfig: globalConfigPda,
+        authority: user,
+        systemProgram: SystemProgram.programId,
+        roles: null,
+        proposal: null,
+      })
+      .rpc();
+
+    const chainConfig = await program.account["chainConfig"].fetch(
+      chainConfigPda(program, 5)
+    );
+    expect(chainConfig.enabled).to.equal(true);
+    expect(chainConfig.minAmountIn.toNumber()).to.equal(1_000);
+    expect(chainConfig.platformFeeOverride).to.equal(10);
+  });
+
+  it("should reject orders that don't match the chain config", async () => {
+    const expiry = new anchor.BN(Math.floor(Date.now() / 1000) + 3600);
+
+    // 32 字节 recipient 不符合 EVM 地址格式
+    let caughtError = null;
+    try {
+      await createSolOrder(program, user, new anchor.BN(1_000_000), expiry, {
+        toChainId: new anchor.BN(5),
+      });
+    } catch (err) {
+      caughtError = err;
+    }
+    expect((caughtError as AnchorError).error.errorCode.code).to.equal(
+      "InvalidRecipient"
+    );
+
+    const evmRecipient = new Uint8Array(32);
+    evmRecipient.set(Buffer.alloc(20, 1), 12);
+
+    // 低于最小下单金额
+    caughtError = null;
+    try {
+      await createSolOrder(program, user, new anchor.BN(999), expiry, {
+        toChainId: new anchor.BN(5),
+        recipient: evmRecipient,
+      });
+    } catch (err) {
+      caughtError = err;
+    }
+    expect((caughtError as AnchorError).error.errorCode.code).to.equal(
+      "AmountOutOfRange"
+    );
+
+    // 目标链不能是本链
+    caughtError = null;
+    try {
+      await createSolOrder(program, user, new anchor.BN(1_000_000), expiry, {
+        toChainId: new anchor.BN(10002),
+      });
+    } catch (err) {
+      caughtError = err;
+    }
+    expect(caughtError).to.not.be.null;
+  });
 });
 
 describe("openOrder test", () => {
@@ -296,7 +437,7 @@ describe("openOrder test", () => {
     const accountInfo = await provider.connection.getAccountInfo(orderPda);
     const rentExempt =
       await provider.connection.getMinimumBalanceForRentExemption(
-        8 + 32 + 8 + 8 + 8 + 32 + 32 + 32 + 8 + 32 + 1
+        program.account["limitOrder"].size
       );
     const actualDeposit = accountInfo.lamports - rentExempt;
 
@@ -354,6 +495,9 @@ describe("cancelOrder test", () => {
         refundReceiver: user,
         globalConfig: globalConfigPda,
         systemProgram: SystemProgram.programId,
+        orderBook: null,
+        sessionKey: null,
+        roles: null,
       })
       .rpc();
 
@@ -368,6 +512,58 @@ describe("cancelOrder test", () => {
     expect(closedOrderInfo).to.be.null;
   });
 
+  it("should refund a cancelled order to its refund address", async () => {
+    const refundAddress = anchor.web3.Keypair.generate().publicKey;
+    const expiry = new anchor.BN(Math.floor(Date.now() / 1000) + 3700);
+    const amount = new anchor.BN(1_000_000);
+
+    const [orderPda] = await createSolOrder(program, user, amount, expiry, {
+      refundAddress,
+    });
+    const order = await program.account["limitOrder"].fetch(orderPda);
+    expect(order.refundAddress.toBase58()).to.equal(refundAddress.toBase58());
+
+    // 退款只能发往订单的退款地址
+    let caughtError = null;
+    try {
+      await program.methods
+        .cancelOrderSol()
+        .accounts({
+          order: orderPda,
+          user,
+          refundReceiver: user,
+          globalConfig: globalConfigPda,
+          systemProgram: SystemProgram.programId,
+          orderBook: null,
+          sessionKey: null,
+          roles: null,
+        })
+        .rpc();
+    } catch (err) {
+      caughtError = err;
+    }
+    expect((caughtError as AnchorError).error.errorCode.code).to.equal(
+      "InvalidRefundReceiver"
+    );
+
+    await program.methods
+      .cancelOrderSol()
+      .accounts({
+        order: orderPda,
+        user,
+        refundReceiver: refundAddress,
+        globalConfig: globalConfigPda,
+        systemProgram: SystemProgram.programId,
+        orderBook: null,
+        sessionKey: null,
+        roles: null,
+      })
+      .rpc();
+
+    const refunded = await provider.connection.getBalance(refundAddress);
+    expect(refunded).to.be.at.least(amount.toNumber());
+  });
+
   it("should cancel a SPL-token limit order", async () => {
     // 1. 创建测试 SPL Token mint
     const mint = await createMint(
@@ -434,6 +630,9 @@ describe("cancelOrder test", () => {
         refundReceiver: user,
         globalConfig: globalConfigPda,
         tokenProgram: TOKEN_PROGRAM_ID,
+        orderBook: null,
+        sessionKey: null,
+        roles: null,
       })
       .rpc();
 
@@ -517,7 +716,13 @@ describe("executeOrderSol test", () => {
         treasury: user,
         executor: user,
         globalConfig: globalConfigPda,
+        chainConfig: chainConfigPda(program, 2),
         refundReceiver: user,
+        guardianSet: null,
+        instructions: null,
+        executorBond: null,
+        roles: null,
+        orderBook: null,
       })
       .rpc();
 
@@ -628,9 +833,15 @@ describe("executeOrderSol test", () => {
         targetTokenAccount: targetTokenAccount.address,
         treasuryTokenAccount: treasuryAta,
         globalConfig: globalConfigPda,
+        chainConfig: chainConfigPda(program, 2),
         refundReceiver: user,
         executor: user,
         tokenProgram: TOKEN_PROGRAM_ID,
+        guardianSet: null,
+        instructions: null,
+        executorBond: null,
+        roles: null,
+        orderBook: null,
       })
       .rpc();
 
@@ -662,3 +873,2147 @@ describe("executeOrderSol test", () => {
     expect(orderInfo).to.be.null;
   });
 });
+
+describe("htlcOrder test", () => {
+  const provider = anchor.AnchorProvider.local();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace.limitOrder as anchor.Program;
+  const user = provider.wallet.publicKey;
+
+  let globalConfigPda: PublicKey;
+  [globalConfigPda] = PublicKey.findProgramAddressSync(
+    [Buffer.from("global-config")],
+    program.programId
+  );
+
+  const preimage = new Uint8Array(
+    Buffer.from("interra-htlc-secret".padEnd(32))
+  );
+  const hashlock = new Uint8Array(
+    createHash("sha256").update(preimage).digest()
+  );
+
+  it("should let a solver claim a SOL HTLC order with the preimage", async () => {
+    const now = Math.floor(Date.now() / 1000);
+    const expiry = new anchor.BN(now + 3700);
+    const amountIn = new anchor.BN(10_000_000);
+    const solver = anchor.web3.Keypair.generate();
+    const [orderPda] = await createSolOrder(program, user, amountIn, expiry, {
+      hashlock,
+      timelock: new anchor.BN(now + 600),
+      htlcSolver: solver.publicKey,
+    });
+
+    const watcher = anchor.web3.Keypair.generate();
+    for (const key of [solver.publicKey, watcher.publicKey]) {
+      const airdropSignature = await provider.connection.requestAirdrop(
+        key,
+        1_000_000_000
+      );
+      await provider.connection.confirmTransaction(airdropSignature);
+    }
+
+    // 普通取消应被拒绝
+    let caughtError = null;
+    try {
+      await program.methods
+        .cancelOrderSol()
+        .accounts({
+          order: orderPda,
+          user,
+          refundReceiver: user,
+          globalConfig: globalConfigPda,
+          systemProgram: SystemProgram.programId,
+          orderBook: null,
+          sessionKey: null,
+          roles: null,
+        })
+        .rpc();
+    } catch (err) {
+      caughtError = err;
+    }
+    expect((caughtError as AnchorError).error.errorCode.code).to.equal(
+      "HtlcOrder"
+    );
+
+    // 错误的 preimage
+    caughtError = null;
+    try {
+      await program.methods
+        .claimHtlcOrderSol(Array.from(new Uint8Array(32)))
+        .accounts({
+          order: orderPda,
+          solver: solver.publicKey,
+          treasury: user,
+          globalConfig: globalConfigPda,
+          chainConfig: chainConfigPda(program, 2),
+          refundReceiver: user,
+          orderBook: null,
+        })
+        .signers([solver])
+        .rpc();
+    } catch (err) {
+      caughtError = err;
+    }
+    expect((caughtError as AnchorError).error.errorCode.code).to.equal(
+      "InvalidPreimage"
+    );
+
+    // 知道 preimage 的第三方也不能抢先领取
+    caughtError = null;
+    try {
+      await program.methods
+        .claimHtlcOrderSol(Array.from(preimage))
+        .accounts({
+          order: orderPda,
+          solver: watcher.publicKey,
+          treasury: user,
+          globalConfig: globalConfigPda,
+          chainConfig: chainConfigPda(program, 2),
+          refundReceiver: user,
+          orderBook: null,
+        })
+        .signers([watcher])
+        .rpc();
+    } catch (err) {
+      caughtError = err;
+    }
+    expect((caughtError as AnchorError).error.errorCode.code).to.equal(
+      "NotHtlcSolver"
+    );
+
+    const solverBefore = await provider.connection.getBalance(solver.publicKey);
+
+    await program.methods
+      .claimHtlcOrderSol(Array.from(preimage))
+      .accounts({
+        order: orderPda,
+        solver: solver.publicKey,
+        treasury: user,
+        globalConfig: globalConfigPda,
+        chainConfig: chainConfigPda(program, 2),
+        refundReceiver: user,
+        orderBook: null,
+      })
+      .signers([solver])
+      .rpc();
+
+    const solverAfter = await provider.connection.getBalance(solver.publicKey);
+    const fee = Math.floor((amountIn.toNumber() * 50) / 10_000);
+    // solver 同时支付交易费
+    expect(solverAfter - solverBefore).to.be.at.most(amountIn.toNumber() - fee);
+    expect(solverAfter - solverBefore).to.be.greaterThan(
+      amountIn.toNumber() - fee - 10_000
+    );
+
+    const orderInfo = await provider.connection.getAccountInfo(orderPda);
+    expect(orderInfo).to.be.null;
+  });
+
+  it("should refund a SPL HTLC order to the sender after the timelock", async () => {
+    const mint = await createMint(
+      provider.connection,
+      provider.wallet.payer,
+      user,
+      null,
+      6
+    );
+    const userTokenAccount = await getOrCreateAssociatedTokenAccount(
+      provider.connection,
+      provider.wallet.payer,
+      mint,
+      user
+    );
+    await mintTo(
+      provider.connection,
+      provider.wallet.payer,
+      mint,
+      userTokenAccount.address,
+      user,
+      1_000_000
+    );
+
+    const now = Math.floor(Date.now() / 1000);
+    const expiry = new anchor.BN(now + 3800);
+    const amount = new anchor.BN(1_000_000);
+    const [orderPda, orderTokenAccount] = await createSplOrder(
+      program,
+      provider,
+      user,
+      mint,
+      amount,
+      expiry,
+      { hashlock, timelock: new anchor.BN(now + 3), htlcSolver: user }
+    );
+
+    // timelock 未到期时不可退款
+    let caughtError = null;
+    try {
+      await program.methods
+        .refundHtlcOrderSpl()
+        .accounts({
+          order: orderPda,
+          userTokenAccount: userTokenAccount.address,
+          orderTokenAccount,
+          refundReceiver: user,
+          caller: user,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          orderBook: null,
+        })
+        .rpc();
+    } catch (err) {
+      caughtError = err;
+    }
+    expect((caughtError as AnchorError).error.errorCode.code).to.equal(
+      "TimelockNotExpired"
+    );
+
+    await new Promise((r) => setTimeout(r, 5000));
+
+    // 任何人都可以触发退款，资金始终退回给 sender
+    const keeper = anchor.web3.Keypair.generate();
+    const airdropSignature = await provider.connection.requestAirdrop(
+      keeper.publicKey,
+      1_000_000_000
+    );
+    await provider.connection.confirmTransaction(airdropSignature);
+
+    await program.methods
+      .refundHtlcOrderSpl()
+      .accounts({
+        order: orderPda,
+        userTokenAccount: userTokenAccount.address,
+        orderTokenAccount,
+        refundReceiver: user,
+        caller: keeper.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        orderBook: null,
+      })
+      .signers([keeper])
+      .rpc();
+
+    const userTokenAccountAfter = await getAccount(
+      provider.connection,
+      userTokenAccount.address
+    );
+    expect(Number(userTokenAccountAfter.amount)).to.equal(1_000_000);
+
+    const closedOrderInfo = await provider.connection.getAccountInfo(orderPda);
+    expect(closedOrderInfo).to.be.null;
+  });
+});
+
+describe("guardianAttestation test", () => {
+  const provider = anchor.AnchorProvider.local();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace.limitOrder as anchor.Program;
+  const user = provider.wallet.publicKey;
+
+  let globalConfigPda: PublicKey;
+  [globalConfigPda] = PublicKey.findProgramAddressSync(
+    [Buffer.from("global-config")],
+    program.programId
+  );
+
+  const guardianSetPda = (index: number) =>
+    PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("guardian-set"),
+        new anchor.BN(index).toArrayLike(Buffer, "le", 4),
+      ],
+      program.programId
+    )[0];
+
+  const guardians = [
+    anchor.web3.Keypair.generate(),
+    anchor.web3.Keypair.generate(),
+  ];
+
+  // DOMAIN || order || dest_tx_hash || recipient || amount_delivered
+  const fillAttestation = (order: PublicKey, recipient: Uint8Array) =>
+    Buffer.concat([
+      Buffer.from("interra-limit-order:fill:v1"),
+      order.toBuffer(),
+      Buffer.alloc(32, 7),
+      Buffer.from(recipient),
+      Buffer.alloc(32),
+    ]);
+
+  const attest = (signers: anchor.web3.Keypair[], message: Buffer) =>
+    signers.map((guardian) =>
+      Ed25519Program.createInstructionWithPrivateKey({
+        privateKey: guardian.secretKey,
+        message,
+      })
+    );
+
+  const executeSol = (orderPda: PublicKey, guardianSet: PublicKey) =>
+    program.methods.executeOrderSol(new anchor.BN(0)).accounts({
+      order: orderPda,
+      targetSol: anchor.web3.Keypair.generate().publicKey,
+      treasury: user,
+      executor: user,
+      globalConfig: globalConfigPda,
+      chainConfig: chainConfigPda(program, 2),
+      refundReceiver: user,
+      guardianSet,
+      instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+      executorBond: null,
+      roles: null,
+      orderBook: null,
+    });
+
+  it("initializes the guardian set and requires attestations", async () => {
+    await program.methods
+      .initGuardianSet(guardians.map((g) => g.publicKey), 2)
+      .accounts({
+        guardianSet: guardianSetPda(0),
+        globalConfig: globalConfigPda,
+        authority: user,
+        systemProgram: SystemProgram.programId,
+        roles: null,
+        proposal: null,
+      })
+      .rpc();
+
+    const guardianSet = await program.account["guardianSet"].fetch(
+      guardianSetPda(0)
+    );
+    expect(guardianSet.threshold).to.equal(2);
+    expect(guardianSet.keys.length).to.equal(2);
+
+    const config = await program.account["globalConfig"].fetch(globalConfigPda);
+    expect(config.attestationRequired).to.equal(true);
+  });
+
+  it("should only execute an order with a guardian quorum", async () => {
+    const expiry = new anchor.BN(Math.floor(Date.now() / 1000) + 3900);
+    const [orderPda, params] = await createSolOrder(
+      program,
+      user,
+      new anchor.BN(1_000_000),
+      expiry
+    );
+    const message = fillAttestation(orderPda, params.recipient);
+
+    let caughtError = null;
+    try {
+      await executeSol(orderPda, guardianSetPda(0)).rpc();
+    } catch (err) {
+      caughtError = err;
+    }
+    expect((caughtError as AnchorError).error.errorCode.code).to.equal(
+      "AttestationRequired"
+    );
+
+    caughtError = null;
+    try {
+      await executeSol(orderPda, guardianSetPda(0))
+        .preInstructions(attest([guardians[0]], message))
+        .rpc();
+    } catch (err) {
+      caughtError = err;
+    }
+    expect((caughtError as AnchorError).error.errorCode.code).to.equal(
+      "InsufficientAttestations"
+    );
+
+    await executeSol(orderPda, guardianSetPda(0))
+      .preInstructions(attest(guardians, message))
+      .rpc();
+
+    const orderInfo = await provider.connection.getAccountInfo(orderPda);
+    expect(orderInfo).to.be.null;
+  });
+
+  it("should rotate the guardian set and expire the old one", async () => {
+    const newGuardians = [anchor.web3.Keypair.generate()];
+
+    await program.methods
+      .rotateGuardianSet(
+        newGuardians.map((g) => g.publicKey),
+        1,
+        new anchor.BN(0)
+      )
+      .accounts({
+        currentGuardianSet: guardianSetPda(0),
+        newGuardianSet: guardianSetPda(1),
+        globalConfig: globalConfigPda,
+        authority: user,
+        systemProgram: SystemProgram.programId,
+        roles: null,
+        proposal: null,
+      })
+      .rpc();
+
+    const expiry = new anchor.BN(Math.floor(Date.now() / 1000) + 3950);
+    const [orderPda, params] = await createSolOrder(
+      program,
+      user,
+      new anchor.BN(1_000_000),
+      expiry
+    );
+    const message = fillAttestation(orderPda, params.recipient);
+
+    await new Promise((r) => setTimeout(r, 1500));
+
+    let caughtError = null;
+    try {
+      await executeSol(orderPda, guardianSetPda(0))
+        .preInstructions(attest(guardians, message))
+        .rpc();
+    } catch (err) {
+      caughtError = err;
+    }
+    expect((caughtError as AnchorError).error.errorCode.code).to.equal(
+      "GuardianSetExpired"
+    );
+
+    await executeSol(orderPda, guardianSetPda(1))
+      .preInstructions(attest(newGuardians, message))
+      .rpc();
+
+    // 关闭验证要求，后续测试由 owner 直接执行
+    await program.methods
+      .setAttestationRequired(false)
+      .accounts({
+        globalConfig: globalConfigPda,
+        roles: null,
+        authority: user,
+      })
+      .rpc();
+  });
+});
+
+describe("tokenRoute test", () => {
+  const provider = anchor.AnchorProvider.local();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace.limitOrder as anchor.Program;
+  const user = provider.wallet.publicKey;
+
+  let globalConfigPda: PublicKey;
+  [globalConfigPda] = PublicKey.findProgramAddressSync(
+    [Buffer.from("global-config")],
+    program.programId
+  );
+
+  const nativeMint = new PublicKey(
+    "So11111111111111111111111111111111111111112"
+  );
+  const toToken = new Uint8Array(Buffer.from("disabled".padEnd(32, "\0")));
+
+  it("should reject orders on a disabled route", async () => {
+    await program.methods
+      .setTokenRoute(nativeMint, new anchor.BN(2), Array.from(toToken), {
+        enabled: false,
+        fromDecimals: 9,
+        toDecimals: 18,
+        minAmountIn: new anchor.BN(0),
+        maxAmountIn: new anchor.BN(0),
+      })
+      .accounts({
+        tokenRoute: tokenRoutePda(program, nativeMint, 2, toToken),
+        globalConfig: globalConfigPda,
+        authority: user,
+        systemProgram: SystemProgram.programId,
+        roles: null,
+        proposal: null,
+      })
+      .rpc();
+
+    const expiry = new anchor.BN(Math.floor(Date.now() / 1000) + 4000);
+    let caughtError = null;
+    try {
+      await createSolOrder(program, user, new anchor.BN(1_000_000), expiry, {
+        toToken,
+      });
+    } catch (err) {
+      caughtError = err;
+    }
+    expect((caughtError as AnchorError).error.errorCode.code).to.equal(
+      "TokenRouteNotSupported"
+    );
+  });
+
+  it("lists the enabled routes for a destination chain", async () => {
+    const routes = await listTokenRoutes(program, {
+      fromToken: nativeMint,
+      toChainId: 2,
+    });
+    expect(routes.length).to.be.greaterThan(0);
+    expect(routes.every((route) => route.enabled)).to.be.true;
+    expect(routes.every((route) => route.toChainId.toNumber() === 2)).to.be
+      .true;
+
+    const allRoutes = await listTokenRoutes(program, {
+      fromToken: nativeMint,
+      toChainId: 2,
+      includeDisabled: true,
+    });
+    expect(allRoutes.length).to.equal(routes.length + 1);
+  });
+});
+
+describe("mintPolicy test", () => {
+  const provider = anchor.AnchorProvider.local();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace.limitOrder as anchor.Program;
+  const user = provider.wallet.publicKey;
+
+  let globalConfigPda: PublicKey;
+  [globalConfigPda] = PublicKey.findProgramAddressSync(
+    [Buffer.from("global-config")],
+    program.programId
+  );
+
+  const setMintPolicy = (
+    mint: PublicKey,
+    status: object,
+    requireNoFreezeAuthority: boolean
+  ) =>
+    program.methods
+      .setMintPolicy(mint, {
+        status,
+        minOrderSize: new anchor.BN(0),
+        requireNoFreezeAuthority,
+      })
+      .accounts({
+        mintPolicy: mintPolicyPda(program, mint),
+        globalConfig: globalConfigPda,
+        authority: user,
+        systemProgram: SystemProgram.programId,
+        roles: null,
+        proposal: null,
+      })
+      .rpc();
+
+  const setAllowlistMode = (enabled: boolean) =>
+    program.methods
+      .setMintAllowlistMode(enabled)
+      .accounts({
+        globalConfig: globalConfigPda,
+        roles: null,
+        authority: user,
+      })
+      .rpc();
+
+  const fundedMint = async (freezeAuthority: PublicKey | null) => {
+    const mint = await createMint(
+      provider.connection,
+      provider.wallet.payer,
+      user,
+      freezeAuthority,
+      6
+    );
+    const userTokenAccount = await getOrCreateAssociatedTokenAccount(
+      provider.connection,
+      provider.wallet.payer,
+      mint,
+      user
+    );
+    await mintTo(
+      provider.connection,
+      provider.wallet.payer,
+      mint,
+      userTokenAccount.address,
+      user,
+      1_000_000
+    );
+    return mint;
+  };
+
+  const expectOpenError = async (mint: PublicKey, code: string) => {
+    const expiry = new anchor.BN(Math.floor(Date.now() / 1000) + 4100);
+    let caughtError = null;
+    try {
+      await createSplOrder(
+        program,
+        provider,
+        user,
+        mint,
+        new anchor.BN(1_000),
+        expiry
+      );
+    } catch (err) {
+      caughtError = err;
+    }
+    expect((caughtError as AnchorError).error.errorCode.code).to.equal(code);
+  };
+
+  it("should reject a denied mint", async () => {
+    const mint = await fundedMint(null);
+    await setMintPolicy(mint, { denied: {} }, false);
+    await expectOpenError(mint, "MintNotAllowed");
+  });
+
+  it("should reject a mint with a freeze authority when required", async () => {
+    const mint = await fundedMint(user);
+    await setMintPolicy(mint, { allowed: {} }, true);
+    await expectOpenError(mint, "MintHasFreezeAuthority");
+  });
+
+  it("should only accept allowed mints in allowlist mode", async () => {
+    const mint = await fundedMint(null);
+    await setAllowlistMode(true);
+    try {
+      await expectOpenError(mint, "MintNotAllowed");
+
+      await setMintPolicy(mint, { allowed: {} }, true);
+      const expiry = new anchor.BN(Math.floor(Date.now() / 1000) + 4200);
+      const [orderPda] = await createSplOrder(
+        program,
+        provider,
+        user,
+        mint,
+        new anchor.BN(1_000),
+        expiry
+      );
+      const orderAccount = await program.account["limitOrder"].fetch(orderPda);
+      expect(orderAccount.fromToken.equals(mint)).to.be.true;
+    } finally {
+      await setAllowlistMode(false);
+    }
+  });
+});
+
+describe("wrappedSolOrder test", () => {
+  const provider = anchor.AnchorProvider.local();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace.limitOrder as anchor.Program;
+  const user = provider.wallet.publicKey;
+
+  let globalConfigPda: PublicKey;
+  [globalConfigPda] = PublicKey.findProgramAddressSync(
+    [Buffer.from("global-config")],
+    program.programId
+  );
+
+  it("should escrow wrapped SOL and unwrap it on cancel", async () => {
+    const expiry = new anchor.BN(Math.floor(Date.now() / 1000) + 4300);
+    const amountIn = new anchor.BN(5_000_000);
+    const [orderPda, orderTokenAccount] = await createWsolOrder(
+      program,
+      user,
+      amountIn,
+      expiry
+    );
+
+    const escrow = await getAccount(provider.connection, orderTokenAccount);
+    expect(escrow.isNative).to.be.true;
+    expect(Number(escrow.amount)).to.equal(amountIn.toNumber());
+
+    const orderAccount = await program.account["limitOrder"].fetch(orderPda);
+    expect(orderAccount.wrappedSol).to.be.true;
+
+    const balanceBefore = await provider.connection.getBalance(user);
+
+    await program.methods
+      .cancelOrderWsol()
+      .accounts({
+        order: orderPda,
+        user,
+        orderTokenAccount,
+        refundReceiver: user,
+        globalConfig: globalConfigPda,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        orderBook: null,
+        sessionKey: null,
+        roles: null,
+      })
+      .rpc();
+
+    const balanceAfter = await provider.connection.getBalance(user);
+    expect(balanceAfter - balanceBefore).to.be.greaterThan(
+      amountIn.toNumber()
+    );
+
+    expect(await provider.connection.getAccountInfo(orderTokenAccount)).to.be
+      .null;
+    expect(await provider.connection.getAccountInfo(orderPda)).to.be.null;
+  });
+
+  it("should unwrap to the target and keep the fee as WSOL", async () => {
+    const expiry = new anchor.BN(Math.floor(Date.now() / 1000) + 4400);
+    const amountIn = new anchor.BN(10_000_000);
+    const [orderPda, orderTokenAccount] = await createWsolOrder(
+      program,
+      user,
+      amountIn,
+      expiry
+    );
+
+    const treasuryAta = await getAssociatedTokenAddress(NATIVE_MINT, user);
+    if (!(await provider.connection.getAccountInfo(treasuryAta))) {
+      await createAssociatedTokenAccount(
+        provider.connection,
+        provider.wallet.payer,
+        NATIVE_MINT,
+        user
+      );
+    }
+    const treasuryBefore = await getAccount(provider.connection, treasuryAta);
+
+    const targetSol = anchor.web3.Keypair.generate().publicKey;
+
+    await program.methods
+      .executeOrderWsol(amountIn)
+      .accounts({
+        order: orderPda,
+        orderTokenAccount,
+        targetSol,
+        treasuryTokenAccount: treasuryAta,
+        globalConfig: globalConfigPda,
+        chainConfig: chainConfigPda(program, 2),
+        refundReceiver: user,
+        executor: user,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        guardianSet: null,
+        instructions: null,
+        executorBond: null,
+        roles: null,
+        orderBook: null,
+      })
+      .rpc();
+
+    const fee = Math.floor((amountIn.toNumber() * 50) / 10_000);
+    expect(await provider.connection.getBalance(targetSol)).to.equal(
+      amountIn.toNumber() - fee
+    );
+
+    const treasuryAfter = await getAccount(provider.connection, treasuryAta);
+    expect(Number(treasuryAfter.amount - treasuryBefore.amount)).to.equal(fee);
+
+    expect(await provider.connection.getAccountInfo(orderTokenAccount)).to.be
+      .null;
+    expect(await provider.connection.getAccountInfo(orderPda)).to.be.null;
+  });
+});
+
+describe("executeOrdersBatch test", () => {
+  const provider = anchor.AnchorProvider.local();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace.limitOrder as anchor.Program;
+  const user = provider.wallet.publicKey;
+
+  let globalConfigPda: PublicKey;
+  [globalConfigPda] = PublicKey.findProgramAddressSync(
+    [Buffer.from("global-config")],
+    program.programId
+  );
+
+  const setup = async (count: number, expiryBase: number) => {
+    const mint = await createMint(
+      provider.connection,
+      provider.wallet.payer,
+      user,
+      null,
+      6
+    );
+    const userTokenAccount = await getOrCreateAssociatedTokenAccount(
+      provider.connection,
+      provider.wallet.payer,
+      mint,
+      user
+    );
+    await mintTo(
+      provider.connection,
+      provider.wallet.payer,
+      mint,
+      userTokenAccount.address,
+      user,
+      count * 1_000_000
+    );
+    const target = await getOrCreateAssociatedTokenAccount(
+      provider.connection,
+      provider.wallet.payer,
+      mint,
+      anchor.web3.Keypair.generate().publicKey
+    );
+
+    const orders = [];
+    for (let i = 0; i < count; i++) {
+      const expiry = new anchor.BN(expiryBase + i);
+      const [orderPda, orderTokenAccount] = await createSplOrder(
+        program,
+        provider,
+        user,
+        mint,
+        new anchor.BN(1_000_000),
+        expiry
+      );
+      orders.push({ orderPda, orderTokenAccount });
+    }
+    // treasury 为 global_config.treasury (user) 的 ATA
+    return {
+      treasuryAta: userTokenAccount.address,
+      target: target.address,
+      orders,
+    };
+  };
+
+  const remainingAccounts = (
+    orders: { orderPda: PublicKey; orderTokenAccount: PublicKey }[],
+    target: PublicKey,
+    treasuryAta: PublicKey,
+    refundReceiver: PublicKey
+  ) =>
+    orders.flatMap(({ orderPda, orderTokenAccount }) => [
+      { pubkey: orderPda, isWritable: true, isSigner: false },
+      { pubkey: orderTokenAccount, isWritable: true, isSigner: false },
+      { pubkey: target, isWritable: true, isSigner: false },
+      { pubkey: treasuryAta, isWritable: true, isSigner: false },
+      { pubkey: refundReceiver, isWritable: true, isSigner: false },
+      {
+        pubkey: chainConfigPda(program, 2),
+        isWritable: false,
+        isSigner: false,
+      },
+      // 订单未挂单簿时用程序 ID 占位
+      { pubkey: program.programId, isWritable: false, isSigner: false },
+    ]);
+
+  it("should execute several SPL orders in one transaction", async () => {
+    const now = Math.floor(Date.now() / 1000);
+    const count = 3;
+    const { treasuryAta, target, orders } = await setup(count, now + 4500);
+
+    const signature = await program.methods
+      .executeOrdersBatch(orders.map(() => new anchor.BN(0)), false)
+      .accounts({
+        globalConfig: globalConfigPda,
+        executor: user,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        guardianSet: null,
+        instructions: null,
+        executorBond: null,
+        roles: null,
+      })
+      .remainingAccounts(remainingAccounts(orders, target, treasuryAta, user))
+      .preInstructions([
+        ComputeBudgetProgram.setComputeUnitLimit({ units: 1_400_000 }),
+      ])
+      .rpc({ commitment: "confirmed" });
+
+    const fee = Math.floor((1_000_000 * 50) / 10_000);
+    const targetAfter = await getAccount(provider.connection, target);
+    expect(Number(targetAfter.amount)).to.equal(count * (1_000_000 - fee));
+
+    for (const { orderPda, orderTokenAccount } of orders) {
+      expect(await provider.connection.getAccountInfo(orderPda)).to.be.null;
+      expect(await provider.connection.getAccountInfo(orderTokenAccount)).to.be
+        .null;
+    }
+
+    // 记录每个订单的计算单元消耗
+    const tx = await provider.connection.getTransaction(signature, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    const unitsPerOrder = tx.meta.computeUnitsConsumed / count;
+    console.log(`executeOrdersBatch: ${unitsPerOrder} compute units per order`);
+    expect(unitsPerOrder).to.be.lessThan(100_000);
+  });
+
+  it("should skip invalid orders in skip-failed mode", async () => {
+    const now = Math.floor(Date.now() / 1000);
+    const { treasuryAta, target, orders } = await setup(2, now + 4600);
+
+    // 第二个订单使用错误的 refund receiver
+    const accounts = remainingAccounts(orders, target, treasuryAta, user);
+    accounts[7 + 4] = {
+      pubkey: anchor.web3.Keypair.generate().publicKey,
+      isWritable: true,
+      isSigner: false,
+    };
+
+    const results = [];
+    const listener = await program.addEventListener(
+      "BatchOrderResult",
+      (event: any) => results.push(event)
+    );
+
+    await program.methods
+      .executeOrdersBatch([new anchor.BN(0), new anchor.BN(0)], true)
+      .accounts({
+        globalConfig: globalConfigPda,
+        executor: user,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        guardianSet: null,
+        instructions: null,
+        executorBond: null,
+        roles: null,
+      })
+      .remainingAccounts(accounts)
+      .rpc();
+
+    await new Promise((r) => setTimeout(r, 500));
+    await program.removeEventListener(listener);
+
+    expect(results.length).to.equal(2);
+    expect(results[0].executed).to.be.true;
+    expect(results[1].executed).to.be.false;
+
+    expect(await provider.connection.getAccountInfo(orders[0].orderPda)).to.be
+      .null;
+    expect(await provider.connection.getAccountInfo(orders[1].orderPda)).to.not
+      .be.null;
+  });
+});
+
+describe("batchRefund test", () => {
+  const provider = anchor.AnchorProvider.local();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace.limitOrder as anchor.Program;
+  const user = provider.wallet.publicKey;
+
+  let globalConfigPda: PublicKey;
+  [globalConfigPda] = PublicKey.findProgramAddressSync(
+    [Buffer.from("global-config")],
+    program.programId
+  );
+
+  const writable = (pubkey: PublicKey) => ({
+    pubkey,
+    isWritable: true,
+    isSigner: false,
+  });
+  // 订单未挂单簿时用程序 ID 占位
+  const noOrderBook = {
+    pubkey: program.programId,
+    isWritable: false,
+    isSigner: false,
+  };
+
+  // 每种托管方式各创建一个订单，返回 remaining accounts
+  const openMixedOrders = async (expiryBase: number) => {
+    const mint = await createMint(
+      provider.connection,
+      provider.wallet.payer,
+      user,
+      null,
+      6
+    );
+    const userTokenAccount = await getOrCreateAssociatedTokenAccount(
+      provider.connection,
+      provider.wallet.payer,
+      mint,
+      user
+    );
+    await mintTo(
+      provider.connection,
+      provider.wallet.payer,
+      mint,
+      userTokenAccount.address,
+      user,
+      1_000_000
+    );
+
+    const [solOrder] = await createSolOrder(
+      program,
+      user,
+      new anchor.BN(1_000_000),
+      new anchor.BN(expiryBase)
+    );
+    const [wsolOrder, wsolEscrow] = await createWsolOrder(
+      program,
+      user,
+      new anchor.BN(1_000_000),
+      new anchor.BN(expiryBase + 1)
+    );
+    const [splOrder, splEscrow] = await createSplOrder(
+      program,
+      provider,
+      user,
+      mint,
+      new anchor.BN(1_000_000),
+      new anchor.BN(expiryBase + 2)
+    );
+
+    return {
+      orders: [solOrder, wsolOrder, splOrder],
+      escrows: [wsolEscrow, splEscrow],
+      userTokenAccount: userTokenAccount.address,
+      remainingAccounts: [
+        writable(solOrder),
+        writable(user),
+        noOrderBook,
+        writable(wsolOrder),
+        writable(wsolEscrow),
+        writable(user),
+        noOrderBook,
+        writable(splOrder),
+        writable(splEscrow),
+        writable(userTokenAccount.address),
+        writable(user),
+        noOrderBook,
+      ],
+    };
+  };
+
+  it("should cancel the sender's orders in one transaction", async () => {
+    const now = Math.floor(Date.now() / 1000);
+    const { orders, escrows, userTokenAccount, remainingAccounts } =
+      await openMixedOrders(now + 4700);
+
+    await program.methods
+      .cancelOrdersBatch()
+      .accounts({
+        user,
+        globalConfig: globalConfigPda,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .remainingAccounts(remainingAccounts)
+      .rpc();
+
+    for (const account of [...orders, ...escrows]) {
+      expect(await provider.connection.getAccountInfo(account)).to.be.null;
+    }
+    const refunded = await getAccount(provider.connection, userTokenAccount);
+    expect(Number(refunded.amount)).to.equal(1_000_000);
+  });
+
+  it("should only let the owner force refund orders", async () => {
+    const now = Math.floor(Date.now() / 1000);
+    const { orders, remainingAccounts } = await openMixedOrders(now + 4800);
+
+    const nonOwner = anchor.web3.Keypair.generate();
+    const airdropSignature = await provider.connection.requestAirdrop(
+      nonOwner.publicKey,
+      1_000_000_000
+    );
+    await provider.connection.confirmTransaction(airdropSignature);
+
+    let caughtError = null;
+    try {
+      await program.methods
+        .forceRefundBatch(7)
+        .accounts({
+          globalConfig: globalConfigPda,
+          authority: nonOwner.publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+            roles: null,
+          proposal: null,
+        })
+        .remainingAccounts(remainingAccounts)
+        .signers([nonOwner])
+        .rpc();
+    } catch (err) {
+      caughtError = err;
+    }
+    expect((caughtError as AnchorError).error.errorCode.code).to.equal(
+      "ConstraintHasOne"
+    );
+
+    const events = [];
+    const listener = await program.addEventListener(
+      "OrderCancelled",
+      (event: any) => events.push(event)
+    );
+
+    await program.methods
+      .forceRefundBatch(7)
+      .accounts({
+        globalConfig: globalConfigPda,
+        authority: user,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        roles: null,
+        proposal: null,
+      })
+      .remainingAccounts(remainingAccounts)
+      .rpc();
+
+    await new Promise((r) => setTimeout(r, 500));
+    await program.removeEventListener(listener);
+
+    expect(events.length).to.equal(orders.length);
+    expect(events.every((event) => event.reasonCode === 7)).to.be.true;
+    expect(events.every((event) => "forceRefund" in event.reason)).to.be.true;
+    for (const order of orders) {
+      expect(await provider.connection.getAccountInfo(order)).to.be.null;
+    }
+  });
+});
+
+describe("cancelRequest test", () => {
+  const provider = anchor.AnchorProvider.local();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace.limitOrder as anchor.Program;
+  const owner = provider.wallet.publicKey;
+
+  let globalConfigPda: PublicKey;
+  [globalConfigPda] = PublicKey.findProgramAddressSync(
+    [Buffer.from("global-config")],
+    program.programId
+  );
+
+  const setGracePeriod = (seconds: number) =>
+    program.methods
+      .setCancelGracePeriod(new anchor.BN(seconds))
+      .accounts({
+        globalConfig: globalConfigPda,
+        roles: null,
+        authority: owner,
+      })
+      .rpc();
+
+  it("should only let the sender cancel after the grace period", async () => {
+    const sender = anchor.web3.Keypair.generate();
+    const airdropSignature = await provider.connection.requestAirdrop(
+      sender.publicKey,
+      1_000_000_000
+    );
+    await provider.connection.confirmTransaction(airdropSignature);
+
+    const expiry = new anchor.BN(Math.floor(Date.now() / 1000) + 3600);
+    const [orderPda] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("limit_order"),
+        sender.publicKey.toBuffer(),
+        expiry.toArrayLike(Buffer, "le", 8),
+      ],
+      program.programId
+    );
+    const toToken = new Uint8Array(Buffer.from("satoxi".padEnd(32, "\0")));
+    const recipient = new Uint8Array(32);
+    recipient.set(sender.publicKey.toBytes());
+
+    await program.methods
+      .openOrderSol({
+        fromToken: NATIVE_MINT,
+        fromChainId: new anchor.BN(10002),
+        amountIn: new anchor.BN(1_000_000),
+        toChainId: new anchor.BN(2),
+        toToken,
+        recipient,
+        expiry,
+        amountOut: new Uint8Array(32),
+        hashlock: new Uint8Array(32),
+        timelock: new anchor.BN(0),
+        htlcSolver: PublicKey.default,
+        exclusiveExecutor: PublicKey.default,
+        exclusivityDeadline: new anchor.BN(0),
+        refundAddress: PublicKey.default,
+        goodTillCancelled: false,
+      })
+      .accounts({
+        order: orderPda,
+        user: sender.publicKey,
+        payer: sender.publicKey,
+        globalConfig: globalConfigPda,
+        chainConfig: chainConfigPda(program, 2),
+        tokenRoute: tokenRoutePda(program, NATIVE_MINT, 2, toToken),
+        priceFeed: priceFeedPda(program, NATIVE_MINT),
+        systemProgram: SystemProgram.programId,
+        instructions: null,
+        orderBook: null,
+        sessionKey: null,
+      })
+      .signers([sender])
+      .rpc();
+
+    const cancel = () =>
+      program.methods
+        .cancelOrderSol()
+        .accounts({
+          order: orderPda,
+          user: sender.publicKey,
+          refundReceiver: sender.publicKey,
+          globalConfig: globalConfigPda,
+          systemProgram: SystemProgram.programId,
+          orderBook: null,
+          sessionKey: null,
+          roles: null,
+        })
+        .signers([sender])
+        .rpc();
+
+    await setGracePeriod(2);
+    try {
+      // 未请求取消时不能直接取消
+      let caughtError = null;
+      try {
+        await cancel();
+      } catch (err) {
+        caughtError = err;
+      }
+      expect((caughtError as AnchorError).error.errorCode.code).to.equal(
+        "CancelGracePeriodActive"
+      );
+
+      await program.methods
+        .requestCancel()
+        .accounts({
+          order: orderPda,
+          user: sender.publicKey,
+          globalConfig: globalConfigPda,
+          sessionKey: null,
+        })
+        .signers([sender])
+        .rpc();
+
+      await new Promise((r) => setTimeout(r, 3000));
+
+      const events = [];
+      const listener = await program.addEventListener(
+        "OrderCancelled",
+        (event: any) => events.push(event)
+      );
+
+      await cancel();
+
+      await new Promise((r) => setTimeout(r, 500));
+      await program.removeEventListener(listener);
+
+      expect(events.length).to.equal(1);
+      expect("sender" in events[0].reason).to.be.true;
+      expect(await provider.connection.getAccountInfo(orderPda)).to.be.null;
+    } finally {
+      await setGracePeriod(0);
+    }
+  });
+});
+
+describe("sessionKey test", () => {
+  const provider = anchor.AnchorProvider.local();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace.limitOrder as anchor.Program;
+  const owner = provider.wallet.publicKey;
+  const wallet = anchor.web3.Keypair.generate();
+  const key = anchor.web3.Keypair.generate();
+  const sessionKey = sessionKeyPda(program, wallet.publicKey, key.publicKey);
+
+  let globalConfigPda: PublicKey;
+  [globalConfigPda] = PublicKey.findProgramAddressSync(
+    [Buffer.from("global-config")],
+    program.programId
+  );
+
+  const airdrop = async (to: PublicKey) => {
+    const signature = await provider.connection.requestAirdrop(
+      to,
+      2_000_000_000
+    );
+    await provider.connection.confirmTransaction(signature);
+  };
+
+  const createSessionKey = (allowedMints: PublicKey[]) =>
+    program.methods
+      .createSessionKey({
+        key: key.publicKey,
+        expiresAt: new anchor.BN(Math.floor(Date.now() / 1000) + 3600),
+        openAllowance: new anchor.BN(1_000_000),
+        canCancel: true,
+        allowedMints,
+      })
+      .accounts({
+        sessionKey,
+        user: wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([wallet])
+      .rpc();
+
+  const setGracePeriod = (seconds: number) =>
+    program.methods
+      .setCancelGracePeriod(new anchor.BN(seconds))
+      .accounts({
+        globalConfig: globalConfigPda,
+        roles: null,
+        authority: owner,
+      })
+      .rpc();
+
+  it("should open, request and cancel orders for its wallet", async () => {
+    await airdrop(wallet.publicKey);
+    await airdrop(key.publicKey);
+
+    // 额度按单个 mint 的最小单位计算，必须指定唯一的 mint
+    let caughtError = null;
+    try {
+      await createSessionKey([]);
+    } catch (err) {
+      caughtError = err;
+    }
+    expect((caughtError as AnchorError).error.errorCode.code).to.equal(
+      "InvalidParameter"
+    );
+    await createSessionKey([NATIVE_MINT]);
+
+    const expiry = new anchor.BN(Math.floor(Date.now() / 1000) + 3600);
+    const [orderPda] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("limit_order"),
+        wallet.publicKey.toBuffer(),
+        expiry.toArrayLike(Buffer, "le", 8),
+      ],
+      program.programId
+    );
+    const toToken = new Uint8Array(Buffer.from("satoxi".padEnd(32, "\0")));
+    const recipient = new Uint8Array(32);
+    recipient.set(wallet.publicKey.toBytes());
+
+    await program.methods
+      .openOrderSol({
+        fromToken: NATIVE_MINT,
+        fromChainId: new anchor.BN(10002),
+        amountIn: new anchor.BN(1_000_000),
+        toChainId: new anchor.BN(2),
+        toToken,
+        recipient,
+        expiry,
+        amountOut: new Uint8Array(32),
+        hashlock: new Uint8Array(32),
+        timelock: new anchor.BN(0),
+        htlcSolver: PublicKey.default,
+        exclusiveExecutor: PublicKey.default,
+        exclusivityDeadline: new anchor.BN(0),
+        refundAddress: PublicKey.default,
+        goodTillCancelled: false,
+      })
+      .accounts({
+        order: orderPda,
+        user: key.publicKey,
+        payer: key.publicKey,
+        globalConfig: globalConfigPda,
+        chainConfig: chainConfigPda(program, 2),
+        tokenRoute: tokenRoutePda(program, NATIVE_MINT, 2, toToken),
+        priceFeed: priceFeedPda(program, NATIVE_MINT),
+        systemProgram: SystemProgram.programId,
+        instructions: null,
+        orderBook: null,
+        sessionKey,
+      })
+      .signers([key])
+      .rpc();
+
+    const order = await program.account["limitOrder"].fetch(orderPda);
+    expect(order.sender.toBase58()).to.equal(wallet.publicKey.toBase58());
+    const session = await program.account["sessionKey"].fetch(sessionKey);
+    expect(session.openAllowance.toNumber()).to.equal(0);
+
+    await setGracePeriod(2);
+    try {
+      await program.methods
+        .requestCancel()
+        .accounts({
+          order: orderPda,
+          user: key.publicKey,
+          globalConfig: globalConfigPda,
+          sessionKey,
+        })
+        .signers([key])
+        .rpc();
+
+      await new Promise((r) => setTimeout(r, 3000));
+
+      const walletBefore = await provider.connection.getBalance(
+        wallet.publicKey
+      );
+      await program.methods
+        .cancelOrderSol()
+        .accounts({
+          order: orderPda,
+          user: key.publicKey,
+          refundReceiver: wallet.publicKey,
+          globalConfig: globalConfigPda,
+          systemProgram: SystemProgram.programId,
+          orderBook: null,
+          sessionKey,
+          roles: null,
+        })
+        .signers([key])
+        .rpc();
+
+      expect(await provider.connection.getAccountInfo(orderPda)).to.be.null;
+      const walletAfter = await provider.connection.getBalance(
+        wallet.publicKey
+      );
+      expect(walletAfter).to.be.greaterThan(walletBefore + 1_000_000);
+    } finally {
+      await setGracePeriod(0);
+    }
+
+    await program.methods
+      .revokeSessionKey()
+      .accounts({ sessionKey, user: wallet.publicKey })
+      .signers([wallet])
+      .rpc();
+    expect(await provider.connection.getAccountInfo(sessionKey)).to.be.null;
+  });
+});
+
+describe("executorBond test", () => {
+  const provider = anchor.AnchorProvider.local();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace.limitOrder as anchor.Program;
+  const owner = provider.wallet.publicKey;
+  const solver = anchor.web3.Keypair.generate();
+  const guardian = anchor.web3.Keypair.generate();
+
+  let globalConfigPda: PublicKey;
+  [globalConfigPda] = PublicKey.findProgramAddressSync(
+    [Buffer.from("global-config")],
+    program.programId
+  );
+
+  const [executorBondPda] = PublicKey.findProgramAddressSync(
+    [
+      Buffer.from("executor-bond"),
+      solver.publicKey.toBuffer(),
+      NATIVE_MINT.toBuffer(),
+    ],
+    program.programId
+  );
+
+  const guardianSetPda = (index: number) =>
+    PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("guardian-set"),
+        new anchor.BN(index).toArrayLike(Buffer, "le", 4),
+      ],
+      program.programId
+    )[0];
+
+  const setBondConfig = (
+    bondMultiple: number,
+    unbondCooldown: number,
+    slashWindow: number
+  ) =>
+    program.methods
+      .setBondConfig(
+        bondMultiple,
+        new anchor.BN(unbondCooldown),
+        new anchor.BN(slashWindow)
+      )
+      .accounts({
+        globalConfig: globalConfigPda,
+        roles: null,
+        authority: owner,
+      })
+      .rpc();
+
+  const bondSol = (amount: number) =>
+    program.methods
+      .bondExecutorSol(new anchor.BN(amount))
+      .accounts({
+        executorBond: executorBondPda,
+        executor: solver.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([solver])
+      .rpc();
+
+  const executeAsSolver = (orderPda: PublicKey) =>
+    program.methods
+      .executeOrderSol(new anchor.BN(0))
+      .accounts({
+        order: orderPda,
+        targetSol: anchor.web3.Keypair.generate().publicKey,
+        treasury: owner,
+        executor: solver.publicKey,
+        globalConfig: globalConfigPda,
+        chainConfig: chainConfigPda(program, 2),
+        refundReceiver: owner,
+        guardianSet: null,
+        instructions: null,
+        executorBond: executorBondPda,
+        roles: null,
+        orderBook: null,
+      })
+      .signers([solver])
+      .rpc();
+
+  before(async () => {
+    const airdropSignature = await provider.connection.requestAirdrop(
+      solver.publicKey,
+      2_000_000_000
+    );
+    await provider.connection.confirmTransaction(airdropSignature);
+  });
+
+  it("should cap bonded execution at a multiple of the bond", async () => {
+    await setBondConfig(2, 60, 60);
+    await bondSol(100_000_000);
+
+    const [firstOrder] = await createSolOrder(
+      program,
+      owner,
+      new anchor.BN(150_000_000),
+      new anchor.BN(Math.floor(Date.now() / 1000) + 4500)
+    );
+    await executeAsSolver(firstOrder);
+
+    const [secondOrder] = await createSolOrder(
+      program,
+      owner,
+      new anchor.BN(100_000_000),
+      new anchor.BN(Math.floor(Date.now() / 1000) + 4600)
+    );
+    let caughtError = null;
+    try {
+      await executeAsSolver(secondOrder);
+    } catch (err) {
+      caughtError = err;
+    }
+    expect((caughtError as AnchorError).error.errorCode.code).to.equal(
+      "BondLimitExceeded"
+    );
+
+    const bond = await program.account["executorBond"].fetch(executorBondPda);
+    expect(bond.pending.length).to.equal(1);
+    expect(bond.pending[0].order.toBase58()).to.equal(firstOrder.toBase58());
+
+    // 清理未执行的订单
+    await program.methods
+      .cancelOrderSol()
+      .accounts({
+        order: secondOrder,
+        user: owner,
+        refundReceiver: owner,
+        globalConfig: globalConfigPda,
+        systemProgram: SystemProgram.programId,
+        orderBook: null,
+        sessionKey: null,
+        roles: null,
+      })
+      .rpc();
+  });
+
+  it("should slash the bond on attested non-delivery", async () => {
+    const config = await program.account["globalConfig"].fetch(
+      globalConfigPda
+    );
+    const currentIndex = config.guardianSetIndex;
+    await program.methods
+      .rotateGuardianSet([guardian.publicKey], 1, new anchor.BN(0))
+      .accounts({
+        currentGuardianSet: guardianSetPda(currentIndex),
+        newGuardianSet: guardianSetPda(currentIndex + 1),
+        globalConfig: globalConfigPda,
+        authority: owner,
+        systemProgram: SystemProgram.programId,
+        roles: null,
+        proposal: null,
+      })
+      .rpc();
+
+    const bondBefore = await program.account["executorBond"].fetch(
+      executorBondPda
+    );
+    const order = bondBefore.pending[0].order as PublicKey;
+
+    // DOMAIN || order || executor
+    const message = Buffer.concat([
+      Buffer.from("interra-limit-order:non-delivery:v1"),
+      order.toBuffer(),
+      solver.publicKey.toBuffer(),
+    ]);
+
+    const balanceBefore = await provider.connection.getBalance(owner);
+    await program.methods
+      .slashExecutorSol(order)
+      .accounts({
+        executorBond: executorBondPda,
+        recipient: owner,
+        globalConfig: globalConfigPda,
+        authority: owner,
+        guardianSet: guardianSetPda(currentIndex + 1),
+        instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+        roles: null,
+      })
+      .preInstructions([
+        Ed25519Program.createInstructionWithPrivateKey({
+          privateKey: guardian.secretKey,
+          message,
+        }),
+      ])
+      .rpc();
+    const balanceAfter = await provider.connection.getBalance(owner);
+
+    // 保证金只有 0.1 SOL，全部赔付给用户（扣除交易手续费）
+    expect(balanceAfter - balanceBefore).to.be.greaterThan(99_000_000);
+    const bond = await program.account["executorBond"].fetch(executorBondPda);
+    expect(bond.amount.toNumber()).to.equal(0);
+    expect(bond.pending.length).to.equal(0);
+  });
+
+  it("should only withdraw after the unbond cooldown", async () => {
+    await setBondConfig(2, 2, 1);
+    await bondSol(50_000_000);
+
+    await program.methods
+      .requestUnbond(new anchor.BN(50_000_000))
+      .accounts({
+        executorBond: executorBondPda,
+        executor: solver.publicKey,
+        globalConfig: globalConfigPda,
+      })
+      .signers([solver])
+      .rpc();
+
+    const withdraw = () =>
+      program.methods
+        .withdrawBondSol()
+        .accounts({
+          executorBond: executorBondPda,
+          executor: solver.publicKey,
+          globalConfig: globalConfigPda,
+        })
+        .signers([solver])
+        .rpc();
+
+    let caughtError = null;
+    try {
+      await withdraw();
+    } catch (err) {
+      caughtError = err;
+    }
+    expect((caughtError as AnchorError).error.errorCode.code).to.equal(
+      "UnbondCooldownActive"
+    );
+
+    await new Promise((r) => setTimeout(r, 3000));
+    await withdraw();
+
+    const bond = await program.account["executorBond"].fetch(executorBondPda);
+    expect(bond.unbondingAmount.toNumber()).to.equal(0);
+
+    await setBondConfig(0, 0, 0);
+  });
+});
+
+describe("rfqQuote test", () => {
+  const provider = anchor.AnchorProvider.local();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace.limitOrder as anchor.Program;
+  const user = provider.wallet.publicKey;
+  const solver = anchor.web3.Keypair.generate();
+
+  let globalConfigPda: PublicKey;
+  [globalConfigPda] = PublicKey.findProgramAddressSync(
+    [Buffer.from("global-config")],
+    program.programId
+  );
+
+  const toToken = new Uint8Array(Buffer.from("satoxi".padEnd(32, "\0")));
+
+  // DOMAIN || borsh(FillQuote)
+  const quoteMessage = (params: any) =>
+    Buffer.concat([
+      Buffer.from("interra-limit-order:quote:v1"),
+      user.toBuffer(),
+      params.expiry.toArrayLike(Buffer, "le", 8),
+      params.fromToken.toBuffer(),
+      params.amountIn.toArrayLike(Buffer, "le", 8),
+      params.toChainId.toArrayLike(Buffer, "le", 8),
+      Buffer.from(params.toToken),
+      Buffer.from(params.recipient),
+      Buffer.from(params.amountOut),
+      params.exclusivityDeadline.toArrayLike(Buffer, "le", 8),
+    ]);
+
+  const openWithQuote = (
+    params: any,
+    orderPda: PublicKey,
+    quoteSigner: anchor.web3.Keypair
+  ) =>
+    program.methods
+      .openOrderSol(params)
+      .accounts({
+        order: orderPda,
+        user,
+        payer: user,
+        globalConfig: globalConfigPda,
+        chainConfig: chainConfigPda(program, 2),
+        tokenRoute: tokenRoutePda(program, NATIVE_MINT, 2, toToken),
+        priceFeed: priceFeedPda(program, NATIVE_MINT),
+        systemProgram: SystemProgram.programId,
+        instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+        orderBook: null,
+        sessionKey: null,
+      })
+      .preInstructions([
+        Ed25519Program.createInstructionWithPrivateKey({
+          privateKey: quoteSigner.secretKey,
+          message: quoteMessage(params),
+        }),
+      ])
+      .rpc();
+
+  const execute = (orderPda: PublicKey) =>
+    program.methods
+      .executeOrderSol(new anchor.BN(0))
+      .accounts({
+        order: orderPda,
+        targetSol: anchor.web3.Keypair.generate().publicKey,
+        treasury: user,
+        executor: user,
+        globalConfig: globalConfigPda,
+        chainConfig: chainConfigPda(program, 2),
+        refundReceiver: user,
+        guardianSet: null,
+        instructions: null,
+        executorBond: null,
+        roles: null,
+        orderBook: null,
+      })
+      .rpc();
+
+  it("should reserve a quoted order for its solver until the deadline", async () => {
+    const now = Math.floor(Date.now() / 1000);
+    const expiry = new anchor.BN(now + 4700);
+    const [orderPda] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("limit_order"),
+        user.toBuffer(),
+        expiry.toArrayLike(Buffer, "le", 8),
+      ],
+      program.programId
+    );
+    const recipient = new Uint8Array(32);
+    recipient.set(user.toBytes());
+    const params = {
+      fromToken: NATIVE_MINT,
+      fromChainId: new anchor.BN(10002),
+      amountIn: new anchor.BN(1_000_000),
+      toChainId: new anchor.BN(2),
+      toToken,
+      recipient,
+      expiry,
+      amountOut: new Uint8Array(32),
+      hashlock: new Uint8Array(32),
+      timelock: new anchor.BN(0),
+      htlcSolver: PublicKey.default,
+      exclusiveExecutor: solver.publicKey,
+      exclusivityDeadline: new anchor.BN(now + 3),
+      refundAddress: PublicKey.default,
+      goodTillCancelled: false,
+    };
+
+    // 非报价方签名的报价应被拒绝
+    let caughtError = null;
+    try {
+      await openWithQuote(params, orderPda, anchor.web3.Keypair.generate());
+    } catch (err) {
+      caughtError = err;
+    }
+    expect((caughtError as AnchorError).error.errorCode.code).to.equal(
+      "InvalidQuote"
+    );
+
+    await openWithQuote(params, orderPda, solver);
+    const order = await program.account["limitOrder"].fetch(orderPda);
+    expect(order.exclusiveExecutor.toBase58()).to.equal(
+      solver.publicKey.toBase58()
+    );
+
+    caughtError = null;
+    try {
+      await execute(orderPda);
+    } catch (err) {
+      caughtError = err;
+    }
+    expect((caughtError as AnchorError).error.errorCode.code).to.equal(
+      "ExclusivityActive"
+    );
+
+    await new Promise((r) => setTimeout(r, 4000));
+    await execute(orderPda);
+    expect(await provider.connection.getAccountInfo(orderPda)).to.be.null;
+  });
+});
+
+describe("migration test", () => {
+  const provider = anchor.AnchorProvider.local();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace.limitOrder as anchor.Program;
+  const user = provider.wallet.publicKey;
+
+  let globalConfigPda: PublicKey;
+  [globalConfigPda] = PublicKey.findProgramAddressSync(
+    [Buffer.from("global-config")],
+    program.programId
+  );
+
+  it("should reject migrating a current config", async () => {
+    let caughtError = null;
+    try {
+      await program.methods
+        .migrateConfig(new anchor.BN(10002))
+        .accounts({
+          globalConfig: globalConfigPda,
+          authority: user,
+          roles: null,
+          proposal: null,
+        })
+        .rpc();
+    } catch (err) {
+      caughtError = err;
+    }
+    expect((caughtError as AnchorError).error.errorCode.code).to.equal(
+      "AlreadyMigrated"
+    );
+  });
+
+  it("should reject migrating a current order", async () => {
+    const expiry = new anchor.BN(Math.floor(Date.now() / 1000) + 4800);
+    const [orderPda] = await createSolOrder(
+      program,
+      user,
+      new anchor.BN(1_000_000),
+      expiry
+    );
+    const order = await program.account["limitOrder"].fetch(orderPda);
+    expect(order.version).to.equal(4);
+
+    let caughtError = null;
+    try {
+      await program.methods
+        .migrateOrder()
+        .accounts({
+          order: orderPda,
+          globalConfig: globalConfigPda,
+          authority: user,
+          systemProgram: SystemProgram.programId,
+          roles: null,
+          proposal: null,
+        })
+        .rpc();
+    } catch (err) {
+      caughtError = err;
+    }
+    expect((caughtError as AnchorError).error.errorCode.code).to.equal(
+      "AlreadyMigrated"
+    );
+  });
+});
+
+describe("orderBook test", () => {
+  const provider = anchor.AnchorProvider.local();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace.limitOrder as anchor.Program;
+  const user = provider.wallet.publicKey;
+  const orderBook = orderBookPda(program, NATIVE_MINT, 2);
+
+  let globalConfigPda: PublicKey;
+  [globalConfigPda] = PublicKey.findProgramAddressSync(
+    [Buffer.from("global-config")],
+    program.programId
+  );
+
+  const cancel = (orderPda: PublicKey, book: PublicKey | null) =>
+    program.methods
+      .cancelOrderSol()
+      .accounts({
+        order: orderPda,
+        user,
+        refundReceiver: user,
+        globalConfig: globalConfigPda,
+        systemProgram: SystemProgram.programId,
+        orderBook: book,
+        sessionKey: null,
+        roles: null,
+      })
+      .rpc();
+
+  it("should track open orders of a market", async () => {
+    await program.methods
+      .initOrderBook(NATIVE_MINT, new anchor.BN(2))
+      .accounts({
+        orderBook,
+        globalConfig: globalConfigPda,
+        authority: user,
+        systemProgram: SystemProgram.programId,
+        roles: null,
+        proposal: null,
+      })
+      .rpc();
+
+    const now = Math.floor(Date.now() / 1000);
+    const [listedOrder] = await createSolOrder(
+      program,
+      user,
+      new anchor.BN(1_000_000),
+      new anchor.BN(now + 4900),
+      {},
+      orderBook
+    );
+    const [expiringOrder] = await createSolOrder(
+      program,
+      user,
+      new anchor.BN(1_000_000),
+      new anchor.BN(now + 3),
+      {},
+      orderBook
+    );
+
+    let entries = await fetchOrderBook(program, NATIVE_MINT, 2);
+    expect(entries.map((entry) => entry.order.toBase58())).to.have.members([
+      listedOrder.toBase58(),
+      expiringOrder.toBase58(),
+    ]);
+
+    // 已上架的订单取消时必须同时更新 order book
+    let caughtError = null;
+    try {
+      await cancel(listedOrder, null);
+    } catch (err) {
+      caughtError = err;
+    }
+    expect((caughtError as AnchorError).error.errorCode.code).to.equal(
+      "OrderBookRequired"
+    );
+
+    await cancel(listedOrder, orderBook);
+    entries = await fetchOrderBook(program, NATIVE_MINT, 2);
+    expect(entries.length).to.equal(1);
+
+    await new Promise((r) => setTimeout(r, 4000));
+    await program.methods.pruneOrderBook().accounts({ orderBook }).rpc();
+    entries = await fetchOrderBook(program, NATIVE_MINT, 2);
+    expect(entries.length).to.equal(0);
+
+    await cancel(expiringOrder, orderBook);
+  });
+
+  it("should delist batched orders through their own book slot", async () => {
+    const now = Math.floor(Date.now() / 1000);
+    const [listedOrder] = await createSolOrder(
+      program,
+      user,
+      new anchor.BN(1_000_000),
+      new anchor.BN(now + 5000),
+      {},
+      orderBook
+    );
+    const [unlistedOrder] = await createSolOrder(
+      program,
+      user,
+      new anchor.BN(1_000_000),
+      new anchor.BN(now + 5001)
+    );
+
+    // 每个订单带上自己的 order book，未上架的订单用程序 ID 占位
+    await program.methods
+      .cancelOrdersBatch()
+      .accounts({
+        user,
+        globalConfig: globalConfigPda,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .remainingAccounts([
+        { pubkey: listedOrder, isWritable: true, isSigner: false },
+        { pubkey: user, isWritable: true, isSigner: false },
+        { pubkey: orderBook, isWritable: true, isSigner: false },
+        { pubkey: unlistedOrder, isWritable: true, isSigner: false },
+        { pubkey: user, isWritable: true, isSigner: false },
+        { pubkey: program.programId, isWritable: false, isSigner: false },
+      ])
+      .rpc();
+
+    const entries = await fetchOrderBook(program, NATIVE_MINT, 2);
+    expect(entries.length).to.equal(0);
+    expect(await provider.connection.getAccountInfo(listedOrder)).to.be.null;
+    expect(await provider.connection.getAccountInfo(unlistedOrder)).to.be.null;
+  });
+});
+
+describe("roles test", () => {
+  const provider = anchor.AnchorProvider.local();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace.limitOrder as anchor.Program;
+  const user = provider.wallet.publicKey;
+  const pauser = anchor.web3.Keypair.generate();
+  const secondAdmin = anchor.web3.Keypair.generate();
+
+  let globalConfigPda: PublicKey;
+  [globalConfigPda] = PublicKey.findProgramAddressSync(
+    [Buffer.from("global-config")],
+    program.programId
+  );
+  const [rolesPda] = PublicKey.findProgramAddressSync(
+    [Buffer.from("roles")],
+    program.programId
+  );
+
+  const proposalPda = (id: anchor.BN) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("admin-proposal"), id.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    )[0];
+
+  const setPaused = (paused: boolean) =>
+    program.methods
+      .setPaused(paused)
+      .accounts({
+        globalConfig: globalConfigPda,
+        roles: rolesPda,
+        authority: pauser.publicKey,
+      })
+      .signers([pauser])
+      .rpc();
+
+  it("should gate admin operations by role", async () => {
+    await program.methods
+      .initRoles()
+      .accounts({
+        globalConfig: globalConfigPda,
+        roles: rolesPda,
+        owner: user,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    await program.methods
+      .grantRole(pauser.publicKey, { pauser: {} })
+      .accounts({
+        globalConfig: globalConfigPda,
+        roles: rolesPda,
+        authority: user,
+      })
+      .rpc();
+
+    // Pauser 只能暂停，不能修改其他配置
+    let caughtError = null;
+    try {
+      await program.methods
+        .setPlatformFee(0)
+        .accounts({
+          globalConfig: globalConfigPda,
+          roles: rolesPda,
+          authority: pauser.publicKey,
+        })
+        .signers([pauser])
+        .rpc();
+    } catch (err) {
+      caughtError = err;
+    }
+    expect((caughtError as AnchorError).error.errorCode.code).to.equal(
+      "MissingRole"
+    );
+
+    await setPaused(true);
+    caughtError = null;
+    try {
+      await createSolOrder(
+        program,
+        user,
+        new anchor.BN(1_000_000),
+        new anchor.BN(Math.floor(Date.now() / 1000) + 5000)
+      );
+    } catch (err) {
+      caughtError = err;
+    }
+    expect((caughtError as AnchorError).error.errorCode.code).to.equal(
+      "Paused"
+    );
+    await setPaused(false);
+  });
+
+  it("should require multisig approval above the admin threshold", async () => {
+    await program.methods
+      .grantRole(secondAdmin.publicKey, { admin: {} })
+      .accounts({
+        globalConfig: globalConfigPda,
+        roles: rolesPda,
+        authority: user,
+      })
+      .rpc();
+    await program.methods
+      .setAdminThreshold(2)
+      .accounts({
+        globalConfig: globalConfigPda,
+        roles: rolesPda,
+        authority: user,
+      })
+      .rpc();
+
+    let caughtError = null;
+    try {
+      await program.methods
+        .setChainId(new anchor.BN(1))
+        .accounts({
+          globalConfig: globalConfigPda,
+          roles: rolesPda,
+          authority: user,
+        })
+        .rpc();
+    } catch (err) {
+      caughtError = err;
+    }
+    expect((caughtError as AnchorError).error.errorCode.code).to.equal(
+      "MultisigRequired"
+    );
+
+    // 2-of-2：提议者自动批准，再由第二个 admin 批准后执行
+    const roles = await program.account["roles"].fetch(rolesPda);
+    const proposal = proposalPda(roles.proposalCount);
+    await program.methods
+      .proposeAdminAction({ setAdminThreshold: { threshold: 0 } })
+      .accounts({
+        globalConfig: globalConfigPda,
+        roles: rolesPda,
+        proposal,
+        proposer: user,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    caughtError = null;
+    try {
+      await program.methods
+        .executeAdminAction()
+        .accounts({
+          globalConfig: globalConfigPda,
+          roles: rolesPda,
+          proposal,
+          executor: user,
+        })
+        .rpc();
+    } catch (err) {
+      caughtError = err;
+    }
+    expect((caughtError as AnchorError).error.errorCode.code).to.equal(
+      "InsufficientApprovals"
+    );
+
+    await program.methods
+      .approveAdminAction()
+      .accounts({
+        globalConfig: globalConfigPda,
+        roles: rolesPda,
+        proposal,
+        approver: secondAdmin.publicKey,
+      })
+      .signers([secondAdmin])
+      .rpc();
+    await program.methods
+      .executeAdminAction()
+      .accounts({
+        globalConfig: globalConfigPda,
+        roles: rolesPda,
+        proposal,
+        executor: user,
+      })
+      .rpc();
+
+    const rolesAfter = await program.account["roles"].fetch(rolesPda);
+    expect(rolesAfter.adminThreshold).to.equal(0);
+    const executed = await program.account["adminProposal"].fetch(proposal);
+    expect(executed.executed).to.equal(true);
+  });
+});
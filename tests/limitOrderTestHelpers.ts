@@ -2,8 +2,16 @@ import * as anchor from "@coral-xyz/anchor";
 import { PublicKey, SystemProgram } from "@solana/web3.js";
 import {
   getOrCreateAssociatedTokenAccount,
+  NATIVE_MINT,
   TOKEN_PROGRAM_ID,
 } from "@solana/spl-token";
+import {
+  chainConfigPda,
+  globalConfigPda,
+  mintPolicyPda,
+  priceFeedPda,
+  tokenRoutePda,
+} from "../sdk/limitOrderSdk";
 
 type OpenOrderParams = {
   fromToken: PublicKey;
@@ -14,13 +22,53 @@ type OpenOrderParams = {
   recipient: Uint8Array;
   expiry: anchor.BN;
   amountOut: Uint8Array;
+  hashlock: Uint8Array;
+  timelock: anchor.BN;
+  htlcSolver: PublicKey;
+  exclusiveExecutor: PublicKey;
+  exclusivityDeadline: anchor.BN;
+  refundAddress: PublicKey;
+  goodTillCancelled: boolean;
 };
 
+export async function ensureTokenRoute(
+  program: anchor.Program,
+  fromToken: PublicKey,
+  toChainId: anchor.BN,
+  toToken: Uint8Array,
+  fromDecimals: number
+): Promise<PublicKey> {
+  const tokenRoute = tokenRoutePda(program, fromToken, toChainId, toToken);
+  const info = await program.provider.connection.getAccountInfo(tokenRoute);
+  if (!info) {
+    await program.methods
+      .setTokenRoute(fromToken, toChainId, Array.from(toToken), {
+        enabled: true,
+        fromDecimals,
+        toDecimals: 18,
+        minAmountIn: new anchor.BN(0),
+        maxAmountIn: new anchor.BN(0),
+      })
+      .accounts({
+        tokenRoute,
+        globalConfig: globalConfigPda(program),
+        authority: program.provider.publicKey,
+        roles: null,
+        proposal: null,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+  }
+  return tokenRoute;
+}
+
 export async function createSolOrder(
   program: anchor.Program,
   user: PublicKey,
   amountIn: anchor.BN,
-  expiry: anchor.BN
+  expiry: anchor.BN,
+  overrides: Partial<OpenOrderParams> = {},
+  orderBook: PublicKey | null = null
 ): Promise<[PublicKey, OpenOrderParams, number]> {
   const [orderPda, bump] = PublicKey.findProgramAddressSync(
     [
@@ -44,14 +92,38 @@ export async function createSolOrder(
     })(),
     expiry,
     amountOut: new Uint8Array(32),
+    hashlock: new Uint8Array(32),
+    timelock: new anchor.BN(0),
+    htlcSolver: PublicKey.default,
+    exclusiveExecutor: PublicKey.default,
+    exclusivityDeadline: new anchor.BN(0),
+    refundAddress: PublicKey.default,
+    goodTillCancelled: false,
+    ...overrides,
   };
 
+  const tokenRoute = await ensureTokenRoute(
+    program,
+    params.fromToken,
+    params.toChainId,
+    params.toToken,
+    9
+  );
+
   await program.methods
     .openOrderSol(params)
     .accounts({
       order: orderPda,
       user: user,
+      payer: user,
+      globalConfig: globalConfigPda(program),
+      chainConfig: chainConfigPda(program, params.toChainId),
+      tokenRoute,
+      priceFeed: priceFeedPda(program, params.fromToken),
       systemProgram: SystemProgram.programId,
+      instructions: null,
+      orderBook,
+      sessionKey: null,
     })
     .rpc();
 
@@ -64,7 +136,9 @@ export async function createSplOrder(
   user: PublicKey,
   mint: PublicKey,
   amount: anchor.BN,
-  expiry: anchor.BN
+  expiry: anchor.BN,
+  overrides: Partial<OpenOrderParams> = {},
+  orderBook: PublicKey | null = null
 ): Promise<[PublicKey, PublicKey, OpenOrderParams, number]> {
   const [orderPda, bump] = PublicKey.findProgramAddressSync(
     [
@@ -100,19 +174,121 @@ export async function createSplOrder(
     })(),
     expiry,
     amountOut: new Uint8Array(32),
+    hashlock: new Uint8Array(32),
+    timelock: new anchor.BN(0),
+    htlcSolver: PublicKey.default,
+    exclusiveExecutor: PublicKey.default,
+    exclusivityDeadline: new anchor.BN(0),
+    refundAddress: PublicKey.default,
+    goodTillCancelled: false,
+    ...overrides,
   };
 
+  const tokenRoute = await ensureTokenRoute(
+    program,
+    params.fromToken,
+    params.toChainId,
+    params.toToken,
+    6
+  );
+
   await program.methods
     .openOrderSpl(params)
     .accounts({
       order: orderPda,
       user,
+      payer: user,
       userTokenAccount: userTokenAccount.address,
       orderTokenAccount,
       tokenMint: mint,
+      globalConfig: globalConfigPda(program),
+      chainConfig: chainConfigPda(program, params.toChainId),
+      tokenRoute,
+      mintPolicy: mintPolicyPda(program, mint),
+      priceFeed: priceFeedPda(program, params.fromToken),
+      tokenProgram: TOKEN_PROGRAM_ID,
+      systemProgram: SystemProgram.programId,
+      associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+      instructions: null,
+      orderBook,
+      sessionKey: null,
+    })
+    .rpc();
+
+  return [orderPda, orderTokenAccount, params, bump];
+}
+
+export async function createWsolOrder(
+  program: anchor.Program,
+  user: PublicKey,
+  amountIn: anchor.BN,
+  expiry: anchor.BN,
+  overrides: Partial<OpenOrderParams> = {},
+  orderBook: PublicKey | null = null
+): Promise<[PublicKey, PublicKey, OpenOrderParams, number]> {
+  const [orderPda, bump] = PublicKey.findProgramAddressSync(
+    [
+      Buffer.from("limit_order"),
+      user.toBuffer(),
+      expiry.toArrayLike(Buffer, "le", 8),
+    ],
+    program.programId
+  );
+
+  const orderTokenAccount = anchor.utils.token.associatedAddress({
+    mint: NATIVE_MINT,
+    owner: orderPda,
+  });
+
+  const params = {
+    fromToken: NATIVE_MINT,
+    fromChainId: new anchor.BN(10002),
+    amountIn,
+    toChainId: new anchor.BN(2),
+    toToken: new Uint8Array(Buffer.from("satoxi".padEnd(32, "\0"))),
+    recipient: (() => {
+      const arr = new Uint8Array(32);
+      arr.set(user.toBytes());
+      return arr;
+    })(),
+    expiry,
+    amountOut: new Uint8Array(32),
+    hashlock: new Uint8Array(32),
+    timelock: new anchor.BN(0),
+    htlcSolver: PublicKey.default,
+    exclusiveExecutor: PublicKey.default,
+    exclusivityDeadline: new anchor.BN(0),
+    refundAddress: PublicKey.default,
+    goodTillCancelled: false,
+    ...overrides,
+  };
+
+  const tokenRoute = await ensureTokenRoute(
+    program,
+    params.fromToken,
+    params.toChainId,
+    params.toToken,
+    9
+  );
+
+  await program.methods
+    .openOrderWsol(params)
+    .accounts({
+      order: orderPda,
+      user,
+      payer: user,
+      orderTokenAccount,
+      tokenMint: NATIVE_MINT,
+      globalConfig: globalConfigPda(program),
+      chainConfig: chainConfigPda(program, params.toChainId),
+      tokenRoute,
+      priceFeed: priceFeedPda(program, params.fromToken),
       tokenProgram: TOKEN_PROGRAM_ID,
       systemProgram: SystemProgram.programId,
       associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+      instructions: null,
+      orderBook,
+      sessionKey: null,
     })
     .rpc();
 